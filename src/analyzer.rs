@@ -1,37 +1,579 @@
+use crate::baseline;
 use crate::cache;
-use crate::config::Config;
-use crate::error::Result;
-use crate::git::ParsedDiff;
-use crate::llm::{self, RawIssue};
+use crate::config::{Config, Corpus, LlmTask, RulesConfig};
+use crate::consensus::{self, ProviderIssues};
+use crate::deps::{self, BumpDiff};
+use crate::embeddings;
+use crate::error::{DriftcheckError, Result};
+use crate::excerpt;
+use crate::expr::{Expr, Value};
+use crate::feedback;
+use crate::git::{self, ParsedDiff};
+use crate::history::{self, IssueState};
+use crate::lfs;
+use crate::llm::{self, stream, DocKind, RawIssue};
+use crate::paths;
 use crate::progress::MultiProgress;
-use crate::search;
-use std::path::PathBuf;
-use tracing::{debug, info};
+use crate::risk::{self, FileRisk};
+use crate::search::{self, QueryMode, SearchMode};
+use crate::suppress;
+use crate::symbols;
+use glob::Pattern;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::field::Empty;
+use tracing::{debug, info, warn, Instrument, Span};
+
+/// Largest diff sent in one analysis call (about 6000 tokens at 4 chars per token); larger
+/// diffs are split by file and analyzed concurrently
+const MAX_GROUP_DIFF_CHARS: usize = 24_000;
+
+/// Most search queries guessed per changed file, by `search.query_mode = "heuristic"` and
+/// `--dry-run`
+const MAX_HEURISTIC_QUERIES: usize = 10;
+
+/// A search round where fewer than this share of queries match any doc is refined
+/// (`docs.refine_rounds`)
+const MIN_QUERY_HIT_RATIO: f32 = 0.25;
+
+/// Smallest doc budget (in tokens) an analysis is retried with after a context-length error
+const MIN_RETRY_CONTEXT_TOKENS: usize = 512;
 
 /// An issue detected by the analysis
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Issue {
     pub file: PathBuf,
     pub line: usize,
     pub description: String,
     pub doc_excerpt: String,
+    /// The model's original excerpt when `doc_excerpt` was trimmed to the verified region
+    pub full_excerpt: Option<String>,
     pub suggested_fix: Option<String>,
+    /// LLM-reported certainty that the docs are wrong (0.0 to 1.0)
+    pub confidence: Option<f32>,
+    pub kind: DocKind,
+    /// Stable identity used to track the issue across runs
+    pub fingerprint: String,
+    pub state: IssueState,
+    pub severity: Severity,
+    /// Named `[docs.<name>]` corpus the doc belongs to, if corpora are configured
+    pub corpus: Option<String>,
+    /// Generated fix as a unified diff, set by `check --with-fixes`
+    pub fix_patch: Option<String>,
+    /// Where in the diff the code the issue is about changed
+    pub code_location: Option<CodeLocation>,
+    /// Was snoozed (`driftcheck snooze`) and the snooze has run out
+    #[serde(default)]
+    pub snooze_expired: bool,
+}
+
+/// A code symbol resolved to a line of the changed code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeLocation {
+    pub file: PathBuf,
+    /// Line in the new version of the file
+    pub line: usize,
+    pub symbol: String,
+}
+
+impl fmt::Display for CodeLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
+}
+
+/// How an issue affects the exit status, set from the corpus and `[rules]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Fails `check` and blocks the push
+    #[default]
+    Error,
+    /// Reported only
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+impl Issue {
+    /// Whether this issue should fail the check or block a push
+    pub fn is_blocking(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// State, severity (when not an error) and confidence, e.g. "new, warning, 85% confidence"
+    pub fn status_label(&self) -> String {
+        let mut label = self.state.to_string();
+        if !self.is_blocking() {
+            label.push_str(&format!(", {}", self.severity));
+        }
+        if let Some(confidence) = self.confidence {
+            label.push_str(&format!(", {:.0}% confidence", confidence * 100.0));
+        }
+        if self.snooze_expired {
+            label.push_str(", snooze expired");
+        }
+        label
+    }
 }
 
 impl From<RawIssue> for Issue {
     fn from(raw: RawIssue) -> Self {
+        // Models echo Windows paths with `\` separators
+        let file = paths::normalize(Path::new(&raw.file));
+        let fingerprint = history::fingerprint(&file, &raw.description, &raw.doc_excerpt);
         Self {
-            file: PathBuf::from(&raw.file),
+            kind: DocKind::from_path(&raw.file),
+            file,
             line: raw.line,
             description: raw.description,
             doc_excerpt: raw.doc_excerpt,
+            full_excerpt: None,
             suggested_fix: raw.suggested_fix,
+            confidence: raw.confidence,
+            fingerprint,
+            state: IssueState::New,
+            severity: Severity::Error,
+            corpus: None,
+            fix_patch: None,
+            code_location: None,
+            snooze_expired: false,
+        }
+    }
+}
+
+/// Order of reported issues (`general.sort_by`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// Errors before warnings, then by confidence
+    Severity,
+    /// By file, then line
+    File,
+    /// Most confident first
+    Confidence,
+    /// By line, then file
+    Line,
+}
+
+impl SortBy {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.general.sort_by.as_str() {
+            "severity" => Ok(SortBy::Severity),
+            "file" => Ok(SortBy::File),
+            "confidence" => Ok(SortBy::Confidence),
+            "line" => Ok(SortBy::Line),
+            other => Err(DriftcheckError::ConfigInvalid(format!(
+                "general.sort_by must be \"severity\", \"file\", \"confidence\" or \"line\", got {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+/// What `analyze` found: the issues, and what reports show about how they were found
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub issues: Vec<Issue>,
+    /// Drift risk of each changed source file, riskiest first
+    pub risk: Vec<FileRisk>,
+    /// What each provider found, with `[llm.consensus]`
+    pub consensus: Vec<ProviderIssues>,
+}
+
+/// What the pipeline records while analyzing, besides the issues
+#[derive(Default)]
+struct Run {
+    /// Doc files sent to the LLM
+    analyzed: HashSet<PathBuf>,
+    risk: Vec<FileRisk>,
+    consensus: Vec<ProviderIssues>,
+}
+
+/// Run the full analysis pipeline and record issue lifecycle states
+#[tracing::instrument(name = "analyze", skip_all, fields(diff_bytes = diff.len(), issues = Empty))]
+pub async fn analyze(config: &Config, diff: &str) -> Result<Analysis> {
+    let sort_by = SortBy::from_config(config)?;
+    let mut run = Run::default();
+    let issues = filter_confidence(
+        config.docs.min_confidence,
+        analyze_diff(config, diff, &mut run).await?,
+    );
+    let mut issues = filter_categories(&config.general.disabled_categories, issues);
+    // Severity rules may match on `state`, which `track` only records for the issues kept
+    if let Err(e) = history::assign_states(&mut issues) {
+        debug!("Failed to read issue history: {}", e);
+    }
+    let issues = apply_rules(&config.rules, issues);
+    let issues = suppress::filter_suppressed(&config.root, issues);
+    let mut issues = match baseline::load(config) {
+        Ok(baseline) => baseline.filter(issues),
+        Err(e) => {
+            warn!("Ignoring baseline: {}", e);
+            issues
+        }
+    };
+
+    if let Err(e) = history::track(&mut issues, &run.analyzed) {
+        debug!("Failed to update issue history: {}", e);
+    }
+
+    sort_issues(&mut issues, sort_by);
+    Span::current().record("issues", issues.len());
+    Ok(Analysis {
+        issues,
+        risk: run.risk,
+        consensus: run.consensus,
+    })
+}
+
+/// Analyze `diff` once more against the current content of `files`, the docs fixes were
+/// applied to, and return the issues that remain. No search: the files are the context.
+/// Filtered like `analyze`, but not recorded in the issue history.
+pub async fn verify_fixes(config: &Config, diff: &str, files: &[PathBuf]) -> Result<Vec<Issue>> {
+    let diff = &scoped_diff(config, diff);
+    let mut doc_chunks = Vec::new();
+    for file in files {
+        let content = fs::read_to_string(config.resolve(file))?;
+        let name = paths::to_slash(file);
+        doc_chunks.push(llm::DocChunk {
+            kind: DocKind::from_path(&name),
+            file: name,
+            start_line: 1,
+            end_line: content.lines().count().max(1),
+            content,
+        });
+    }
+    if diff.is_empty() || doc_chunks.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let (batches, dropped) = partition_to_budget(
+        doc_chunks,
+        config.docs.max_context_tokens,
+        config.docs.max_batches,
+    );
+    if dropped > 0 {
+        warn!(
+            "Verifying fixes without {} doc file(s) that did not fit the token budget",
+            dropped
+        );
+    }
+    let groups = git::split_diff(diff, MAX_GROUP_DIFF_CHARS);
+    let (raw_issues, _) = analyze_batches(config, &groups, batches, &mut Vec::new()).await?;
+
+    let file_diffs = git::file_diffs(diff);
+    let mut issues: Vec<Issue> = raw_issues
+        .into_iter()
+        .map(|raw| Issue {
+            code_location: symbols::resolve(
+                &file_diffs,
+                raw.code_symbol.as_deref(),
+                &raw.description,
+            ),
+            ..Issue::from(raw)
+        })
+        .collect();
+    excerpt::trim_all(&config.root, &mut issues);
+
+    let issues = filter_confidence(config.docs.min_confidence, issues);
+    let issues = filter_categories(&config.general.disabled_categories, issues);
+    let issues = apply_rules(&config.rules, issues);
+    let issues = suppress::filter_suppressed(&config.root, issues);
+    match baseline::load(config) {
+        Ok(baseline) => Ok(baseline.filter(issues)),
+        Err(e) => {
+            warn!("Ignoring baseline: {}", e);
+            Ok(issues)
         }
     }
 }
 
-/// Run the full analysis pipeline
-pub async fn analyze(config: &Config, diff: &str) -> Result<Vec<Issue>> {
+/// Sort issues for every output. Issues without a confidence are treated as 0.5.
+pub fn sort_issues(issues: &mut [Issue], sort_by: SortBy) {
+    let confidence = |issue: &Issue| issue.confidence.unwrap_or(0.5);
+    // Stable sorts keep the LLM's order among equal issues
+    match sort_by {
+        SortBy::Severity => issues.sort_by(|a, b| {
+            b.is_blocking()
+                .cmp(&a.is_blocking())
+                .then(confidence(b).total_cmp(&confidence(a)))
+        }),
+        SortBy::File => issues.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line))),
+        SortBy::Confidence => issues.sort_by(|a, b| confidence(b).total_cmp(&confidence(a))),
+        SortBy::Line => issues.sort_by(|a, b| a.line.cmp(&b.line).then(a.file.cmp(&b.file))),
+    }
+}
+
+/// Drop issues the model is less than `min` confident about; issues without a confidence are kept
+pub fn filter_confidence(min: f32, issues: Vec<Issue>) -> Vec<Issue> {
+    issues
+        .into_iter()
+        .filter(|issue| {
+            let keep = issue.confidence.is_none_or(|c| c >= min);
+            if !keep {
+                debug!(
+                    "Dropped low-confidence issue in {}:{}",
+                    issue.file.display(),
+                    issue.line
+                );
+            }
+            keep
+        })
+        .collect()
+}
+
+/// Drop issues in the disabled categories (`general.disabled_categories`, see
+/// `feedback::categorize`)
+pub fn filter_categories(disabled: &[String], issues: Vec<Issue>) -> Vec<Issue> {
+    let disabled: Vec<&str> = disabled
+        .iter()
+        .filter_map(|name| feedback::parse_category(name))
+        .collect();
+    if disabled.is_empty() {
+        return issues;
+    }
+    issues
+        .into_iter()
+        .filter(|issue| !disabled.contains(&feedback::categorize(&issue.description)))
+        .collect()
+}
+
+/// Drop issues in `ignore` paths and downgrade issues in `warn_only` paths
+pub fn apply_rules(rules: &RulesConfig, issues: Vec<Issue>) -> Vec<Issue> {
+    let ignore = compile_patterns(&rules.ignore);
+    let warn_only = compile_patterns(&rules.warn_only);
+    let matches = |patterns: &[Pattern], file: &Path| patterns.iter().any(|p| p.matches_path(file));
+    let severity_rules: Vec<(Severity, Expr)> = rules
+        .severity
+        .iter()
+        .filter_map(|rule| {
+            let severity = match rule.severity.as_str() {
+                "warning" => Severity::Warning,
+                _ => Severity::Error,
+            };
+            match Expr::parse(&rule.when, RULE_FIELDS) {
+                Ok(expr) => Some((severity, expr)),
+                Err(e) => {
+                    warn!("Invalid severity rule '{}': {}", rule.when, e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    issues
+        .into_iter()
+        .filter(|issue| !matches(&ignore, &issue.file))
+        .map(|mut issue| {
+            if matches(&warn_only, &issue.file) {
+                issue.severity = Severity::Warning;
+            }
+            let field = |name: &str| rule_field(&issue, name);
+            if let Some((severity, _)) = severity_rules.iter().find(|(_, expr)| expr.eval(&field)) {
+                issue.severity = *severity;
+            }
+            issue
+        })
+        .collect()
+}
+
+/// Issue fields `[[rules.severity]]` conditions may use
+pub const RULE_FIELDS: &[&str] = &[
+    "path",
+    "category",
+    "kind",
+    "corpus",
+    "confidence",
+    "state",
+    "severity",
+    "description",
+    "code_path",
+    "line",
+];
+
+/// Value of one of `RULE_FIELDS` for an issue; `category` is `feedback::categorize` of the
+/// description and `severity` the one set by the corpus and path rules
+fn rule_field(issue: &Issue, name: &str) -> Option<Value> {
+    let text = |s: String| Some(Value::Str(s));
+    match name {
+        "path" => text(paths::to_slash(&issue.file)),
+        "category" => text(feedback::categorize(&issue.description).to_string()),
+        "kind" => text(issue.kind.as_str().to_string()),
+        "corpus" => issue.corpus.clone().map(Value::Str),
+        "confidence" => issue.confidence.map(|c| Value::Num(f64::from(c))),
+        "state" => text(issue.state.to_string()),
+        "severity" => text(issue.severity.to_string()),
+        "description" => text(issue.description.clone()),
+        "code_path" => issue
+            .code_location
+            .as_ref()
+            .map(|location| Value::Str(paths::to_slash(&location.file))),
+        "line" => Some(Value::Num(issue.line as f64)),
+        _ => None,
+    }
+}
+
+fn compile_patterns(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .filter_map(|p| match Pattern::new(&paths::slash_pattern(p)) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                warn!("Invalid rules pattern '{}': {}", p, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Keep the `max` most relevant issues, in their current order, and return how many were
+/// dropped. Dismissed issues rank last and warnings after errors, then issues are ranked by
+/// confidence; issues without a confidence are treated as 0.5. A `max` of 0 keeps everything.
+pub fn cap_issues(issues: &mut Vec<Issue>, max: usize) -> usize {
+    if max == 0 || issues.len() <= max {
+        return 0;
+    }
+
+    let rank = |issue: &Issue| {
+        let dismissed = issue.state == IssueState::Dismissed;
+        let warning = issue.severity != Severity::Error;
+        (dismissed, warning, -issue.confidence.unwrap_or(0.5))
+    };
+    // Stable sort keeps the earlier of equally ranked issues
+    let mut ranked: Vec<usize> = (0..issues.len()).collect();
+    ranked.sort_by(|&a, &b| {
+        rank(&issues[a])
+            .partial_cmp(&rank(&issues[b]))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let kept: HashSet<usize> = ranked.into_iter().take(max).collect();
+
+    let hidden = issues.len() - max;
+    let mut idx = 0;
+    issues.retain(|_| {
+        idx += 1;
+        kept.contains(&(idx - 1))
+    });
+    hidden
+}
+
+/// The part of a diff that is analyzed: files in `diff_include`/`diff_exclude` scope,
+/// without Git LFS pointer updates
+fn scoped_diff(config: &Config, diff: &str) -> String {
+    lfs::strip_pointer_diffs(&git::filter_diff(
+        diff,
+        &config.general.diff_include,
+        &config.general.diff_exclude,
+    ))
+}
+
+/// Route a diff that mostly bumps dependency versions to the dependency check, and analyze
+/// the rest of it, if any, with the full pipeline. Lockfile changes are never analyzed.
+/// What the run records (doc files sent to the LLM, risk scores) is added to `run`.
+async fn analyze_diff(config: &Config, diff: &str, run: &mut Run) -> Result<Vec<Issue>> {
+    let bump = config
+        .general
+        .dependency_analysis
+        .then(|| deps::split(&scoped_diff(config, diff)))
+        .flatten();
+    let Some(bump) = bump else {
+        return run_pipeline(config, diff, run).await;
+    };
+
+    info!(
+        "Diff mostly changes dependencies; checking docs against {} version change(s)",
+        bump.bumps.len()
+    );
+    let mut issues = dependency_issues(config, &bump, run).await?;
+    if !bump.rest.is_empty() {
+        issues.extend(run_pipeline(config, &bump.rest, run).await?);
+    }
+    Ok(issues)
+}
+
+/// Check install instructions, minimum versions and compatibility statements in every corpus
+/// against the versions `bump` changes
+async fn dependency_issues(config: &Config, bump: &BumpDiff, run: &mut Run) -> Result<Vec<Issue>> {
+    if bump.bumps.is_empty() {
+        debug!("No dependency versions changed");
+        return Ok(vec![]);
+    }
+
+    let queries = deps::queries(&bump.bumps);
+    let versions = deps::describe(&bump.bumps);
+    let file_diffs = git::file_diffs(&bump.manifests);
+    let named = !config.docs.corpora.is_empty();
+    let mut issues = Vec::new();
+    for corpus in config.docs.corpora() {
+        let severity = corpus_severity(&corpus)?;
+        let matches = search::search_queries(&config.root, &corpus.docs, &queries).await?;
+        let doc_chunks = search::merge_matches(matches);
+        if doc_chunks.is_empty() {
+            debug!(
+                "No documentation mentions the changed dependencies in {}",
+                corpus.name
+            );
+            continue;
+        }
+
+        let total = doc_chunks.len();
+        let (batches, dropped) = partition_to_budget(
+            doc_chunks,
+            corpus.docs.max_context_tokens,
+            corpus.docs.max_batches,
+        );
+        if dropped > 0 {
+            warn!(
+                "Checking {} of {} doc chunks in {} against dependency changes; {} did not fit \
+                 in {} batch(es) of {} tokens (raise docs.max_batches or docs.max_context_tokens)",
+                total - dropped,
+                total,
+                corpus.name,
+                dropped,
+                batches.len(),
+                corpus.docs.max_context_tokens
+            );
+        }
+
+        record_analyzed(&mut run.analyzed, &batches);
+        for chunks in batches {
+            let raw_issues =
+                llm::analyze_dependencies(config, &versions, &bump.manifests, &chunks).await?;
+            issues.extend(raw_issues.into_iter().map(|raw| Issue {
+                severity,
+                corpus: named.then(|| corpus.name.clone()),
+                code_location: symbols::resolve(
+                    &file_diffs,
+                    raw.code_symbol.as_deref(),
+                    &raw.description,
+                ),
+                ..Issue::from(raw)
+            }));
+        }
+    }
+    excerpt::trim_all(&config.root, &mut issues);
+    Ok(issues)
+}
+
+async fn run_pipeline(config: &Config, diff: &str, run: &mut Run) -> Result<Vec<Issue>> {
+    // Drop files that are out of scope before anything reaches the LLM
+    let diff = &scoped_diff(config, diff);
+
     // Parse the diff
     let parsed = ParsedDiff::parse(diff);
 
@@ -43,109 +585,1366 @@ pub async fn analyze(config: &Config, diff: &str) -> Result<Vec<Issue>> {
     info!("Analyzing changes to {} files", parsed.files.len());
 
     // Set up progress indicator
-    let mut progress = MultiProgress::new(vec![
-        "Generating search queries",
-        "Searching documentation",
-        "Analyzing consistency",
-    ]);
+    let mode = SearchMode::from_config(config)?;
+    let mut progress = MultiProgress::new(match mode {
+        SearchMode::Ripgrep => vec![
+            "Generating search queries",
+            "Searching documentation",
+            "Analyzing consistency",
+        ],
+        SearchMode::Embeddings => vec![
+            "Searching documentation (embeddings)",
+            "Analyzing consistency",
+        ],
+    });
 
-    // Step 1: Generate search queries
-    progress.next_step();
+    let retrieved = match mode {
+        SearchMode::Ripgrep => {
+            // Step 1: Generate search queries
+            progress.next_step();
 
-    let queries = if config.cache.enabled {
-        match cache::get_queries(diff) {
-            Some(cached) => {
-                debug!("Using cached search queries");
+            let (queries, cached) = search_queries(config, diff).await?;
+            if cached {
                 progress.update("using cache");
-                cached
             }
-            None => {
-                debug!("Generating new search queries");
-                let queries = llm::generate_search_queries(config, diff).await?;
-
-                // Cache the queries
-                if let Err(e) = cache::store_queries(diff, &queries) {
-                    debug!("Failed to cache queries: {}", e);
-                }
 
-                queries
+            if queries.is_empty() {
+                debug!("No search queries generated");
+                progress.finish();
+                return Ok(vec![]);
             }
+
+            info!("Generated {} search queries", queries.len());
+
+            // Step 2: Search documentation, each corpus within its own token budget
+            progress.next_step();
+            progress.update(&format!("{} queries", queries.len()));
+            retrieve_docs(config, diff, queries, &progress).await?
+        }
+        SearchMode::Embeddings => {
+            // No queries: doc chunks are ranked by similarity to the diff itself
+            progress.next_step();
+            embeddings::retrieve(config, diff).await?
         }
-    } else {
-        llm::generate_search_queries(config, diff).await?
     };
 
-    if queries.is_empty() {
-        debug!("No search queries generated");
-        progress.finish();
-        return Ok(vec![]);
+    // Score the changed files, so a tight budget is spent on the docs of the riskiest
+    let file_diffs = git::file_diffs(diff);
+    let risks = risk::assess(
+        &file_diffs,
+        &retrieved
+            .iter()
+            .flat_map(|(_, chunks)| chunks)
+            .collect::<Vec<_>>(),
+        &history::drift_counts(),
+    );
+    for r in &risks {
+        debug!(
+            "Drift risk {} for {} ({} API changes, {} doc references, {} past issues)",
+            r.score, r.file, r.api_changes, r.doc_references, r.past_drift
+        );
     }
+    run.risk = risks.clone();
 
-    info!("Generated {} search queries", queries.len());
+    let mut selections = Vec::new();
+    for (corpus, doc_chunks) in retrieved {
+        let severity = corpus_severity(&corpus)?;
 
-    // Step 2: Search documentation
-    progress.next_step();
-    progress.update(&format!("{} queries", queries.len()));
+        if doc_chunks.is_empty() {
+            debug!("No relevant documentation found in {}", corpus.name);
+            continue;
+        }
 
-    let doc_chunks = search::find_relevant_docs(&config.docs, &queries).await?;
+        info!(
+            "Found {} documentation chunks in {}",
+            doc_chunks.len(),
+            corpus.name
+        );
 
-    if doc_chunks.is_empty() {
+        // Split into as many calls as the token budget needs
+        let total = doc_chunks.len();
+        let (batches, dropped) = match partition_to_budget(
+            doc_chunks.clone(),
+            corpus.docs.max_context_tokens,
+            corpus.docs.max_batches,
+        ) {
+            (_, dropped) if dropped > 0 => partition_to_budget(
+                risk::prioritize(doc_chunks, &file_diffs, &risks),
+                corpus.docs.max_context_tokens,
+                corpus.docs.max_batches,
+            ),
+            fit => fit,
+        };
+        if dropped > 0 {
+            warn!(
+                "Analyzing {} of {} doc chunks in {}; {} did not fit in {} batch(es) of {} tokens \
+                 (raise docs.max_batches or docs.max_context_tokens)",
+                total - dropped,
+                total,
+                corpus.name,
+                dropped,
+                batches.len(),
+                corpus.docs.max_context_tokens
+            );
+        }
+        selections.push((corpus, severity, batches));
+    }
+
+    if selections.is_empty() {
         debug!("No relevant documentation found");
         progress.finish();
         return Ok(vec![]);
     }
 
-    info!("Found {} documentation chunks", doc_chunks.len());
-
-    // Truncate if over token budget
-    let doc_chunks = truncate_to_budget(doc_chunks, config.docs.max_context_tokens);
-
     // Step 3: Analyze consistency
     progress.next_step();
-    progress.update(&format!("{} doc chunks", doc_chunks.len()));
+    let chunk_count: usize = selections
+        .iter()
+        .flat_map(|(_, _, batches)| batches)
+        .map(Vec::len)
+        .sum();
+    let batch_count: usize = selections.iter().map(|(_, _, batches)| batches.len()).sum();
+    let mut detail = format!("{} doc chunks", chunk_count);
+    progress.update(&detail);
+    if batch_count > selections.len() {
+        info!(
+            "Analyzing {} doc chunks in {} batches",
+            chunk_count, batch_count
+        );
+        detail = format!("{} doc chunks in {} batches", chunk_count, batch_count);
+        progress.update(&detail);
+    }
+
+    let groups = git::split_diff(diff, MAX_GROUP_DIFF_CHARS);
+    if groups.len() > 1 {
+        info!(
+            "Analyzing {} files in {} groups",
+            parsed.files.len(),
+            groups.len()
+        );
+        detail = format!("{} doc chunks, {} groups", chunk_count, groups.len());
+        progress.update(&detail);
+    }
 
-    let raw_issues = llm::analyze_consistency(config, diff, &doc_chunks).await?;
+    // Corpora are analyzed separately so one can't crowd another out of the context window
+    let named = !config.docs.corpora.is_empty();
+    let mut issues = Vec::new();
+    let mut all_cached = true;
+    let live = stream::Live::new();
+    for (corpus, severity, batches) in selections {
+        record_analyzed(&mut run.analyzed, &batches);
+        let analysis = stream::scope(
+            live.clone(),
+            analyze_batches(config, &groups, batches, &mut run.consensus),
+        );
+        let (raw_issues, cached) = if config.llm.stream {
+            with_live_progress(&progress, &detail, &live, analysis).await?
+        } else {
+            analysis.await?
+        };
+        all_cached &= cached;
+        issues.extend(raw_issues.into_iter().map(|raw| Issue {
+            severity,
+            corpus: named.then(|| corpus.name.clone()),
+            code_location: symbols::resolve(
+                &file_diffs,
+                raw.code_symbol.as_deref(),
+                &raw.description,
+            ),
+            ..Issue::from(raw)
+        }));
+    }
+    if all_cached {
+        progress.update("using cache");
+    }
 
     progress.finish();
 
-    if raw_issues.is_empty() {
+    if issues.is_empty() {
         return Ok(vec![]);
     }
 
-    info!("Found {} potential issues", raw_issues.len());
+    info!("Found {} potential issues", issues.len());
 
-    // Convert to Issue structs
-    let issues: Vec<Issue> = raw_issues.into_iter().map(Issue::from).collect();
+    excerpt::trim_all(&config.root, &mut issues);
 
     Ok(issues)
 }
 
-/// Truncate document chunks to fit within token budget
-fn truncate_to_budget(mut chunks: Vec<llm::DocChunk>, max_tokens: usize) -> Vec<llm::DocChunk> {
+/// Add the doc files of `batches` to the docs analyzed in this run
+fn record_analyzed(analyzed: &mut HashSet<PathBuf>, batches: &[Vec<llm::DocChunk>]) {
+    analyzed.extend(
+        batches
+            .iter()
+            .flatten()
+            .map(|chunk| paths::normalize(Path::new(&chunk.file))),
+    );
+}
+
+/// Search every corpus for `queries`. While too few queries match any doc, ask the model for
+/// refined queries, at most `docs.refine_rounds` times, and search those too.
+async fn retrieve_docs(
+    config: &Config,
+    diff: &str,
+    queries: Vec<String>,
+    progress: &MultiProgress,
+) -> Result<Vec<(Corpus, Vec<llm::DocChunk>)>> {
+    let corpora = config.docs.corpora();
+    let mut matches = Vec::new();
+    for corpus in &corpora {
+        matches.push(search::search_queries(&config.root, &corpus.docs, &queries).await?);
+    }
+
+    // Heuristic queries are meant to work offline, so they are never refined by the LLM
+    let rounds = match QueryMode::from_config(config)? {
+        QueryMode::Llm => config.docs.refine_rounds,
+        QueryMode::Heuristic => 0,
+    };
+    let mut tried = queries;
+    for round in 1..=rounds {
+        let Some((matched, missed)) = weak_search(&tried, matches.iter().flatten()) else {
+            break;
+        };
+
+        info!(
+            "{} of {} search queries matched documentation; refining (round {})",
+            matched.len(),
+            tried.len(),
+            round
+        );
+        progress.update(&format!("refining queries (round {})", round));
+        let refined = match llm::refine_search_queries(config, diff, &missed, &matched).await {
+            Ok(refined) if !refined.is_empty() => refined,
+            Ok(_) => break,
+            Err(e) => {
+                warn!("Failed to refine search queries: {}", e);
+                break;
+            }
+        };
+        debug!("Refined search queries: {:?}", refined);
+
+        for (corpus, corpus_matches) in corpora.iter().zip(&mut matches) {
+            corpus_matches
+                .extend(search::search_queries(&config.root, &corpus.docs, &refined).await?);
+        }
+        tried.extend(refined);
+    }
+
+    Ok(corpora
+        .into_iter()
+        .zip(matches)
+        .map(|(corpus, matches)| (corpus, search::merge_matches(matches)))
+        .collect())
+}
+
+/// The queries that matched docs and those that did not, when too few matched
+fn weak_search<'a>(
+    tried: &[String],
+    matches: impl Iterator<Item = &'a search::QueryMatches> + Clone,
+) -> Option<(Vec<String>, Vec<String>)> {
+    let (matched, missed): (Vec<String>, Vec<String>) = tried.iter().cloned().partition(|q| {
+        matches
+            .clone()
+            .any(|m| &m.query == q && !m.chunks.is_empty())
+    });
+    let strong =
+        !matched.is_empty() && matched.len() as f32 >= tried.len() as f32 * MIN_QUERY_HIT_RATIO;
+    (!strong).then_some((matched, missed))
+}
+
+/// Severity of issues in a corpus, from its `severity` setting
+pub(crate) fn corpus_severity(corpus: &Corpus) -> Result<Severity> {
+    match corpus.severity.as_str() {
+        "error" => Ok(Severity::Error),
+        "warning" => Ok(Severity::Warning),
+        other => Err(DriftcheckError::ConfigInvalid(format!(
+            "docs.{}.severity must be \"error\" or \"warning\", got {:?}",
+            corpus.name, other
+        ))),
+    }
+}
+
+/// Generate search queries for the diff, using the cache when enabled.
+/// Queries are cached per changed file, so a new commit only regenerates the files it touches.
+/// Returns the queries and whether they all came from the cache.
+#[tracing::instrument(name = "queries", skip_all, fields(queries = Empty, cached = Empty))]
+async fn search_queries(config: &Config, diff: &str) -> Result<(Vec<String>, bool)> {
+    if QueryMode::from_config(config)? == QueryMode::Heuristic {
+        let queries = heuristic_diff_queries(diff);
+        Span::current().record("queries", queries.len());
+        return Ok((queries, false));
+    }
+    if !config.cache.enabled {
+        return Ok((llm::generate_search_queries(config, diff).await?, false));
+    }
+
+    let files = git::file_diffs(diff);
+    let mut per_file: Vec<Option<Vec<String>>> = files
+        .iter()
+        .map(|(path, file_diff)| cache::get_queries(path, file_diff))
+        .collect();
+    let missing: Vec<usize> = (0..files.len())
+        .filter(|&i| per_file[i].is_none())
+        .collect();
+
+    if missing.is_empty() {
+        debug!("Using cached search queries");
+    } else {
+        debug!(
+            "Generating search queries for {} of {} files",
+            missing.len(),
+            files.len()
+        );
+
+        let config = Arc::new(config.clone());
+        let permits = Arc::new(Semaphore::new(config.llm.max_concurrency.max(1)));
+        let handles: Vec<_> = missing
+            .iter()
+            .map(|&i| {
+                let config = Arc::clone(&config);
+                let permits = Arc::clone(&permits);
+                let file_diff = files[i].1.clone();
+                tokio::spawn(
+                    async move {
+                        let _permit = permits.acquire_owned().await.ok();
+                        llm::generate_search_queries(&config, &file_diff).await
+                    }
+                    .in_current_span(),
+                )
+            })
+            .collect();
+
+        for (&i, handle) in missing.iter().zip(handles) {
+            let queries = handle
+                .await
+                .map_err(|e| DriftcheckError::LlmError(format!("query task failed: {}", e)))??;
+            let (path, file_diff) = &files[i];
+            if let Err(e) = cache::store_queries(path, file_diff, &queries) {
+                debug!("Failed to cache queries: {}", e);
+            }
+            per_file[i] = Some(queries);
+        }
+    }
+
+    // Merge in diff order, dropping queries shared between files
+    let mut queries: Vec<String> = Vec::new();
+    for query in per_file.into_iter().flatten().flatten() {
+        if !queries.contains(&query) {
+            queries.push(query);
+        }
+    }
+
+    Span::current()
+        .record("queries", queries.len())
+        .record("cached", missing.is_empty());
+    Ok((queries, missing.is_empty()))
+}
+
+/// Run the consistency analysis for every diff group against every doc batch, at most
+/// `llm.max_concurrency` calls at a time.
+/// Returns the merged issues and whether every call came from the cache; with
+/// `[llm.consensus]`, what each provider found is added to `providers`.
+#[tracing::instrument(
+    name = "analysis",
+    skip_all,
+    fields(groups = groups.len(), batches = batches.len(), issues = Empty)
+)]
+async fn analyze_batches(
+    config: &Config,
+    groups: &[String],
+    batches: Vec<Vec<llm::DocChunk>>,
+    providers: &mut Vec<ProviderIssues>,
+) -> Result<(Vec<RawIssue>, bool)> {
+    if let ([diff], [doc_chunks]) = (groups, batches.as_slice()) {
+        let (issues, cached, found) = consensus_issues(config, diff, doc_chunks).await?;
+        add_provider_issues(providers, found);
+        Span::current().record("issues", issues.len());
+        return Ok((issues, cached));
+    }
+
+    let config = Arc::new(config.clone());
+    let permits = Arc::new(Semaphore::new(config.llm.max_concurrency.max(1)));
+
+    let mut handles = Vec::new();
+    for doc_chunks in batches.into_iter().map(Arc::new) {
+        for diff in groups {
+            let config = Arc::clone(&config);
+            let doc_chunks = Arc::clone(&doc_chunks);
+            let permits = Arc::clone(&permits);
+            let diff = diff.clone();
+
+            handles.push(tokio::spawn(
+                stream::scope_opt(stream::current(), async move {
+                    let _permit = permits.acquire_owned().await.ok();
+                    consensus_issues(&config, &diff, &doc_chunks).await
+                })
+                .in_current_span(),
+            ));
+        }
+    }
+
+    // Await in spawn order so the merged issues are stable across runs
+    let mut issues = Vec::new();
+    let mut all_cached = true;
+    for handle in handles {
+        let (group_issues, cached, found) = handle
+            .await
+            .map_err(|e| DriftcheckError::LlmError(format!("analysis task failed: {}", e)))??;
+        add_provider_issues(providers, found);
+        issues.extend(group_issues);
+        all_cached &= cached;
+    }
+
+    let issues = dedup_issues(issues);
+    Span::current().record("issues", issues.len());
+    Ok((issues, all_cached))
+}
+
+/// Add what each provider found in one analysis call to `providers`
+fn add_provider_issues(providers: &mut Vec<ProviderIssues>, found: Vec<ProviderIssues>) {
+    for result in found {
+        consensus::record(providers, &result.provider, &result.model, &result.issues);
+    }
+}
+
+/// Await `future`, adding the characters streamed into `live` so far to the step's `detail`
+async fn with_live_progress<F: Future>(
+    progress: &MultiProgress,
+    detail: &str,
+    live: &stream::Live,
+    future: F,
+) -> F::Output {
+    tokio::pin!(future);
+    let mut ticker = tokio::time::interval(Duration::from_millis(200));
+    loop {
+        tokio::select! {
+            output = &mut future => return output,
+            _ = ticker.tick() => {
+                let received = live.received();
+                if received > 0 {
+                    progress.update(&format!("{}, {} chars received", detail, received));
+                }
+            }
+        }
+    }
+}
+
+/// Merge issues reported by several groups for the same doc excerpt, keeping the most confident
+fn dedup_issues(issues: Vec<RawIssue>) -> Vec<RawIssue> {
+    let mut merged: Vec<RawIssue> = Vec::new();
+    for issue in issues {
+        let excerpt = normalize_excerpt(&issue.doc_excerpt);
+        let existing = merged.iter_mut().find(|m| {
+            m.file == issue.file
+                && if excerpt.is_empty() {
+                    m.line == issue.line && m.description == issue.description
+                } else {
+                    normalize_excerpt(&m.doc_excerpt) == excerpt
+                }
+        });
+        match existing {
+            Some(existing) => {
+                if issue.confidence.unwrap_or(0.0) > existing.confidence.unwrap_or(0.0) {
+                    *existing = issue;
+                }
+            }
+            None => merged.push(issue),
+        }
+    }
+    merged
+}
+
+fn normalize_excerpt(excerpt: &str) -> String {
+    excerpt.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Run the consistency analysis, with the `[llm.consensus]` provider in parallel when one is
+/// configured, and merge what the two found. Also returns what each provider found.
+async fn consensus_issues(
+    config: &Config,
+    diff: &str,
+    doc_chunks: &[llm::DocChunk],
+) -> Result<(Vec<RawIssue>, bool, Vec<ProviderIssues>)> {
+    let (Some(consensus), Some(llm)) = (&config.llm.consensus, config.llm.consensus_llm()) else {
+        let (issues, cached) = consistency_issues(config, diff, doc_chunks).await?;
+        return Ok((issues, cached, Vec::new()));
+    };
+    let mode = consensus.mode()?;
+    let second = Config {
+        llm,
+        ..config.clone()
+    };
+
+    let (first, other) = tokio::join!(
+        consistency_issues(config, diff, doc_chunks),
+        consistency_issues(&second, diff, doc_chunks)
+    );
+    let ((first, first_cached), (other, other_cached)) = (first?, other?);
+    let first_llm = config.llm.for_task(LlmTask::Analysis);
+    let mut providers = Vec::new();
+    consensus::record(
+        &mut providers,
+        &first_llm.provider,
+        &first_llm.model,
+        &first,
+    );
+    consensus::record(
+        &mut providers,
+        &second.llm.provider,
+        &second.llm.model,
+        &other,
+    );
+    Ok((
+        consensus::merge(first, other, mode),
+        first_cached && other_cached,
+        providers,
+    ))
+}
+
+/// Run the consistency analysis, using the cache when enabled.
+/// Returns the issues and whether they came from the cache.
+async fn consistency_issues(
+    config: &Config,
+    diff: &str,
+    doc_chunks: &[llm::DocChunk],
+) -> Result<(Vec<RawIssue>, bool)> {
+    if !config.cache.enabled {
+        return Ok((
+            analyze_within_context(config, diff, doc_chunks).await?,
+            false,
+        ));
+    }
+
+    let system_prompt = config.prompts.render(&config.prompts.analysis);
+    if let Some(cached) = cache::get_analysis(
+        diff,
+        doc_chunks,
+        &system_prompt,
+        config.llm.task_model(LlmTask::Analysis),
+    ) {
+        debug!("Using cached analysis");
+        return Ok((cached, true));
+    }
+
+    let issues = analyze_within_context(config, diff, doc_chunks).await?;
+
+    if let Err(e) = cache::store_analysis(
+        diff,
+        doc_chunks,
+        &system_prompt,
+        config.llm.task_model(LlmTask::Analysis),
+        &issues,
+    ) {
+        debug!("Failed to cache analysis: {}", e);
+    }
+
+    Ok((issues, false))
+}
+
+/// `llm::analyze_consistency`, halving the doc chunk budget and retrying while the request
+/// exceeds the model's context window
+async fn analyze_within_context(
+    config: &Config,
+    diff: &str,
+    doc_chunks: &[llm::DocChunk],
+) -> Result<Vec<RawIssue>> {
+    let mut chunks = doc_chunks.to_vec();
+    loop {
+        match llm::analyze_consistency(config, diff, &chunks).await {
+            Err(DriftcheckError::LlmContextExceeded(message)) => {
+                let tokens = chunks
+                    .iter()
+                    .map(|c| estimate_tokens(&c.content))
+                    .sum::<usize>()
+                    / 2;
+                if tokens < MIN_RETRY_CONTEXT_TOKENS {
+                    return Err(DriftcheckError::LlmContextExceeded(message));
+                }
+                let (batches, dropped) = partition_to_budget(chunks, tokens, 1);
+                warn!(
+                    "Context window exceeded; retrying with {} tokens of docs ({} chunks dropped)",
+                    tokens, dropped
+                );
+                chunks = batches.into_iter().next().unwrap_or_default();
+            }
+            result => return result,
+        }
+    }
+}
+
+/// What `cache warm` prepared for a diff
+#[derive(Debug)]
+pub struct Warmup {
+    pub changed_files: usize,
+    pub queries: usize,
+    /// The queries were already cached, so no LLM call was made
+    pub already_cached: bool,
+    pub doc_files: usize,
+    /// Doc chunks in the embedding index, in `search.mode = "embeddings"`
+    pub indexed_chunks: Option<usize>,
+}
+
+/// Generate and cache search queries for a diff ahead of a later check.
+/// Uses the same diff filtering as `analyze`, so the cache keys match.
+/// In embeddings mode, builds the doc embedding index instead.
+pub async fn warm(config: &Config, diff: &str) -> Result<Warmup> {
+    if !config.cache.enabled {
+        return Err(DriftcheckError::ConfigInvalid(
+            "cache.enabled is false, so there is nothing to warm".to_string(),
+        ));
+    }
+    Config::ensure_writable("warming the cache")?;
+
+    let diff = &scoped_diff(config, diff);
+    let parsed = ParsedDiff::parse(diff);
+
+    let mut warmup = Warmup {
+        changed_files: parsed.files.len(),
+        queries: 0,
+        already_cached: false,
+        doc_files: 0,
+        indexed_chunks: None,
+    };
+    if SearchMode::from_config(config)? == SearchMode::Embeddings {
+        warmup.indexed_chunks = Some(embeddings::build_index(config).await?);
+        return Ok(warmup);
+    }
+    if parsed.files.is_empty() {
+        return Ok(warmup);
+    }
+
+    let (queries, cached) = search_queries(config, diff).await?;
+    warmup.queries = queries.len();
+    warmup.already_cached = cached;
+    let mut doc_files = HashSet::new();
+    for corpus in config.docs.corpora() {
+        doc_files.extend(search::expand_doc_paths(
+            &config.root,
+            &corpus.docs.paths,
+            &corpus.docs.ignore,
+        )?);
+    }
+    warmup.doc_files = doc_files.len();
+
+    Ok(warmup)
+}
+
+/// Doc files matched by a single search query
+#[derive(Debug)]
+pub struct QueryExplanation {
+    pub query: String,
+    /// Matched files with the number of matching chunks in each
+    pub files: Vec<(String, usize)>,
+}
+
+/// A merged doc chunk and the analysis batch it was placed in
+#[derive(Debug)]
+pub struct ChunkExplanation {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: DocKind,
+    pub tokens: usize,
+    /// 1-based batch number, or `None` when the chunk did not fit in `docs.max_batches`
+    pub batch: Option<usize>,
+}
+
+/// Merged chunks of one corpus, batched within its own token budget
+#[derive(Debug)]
+pub struct CorpusExplanation {
+    pub name: String,
+    pub chunks: Vec<ChunkExplanation>,
+    pub max_context_tokens: usize,
+    pub batches: usize,
+}
+
+/// How documentation was selected for a diff, without running the analysis
+#[derive(Debug)]
+pub struct Explanation {
+    pub changed_files: usize,
+    pub queries_cached: bool,
+    pub queries: Vec<QueryExplanation>,
+    pub corpora: Vec<CorpusExplanation>,
+}
+
+/// Run query generation and doc search, then report which chunks would be sent to the LLM
+pub async fn explain(config: &Config, diff: &str) -> Result<Explanation> {
+    let diff = &scoped_diff(config, diff);
+    let parsed = ParsedDiff::parse(diff);
+
+    let mut explanation = Explanation {
+        changed_files: parsed.files.len(),
+        queries_cached: false,
+        queries: vec![],
+        corpora: vec![],
+    };
+    if parsed.files.is_empty() {
+        return Ok(explanation);
+    }
+
+    let (queries, cached) = search_queries(config, diff).await?;
+    explanation.queries_cached = cached;
+    explain_search(config, &queries, &mut explanation).await?;
+    Ok(explanation)
+}
+
+/// Search every corpus for `queries` and record the matches and selected chunks
+async fn explain_search(
+    config: &Config,
+    queries: &[String],
+    explanation: &mut Explanation,
+) -> Result<()> {
+    let mut matches = Vec::new();
+    for corpus in config.docs.corpora() {
+        let corpus_matches = search::search_queries(&config.root, &corpus.docs, queries).await?;
+        matches.extend(corpus_matches.iter().cloned());
+        explanation
+            .corpora
+            .push(explain_chunks(&corpus, corpus_matches));
+    }
+
+    explanation.queries = queries
+        .iter()
+        .map(|query| {
+            let mut files: Vec<(String, usize)> = Vec::new();
+            let chunks = matches
+                .iter()
+                .filter(|m| &m.query == query)
+                .flat_map(|m| &m.chunks);
+            for chunk in chunks {
+                match files.iter_mut().find(|(f, _)| *f == chunk.file) {
+                    Some((_, count)) => *count += 1,
+                    None => files.push((chunk.file.clone(), 1)),
+                }
+            }
+            files.sort();
+            QueryExplanation {
+                query: query.clone(),
+                files,
+            }
+        })
+        .collect();
+    Ok(())
+}
+
+/// LLM calls of one pipeline stage that a check would make
+#[derive(Debug)]
+pub struct StageEstimate {
+    pub stage: &'static str,
+    /// Prompt template the calls use (`[prompts]` key)
+    pub prompt: &'static str,
+    pub calls: usize,
+    /// Calls the cache would answer
+    pub cached: usize,
+    /// Estimated input tokens of the calls sent to the LLM
+    pub tokens: usize,
+    /// Only made when the search finds too little, so `calls` is a maximum
+    pub conditional: bool,
+}
+
+/// What a check would send to the LLM, from `check --dry-run`
+#[derive(Debug)]
+pub struct DryRun {
+    pub explanation: Explanation,
+    /// Changed files whose search queries were guessed from the diff because none were
+    /// cached; the model picks its own in a real run
+    pub heuristic_files: usize,
+    /// Doc search was skipped because `search.mode = "embeddings"` needs API calls
+    pub embeddings: bool,
+    pub stages: Vec<StageEstimate>,
+}
+
+/// Parse and search like `analyze`, then estimate the LLM calls it would make without
+/// making any: uncached search queries are guessed from identifiers in the diff.
+pub async fn dry_run(config: &Config, diff: &str) -> Result<DryRun> {
+    let diff = &scoped_diff(config, diff);
+    let mut dry_run = DryRun {
+        explanation: Explanation {
+            changed_files: ParsedDiff::parse(diff).files.len(),
+            queries_cached: false,
+            queries: vec![],
+            corpora: vec![],
+        },
+        heuristic_files: 0,
+        embeddings: SearchMode::from_config(config)? == SearchMode::Embeddings,
+        stages: vec![],
+    };
+    // Ranking docs by embeddings takes API calls, so there is nothing to show
+    if dry_run.explanation.changed_files == 0 || dry_run.embeddings {
+        return Ok(dry_run);
+    }
+
+    if QueryMode::from_config(config)? == QueryMode::Heuristic {
+        let queries = heuristic_diff_queries(diff);
+        explain_search(config, &queries, &mut dry_run.explanation).await?;
+        dry_run
+            .stages
+            .push(analysis_estimate(config, diff, &queries).await?);
+        return Ok(dry_run);
+    }
+
+    // Search queries: from the cache, else guessed; one call per uncached file, or one for
+    // the whole diff without the cache
+    let query_prompt = config.prompts.render(&config.prompts.search_queries);
+    let mut queries: Vec<String> = Vec::new();
+    let mut stage = StageEstimate {
+        stage: "search queries",
+        prompt: "search_queries",
+        calls: 0,
+        cached: 0,
+        tokens: 0,
+        conditional: false,
+    };
+    for (path, file_diff) in git::file_diffs(diff) {
+        let cached = config
+            .cache
+            .enabled
+            .then(|| cache::get_queries(&path, &file_diff))
+            .flatten();
+        if cached.is_some() {
+            stage.cached += 1;
+        } else {
+            dry_run.heuristic_files += 1;
+            if config.cache.enabled {
+                stage.tokens += estimate_tokens(&query_prompt) + estimate_tokens(&file_diff);
+            }
+        }
+        let file_queries = cached.unwrap_or_else(|| heuristic_queries(&file_diff));
+        for query in file_queries {
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+        stage.calls += 1;
+    }
+    if !config.cache.enabled {
+        stage.calls = 1;
+        stage.tokens = estimate_tokens(&query_prompt) + estimate_tokens(diff);
+    }
+    dry_run.explanation.queries_cached = dry_run.heuristic_files == 0;
+    dry_run.stages.push(stage);
+
+    explain_search(config, &queries, &mut dry_run.explanation).await?;
+
+    if config.docs.refine_rounds > 0 {
+        let refine_tokens = estimate_tokens(&query_prompt) + estimate_tokens(diff);
+        dry_run.stages.push(StageEstimate {
+            stage: "query refinement",
+            prompt: "search_queries",
+            calls: config.docs.refine_rounds,
+            cached: 0,
+            tokens: config.docs.refine_rounds * refine_tokens,
+            conditional: true,
+        });
+    }
+
+    dry_run
+        .stages
+        .push(analysis_estimate(config, diff, &queries).await?);
+
+    Ok(dry_run)
+}
+
+/// Estimated analysis calls: every diff group against every batch of every corpus
+async fn analysis_estimate(
+    config: &Config,
+    diff: &str,
+    queries: &[String],
+) -> Result<StageEstimate> {
+    let analysis_prompt = config.prompts.render(&config.prompts.analysis);
+    let groups = git::split_diff(diff, MAX_GROUP_DIFF_CHARS);
+    let mut stage = StageEstimate {
+        stage: "analysis",
+        prompt: "analysis",
+        calls: 0,
+        cached: 0,
+        tokens: 0,
+        conditional: false,
+    };
+    for corpus in config.docs.corpora() {
+        let matches = search::search_queries(&config.root, &corpus.docs, queries).await?;
+        let (batches, _) = partition_to_budget(
+            search::merge_matches(matches),
+            corpus.docs.max_context_tokens,
+            corpus.docs.max_batches,
+        );
+        for batch in &batches {
+            for group in &groups {
+                stage.calls += 1;
+                let cached = config.cache.enabled
+                    && cache::get_analysis(
+                        group,
+                        batch,
+                        &analysis_prompt,
+                        config.llm.task_model(LlmTask::Analysis),
+                    )
+                    .is_some();
+                if cached {
+                    stage.cached += 1;
+                } else {
+                    stage.tokens += estimate_tokens(&analysis_prompt)
+                        + estimate_tokens(&llm::consistency_message(group, batch));
+                }
+            }
+        }
+    }
+    Ok(stage)
+}
+
+/// `heuristic_queries` of every file in `diff`, without duplicates
+fn heuristic_diff_queries(diff: &str) -> Vec<String> {
+    let mut queries: Vec<String> = Vec::new();
+    for (_, file_diff) in git::file_diffs(diff) {
+        for query in heuristic_queries(&file_diff) {
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+    }
+    queries
+}
+
+/// Search queries guessed from a file's diff without the LLM: names defined and CLI flags
+/// on changed lines, quoted identifiers, keys and sections of config files, and headings of
+/// Markdown files
+fn heuristic_queries(file_diff: &str) -> Vec<String> {
+    static NAME: OnceLock<Regex> = OnceLock::new();
+    static CONFIG_KEY: OnceLock<Regex> = OnceLock::new();
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    let name = NAME.get_or_init(|| {
+        Regex::new(
+            r#"\b(?:fn|def|class|function|func|struct|enum|trait|interface|type|const|static|let|var)\s+([A-Za-z_][A-Za-z0-9_]{3,})|(--[a-z][a-z0-9-]+)|["'`]([A-Za-z][A-Za-z0-9]*(?:[_.-][A-Za-z0-9]+)+)["'`]"#,
+        )
+        .unwrap()
+    });
+    let config_key = CONFIG_KEY.get_or_init(|| {
+        Regex::new(r"^\s*(?:-\s+)?\[*([A-Za-z_][A-Za-z0-9_.-]{2,})(?:\]+\s*$|\s*[=:])").unwrap()
+    });
+    let heading = HEADING.get_or_init(|| Regex::new(r"^#{1,6}\s+(.*?)[\s#]*$").unwrap());
+
+    let path = file_diff
+        .lines()
+        .find_map(|line| line.strip_prefix("+++ "))
+        .map(|path| path.strip_prefix("b/").unwrap_or(path))
+        .unwrap_or_default();
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .unwrap_or_default();
+    let is_config = matches!(
+        extension,
+        "toml" | "yaml" | "yml" | "ini" | "cfg" | "conf" | "properties" | "env"
+    );
+    let is_markdown = DocKind::from_path(path) == DocKind::Markdown;
+
+    let mut queries: Vec<String> = Vec::new();
+    let mut add = |found: &str| {
+        let query = regex::escape(found.trim());
+        if !query.is_empty() && !queries.contains(&query) {
+            queries.push(query);
+        }
+    };
+    let changed = file_diff
+        .lines()
+        .filter(|line| !line.starts_with("+++") && !line.starts_with("---"))
+        .filter_map(|line| line.strip_prefix('+').or_else(|| line.strip_prefix('-')));
+    for line in changed {
+        if is_markdown {
+            if let Some(captures) = heading.captures(line) {
+                add(&captures[1]);
+                continue;
+            }
+        }
+        if is_config {
+            if let Some(captures) = config_key.captures(line) {
+                add(&captures[1]);
+            }
+        }
+        for captures in name.captures_iter(line) {
+            if let Some(found) = captures.iter().skip(1).flatten().next() {
+                add(found.as_str());
+            }
+        }
+    }
+    queries.truncate(MAX_HEURISTIC_QUERIES);
+    queries
+}
+
+/// Merge one corpus's matches and assign chunks to the batches they are analyzed in
+fn explain_chunks(corpus: &Corpus, matches: Vec<search::QueryMatches>) -> CorpusExplanation {
+    let chunks = search::merge_matches(matches);
+    let (batches, _) = partition_to_budget(
+        chunks.clone(),
+        corpus.docs.max_context_tokens,
+        corpus.docs.max_batches,
+    );
+    CorpusExplanation {
+        name: corpus.name.clone(),
+        max_context_tokens: corpus.docs.max_context_tokens,
+        batches: batches.len(),
+        chunks: chunks
+            .into_iter()
+            .map(|chunk| {
+                let kept = batches.iter().enumerate().find_map(|(i, batch)| {
+                    batch
+                        .iter()
+                        .find(|c| c.file == chunk.file && c.start_line == chunk.start_line)
+                        .map(|c| (i + 1, c))
+                });
+                ChunkExplanation {
+                    tokens: estimate_tokens(&kept.map_or(&chunk, |(_, c)| c).content),
+                    batch: kept.map(|(batch, _)| batch),
+                    file: chunk.file,
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    kind: chunk.kind,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Rough estimate: 4 chars per token
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Split document chunks into at most `max_batches` batches that each fit the token budget.
+/// Returns the batches and the number of chunks that did not fit in any of them.
+pub fn partition_to_budget(
+    mut chunks: Vec<llm::DocChunk>,
+    max_tokens: usize,
+    max_batches: usize,
+) -> (Vec<Vec<llm::DocChunk>>, usize) {
     // Rough estimate: 4 chars per token
     let chars_budget = max_tokens * 4;
-    let mut total_chars = 0;
-    let mut result = Vec::new();
+    let mut batches: Vec<Vec<llm::DocChunk>> = Vec::new();
+    let mut batch_chars = 0;
 
     // Sort by relevance (for now, just by size - smaller chunks are more focused)
     chunks.sort_by_key(|c| c.content.len());
 
+    let total = chunks.len();
+    let mut placed = 0;
     for chunk in chunks {
         let chunk_chars = chunk.content.len();
-        if total_chars + chunk_chars > chars_budget {
-            // Truncate this chunk if it's the first one
-            if result.is_empty() {
-                let truncated_content = chunk.content.chars().take(chars_budget).collect();
-                result.push(llm::DocChunk {
-                    content: truncated_content,
-                    ..chunk
-                });
+        let full = batches.len() >= max_batches.max(1);
+        match batches.last_mut() {
+            Some(batch) if batch_chars + chunk_chars <= chars_budget => {
+                batch_chars += chunk_chars;
+                batch.push(chunk);
+            }
+            _ if full => break,
+            _ => {
+                // A chunk larger than the whole budget is truncated into a batch of its own
+                let content: String = chunk.content.chars().take(chars_budget).collect();
+                batch_chars = content.len();
+                batches.push(vec![llm::DocChunk { content, ..chunk }]);
             }
-            break;
         }
-        total_chars += chunk_chars;
-        result.push(chunk);
+        placed += 1;
+    }
+
+    (batches, total - placed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(description: &str, confidence: Option<f32>) -> Issue {
+        issue_in("README.md", description, confidence)
+    }
+
+    fn issue_in(file: &str, description: &str, confidence: Option<f32>) -> Issue {
+        Issue::from(RawIssue {
+            file: file.to_string(),
+            line: 1,
+            description: description.to_string(),
+            doc_excerpt: String::new(),
+            suggested_fix: None,
+            confidence,
+            code_symbol: None,
+        })
+    }
+
+    #[test]
+    fn test_cap_issues_keeps_most_confident() {
+        let mut issues = vec![
+            issue("low", Some(0.2)),
+            issue("unknown", None),
+            issue("high", Some(0.9)),
+            issue("dismissed", Some(1.0)),
+        ];
+        issues[3].state = IssueState::Dismissed;
+
+        let hidden = cap_issues(&mut issues, 2);
+        assert_eq!(hidden, 2);
+        // The kept issues stay in their `sort_by` order
+        let kept: Vec<_> = issues.iter().map(|i| i.description.as_str()).collect();
+        assert_eq!(kept, vec!["unknown", "high"]);
+    }
+
+    #[test]
+    fn test_cap_issues_keeps_errors_before_warnings() {
+        let mut issues = vec![issue("warning", Some(0.95)), issue("error", Some(0.4))];
+        issues[0].severity = Severity::Warning;
+
+        assert_eq!(cap_issues(&mut issues, 1), 1);
+        assert_eq!(issues[0].description, "error");
+    }
+
+    #[test]
+    fn test_sort_issues() {
+        let mut issues = vec![
+            issue_in("docs/b.md", "warning", Some(0.9)),
+            issue_in("docs/a.md", "low", Some(0.2)),
+            issue_in("README.md", "high", Some(0.8)),
+        ];
+        issues[0].severity = Severity::Warning;
+        issues[1].line = 3;
+
+        let order = |issues: &[Issue]| -> Vec<String> {
+            issues.iter().map(|i| i.description.clone()).collect()
+        };
+        sort_issues(&mut issues, SortBy::Severity);
+        assert_eq!(order(&issues), vec!["high", "low", "warning"]);
+        sort_issues(&mut issues, SortBy::Confidence);
+        assert_eq!(order(&issues), vec!["warning", "high", "low"]);
+        sort_issues(&mut issues, SortBy::File);
+        assert_eq!(order(&issues), vec!["high", "low", "warning"]);
+        sort_issues(&mut issues, SortBy::Line);
+        assert_eq!(order(&issues), vec!["high", "warning", "low"]);
+    }
+
+    #[test]
+    fn test_cap_issues_zero_is_unlimited() {
+        let mut issues = vec![issue("a", None), issue("b", None)];
+        assert_eq!(cap_issues(&mut issues, 0), 0);
+        assert_eq!(issues.len(), 2);
+    }
+
+    #[test]
+    fn test_filter_confidence() {
+        let issues = vec![
+            issue("low", Some(0.3)),
+            issue("unknown", None),
+            issue("high", Some(0.8)),
+        ];
+        let kept: Vec<_> = filter_confidence(0.5, issues)
+            .into_iter()
+            .map(|i| i.description)
+            .collect();
+        assert_eq!(kept, vec!["unknown", "high"]);
+        assert_eq!(
+            issue("high", Some(0.8)).status_label(),
+            "new, 80% confidence"
+        );
+
+        let issues = vec![
+            issue("The example no longer compiles", None),
+            issue("The `--fast` flag was renamed", None),
+        ];
+        let kept: Vec<_> = filter_categories(&["example-drift".to_string()], issues)
+            .into_iter()
+            .map(|i| i.description)
+            .collect();
+        assert_eq!(kept, vec!["The `--fast` flag was renamed"]);
     }
 
-    result
+    #[test]
+    fn test_apply_rules() {
+        let rules = RulesConfig {
+            ignore: vec!["docs/legacy/**".to_string()],
+            warn_only: vec!["docs/**".to_string()],
+            severity: Vec::new(),
+        };
+        let issues = apply_rules(
+            &rules,
+            vec![
+                issue_in("docs/legacy/old.md", "archived", None),
+                issue_in("docs/guide.md", "guide", None),
+                issue_in("README.md", "readme", None),
+            ],
+        );
+
+        let kept: Vec<(&str, Severity)> = issues
+            .iter()
+            .map(|i| (i.description.as_str(), i.severity))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![("guide", Severity::Warning), ("readme", Severity::Error)]
+        );
+
+        let rules: RulesConfig = toml::from_str(
+            r#"
+            warn_only = ["docs/**"]
+            [[severity]]
+            severity = "error"
+            when = 'category == "signature" && path startswith "docs/api"'
+            [[severity]]
+            severity = "warning"
+            when = "confidence < 0.6"
+            [[severity]]
+            severity = "warning"
+            when = 'state == "recurring"'
+            "#,
+        )
+        .unwrap();
+        let recurring = Issue {
+            state: IssueState::Recurring,
+            ..issue_in("README.md", "outdated example", None)
+        };
+        let issues = apply_rules(
+            &rules,
+            vec![
+                issue_in("docs/api/client.md", "signature of connect changed", None),
+                issue_in("docs/guide.md", "signature of connect changed", None),
+                issue_in("README.md", "outdated example", Some(0.4)),
+                issue_in("README.md", "outdated example", None),
+                recurring,
+            ],
+        );
+        let severities: Vec<Severity> = issues.iter().map(|i| i.severity).collect();
+        assert_eq!(
+            severities,
+            vec![
+                Severity::Error,
+                Severity::Warning,
+                Severity::Warning,
+                Severity::Error,
+                Severity::Warning
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partition_to_budget() {
+        let chunk = |file: &str, chars: usize| llm::DocChunk {
+            file: file.to_string(),
+            start_line: 1,
+            end_line: 1,
+            content: "x".repeat(chars),
+            kind: DocKind::Markdown,
+        };
+        let chunks = vec![
+            chunk("c.md", 300),
+            chunk("a.md", 100),
+            chunk("b.md", 200),
+            chunk("huge.md", 1000),
+        ];
+
+        // 100 tokens = 400 chars per batch
+        let (batches, dropped) = partition_to_budget(chunks.clone(), 100, 4);
+        let files: Vec<Vec<&str>> = batches
+            .iter()
+            .map(|b| b.iter().map(|c| c.file.as_str()).collect())
+            .collect();
+        assert_eq!(
+            files,
+            vec![vec!["a.md", "b.md"], vec!["c.md"], vec!["huge.md"]]
+        );
+        assert_eq!(batches[2][0].content.len(), 400);
+        assert_eq!(dropped, 0);
+
+        let (batches, dropped) = partition_to_budget(chunks, 100, 2);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(dropped, 1);
+    }
+
+    #[test]
+    fn test_weak_search() {
+        let found = |query: &str, hits: usize| search::QueryMatches {
+            query: query.to_string(),
+            chunks: vec![
+                llm::DocChunk {
+                    file: "README.md".to_string(),
+                    start_line: 1,
+                    end_line: 1,
+                    content: String::new(),
+                    kind: DocKind::Markdown,
+                };
+                hits
+            ],
+        };
+        let tried: Vec<String> = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+
+        let (matched, missed) = weak_search(&tried, [found("a", 1), found("b", 0)].iter()).unwrap();
+        assert_eq!(matched, vec!["a"]);
+        assert_eq!(missed, vec!["b", "c", "d", "e"]);
+        assert!(weak_search(&tried, [found("a", 1), found("c", 2)].iter()).is_none());
+        assert!(weak_search(&tried, std::iter::empty()).is_some());
+    }
+
+    #[test]
+    fn test_heuristic_queries() {
+        let file_diff = "diff --git a/src/cli.rs b/src/cli.rs
+--- a/src/cli.rs
++++ b/src/cli.rs
+@@ -1,4 +1,4 @@
+-    #[arg(long = \"no-color\")]
++    #[arg(long = \"plain\", env = \"APP_PLAIN\")]
++pub fn render_plain() {}
+     let unchanged_name = 1;
+";
+        assert_eq!(
+            heuristic_queries(file_diff),
+            vec!["no\\-color", "APP_PLAIN", "render_plain"]
+        );
+
+        let diff = "diff --git a/config.toml b/config.toml
+--- a/config.toml
++++ b/config.toml
+@@ -1,3 +1,3 @@
+-[server]
++[http.server]
+-request_timeout = 30
++timeout_secs = 30
+diff --git a/docs/guide.md b/docs/guide.md
+--- a/docs/guide.md
++++ b/docs/guide.md
+@@ -1,2 +1,2 @@
+-## Plain output ##
++## Color output
+";
+        assert_eq!(
+            heuristic_diff_queries(diff),
+            vec![
+                "server",
+                "http\\.server",
+                "request_timeout",
+                "timeout_secs",
+                "Plain output",
+                "Color output"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_issues_across_groups() {
+        let raw = |excerpt: &str, description: &str, confidence: f32| RawIssue {
+            file: "README.md".to_string(),
+            line: 3,
+            description: description.to_string(),
+            doc_excerpt: excerpt.to_string(),
+            suggested_fix: None,
+            confidence: Some(confidence),
+            code_symbol: None,
+        };
+
+        let merged = dedup_issues(vec![
+            raw("Run `tool --fast`", "Flag renamed", 0.6),
+            raw(
+                "Run `tool  --fast`\n",
+                "Flag --fast was renamed to --quick",
+                0.9,
+            ),
+            raw("Set `timeout = 30`", "Default changed", 0.8),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].description, "Flag --fast was renamed to --quick");
+        assert_eq!(merged[1].description, "Default changed");
+    }
 }
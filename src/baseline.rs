@@ -0,0 +1,226 @@
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::paths;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+/// Baseline of accepted drift, committed alongside the config so the whole team shares it
+pub const BASELINE_FILE: &str = ".driftcheck-baseline.json";
+
+const BASELINE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    pub issues: Vec<BaselineEntry>,
+    /// Issues hidden until a date, e.g. while their doc update waits for a follow-up PR
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snoozed: Vec<Snooze>,
+}
+
+/// An accepted issue. Matched by fingerprint (file + doc excerpt);
+/// file and description are only there to make the file reviewable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    pub file: String,
+    pub description: String,
+}
+
+/// A snoozed issue. Once `until` has passed the issue is reported again, marked as
+/// `snooze_expired`, until it is fixed or snoozed again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snooze {
+    pub fingerprint: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub file: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub until: DateTime<Utc>,
+}
+
+impl Default for Baseline {
+    fn default() -> Self {
+        Self {
+            version: BASELINE_VERSION,
+            issues: Vec::new(),
+            snoozed: Vec::new(),
+        }
+    }
+}
+
+impl Baseline {
+    /// Drop baselined and snoozed issues, returning the rest
+    pub fn filter(&self, issues: Vec<Issue>) -> Vec<Issue> {
+        self.filter_at(issues, Utc::now())
+    }
+
+    fn filter_at(&self, issues: Vec<Issue>, now: DateTime<Utc>) -> Vec<Issue> {
+        let accepted: HashSet<&str> = self.issues.iter().map(|e| e.fingerprint.as_str()).collect();
+        issues
+            .into_iter()
+            .filter(|issue| !accepted.contains(issue.fingerprint.as_str()))
+            .filter_map(|mut issue| {
+                match self
+                    .snoozed
+                    .iter()
+                    .find(|s| s.fingerprint == issue.fingerprint)
+                {
+                    Some(snooze) if snooze.until > now => return None,
+                    Some(_) => issue.snooze_expired = true,
+                    None => {}
+                }
+                Some(issue)
+            })
+            .collect()
+    }
+
+    /// Snooze the issue with `fingerprint` until `until`, replacing an earlier snooze
+    pub fn snooze(&mut self, snooze: Snooze) {
+        self.snoozed.retain(|s| s.fingerprint != snooze.fingerprint);
+        self.snoozed.push(snooze);
+        self.snoozed.sort_by(|a, b| {
+            a.file
+                .cmp(&b.file)
+                .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+        });
+    }
+
+    /// Add issues that are not baselined yet, returning how many were added
+    pub fn extend(&mut self, issues: &[Issue]) -> usize {
+        let before = self.issues.len();
+        for issue in issues {
+            if !self
+                .issues
+                .iter()
+                .any(|e| e.fingerprint == issue.fingerprint)
+            {
+                self.issues.push(BaselineEntry {
+                    fingerprint: issue.fingerprint.clone(),
+                    file: paths::to_slash(&issue.file),
+                    description: issue.description.clone(),
+                });
+            }
+        }
+        // Stable order keeps diffs of the committed file small
+        self.issues.sort_by(|a, b| {
+            a.file
+                .cmp(&b.file)
+                .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+        });
+        self.issues.len() - before
+    }
+}
+
+/// Parse a snooze duration such as "12h", "7d" or "2w"
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let invalid = || {
+        DriftcheckError::ConfigInvalid(format!(
+            "invalid duration {:?}: expected a number of hours, days or weeks, e.g. \"7d\"",
+            text
+        ))
+    };
+    let text = text.trim();
+    let unit = text.chars().last().ok_or_else(invalid)?;
+    let count: i64 = text[..text.len() - unit.len_utf8()]
+        .parse()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(invalid)?;
+    match unit {
+        'h' => Duration::try_hours(count),
+        'd' => Duration::try_days(count),
+        'w' => Duration::try_weeks(count),
+        _ => None,
+    }
+    .ok_or_else(invalid)
+}
+
+pub fn path(config: &Config) -> PathBuf {
+    config.resolve(BASELINE_FILE)
+}
+
+/// Load the baseline, or an empty one if the repository has none
+pub fn load(config: &Config) -> Result<Baseline> {
+    let path = path(config);
+    if !path.exists() {
+        return Ok(Baseline::default());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| DriftcheckError::ConfigInvalid(format!("{}: {}", BASELINE_FILE, e)))
+}
+
+pub fn save(config: &Config, baseline: &Baseline) -> Result<()> {
+    Config::ensure_writable("writing the baseline")?;
+
+    let content = serde_json::to_string_pretty(baseline)
+        .map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+    fs::write(path(config), content + "\n").map_err(|e| DriftcheckError::ReportError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    fn issue(file: &str, excerpt: &str) -> Issue {
+        Issue::from(RawIssue {
+            file: file.to_string(),
+            line: 1,
+            description: "Outdated".to_string(),
+            doc_excerpt: excerpt.to_string(),
+            suggested_fix: None,
+            confidence: None,
+            code_symbol: None,
+        })
+    }
+
+    #[test]
+    fn test_baseline_filters_accepted_issues() {
+        let mut baseline = Baseline::default();
+        assert_eq!(
+            baseline.extend(&[issue("docs/old.md", "Run `foo --legacy`")]),
+            1
+        );
+        assert_eq!(
+            baseline.extend(&[issue("docs/old.md", "Run `foo --legacy`")]),
+            0
+        );
+
+        // Matches despite whitespace changes in the excerpt
+        let remaining = baseline.filter(vec![
+            issue("docs/old.md", "Run  `foo --legacy`\n"),
+            issue("README.md", "Run `foo --legacy`"),
+        ]);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].file, PathBuf::from("README.md"));
+    }
+
+    #[test]
+    fn test_snooze_expires() {
+        let snoozed = issue("docs/api.md", "`GET /v1/users`");
+        let now = Utc::now();
+        let mut baseline = Baseline::default();
+        baseline.snooze(Snooze {
+            fingerprint: snoozed.fingerprint.clone(),
+            file: "docs/api.md".to_string(),
+            description: String::new(),
+            until: now + parse_duration("7d").unwrap(),
+        });
+
+        assert!(baseline.filter_at(vec![snoozed.clone()], now).is_empty());
+        let later = now + parse_duration("2w").unwrap();
+        let resurfaced = baseline.filter_at(vec![snoozed], later);
+        assert!(resurfaced[0].snooze_expired);
+        assert!(resurfaced[0].status_label().contains("snooze expired"));
+
+        assert_eq!(parse_duration("12h").unwrap(), Duration::hours(12));
+        assert!(parse_duration("7").is_err() && parse_duration("0d").is_err());
+    }
+}
@@ -1,20 +1,31 @@
 use crate::config::Config;
 use crate::error::{DriftcheckError, Result};
+use crate::llm::{DocChunk, RawIssue};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::debug;
 
+/// Subdirectory of the cache holding parsed analysis results
+const ANALYSIS_DIR: &str = "analysis";
+
 #[derive(Debug, Serialize, Deserialize)]
 struct CacheEntry {
     queries: Vec<String>,
     created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct AnalysisEntry {
+    issues: Vec<RawIssue>,
+    created_at: DateTime<Utc>,
+}
+
 pub struct CacheStats {
     pub entries: usize,
+    pub analysis_entries: usize,
     pub size_bytes: u64,
     pub path: PathBuf,
 }
@@ -23,17 +34,41 @@ pub struct CacheStats {
 fn get_cache_dir() -> Result<PathBuf> {
     let git_root = Config::find_git_root()?;
     let config = Config::load().unwrap_or_default();
-    Ok(git_root.join(&config.cache.dir))
+    Ok(Config::resolve_in(&git_root, &config.cache.dir))
 }
 
-/// Generate a cache key from diff content
-fn cache_key(diff: &str) -> String {
+/// Key for one changed file's search queries: its path and hunks. The header (with blob
+/// hashes) is left out, so the key only changes when the file's own changes do.
+fn queries_key(path: &str, file_diff: &str) -> String {
+    let hunks = file_diff
+        .find("\n@@")
+        .map_or(file_diff, |start| &file_diff[start + 1..]);
+
     let mut hasher = Sha256::new();
-    hasher.update(diff.as_bytes());
+    hasher.update(path.as_bytes());
+    hasher.update([0]);
+    hasher.update(hunks.as_bytes());
     let result = hasher.finalize();
     hex::encode(&result[..8]) // Use first 8 bytes for shorter filenames
 }
 
+/// Key for an analysis result: the diff, every doc chunk sent with it, and the prompt and model
+/// that produced it, so editing a doc or a prompt never serves stale issues
+fn analysis_key(diff: &str, doc_chunks: &[DocChunk], system_prompt: &str, model: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [diff, system_prompt, model] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    for chunk in doc_chunks {
+        hasher
+            .update(format!("{}:{}-{}\n", chunk.file, chunk.start_line, chunk.end_line).as_bytes());
+        hasher.update(chunk.content.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
 // We need hex encoding
 mod hex {
     pub fn encode(bytes: &[u8]) -> String {
@@ -41,10 +76,35 @@ mod hex {
     }
 }
 
-/// Get cached search queries for a diff
-pub fn get_queries(diff: &str) -> Option<Vec<String>> {
+/// Whether an entry created at `created_at` is past the configured TTL
+fn is_expired(created_at: DateTime<Utc>) -> bool {
+    let config = Config::load().unwrap_or_default();
+    let ttl = chrono::Duration::seconds(config.cache.ttl as i64);
+    Utc::now() - created_at > ttl
+}
+
+fn remove_expired(cache_file: &Path) {
+    debug!("Cache entry expired");
+    if !Config::is_read_only() {
+        let _ = fs::remove_file(cache_file);
+    }
+}
+
+fn write_entry<T: Serialize>(cache_file: &Path, entry: &T) -> Result<()> {
+    if let Some(dir) = cache_file.parent() {
+        fs::create_dir_all(dir).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    }
+
+    let content = serde_json::to_string_pretty(entry)
+        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+
+    fs::write(cache_file, content).map_err(|e| DriftcheckError::CacheError(e.to_string()))
+}
+
+/// Get cached search queries for one changed file's section of a diff
+pub fn get_queries(path: &str, file_diff: &str) -> Option<Vec<String>> {
     let cache_dir = get_cache_dir().ok()?;
-    let key = cache_key(diff);
+    let key = queries_key(path, file_diff);
     let cache_file = cache_dir.join(format!("{}.json", key));
 
     if !cache_file.exists() {
@@ -54,49 +114,88 @@ pub fn get_queries(diff: &str) -> Option<Vec<String>> {
     let content = fs::read_to_string(&cache_file).ok()?;
     let entry: CacheEntry = serde_json::from_str(&content).ok()?;
 
-    // Check TTL
-    let config = Config::load().unwrap_or_default();
-    let ttl = chrono::Duration::seconds(config.cache.ttl as i64);
-    let age = Utc::now() - entry.created_at;
-
-    if age > ttl {
-        debug!("Cache entry expired");
-        let _ = fs::remove_file(&cache_file);
+    if is_expired(entry.created_at) {
+        remove_expired(&cache_file);
         return None;
     }
 
     Some(entry.queries)
 }
 
-/// Store search queries in cache
-pub fn store_queries(diff: &str, queries: &[String]) -> Result<()> {
-    let cache_dir = get_cache_dir()?;
-
-    // Create cache directory if it doesn't exist
-    if !cache_dir.exists() {
-        fs::create_dir_all(&cache_dir).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
-    }
+/// Store search queries for one changed file's section of a diff
+pub fn store_queries(path: &str, file_diff: &str, queries: &[String]) -> Result<()> {
+    Config::ensure_writable("writing the cache")?;
 
-    let key = cache_key(diff);
+    let cache_dir = get_cache_dir()?;
+    let key = queries_key(path, file_diff);
     let cache_file = cache_dir.join(format!("{}.json", key));
 
     let entry = CacheEntry {
         queries: queries.to_vec(),
         created_at: Utc::now(),
     };
+    write_entry(&cache_file, &entry)?;
 
-    let content = serde_json::to_string_pretty(&entry)
-        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    debug!("Cached queries to {}", cache_file.display());
 
-    fs::write(&cache_file, content).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    Ok(())
+}
 
-    debug!("Cached queries to {}", cache_file.display());
+/// Get cached analysis issues for a diff and its doc chunks
+pub fn get_analysis(
+    diff: &str,
+    doc_chunks: &[DocChunk],
+    system_prompt: &str,
+    model: &str,
+) -> Option<Vec<RawIssue>> {
+    let cache_dir = get_cache_dir().ok()?;
+    let key = analysis_key(diff, doc_chunks, system_prompt, model);
+    let cache_file = cache_dir.join(ANALYSIS_DIR).join(format!("{}.json", key));
+
+    if !cache_file.exists() {
+        return None;
+    }
+
+    let content = fs::read_to_string(&cache_file).ok()?;
+    let entry: AnalysisEntry = serde_json::from_str(&content).ok()?;
+
+    if is_expired(entry.created_at) {
+        remove_expired(&cache_file);
+        return None;
+    }
+
+    Some(entry.issues)
+}
+
+/// Store parsed analysis issues in cache
+pub fn store_analysis(
+    diff: &str,
+    doc_chunks: &[DocChunk],
+    system_prompt: &str,
+    model: &str,
+    issues: &[RawIssue],
+) -> Result<()> {
+    Config::ensure_writable("writing the cache")?;
+
+    let cache_dir = get_cache_dir()?;
+    let key = analysis_key(diff, doc_chunks, system_prompt, model);
+    let cache_file = cache_dir.join(ANALYSIS_DIR).join(format!("{}.json", key));
+
+    let entry = AnalysisEntry {
+        issues: issues.to_vec(),
+        created_at: Utc::now(),
+    };
+    write_entry(&cache_file, &entry)?;
+
+    debug!("Cached analysis to {}", cache_file.display());
 
     Ok(())
 }
 
 /// Clear the cache
 pub fn clear() -> Result<()> {
+    Config::ensure_writable("clearing the cache")?;
+
     let cache_dir = get_cache_dir()?;
 
     if cache_dir.exists() {
@@ -110,18 +209,27 @@ pub fn clear() -> Result<()> {
 pub fn stats() -> Result<CacheStats> {
     let cache_dir = get_cache_dir()?;
 
-    if !cache_dir.exists() {
-        return Ok(CacheStats {
-            entries: 0,
-            size_bytes: 0,
-            path: cache_dir,
-        });
+    let (entries, queries_size) = count_files(&cache_dir)?;
+    let (analysis_entries, analysis_size) = count_files(&cache_dir.join(ANALYSIS_DIR))?;
+
+    Ok(CacheStats {
+        entries,
+        analysis_entries,
+        size_bytes: queries_size + analysis_size,
+        path: cache_dir,
+    })
+}
+
+/// Number and total size of the files directly in `dir`
+fn count_files(dir: &Path) -> Result<(usize, u64)> {
+    if !dir.exists() {
+        return Ok((0, 0));
     }
 
     let mut entries = 0;
     let mut size_bytes = 0;
 
-    for entry in fs::read_dir(&cache_dir)
+    for entry in fs::read_dir(dir)
         .map_err(|e| DriftcheckError::CacheError(e.to_string()))?
         .flatten()
     {
@@ -133,9 +241,50 @@ pub fn stats() -> Result<CacheStats> {
         }
     }
 
-    Ok(CacheStats {
-        entries,
-        size_bytes,
-        path: cache_dir,
-    })
+    Ok((entries, size_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::DocKind;
+
+    fn chunk(content: &str) -> DocChunk {
+        DocChunk {
+            file: "README.md".to_string(),
+            start_line: 1,
+            end_line: 3,
+            content: content.to_string(),
+            kind: DocKind::Markdown,
+        }
+    }
+
+    #[test]
+    fn test_queries_key_ignores_header() {
+        let a =
+            "diff --git a/src/lib.rs b/src/lib.rs\nindex 1a2b..3c4d 100644\n@@ -1 +1 @@\n-a\n+b\n";
+        let b =
+            "diff --git a/src/lib.rs b/src/lib.rs\nindex 9f8e..3c4d 100644\n@@ -1 +1 @@\n-a\n+b\n";
+        assert_eq!(queries_key("src/lib.rs", a), queries_key("src/lib.rs", b));
+        assert_ne!(queries_key("src/lib.rs", a), queries_key("src/main.rs", a));
+        assert_ne!(
+            queries_key("src/lib.rs", a),
+            queries_key("src/lib.rs", &a.replace("+b", "+c"))
+        );
+    }
+
+    #[test]
+    fn test_analysis_key_covers_docs_and_prompt() {
+        let docs = [chunk("Run `tool --fast`")];
+        let key = analysis_key("+fast", &docs, "prompt", "gpt-4o");
+
+        assert_eq!(key, analysis_key("+fast", &docs, "prompt", "gpt-4o"));
+        assert_ne!(
+            key,
+            analysis_key("+fast", &[chunk("Run `tool --slow`")], "prompt", "gpt-4o")
+        );
+        assert_ne!(key, analysis_key("+fast", &docs, "other prompt", "gpt-4o"));
+        assert_ne!(key, analysis_key("+fast", &docs, "prompt", "gpt-4o-mini"));
+        assert_ne!(key, analysis_key("+fast", &[], "prompt", "gpt-4o"));
+    }
 }
@@ -1,9 +1,22 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "driftcheck")]
 #[command(author, version, about = "Documentation drift detection for Git", long_about = None)]
 pub struct Cli {
+    /// Never write to disk (no cache, history, reports, fixes or hook changes)
+    #[arg(long, global = true)]
+    pub read_only: bool,
+
+    /// How errors are reported on stderr
+    #[arg(long, value_enum, global = true, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+
+    /// Use this `[llm.profiles]` entry for every LLM task
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -15,6 +28,10 @@ pub enum Commands {
         /// Force overwrite existing configuration
         #[arg(short, long)]
         force: bool,
+
+        /// Add driftcheck's local files to .gitignore without asking
+        #[arg(long)]
+        gitignore: bool,
     },
 
     /// Check for documentation drift (runs the analysis)
@@ -23,13 +40,130 @@ pub enum Commands {
         #[arg(short, long)]
         range: Option<String>,
 
+        /// Check staged changes (the index) instead of a commit range
+        #[arg(long, conflicts_with = "range")]
+        staged: bool,
+
+        /// Analyze a unified diff or patch file (e.g. from `git format-patch`) instead of
+        /// commits; implies --read-only
+        #[arg(long, value_name = "PATCH", conflicts_with_all = ["range", "staged"])]
+        diff_file: Option<PathBuf>,
+
+        /// Read the diff to analyze from stdin; implies --read-only
+        #[arg(long, conflicts_with_all = ["range", "staged", "diff_file"])]
+        stdin: bool,
+
         /// Run in non-interactive mode even if TTY is available
         #[arg(long)]
         no_tui: bool,
+
+        /// Output format (any format other than text disables the TUI)
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Write the report to a file instead of stdout (non-text formats only)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Show generated search queries, matched doc files and selected chunks, then exit
+        /// without running the analysis
+        #[arg(long)]
+        explain_queries: bool,
+
+        /// Show the docs that would be selected and the LLM calls a check would make (prompt
+        /// templates, estimated tokens) without calling the LLM
+        #[arg(long, conflicts_with_all = ["explain_queries", "capture_prompts", "with_fixes"])]
+        dry_run: bool,
+
+        /// Show all issues, ignoring `general.max_issues`
+        #[arg(long)]
+        all: bool,
+
+        /// Include the exact prompts and raw LLM responses of each stage (requires --format json)
+        #[arg(long)]
+        capture_prompts: bool,
+
+        /// Generate a fix for each issue and include it as a unified diff (requires --format json or sarif)
+        #[arg(long)]
+        with_fixes: bool,
+
+        /// Generate the --with-fixes fixes through the provider's batch API: about half the
+        /// cost, but may take hours
+        #[arg(long, requires = "with_fixes")]
+        batch: bool,
+    },
+
+    /// Check for drift in CI, reporting issues in the CI provider's format
+    Ci {
+        /// Commit range to check (default: origin/$GITHUB_BASE_REF..HEAD on pull requests,
+        /// otherwise @{u}..HEAD)
+        #[arg(short, long)]
+        range: Option<String>,
+
+        /// Emit GitHub Actions annotations and a job summary (default inside GitHub Actions)
+        #[arg(long)]
+        github: bool,
+    },
+
+    /// Post issues as inline review comments on a GitHub pull request (needs GITHUB_TOKEN).
+    /// Re-runs update existing comments and mark those of fixed issues resolved.
+    Comment {
+        /// Pull request number
+        #[arg(long)]
+        pr: u64,
+
+        /// Commit range to check (default: origin/<PR base branch>..HEAD)
+        #[arg(short, long)]
+        range: Option<String>,
+
+        /// Repository as owner/name (default: $GITHUB_REPOSITORY)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+
+    /// Review the issues of a previous `check` or hook run in the TUI without re-analyzing
+    /// (lists the recorded runs without --last or --run)
+    Review {
+        /// Replay the most recent run
+        #[arg(long, conflicts_with = "run")]
+        last: bool,
+
+        /// Replay the run with this id
+        #[arg(long)]
+        run: Option<String>,
+    },
+
+    /// Check that release-facing docs (changelog, migration guides, README) cover a tag range
+    ReleaseCheck {
+        /// Tag-to-tag range, e.g. v1.2.0..v1.3.0
+        range: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+
+    /// Verify a sample of doc sections against the current code, not a diff, and report
+    /// likely stale docs (for a scheduled job)
+    Sweep {
+        /// Doc sections to verify, most likely stale first (default: sweep.sample)
+        #[arg(long)]
+        sample: Option<usize>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Write the report to a file instead of stdout (non-text formats only)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 
     /// Show or edit configuration
     Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+
         /// Open configuration in $EDITOR
         #[arg(short, long)]
         edit: bool,
@@ -37,13 +171,31 @@ pub enum Commands {
         /// Show the path to the configuration file
         #[arg(long)]
         path: bool,
+
+        /// Validate the configuration and list doc patterns that match no files
+        #[arg(long)]
+        check: bool,
     },
 
-    /// Enable driftcheck
-    Enable,
+    /// Enable driftcheck, or report issue categories again
+    Enable {
+        /// Report this category again (cli, config, signature, example, api,
+        /// removed-or-renamed, other; a "-drift" suffix is allowed); repeatable
+        #[arg(long)]
+        category: Vec<String>,
+
+        /// Enable driftcheck and every category
+        #[arg(long, conflicts_with = "category")]
+        all: bool,
+    },
 
-    /// Disable driftcheck (without uninstalling)
-    Disable,
+    /// Disable driftcheck (without uninstalling), or stop reporting issue categories
+    Disable {
+        /// Stop reporting this category (e.g. example-drift) instead of disabling
+        /// driftcheck; repeatable
+        #[arg(long)]
+        category: Vec<String>,
+    },
 
     /// Cache management
     Cache {
@@ -51,16 +203,69 @@ pub enum Commands {
         action: CacheAction,
     },
 
+    /// Accept the current issues in .driftcheck-baseline.json so later runs ignore them
+    Baseline {
+        /// Commit range to analyze (default: @{u}..HEAD)
+        #[arg(short, long)]
+        range: Option<String>,
+    },
+
+    /// Hide an issue until a date, e.g. while its doc update waits for a follow-up PR
+    Snooze {
+        /// Issue fingerprint (shown in the text report and JSON output)
+        fingerprint: String,
+        /// How long to hide it: hours, days or weeks, e.g. "12h", "7d", "2w"
+        #[arg(long = "for", default_value = "7d")]
+        duration: String,
+    },
+
+    /// Scan the repository for documentation and propose `docs.paths` entries
+    DiscoverDocs {
+        /// Add the discovered paths to the configuration file
+        #[arg(long)]
+        apply: bool,
+    },
+
+    /// Anonymized feedback on skipped issues
+    Feedback {
+        #[command(subcommand)]
+        action: FeedbackAction,
+    },
+
     /// Install or update the pre-push hook
     InstallHook {
         /// Force overwrite existing hook
         #[arg(short, long)]
         force: bool,
+
+        /// Append driftcheck to an existing pre-push hook instead of replacing it
+        #[arg(long, conflicts_with = "force")]
+        chain: bool,
+    },
+
+    /// Run a language server that shows issues as diagnostics in the editor, with quickfix
+    /// code actions applying their fixes
+    Lsp,
+
+    /// Run the pre-push hook against a range without pushing, with the hook's output and exit code
+    /// (e.g. to validate the configuration in CI)
+    SimulatePush {
+        /// Remote to simulate pushing the current branch to (default: the upstream branch)
+        remote: Option<String>,
+
+        /// Commit range to check instead (default: <remote>/<branch>..HEAD, or @{u}..HEAD)
+        range: Option<String>,
     },
 
     /// Internal: Run as pre-push hook (called by git)
     #[command(hide = true)]
-    Hook,
+    Hook {
+        /// Name of the remote being pushed to
+        remote: Option<String>,
+
+        /// URL being pushed to
+        url: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -70,4 +275,51 @@ pub enum CacheAction {
 
     /// Show cache statistics
     Stats,
+
+    /// Generate and cache search queries for a range ahead of a later check (e.g. early in CI)
+    Warm {
+        /// Commit range to warm (default: @{u}..HEAD)
+        #[arg(short, long)]
+        range: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the JSON Schema of .driftcheck.toml, for editor completion and validation
+    Schema,
+}
+
+#[derive(Subcommand)]
+pub enum FeedbackAction {
+    /// Export recorded feedback as JSON
+    Export {
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text (or the TUI when a TTY is available)
+    Text,
+    /// Standalone HTML report written to stdout
+    Html,
+    /// Markdown report (suitable for PR comments) written to stdout
+    Markdown,
+    /// Machine-readable JSON report written to stdout
+    Json,
+    /// SARIF 2.1.0 log for code scanning (e.g. GitHub code scanning)
+    Sarif,
+    /// GitLab Code Quality report (Code Climate JSON) for the merge request widget
+    Codeclimate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Human-readable message
+    Text,
+    /// Single-line JSON object with a stable error code and category
+    Json,
 }
@@ -1,8 +1,25 @@
+use crate::analyzer::RULE_FIELDS;
 use crate::error::{DriftcheckError, Result};
+use crate::expr::Expr;
+use crate::feedback;
+use crate::git;
+use crate::llm::usage::Usage;
+use crate::policy;
+use crate::search;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Set by `--read-only`; checked alongside DRIFTCHECK_READ_ONLY
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--profile`; takes precedence over DRIFTCHECK_PROFILE
+static PROFILE: Mutex<Option<String>> = Mutex::new(None);
 
 const DEFAULT_ANALYSIS_PROMPT: &str = r#"You are a strict documentation consistency reviewer. Your job is to find ONLY clear, obvious documentation errors caused by code changes.
 
@@ -29,22 +46,120 @@ Output as JSON array with objects containing:
 - "line": approximate line number (0 if unknown)
 - "description": what is FACTUALLY WRONG (be specific)
 - "doc_excerpt": the exact doc text that is wrong
-- "suggested_fix": minimal fix (optional)"#;
+- "suggested_fix": minimal fix (optional)
+- "confidence": how certain you are that the docs are wrong, from 0.0 to 1.0
+- "code_symbol": the function, CLI flag or config key in the diff that the docs contradict, exactly as it appears in the code (optional)"#;
 
 const DEFAULT_SEARCH_QUERIES_PROMPT: &str = r#"Given this code diff, output a JSON array of search patterns to find related documentation.
 Focus on: function names, class names, API endpoints, CLI flags, config keys, error messages.
 Output ONLY valid JSON, no explanation. Example: ["process_data", "API endpoint", "--verbose"]"#;
 
-const DEFAULT_SUGGESTIONS_PROMPT: &str = r#"Given the documentation issue identified, suggest a minimal fix.
-Output as a unified diff patch that can be applied with `patch -p1`."#;
+const DEFAULT_SUGGESTIONS_PROMPT: &str = r#"You are a documentation editor. Given an issue description and the current documentation file, suggest a minimal fix.
+
+Rules:
+1. Output ONLY a unified diff against the current file (with ---/+++ headers and @@ hunks), no explanations
+2. Make minimal changes - only fix what's necessary
+3. Include a few unchanged context lines around each change, copied exactly from the file
+4. If the issue mentions missing documentation, add it in the appropriate place
+
+If a diff is not possible, output the COMPLETE fixed file instead."#;
+
+const DEFAULT_CRITIC_PROMPT: &str = r#"You are reviewing a proposed documentation fix before it is written to disk.
+
+Given the issue, the original file and the fixed file, decide whether the fix addresses the issue WITHOUT altering unrelated content.
+
+Reject the fix if it:
+- Does not address the described issue
+- Changes, removes or reformats content unrelated to the issue
+- Introduces new claims that are not supported by the issue description
+
+Output ONLY a JSON object: {"approved": true|false, "reason": "short explanation"}"#;
+
+const DEFAULT_RELEASE_PROMPT: &str = r#"You are checking whether a project's release-facing documentation is ready for a release.
+
+You are given the commits and code diff between two release tags, and the release-facing docs (changelog, migration guides, README).
+
+Report an issue if:
+1. A user-visible change (new, renamed or removed CLI flag, config key, API, default value, behavior) is missing from the changelog
+2. A breaking change has no migration notes
+3. Installation or upgrade instructions reference a version, command or option that no longer matches the code
+4. The changelog describes something that is not in this range or contradicts the diff
+
+DO NOT report:
+- Internal refactors, tests or CI changes that users cannot observe
+- Stylistic issues in the changelog
+
+If the docs are ready for release, return an empty array: []
+
+Output as JSON array with objects containing:
+- "file": the documentation file path
+- "line": approximate line number (0 if unknown)
+- "description": what is missing or wrong (be specific)
+- "doc_excerpt": the exact doc text that is wrong (empty if something is missing)
+- "suggested_fix": text to add or replace (optional)
+- "confidence": how certain you are, from 0.0 to 1.0
+- "code_symbol": the CLI flag, config key or API in the diff the issue is about, exactly as it appears in the code (optional)"#;
+
+const DEFAULT_SWEEP_PROMPT: &str = r#"You are auditing a section of a project's documentation against the current code.
+
+You are given one documentation section, excerpts of the current code mentioning the names the section refers to, and the referenced names that appear nowhere in the code.
+
+Report an issue ONLY if the section states something the code clearly contradicts:
+1. A CLI flag, config key, function, type or endpoint that no longer exists or was renamed
+2. A signature, parameter, default value or return type that differs from the code
+3. A code example that would fail against the current code
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+DO NOT report:
+- Names missing from the excerpts when the excerpts may simply be incomplete, unless the name is listed as not found in the code
+- Stylistic improvements, vague wording or missing documentation
+- Anything you cannot verify from the excerpts
+
+If the section is accurate, return an empty array: []
+
+Output as JSON array with objects containing:
+- "file": the documentation file path
+- "line": approximate line number (0 if unknown)
+- "description": what is wrong (be specific)
+- "doc_excerpt": the exact doc text that is wrong
+- "suggested_fix": the corrected text (optional)
+- "confidence": how certain you are, from 0.0 to 1.0
+- "code_symbol": the CLI flag, config key or API in the code the issue is about, exactly as it appears in the code (optional)"#;
+
+const DEFAULT_DEPENDENCIES_PROMPT: &str = r#"You are checking documentation against dependency and toolchain version changes.
+
+You are given the versions that changed in the project's manifests (old -> new), the manifest diff, and documentation excerpts that mention these dependencies or versions.
+
+Report an issue ONLY if the documentation is now wrong because of a version change:
+1. Install or setup instructions that pin, download or require the old version
+2. Minimum-version statements (MSRV, "requires Node 16+", "Python 3.8 or newer") that no longer match
+3. Compatibility tables or support matrices that omit the new version or list a dropped one
+4. Code examples that use an API of a dependency whose major version changed, when the excerpt shows the old usage
+
+DO NOT report:
+- Versions of dependencies the docs don't mention
+- Changelog entries or release notes describing past versions
+- Lockfile details, or anything you cannot tie to a version in the list
+
+If the docs match the new versions, return an empty array: []
+
+Output as JSON array with objects containing:
+- "file": the documentation file path
+- "line": approximate line number (0 if unknown)
+- "description": what is wrong, naming the dependency and both versions
+- "doc_excerpt": the exact doc text that is wrong
+- "suggested_fix": the corrected text (optional)
+- "confidence": how certain you are, from 0.0 to 1.0
+- "code_symbol": the dependency name exactly as it appears in the manifest (optional)"#;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
     #[serde(default)]
     pub general: GeneralConfig,
     #[serde(default)]
     pub docs: DocsConfig,
     #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
     pub llm: LlmConfig,
     #[serde(default)]
     pub prompts: PromptsConfig,
@@ -52,17 +167,78 @@ pub struct Config {
     pub tui: TuiConfig,
     #[serde(default)]
     pub cache: CacheConfig,
+    #[serde(default)]
+    pub release: ReleaseConfig,
+    #[serde(default)]
+    pub sweep: SweepConfig,
+    #[serde(default)]
+    pub rules: RulesConfig,
+    #[serde(default)]
+    pub hook: HookConfig,
+    #[serde(default, skip_serializing_if = "PolicyConfig::is_empty")]
+    pub policy: PolicyConfig,
+    /// Organization policy loaded from the policy file; replaces `policy` when present
+    #[serde(skip)]
+    pub org_policy: Option<PolicyConfig>,
+    /// Repository root that relative paths (docs, fixes, cache) resolve against.
+    /// Set on load so commands behave the same from any subdirectory.
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// `[llm]` as written in the file, before `--profile` rewrote it; what `save` writes back
+    #[serde(skip)]
+    pub(crate) file_llm: Option<LlmConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GeneralConfig {
     #[serde(default = "default_true")]
     pub enabled: bool,
     #[serde(default)]
     pub allow_push_on_error: bool,
+    /// Only changes to files matching these globs are analyzed (all files if empty)
+    #[serde(default)]
+    pub diff_include: Vec<String>,
+    /// Changes to files matching these globs never trigger analysis
+    #[serde(default)]
+    pub diff_exclude: Vec<String>,
+    /// Record anonymized records of skipped issues (see `driftcheck feedback export`)
+    #[serde(default)]
+    pub record_feedback: bool,
+    /// Show at most this many issues, errors first, then by confidence (0 = no limit)
+    #[serde(default = "default_max_issues")]
+    pub max_issues: usize,
+    /// Hook behavior on its first run in a clone: "guarded", "normal" or "skip"
+    #[serde(default = "default_first_run_policy")]
+    pub first_run_policy: String,
+    /// Order of reported issues: "severity", "file", "confidence" or "line"
+    #[serde(default = "default_sort_by")]
+    pub sort_by: String,
+    /// Issue categories never reported (see `driftcheck disable --category`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_categories: Vec<String>,
+    /// Check diffs that mostly bump dependency or toolchain versions only against the docs
+    /// mentioning those versions, instead of running the full analysis
+    #[serde(default = "default_true")]
+    pub dependency_analysis: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl GeneralConfig {
+    /// Every disabled category must be one `feedback::categorize` returns
+    fn check_categories(&self) -> Result<()> {
+        for name in &self.disabled_categories {
+            if feedback::parse_category(name).is_none() {
+                return Err(DriftcheckError::ConfigInvalid(format!(
+                    "general.disabled_categories: unknown category {:?} (categories: {})",
+                    name,
+                    feedback::CATEGORIES.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DocsConfig {
     #[serde(default = "default_doc_paths")]
     pub paths: Vec<String>,
@@ -70,21 +246,418 @@ pub struct DocsConfig {
     pub ignore: Vec<String>,
     #[serde(default = "default_max_context_tokens")]
     pub max_context_tokens: usize,
+    /// Drop issues the model is less confident about than this (0.0 to 1.0; 0 keeps all)
+    #[serde(default)]
+    pub min_confidence: f32,
+    /// Most analysis calls per corpus when its docs exceed `max_context_tokens`
+    #[serde(default = "default_max_batches")]
+    pub max_batches: usize,
+    /// Rounds of refined search queries when the first search finds little or nothing (0 disables)
+    #[serde(default = "default_refine_rounds")]
+    pub refine_rounds: usize,
+    /// Search the real content of Git LFS pointer docs via `git lfs smudge` (skipped otherwise)
+    #[serde(default)]
+    pub smudge_lfs: bool,
+    /// Markdown file listing the product's canonical terms, which fixes must use as written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary: Option<String>,
+    /// Docs from other sources (`[docs.sources.<name>]`), searched along with `paths`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sources: BTreeMap<String, SourceConfig>,
+    /// Named corpora (`[docs.user]`, `[docs.api]`, ...) searched and budgeted separately.
+    /// When any are defined they replace the top-level `paths`.
+    #[serde(flatten, default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub corpora: BTreeMap<String, CorpusConfig>,
+}
+
+/// How relevant doc chunks are found for a diff
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchConfig {
+    /// "ripgrep" (LLM-generated literal queries) or "embeddings" (similarity to the diff)
+    #[serde(default = "default_search_mode")]
+    pub mode: String,
+    /// How ripgrep search queries are made: "llm" (one call per changed file) or "heuristic"
+    /// (identifiers, flags, config keys and headings taken from the diff, no API call)
+    #[serde(default = "default_query_mode")]
+    pub query_mode: String,
+    /// Embedding model served by the `llm.provider` endpoint (e.g. "nomic-embed-text" with ollama)
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+    /// Most chunks retrieved per corpus in embeddings mode
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    /// Chunks less similar to the diff than this (cosine, -1.0 to 1.0) are never retrieved
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CorpusConfig {
+    pub paths: Vec<String>,
+    /// Added to the top-level `docs.ignore`
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Token budget for this corpus (defaults to `docs.max_context_tokens`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context_tokens: Option<usize>,
+    /// Severity of issues in this corpus: "error" or "warning"
+    #[serde(default = "default_corpus_severity")]
+    pub severity: String,
+}
+
+/// A `[docs.sources.<name>]` entry: docs provided by a `search::sources::DocSource`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SourceConfig {
+    /// Source type: "files", or a type registered with `search::sources::register`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Corpus the docs belong to; required when named corpora are defined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corpus: Option<String>,
+    /// Options of the source type, e.g. `paths` and `ignore` for "files"
+    #[serde(flatten)]
+    pub options: BTreeMap<String, serde_json::Value>,
+}
+
+/// A corpus resolved against the top-level docs settings
+#[derive(Debug, Clone)]
+pub struct Corpus {
+    pub name: String,
+    pub docs: DocsConfig,
+    pub severity: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Name of the implicit corpus built from the top-level `paths`
+pub const DEFAULT_CORPUS: &str = "docs";
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LlmConfig {
+    /// API flavor: "openai" (any OpenAI-compatible endpoint) or "anthropic" (Messages API)
+    #[serde(default = "default_provider")]
+    pub provider: String,
     #[serde(default = "default_base_url")]
     pub base_url: String,
     #[serde(default = "default_model")]
     pub model: String,
+    /// Smaller, faster model for search query generation, on the same provider; the analysis
+    /// and fixes keep `model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_model: Option<String>,
     #[serde(default = "default_timeout")]
     pub timeout: u64,
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
+    /// Sampling temperature (0.0 to 2.0)
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+    /// Most tokens per response (provider default if unset; 8192 for Anthropic, which needs one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Nucleus sampling cutoff (0.0 to 1.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Sampling seed for more reproducible responses (OpenAI-compatible APIs and Ollama)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Ask for JSON output (`response_format: json_object`, Ollama `format: json`) on calls
+    /// that expect JSON; Anthropic has no such mode
+    #[serde(default)]
+    pub json_mode: bool,
+    /// Constrain search queries and issues to a JSON schema (OpenAI `json_schema` response
+    /// format, an Anthropic tool call, an Ollama `format` schema); takes precedence over
+    /// `json_mode`
+    #[serde(default)]
+    pub structured_output: bool,
+    /// Response parser: "auto", "openai", "anthropic", "ollama" or "generic"
+    #[serde(default = "default_parser")]
+    pub parser: String,
+    /// Treat persistently empty LLM responses as "no issues" instead of failing
+    #[serde(default)]
+    pub empty_response_as_no_issues: bool,
+    /// Shared request budget across all concurrent LLM calls (unlimited if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// Shared token budget across all concurrent LLM calls (unlimited if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<u32>,
+    /// Analysis calls run at once when a large diff is split into groups
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// Most seconds one call waits on 429 responses before failing; these waits follow the
+    /// provider's Retry-After and don't count towards `max_retries`
+    #[serde(default = "default_max_rate_limit_wait")]
+    pub max_rate_limit_wait: u64,
+    /// Stream responses as they are generated, for live progress in the TUI and spinner
+    #[serde(default)]
+    pub stream: bool,
+    /// Prices per model (exact name or glob) for the cost estimate in the run summary
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pricing: BTreeMap<String, ModelPrice>,
+    /// Environment variable holding the API key, instead of DRIFTCHECK_API_KEY
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    /// A second provider that analyzes every diff alongside this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consensus: Option<ConsensusConfig>,
+    /// Named providers and models (`[llm.profiles.<name>]`), for `--profile` and `[llm.tasks]`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, LlmProfile>,
+    /// The profile each LLM task uses; tasks without one use the settings above
+    #[serde(default)]
+    pub tasks: LlmTasks,
+    /// Calls and tokens of the clients created from this configuration, shared by its clones
+    #[serde(skip)]
+    pub usage: Usage,
+}
+
+impl LlmConfig {
+    /// Reject sampling parameters outside the range providers accept
+    fn check_sampling(&self) -> Result<()> {
+        if !(0.0..=2.0).contains(&self.temperature) {
+            return Err(DriftcheckError::ConfigInvalid(format!(
+                "llm.temperature = {}: must be between 0.0 and 2.0",
+                self.temperature
+            )));
+        }
+        if let Some(top_p) = self.top_p.filter(|p| !(0.0..=1.0).contains(p)) {
+            return Err(DriftcheckError::ConfigInvalid(format!(
+                "llm.top_p = {}: must be between 0.0 and 1.0",
+                top_p
+            )));
+        }
+        if self.max_tokens == Some(0) {
+            return Err(DriftcheckError::ConfigInvalid(
+                "llm.max_tokens must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reject an `[llm.consensus]` section without a model or with an unknown `require`
+    fn check_consensus(&self) -> Result<()> {
+        if let Some(consensus) = &self.consensus {
+            if consensus.model.trim().is_empty() {
+                return Err(DriftcheckError::ConfigInvalid(
+                    "llm.consensus.model must be set".to_string(),
+                ));
+            }
+            consensus.mode()?;
+        }
+        Ok(())
+    }
+
+    /// Reject `[llm.tasks]` entries naming a profile that isn't defined
+    fn check_profiles(&self) -> Result<()> {
+        if self.query_model.is_some() && self.tasks.queries.is_some() {
+            return Err(DriftcheckError::ConfigInvalid(
+                "llm.query_model and llm.tasks.queries are both set; use one".to_string(),
+            ));
+        }
+        for (task, name) in self.tasks.entries() {
+            if let Some(name) = name.filter(|name| !self.profiles.contains_key(*name)) {
+                return Err(DriftcheckError::ConfigInvalid(format!(
+                    "llm.tasks.{}: {}",
+                    task,
+                    self.unknown_profile(name)
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn unknown_profile(&self, name: &str) -> String {
+        let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        format!(
+            "Unknown profile '{}' (defined: {})",
+            name,
+            if known.is_empty() {
+                "none".to_string()
+            } else {
+                known.join(", ")
+            }
+        )
+    }
+
+    /// Switch every task to the named profile (`--profile`)
+    pub fn use_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| DriftcheckError::ConfigInvalid(self.unknown_profile(name)))?;
+        profile.apply(self);
+        self.tasks = LlmTasks::default();
+        self.query_model = None;
+        Ok(())
+    }
+
+    /// The configuration for one task: this one, with the task's `[llm.tasks]` profile (or
+    /// `query_model`) applied
+    pub fn for_task(&self, task: LlmTask) -> LlmConfig {
+        let mut config = self.clone();
+        if let Some(profile) = self
+            .tasks
+            .get(task)
+            .and_then(|name| self.profiles.get(name))
+        {
+            profile.apply(&mut config);
+        }
+        if let (LlmTask::Queries, Some(model)) = (task, &self.query_model) {
+            config.model = model.clone();
+        }
+        config
+    }
+
+    /// The model a task runs on, as `for_task` would configure it
+    pub fn task_model(&self, task: LlmTask) -> &str {
+        match (task, &self.query_model) {
+            (LlmTask::Queries, Some(model)) => model,
+            _ => self
+                .tasks
+                .get(task)
+                .and_then(|name| self.profiles.get(name))
+                .and_then(|profile| profile.model.as_deref())
+                .unwrap_or(&self.model),
+        }
+    }
+
+    /// The configuration of the `[llm.consensus]` provider, sharing this one's sampling,
+    /// retry and rate limit settings
+    pub fn consensus_llm(&self) -> Option<LlmConfig> {
+        let consensus = self.consensus.as_ref()?;
+        Some(LlmConfig {
+            provider: consensus.provider.clone(),
+            base_url: consensus.base_url.clone(),
+            model: consensus.model.clone(),
+            api_key_env: consensus.api_key_env.clone(),
+            consensus: None,
+            tasks: LlmTasks::default(),
+            ..self.clone()
+        })
+    }
+}
+
+/// `[llm.consensus]`: analyze with a second provider in parallel, for fewer false positives
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConsensusConfig {
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    pub model: String,
+    /// Environment variable holding this provider's API key (DRIFTCHECK_API_KEY if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    /// "both" reports only issues both providers found; "flag" also reports the others, with
+    /// low confidence
+    #[serde(default = "default_consensus_require")]
+    pub require: String,
+}
+
+/// How issues only one consensus provider found are handled (`llm.consensus.require`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsensusMode {
+    Both,
+    Flag,
+}
+
+impl ConsensusConfig {
+    pub fn mode(&self) -> Result<ConsensusMode> {
+        match self.require.as_str() {
+            "both" => Ok(ConsensusMode::Both),
+            "flag" => Ok(ConsensusMode::Flag),
+            other => Err(DriftcheckError::ConfigInvalid(format!(
+                "Unknown llm.consensus.require '{}' (expected \"both\" or \"flag\")",
+                other
+            ))),
+        }
+    }
+}
+
+/// An `[llm.profiles.<name>]` entry; unset fields keep the `[llm]` value
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LlmProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Environment variable holding this profile's API key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl LlmProfile {
+    fn apply(&self, config: &mut LlmConfig) {
+        if let Some(provider) = &self.provider {
+            config.provider = provider.clone();
+        }
+        if let Some(base_url) = &self.base_url {
+            config.base_url = base_url.clone();
+        }
+        if let Some(model) = &self.model {
+            config.model = model.clone();
+        }
+        if self.api_key_env.is_some() {
+            config.api_key_env = self.api_key_env.clone();
+        }
+        if self.max_tokens.is_some() {
+            config.max_tokens = self.max_tokens;
+        }
+    }
+}
+
+/// A task driftcheck calls the LLM for, each of which may use its own profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmTask {
+    /// Search queries for finding related docs
+    Queries,
+    /// The consistency analysis and critic pass
+    Analysis,
+    /// Fix generation and fix review
+    Fixes,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// `[llm.tasks]`: the profile name for each task
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LlmTasks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queries: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixes: Option<String>,
+}
+
+impl LlmTasks {
+    pub fn get(&self, task: LlmTask) -> Option<&str> {
+        match task {
+            LlmTask::Queries => self.queries.as_deref(),
+            LlmTask::Analysis => self.analysis.as_deref(),
+            LlmTask::Fixes => self.fixes.as_deref(),
+        }
+    }
+
+    fn entries(&self) -> [(&'static str, Option<&str>); 3] {
+        [
+            ("queries", self.get(LlmTask::Queries)),
+            ("analysis", self.get(LlmTask::Analysis)),
+            ("fixes", self.get(LlmTask::Fixes)),
+        ]
+    }
+}
+
+/// An `[llm.pricing]` entry, in USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PromptsConfig {
     #[serde(default = "default_analysis_prompt")]
     pub analysis: String,
@@ -92,9 +665,20 @@ pub struct PromptsConfig {
     pub search_queries: String,
     #[serde(default = "default_suggestions_prompt")]
     pub suggestions: String,
+    #[serde(default = "default_critic_prompt")]
+    pub critic: String,
+    #[serde(default = "default_release_prompt")]
+    pub release: String,
+    #[serde(default = "default_sweep_prompt")]
+    pub sweep: String,
+    #[serde(default = "default_dependencies_prompt")]
+    pub dependencies: String,
+    /// Project context (name, terminology, style guide, audience) interpolated into prompts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TuiConfig {
     #[serde(default = "default_theme")]
     pub theme: String,
@@ -102,9 +686,24 @@ pub struct TuiConfig {
     pub show_diff_preview: bool,
     #[serde(default)]
     pub auto_apply: bool,
+    #[serde(default)]
+    pub critic_pass: bool,
+    /// Write fixes to `.git/driftcheck/preview/<run-id>/` and apply them in one final step
+    #[serde(default)]
+    pub preview: bool,
+    /// Let a fix also edit other docs that repeat the drifted text, applied as one transaction
+    #[serde(default = "default_true")]
+    pub multi_file_fixes: bool,
+    /// Days the 'z' key snoozes an issue for
+    #[serde(default = "default_snooze_days")]
+    pub snooze_days: u32,
+    /// Before exiting, analyze the docs fixes were applied to against the diff once more, and
+    /// list the issues that remain. Off by default, since it makes more LLM calls.
+    #[serde(default)]
+    pub verify_fixes: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CacheConfig {
     #[serde(default = "default_true")]
     pub enabled: bool,
@@ -114,11 +713,138 @@ pub struct CacheConfig {
     pub ttl: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReleaseConfig {
+    /// Release-facing docs checked by `driftcheck release-check`
+    #[serde(default = "default_release_paths")]
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SweepConfig {
+    /// Doc sections `driftcheck sweep` verifies per run, riskiest first
+    #[serde(default = "default_sweep_sample")]
+    pub sample: usize,
+}
+
+/// Per-path severity overrides for detected issues.
+/// `ignore` wins over `warn_only` when a file matches both; `[[rules.severity]]` entries
+/// are applied last.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct RulesConfig {
+    /// Doc globs whose issues are dropped entirely (e.g. archived docs)
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Doc globs whose issues are reported but never fail the check or block a push
+    #[serde(default)]
+    pub warn_only: Vec<String>,
+    /// Severity by expression over issue fields; the first entry matching an issue wins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub severity: Vec<SeverityRule>,
+}
+
+/// A `[[rules.severity]]` entry, e.g. `severity = "error"` with
+/// `when = 'category == "signature" && path startswith "docs/api"'`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SeverityRule {
+    /// "error" or "warning"
+    pub severity: String,
+    /// Condition over `analyzer::RULE_FIELDS` (see the `expr` module for the syntax)
+    pub when: String,
+}
+
+impl RulesConfig {
+    /// Every `[[rules.severity]]` entry must name a severity and parse
+    fn check_severity(&self) -> Result<()> {
+        for (i, rule) in self.severity.iter().enumerate() {
+            if !matches!(rule.severity.as_str(), "error" | "warning") {
+                return Err(DriftcheckError::ConfigInvalid(format!(
+                    "rules.severity[{}].severity must be \"error\" or \"warning\", got {:?}",
+                    i, rule.severity
+                )));
+            }
+            Expr::parse(&rule.when, RULE_FIELDS).map_err(|e| {
+                DriftcheckError::ConfigInvalid(format!("rules.severity[{}].when: {}", i, e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// What the pre-push hook does, depending on the remote being pushed to
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HookConfig {
+    /// Action when no `[[hook.remotes]]` entry matches: "block", "warn" or "skip"
+    #[serde(default = "default_hook_action")]
+    pub default_action: String,
+    /// Per-remote actions; the first entry matching the push wins
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remotes: Vec<RemoteRule>,
+}
+
+/// A `[[hook.remotes]]` entry. Unset globs match any push.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemoteRule {
+    /// Glob on the remote name (e.g. "mirror-*")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Glob on the push URL (e.g. "*github.com*acme/*")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// "block", "warn" or "skip"
+    pub action: String,
+}
+
+/// Organization-wide limits the local configuration may not relax (see `policy::enforce`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PolicyConfig {
+    /// Lowest severity issues may be lowered to: "error" forbids `rules.warn_only`, warning
+    /// corpora and `rules.ignore`; "warning" forbids `rules.ignore`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_severity: Option<String>,
+    /// Highest allowed `docs.min_confidence`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_min_confidence: Option<f32>,
+    /// Forbid `general.enabled = false`, hook action "skip", `driftcheck disable` and
+    /// DRIFTCHECK_DISABLED
+    #[serde(default)]
+    pub forbid_disable: bool,
+    /// Every LLM `base_url` (`[llm]`, profiles, consensus) must be one of these or below it
+    /// (any endpoint if empty)
+    #[serde(default)]
+    pub allowed_endpoints: Vec<String>,
+}
+
+impl PolicyConfig {
+    pub fn is_empty(&self) -> bool {
+        self.min_severity.is_none()
+            && self.max_min_confidence.is_none()
+            && !self.forbid_disable
+            && self.allowed_endpoints.is_empty()
+    }
+}
+
 // Default value functions
 fn default_true() -> bool {
     true
 }
 
+fn default_max_issues() -> usize {
+    25
+}
+
+fn default_hook_action() -> String {
+    "block".to_string()
+}
+
+fn default_first_run_policy() -> String {
+    "guarded".to_string()
+}
+
+fn default_sort_by() -> String {
+    "severity".to_string()
+}
+
 fn default_doc_paths() -> Vec<String> {
     vec!["README.md".to_string(), "docs/**/*.md".to_string()]
 }
@@ -127,6 +853,42 @@ fn default_max_context_tokens() -> usize {
     8000
 }
 
+fn default_max_batches() -> usize {
+    4
+}
+
+fn default_refine_rounds() -> usize {
+    2
+}
+
+fn default_search_mode() -> String {
+    "ripgrep".to_string()
+}
+
+fn default_query_mode() -> String {
+    "llm".to_string()
+}
+
+fn default_embedding_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+fn default_top_k() -> usize {
+    12
+}
+
+fn default_min_similarity() -> f32 {
+    0.3
+}
+
+fn default_corpus_severity() -> String {
+    "error".to_string()
+}
+
+fn default_provider() -> String {
+    "openai".to_string()
+}
+
 fn default_base_url() -> String {
     "https://api.openai.com/v1".to_string()
 }
@@ -143,6 +905,30 @@ fn default_max_retries() -> u32 {
     2
 }
 
+fn default_snooze_days() -> u32 {
+    7
+}
+
+fn default_temperature() -> f32 {
+    0.1
+}
+
+fn default_max_concurrency() -> usize {
+    4
+}
+
+fn default_max_rate_limit_wait() -> u64 {
+    120
+}
+
+fn default_consensus_require() -> String {
+    "both".to_string()
+}
+
+fn default_parser() -> String {
+    "auto".to_string()
+}
+
 fn default_analysis_prompt() -> String {
     DEFAULT_ANALYSIS_PROMPT.to_string()
 }
@@ -155,6 +941,37 @@ fn default_suggestions_prompt() -> String {
     DEFAULT_SUGGESTIONS_PROMPT.to_string()
 }
 
+fn default_critic_prompt() -> String {
+    DEFAULT_CRITIC_PROMPT.to_string()
+}
+
+fn default_release_prompt() -> String {
+    DEFAULT_RELEASE_PROMPT.to_string()
+}
+
+fn default_sweep_prompt() -> String {
+    DEFAULT_SWEEP_PROMPT.to_string()
+}
+
+fn default_dependencies_prompt() -> String {
+    DEFAULT_DEPENDENCIES_PROMPT.to_string()
+}
+
+fn default_sweep_sample() -> usize {
+    40
+}
+
+fn default_release_paths() -> Vec<String> {
+    vec![
+        "CHANGELOG.md".to_string(),
+        "CHANGES.md".to_string(),
+        "MIGRATION*.md".to_string(),
+        "UPGRADING*.md".to_string(),
+        "docs/**/migrat*.md".to_string(),
+        "README.md".to_string(),
+    ]
+}
+
 fn default_theme() -> String {
     "default".to_string()
 }
@@ -172,6 +989,14 @@ impl Default for GeneralConfig {
         Self {
             enabled: true,
             allow_push_on_error: false,
+            diff_include: vec![],
+            diff_exclude: vec![],
+            record_feedback: false,
+            max_issues: default_max_issues(),
+            first_run_policy: default_first_run_policy(),
+            sort_by: default_sort_by(),
+            disabled_categories: vec![],
+            dependency_analysis: true,
         }
     }
 }
@@ -182,17 +1007,116 @@ impl Default for DocsConfig {
             paths: default_doc_paths(),
             ignore: vec![],
             max_context_tokens: default_max_context_tokens(),
+            min_confidence: 0.0,
+            max_batches: default_max_batches(),
+            refine_rounds: default_refine_rounds(),
+            smudge_lfs: false,
+            glossary: None,
+            sources: BTreeMap::new(),
+            corpora: BTreeMap::new(),
+        }
+    }
+}
+
+impl DocsConfig {
+    /// The corpora to search, or the top-level `paths` as a single corpus when none are named
+    pub fn corpora(&self) -> Vec<Corpus> {
+        if self.corpora.is_empty() {
+            return vec![Corpus {
+                name: DEFAULT_CORPUS.to_string(),
+                docs: self.clone(),
+                severity: default_corpus_severity(),
+            }];
+        }
+
+        self.corpora
+            .iter()
+            .map(|(name, corpus)| Corpus {
+                name: name.clone(),
+                docs: DocsConfig {
+                    paths: corpus.paths.clone(),
+                    ignore: self.ignore.iter().chain(&corpus.ignore).cloned().collect(),
+                    max_context_tokens: corpus
+                        .max_context_tokens
+                        .unwrap_or(self.max_context_tokens),
+                    min_confidence: self.min_confidence,
+                    max_batches: self.max_batches,
+                    refine_rounds: self.refine_rounds,
+                    smudge_lfs: self.smudge_lfs,
+                    glossary: self.glossary.clone(),
+                    sources: self
+                        .sources
+                        .iter()
+                        .filter(|(_, source)| source.corpus.as_ref() == Some(name))
+                        .map(|(n, source)| (n.clone(), source.clone()))
+                        .collect(),
+                    corpora: BTreeMap::new(),
+                },
+                severity: corpus.severity.clone(),
+            })
+            .collect()
+    }
+
+    /// Every doc source must belong to a corpus that exists
+    fn check_sources(&self) -> Result<()> {
+        for (name, source) in &self.sources {
+            let known = match &source.corpus {
+                Some(corpus) if self.corpora.is_empty() => corpus == DEFAULT_CORPUS,
+                Some(corpus) => self.corpora.contains_key(corpus),
+                None => self.corpora.is_empty(),
+            };
+            if !known {
+                return Err(DriftcheckError::ConfigInvalid(format!(
+                    "docs.sources.{}.corpus must name one of the corpora ({})",
+                    name,
+                    self.corpora()
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            }
         }
+        Ok(())
+    }
+
+    /// Doc path patterns of every corpus
+    pub fn all_paths(&self) -> Vec<String> {
+        self.corpora()
+            .into_iter()
+            .flat_map(|corpus| corpus.docs.paths)
+            .collect()
     }
 }
 
 impl Default for LlmConfig {
     fn default() -> Self {
         Self {
+            provider: default_provider(),
             base_url: default_base_url(),
             model: default_model(),
+            query_model: None,
             timeout: default_timeout(),
             max_retries: default_max_retries(),
+            temperature: default_temperature(),
+            max_tokens: None,
+            top_p: None,
+            seed: None,
+            json_mode: false,
+            structured_output: false,
+            parser: default_parser(),
+            empty_response_as_no_issues: false,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            max_concurrency: default_max_concurrency(),
+            max_rate_limit_wait: default_max_rate_limit_wait(),
+            stream: false,
+            pricing: BTreeMap::new(),
+            api_key_env: None,
+            consensus: None,
+            profiles: BTreeMap::new(),
+            tasks: LlmTasks::default(),
+            usage: Usage::default(),
         }
     }
 }
@@ -203,16 +1127,91 @@ impl Default for PromptsConfig {
             analysis: default_analysis_prompt(),
             search_queries: default_search_queries_prompt(),
             suggestions: default_suggestions_prompt(),
+            critic: default_critic_prompt(),
+            release: default_release_prompt(),
+            sweep: default_sweep_prompt(),
+            dependencies: default_dependencies_prompt(),
+            variables: BTreeMap::new(),
         }
     }
 }
 
+impl PromptsConfig {
+    /// Fill `{{name}}` placeholders in a system prompt from `[prompts.variables]`.
+    /// Prompts that reference no variable get them appended as a project context section,
+    /// so the default prompts pick them up without being rewritten.
+    pub fn render(&self, prompt: &str) -> String {
+        if self.variables.is_empty() {
+            return prompt.to_string();
+        }
+
+        let mut rendered = prompt.to_string();
+        for (name, value) in &self.variables {
+            rendered = rendered.replace(&format!("{{{{{}}}}}", name), value);
+        }
+        if rendered != prompt {
+            return rendered;
+        }
+
+        rendered.push_str("\n\n## Project Context\n");
+        rendered.push_str(
+            "Follow the project's own terminology and conventions when describing issues and writing fixes.\n",
+        );
+        for (name, value) in &self.variables {
+            rendered.push_str(&format!("- {}: {}\n", name.replace('_', " "), value));
+        }
+        rendered
+    }
+}
+
 impl Default for TuiConfig {
     fn default() -> Self {
         Self {
             theme: default_theme(),
             show_diff_preview: true,
             auto_apply: false,
+            critic_pass: false,
+            preview: false,
+            multi_file_fixes: true,
+            snooze_days: default_snooze_days(),
+            verify_fixes: false,
+        }
+    }
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            default_action: default_hook_action(),
+            remotes: Vec::new(),
+        }
+    }
+}
+
+impl Default for ReleaseConfig {
+    fn default() -> Self {
+        Self {
+            paths: default_release_paths(),
+        }
+    }
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            sample: default_sweep_sample(),
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_search_mode(),
+            query_mode: default_query_mode(),
+            embedding_model: default_embedding_model(),
+            top_k: default_top_k(),
+            min_similarity: default_min_similarity(),
         }
     }
 }
@@ -235,13 +1234,100 @@ impl Config {
         Self::load_from_path(&path)
     }
 
+    /// JSON Schema of `.driftcheck.toml`, generated from the config structs, for editor
+    /// completion and validation (e.g. taplo). Unknown keys are errors, except in `[docs]`
+    /// where other tables are corpora.
+    pub fn schema() -> serde_json::Value {
+        let mut schema = serde_json::to_value(schemars::schema_for!(Config))
+            .expect("config schema is serializable");
+        schema["title"] = "driftcheck configuration".into();
+        if let Some(definitions) = schema["definitions"].as_object_mut() {
+            for (name, definition) in definitions.iter_mut() {
+                // Source options depend on the source type
+                definition["additionalProperties"] = (name == "SourceConfig").into();
+            }
+        }
+        // The flattened `corpora` map: every other `[docs.<name>]` table is a corpus
+        schema["definitions"]["DocsConfig"]["additionalProperties"] =
+            serde_json::json!({"$ref": "#/definitions/CorpusConfig"});
+        schema["additionalProperties"] = false.into();
+        schema
+    }
+
     /// Load configuration from a specific path
     pub fn load_from_path(path: &Path) -> Result<Self> {
         let contents = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&contents)?;
+        let mut config: Config = toml::from_str(&contents)?;
+        config.normalize_globs(&contents)?;
+        config.docs.check_sources()?;
+        config.rules.check_severity()?;
+        config.general.check_categories()?;
+        config.llm.check_sampling()?;
+        config.llm.check_consensus()?;
+        config.llm.check_profiles()?;
+        if let Some(name) = Self::profile_name() {
+            config.file_llm = Some(config.llm.clone());
+            config.llm.use_profile(&name)?;
+        }
+        config.root = Self::find_git_root()?;
+        config.org_policy = policy::load_org_policy()?;
+        policy::enforce(&config)?;
         Ok(config)
     }
 
+    /// Validate every doc glob and normalize it in place, so a bad pattern fails the load
+    /// with its key, index and line in `contents` instead of a warning mid-search
+    fn normalize_globs(&mut self, contents: &str) -> Result<()> {
+        let mut lists: Vec<(String, &mut Vec<String>)> = vec![
+            ("docs.paths".to_string(), &mut self.docs.paths),
+            ("docs.ignore".to_string(), &mut self.docs.ignore),
+            ("release.paths".to_string(), &mut self.release.paths),
+        ];
+        for (name, corpus) in self.docs.corpora.iter_mut() {
+            lists.push((format!("docs.{}.paths", name), &mut corpus.paths));
+            lists.push((format!("docs.{}.ignore", name), &mut corpus.ignore));
+        }
+
+        for (key, patterns) in lists {
+            for (i, pattern) in patterns.iter_mut().enumerate() {
+                if let Err(problem) = search::check_pattern(pattern) {
+                    let quoted = toml::Value::String(pattern.clone()).to_string();
+                    let line = contents
+                        .lines()
+                        .position(|l| l.contains(&quoted))
+                        .map(|n| format!(" (line {})", n + 1))
+                        .unwrap_or_default();
+                    return Err(DriftcheckError::ConfigInvalid(format!(
+                        "{}[{}] = {}{}: {}",
+                        key, i, quoted, line, problem
+                    )));
+                }
+                *pattern = search::normalize_pattern(pattern);
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve a repository-relative path against the repository root. Paths under `.git/`
+    /// resolve into the repository's git directory, which is elsewhere in worktrees and
+    /// submodules.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        Self::resolve_in(&self.root, path)
+    }
+
+    /// `resolve` against a repository root, without a loaded configuration. Pages of remote
+    /// doc sources (`@<source>/<page>.md`) resolve to their cached copy.
+    pub fn resolve_in(root: &Path, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        if let Some(cached) = search::remote::cache_path(root, path) {
+            return cached;
+        }
+        match path.strip_prefix(".git") {
+            Ok(rest) if path.is_relative() => git::common_dir(root).join(rest),
+            _ => root.join(path),
+        }
+    }
+
     /// Find the configuration file path
     pub fn find_config_path() -> Result<PathBuf> {
         // Check environment variable first
@@ -270,13 +1356,14 @@ impl Config {
         Err(DriftcheckError::ConfigNotFound)
     }
 
-    /// Find the git repository root
+    /// Find the git repository root: the nearest directory with a `.git` directory, or a
+    /// `.git` file redirecting to one (worktrees, submodules)
     pub fn find_git_root() -> Result<PathBuf> {
         let current = env::current_dir()?;
         let mut path = current.as_path();
 
         loop {
-            if path.join(".git").exists() {
+            if git::git_dir(path).is_some() {
                 return Ok(path.to_path_buf());
             }
 
@@ -287,8 +1374,16 @@ impl Config {
         }
     }
 
+    /// The policy in effect: the organization policy file if present, else `[policy]`
+    pub fn policy(&self) -> &PolicyConfig {
+        self.org_policy.as_ref().unwrap_or(&self.policy)
+    }
+
     /// Check if driftcheck is enabled (config + env var)
     pub fn is_enabled(&self) -> bool {
+        if self.policy().forbid_disable {
+            return true;
+        }
         if env::var("DRIFTCHECK_DISABLED")
             .map(|v| v == "1")
             .unwrap_or(false)
@@ -325,6 +1420,44 @@ impl Config {
             .unwrap_or(false)
     }
 
+    /// Enable read-only mode for the rest of the process
+    pub fn set_read_only() {
+        READ_ONLY.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if read-only mode is enabled (--read-only flag or env var)
+    pub fn is_read_only() -> bool {
+        READ_ONLY.load(Ordering::Relaxed)
+            || env::var("DRIFTCHECK_READ_ONLY")
+                .map(|v| v == "1")
+                .unwrap_or(false)
+    }
+
+    /// Use the named `[llm.profiles]` entry for every task in configurations loaded from now on
+    pub fn set_profile(name: &str) {
+        if let Ok(mut profile) = PROFILE.lock() {
+            *profile = Some(name.to_string());
+        }
+    }
+
+    /// The profile selected with `--profile` or DRIFTCHECK_PROFILE
+    pub fn profile_name() -> Option<String> {
+        PROFILE
+            .lock()
+            .ok()
+            .and_then(|profile| profile.clone())
+            .or_else(|| env::var("DRIFTCHECK_PROFILE").ok())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Fail if read-only mode forbids the given write action
+    pub fn ensure_writable(action: &str) -> Result<()> {
+        if Self::is_read_only() {
+            return Err(DriftcheckError::ReadOnly(action.to_string()));
+        }
+        Ok(())
+    }
+
     /// Save the configuration to the default path
     pub fn save(&self) -> Result<()> {
         let git_root = Self::find_git_root()?;
@@ -332,11 +1465,201 @@ impl Config {
         self.save_to_path(&path)
     }
 
-    /// Save the configuration to a specific path
+    /// Save the configuration to a specific path. A profile selected for this run is not
+    /// persisted: `[llm]` is written back as it was loaded.
     pub fn save_to_path(&self, path: &Path) -> Result<()> {
-        let contents = toml::to_string_pretty(self)
-            .map_err(|e| DriftcheckError::ConfigInvalid(e.to_string()))?;
+        Self::ensure_writable("saving the configuration")?;
+        let contents = match &self.file_llm {
+            Some(llm) => toml::to_string_pretty(&Config {
+                llm: llm.clone(),
+                ..self.clone()
+            }),
+            None => toml::to_string_pretty(self),
+        }
+        .map_err(|e| DriftcheckError::ConfigInvalid(e.to_string()))?;
         fs::write(path, contents)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema() {
+        let schema = Config::schema();
+        let definitions = &schema["definitions"];
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(
+            schema["properties"]["llm"]["allOf"][0]["$ref"],
+            "#/definitions/LlmConfig"
+        );
+        assert_eq!(
+            definitions["LlmConfig"]["properties"]["model"]["default"],
+            "gpt-4o"
+        );
+        assert_eq!(definitions["LlmConfig"]["additionalProperties"], false);
+        assert_eq!(
+            definitions["DocsConfig"]["additionalProperties"]["$ref"],
+            "#/definitions/CorpusConfig"
+        );
+        assert_eq!(definitions["CorpusConfig"]["required"][0], "paths");
+        assert!(schema["properties"].get("root").is_none());
+    }
+
+    #[test]
+    fn test_docs_corpora() {
+        let config: Config = toml::from_str(
+            r#"
+            [docs]
+            ignore = ["**/CHANGELOG.md"]
+            max_context_tokens = 6000
+
+            [docs.user]
+            paths = ["README.md", "docs/guide/**/*.md"]
+            max_context_tokens = 4000
+
+            [docs.internal]
+            paths = ["docs/design/**/*.md"]
+            ignore = ["docs/design/archive/**"]
+            severity = "warning"
+            "#,
+        )
+        .unwrap();
+
+        let corpora = config.docs.corpora();
+        assert_eq!(corpora.len(), 2);
+        assert_eq!(corpora[0].name, "internal");
+        assert_eq!(corpora[0].severity, "warning");
+        assert_eq!(corpora[0].docs.max_context_tokens, 6000);
+        assert_eq!(
+            corpora[0].docs.ignore,
+            vec!["**/CHANGELOG.md", "docs/design/archive/**"]
+        );
+        assert_eq!(corpora[1].name, "user");
+        assert_eq!(corpora[1].severity, "error");
+        assert_eq!(corpora[1].docs.max_context_tokens, 4000);
+
+        // Corpora survive a save
+        let saved: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(saved.docs.corpora.len(), 2);
+
+        let default = Config::default().docs.corpora();
+        assert_eq!(default.len(), 1);
+        assert_eq!(default[0].name, DEFAULT_CORPUS);
+    }
+
+    #[test]
+    fn test_normalize_globs() {
+        let contents = r#"
+            [docs]
+            paths = ["./README.md", "docs\\guide/*.md"]
+
+            [docs.internal]
+            paths = ["docs/design/[.md"]
+            "#;
+        let mut config: Config = toml::from_str(contents).unwrap();
+        let err = config.normalize_globs(contents).unwrap_err().to_string();
+        assert!(err.contains(r#"docs.internal.paths[0] = "docs/design/[.md" (line 6)"#));
+
+        config.docs.corpora.clear();
+        config.normalize_globs(contents).unwrap();
+        assert_eq!(config.docs.paths, vec!["README.md", "docs/guide/*.md"]);
+    }
+
+    #[test]
+    fn test_llm_profiles() {
+        let config: Config = toml::from_str(
+            r#"
+            [llm]
+            model = "gpt-4o"
+
+            [llm.profiles.cheap]
+            model = "gpt-4o-mini"
+
+            [llm.profiles.claude]
+            provider = "anthropic"
+            base_url = "https://api.anthropic.com/v1"
+            model = "claude-sonnet-4-5"
+            api_key_env = "ANTHROPIC_API_KEY"
+
+            [llm.tasks]
+            queries = "cheap"
+            fixes = "claude"
+            "#,
+        )
+        .unwrap();
+        let llm = &config.llm;
+        llm.check_profiles().unwrap();
+
+        assert_eq!(llm.for_task(LlmTask::Queries).model, "gpt-4o-mini");
+        assert_eq!(llm.for_task(LlmTask::Queries).provider, "openai");
+        assert_eq!(llm.task_model(LlmTask::Analysis), "gpt-4o");
+        let fixes = llm.for_task(LlmTask::Fixes);
+        assert_eq!(fixes.provider, "anthropic");
+        assert_eq!(fixes.api_key_env.as_deref(), Some("ANTHROPIC_API_KEY"));
+
+        let mut forced = llm.clone();
+        forced.use_profile("cheap").unwrap();
+        assert_eq!(forced.task_model(LlmTask::Fixes), "gpt-4o-mini");
+        assert!(forced
+            .use_profile("strong")
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown profile 'strong' (defined: cheap, claude)"));
+
+        let mut quick = LlmConfig {
+            query_model: Some("gpt-4o-mini".to_string()),
+            ..LlmConfig::default()
+        };
+        assert_eq!(quick.for_task(LlmTask::Queries).model, "gpt-4o-mini");
+        assert_eq!(quick.task_model(LlmTask::Queries), "gpt-4o-mini");
+        assert_eq!(quick.task_model(LlmTask::Analysis), "gpt-4o");
+        quick.tasks.queries = Some("cheap".to_string());
+        assert!(quick.check_profiles().is_err());
+
+        let mut typo = llm.clone();
+        typo.tasks.analysis = Some("stronk".to_string());
+        assert!(typo
+            .check_profiles()
+            .unwrap_err()
+            .to_string()
+            .contains("llm.tasks.analysis: Unknown profile 'stronk'"));
+
+        // Saving a config loaded with `--profile` keeps the file's `[llm]`
+        let mut profiled = config.clone();
+        profiled.file_llm = Some(profiled.llm.clone());
+        profiled.llm.use_profile("claude").unwrap();
+        let path =
+            std::env::temp_dir().join(format!("driftcheck-profile-{}.toml", std::process::id()));
+        profiled.save_to_path(&path).unwrap();
+        let saved: Config = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved.llm.model, "gpt-4o");
+        assert_eq!(saved.llm.tasks.queries.as_deref(), Some("cheap"));
+        assert_eq!(saved.llm.tasks.fixes.as_deref(), Some("claude"));
+    }
+
+    #[test]
+    fn test_prompt_variables() {
+        let mut prompts = PromptsConfig::default();
+        assert_eq!(prompts.render("Check the docs."), "Check the docs.");
+
+        prompts
+            .variables
+            .insert("project_name".to_string(), "Acme CLI".to_string());
+        prompts
+            .variables
+            .insert("audience".to_string(), "platform engineers".to_string());
+
+        assert_eq!(
+            prompts.render("Check the {{project_name}} docs."),
+            "Check the Acme CLI docs."
+        );
+        let appended = prompts.render("Check the docs.");
+        assert!(appended.starts_with("Check the docs.\n\n## Project Context\n"));
+        assert!(appended.contains("- project name: Acme CLI\n"));
+        assert!(appended.contains("- audience: platform engineers\n"));
+    }
+}
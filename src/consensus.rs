@@ -0,0 +1,128 @@
+use crate::config::ConsensusMode;
+use crate::llm::RawIssue;
+use serde::Serialize;
+
+/// Confidence given to an issue only one provider found, with `require = "flag"`
+pub const SINGLE_SOURCE_CONFIDENCE: f32 = 0.3;
+
+/// Lines two providers' issues in the same doc file may be apart and still count as one
+const LINE_TOLERANCE: usize = 3;
+
+/// What one provider of a consensus analysis reported, before merging
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderIssues {
+    pub provider: String,
+    pub model: String,
+    pub issues: Vec<RawIssue>,
+}
+
+/// Add one call's issues to what `model` reported in `results`
+pub fn record(results: &mut Vec<ProviderIssues>, provider: &str, model: &str, issues: &[RawIssue]) {
+    match results
+        .iter_mut()
+        .find(|r| r.provider == provider && r.model == model)
+    {
+        Some(result) => result.issues.extend_from_slice(issues),
+        None => results.push(ProviderIssues {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            issues: issues.to_vec(),
+        }),
+    }
+}
+
+/// Merge two providers' issues. Issues both found are kept once, with the higher
+/// confidence; the others are dropped, or kept at low confidence with `ConsensusMode::Flag`.
+pub fn merge(first: Vec<RawIssue>, second: Vec<RawIssue>, mode: ConsensusMode) -> Vec<RawIssue> {
+    let mut second: Vec<Option<RawIssue>> = second.into_iter().map(Some).collect();
+    let mut merged = Vec::new();
+    let mut single = Vec::new();
+
+    for mut issue in first {
+        let other = second
+            .iter_mut()
+            .find(|other| {
+                other
+                    .as_ref()
+                    .is_some_and(|other| same_issue(&issue, other))
+            })
+            .and_then(Option::take);
+        match other {
+            Some(other) => {
+                issue.confidence = match (issue.confidence, other.confidence) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    (a, b) => a.or(b),
+                };
+                merged.push(issue);
+            }
+            None => single.push(issue),
+        }
+    }
+
+    if mode == ConsensusMode::Flag {
+        for mut issue in single.into_iter().chain(second.into_iter().flatten()) {
+            issue.confidence = Some(
+                issue
+                    .confidence
+                    .unwrap_or(1.0)
+                    .min(SINGLE_SOURCE_CONFIDENCE),
+            );
+            merged.push(issue);
+        }
+    }
+    merged
+}
+
+/// Whether two providers reported the same drift: the same doc file, and the same excerpt or
+/// nearby lines
+fn same_issue(a: &RawIssue, b: &RawIssue) -> bool {
+    let excerpt = |issue: &RawIssue| {
+        issue
+            .doc_excerpt
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    a.file == b.file
+        && ((!a.doc_excerpt.trim().is_empty() && excerpt(a) == excerpt(b))
+            || a.line.abs_diff(b.line) <= LINE_TOLERANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(file: &str, line: usize, excerpt: &str, confidence: f32) -> RawIssue {
+        RawIssue {
+            file: file.to_string(),
+            line,
+            description: format!("{}:{}", file, line),
+            doc_excerpt: excerpt.to_string(),
+            suggested_fix: None,
+            confidence: Some(confidence),
+            code_symbol: None,
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let first = vec![
+            issue("README.md", 10, "timeout = 30", 0.6),
+            issue("README.md", 80, "", 0.9),
+        ];
+        let second = vec![
+            issue("README.md", 40, "timeout  =  30", 0.8),
+            issue("docs/cli.md", 80, "", 0.7),
+        ];
+
+        let both = merge(first.clone(), second.clone(), ConsensusMode::Both);
+        assert_eq!(both.len(), 1);
+        assert_eq!((both[0].line, both[0].confidence), (10, Some(0.8)));
+
+        let flagged = merge(first, second, ConsensusMode::Flag);
+        assert_eq!(flagged.len(), 3);
+        assert!(flagged[1..]
+            .iter()
+            .all(|i| i.confidence == Some(SINGLE_SOURCE_CONFIDENCE)));
+    }
+}
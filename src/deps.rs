@@ -0,0 +1,330 @@
+use crate::git;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Share of a diff's changed lines in manifests and lockfiles from which it counts as a
+/// dependency bump
+const BUMP_SHARE: f32 = 0.8;
+
+/// Queries for docs that state versions without naming a dependency
+const VERSION_QUERIES: &[&str] = &[
+    r"(?i)\bMSRV\b",
+    r"(?i)minimum supported",
+    r"(?i)compatib(le|ility)",
+];
+
+/// Files declaring dependency or toolchain versions
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "go.mod",
+    "Gemfile",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+    ".nvmrc",
+    ".node-version",
+    ".python-version",
+    ".ruby-version",
+    ".tool-versions",
+];
+
+/// Resolved dependency trees, whose churn never makes docs wrong by itself
+const LOCKFILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "uv.lock",
+    "Pipfile.lock",
+    "go.sum",
+    "Gemfile.lock",
+    "composer.lock",
+];
+
+/// A dependency or toolchain version changed in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bump {
+    pub manifest: String,
+    pub name: String,
+    /// `None` for an added dependency
+    pub from: Option<String>,
+    /// `None` for a removed dependency
+    pub to: Option<String>,
+}
+
+/// A diff that mostly bumps versions: the manifest changes, and the changes to other files
+#[derive(Debug, Clone, Default)]
+pub struct BumpDiff {
+    /// Manifest sections of the diff
+    pub manifests: String,
+    /// Sections of files that are neither manifests nor lockfiles, analyzed as usual
+    pub rest: String,
+    pub bumps: Vec<Bump>,
+}
+
+/// Split `diff` if at least `BUMP_SHARE` of its changed lines are in manifests and lockfiles
+pub fn split(diff: &str) -> Option<BumpDiff> {
+    let mut split = BumpDiff::default();
+    let (mut dependency_lines, mut total_lines) = (0, 0);
+    for (path, section) in git::file_diffs(diff) {
+        let changed = section
+            .lines()
+            .filter(|line| !line.starts_with("+++") && !line.starts_with("---"))
+            .filter(|line| line.starts_with('+') || line.starts_with('-'))
+            .count();
+        total_lines += changed;
+        if is_manifest(&path) {
+            dependency_lines += changed;
+            split.bumps.extend(bumps(&path, &section));
+            split.manifests.push_str(&section);
+        } else if is_lockfile(&path) {
+            dependency_lines += changed;
+        } else {
+            split.rest.push_str(&section);
+        }
+    }
+
+    let share = dependency_lines as f32 / total_lines.max(1) as f32;
+    (dependency_lines > 0 && share >= BUMP_SHARE).then_some(split)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn is_manifest(path: &str) -> bool {
+    let name = file_name(path);
+    MANIFESTS.contains(&name) || (name.starts_with("requirements") && name.ends_with(".txt"))
+}
+
+fn is_lockfile(path: &str) -> bool {
+    LOCKFILES.contains(&file_name(path))
+}
+
+/// Versions changed in one manifest's diff section, pairing removed and added lines by name
+pub fn bumps(path: &str, section: &str) -> Vec<Bump> {
+    let mut removed: Vec<(String, String)> = Vec::new();
+    let mut added: Vec<(String, String)> = Vec::new();
+    for line in section.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        }
+        let (list, text) = match (line.strip_prefix('-'), line.strip_prefix('+')) {
+            (Some(text), _) => (&mut removed, text),
+            (_, Some(text)) => (&mut added, text),
+            _ => continue,
+        };
+        if let Some(version) = version_line(path, text) {
+            list.push(version);
+        }
+    }
+
+    let mut bumps: Vec<Bump> = Vec::new();
+    for (name, from) in &removed {
+        let to = added
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone());
+        if to.as_ref() != Some(from) {
+            bumps.push(Bump {
+                manifest: path.to_string(),
+                name: name.clone(),
+                from: Some(from.clone()),
+                to,
+            });
+        }
+    }
+    for (name, to) in &added {
+        if !removed.iter().any(|(n, _)| n == name) {
+            bumps.push(Bump {
+                manifest: path.to_string(),
+                name: name.clone(),
+                from: None,
+                to: Some(to.clone()),
+            });
+        }
+    }
+    bumps
+}
+
+/// Name and version declared on one manifest line
+fn version_line(path: &str, line: &str) -> Option<(String, String)> {
+    static KEY_VALUE: OnceLock<Regex> = OnceLock::new();
+    static REQUIREMENT: OnceLock<Regex> = OnceLock::new();
+    static GO: OnceLock<Regex> = OnceLock::new();
+    static GEM: OnceLock<Regex> = OnceLock::new();
+    static VERSION: OnceLock<Regex> = OnceLock::new();
+    // `serde = "1.0"`, `serde = { version = "1.0" }`, `"react": "^18.2.0"`
+    let key_value = KEY_VALUE.get_or_init(|| {
+        Regex::new(
+            r#"^\s*"?([@A-Za-z0-9_.\-/]+)"?\s*[=:]\s*(?:\{[^}]*?\bversion\s*=\s*)?"([^"]+)""#,
+        )
+        .unwrap()
+    });
+    // `requests>=2.31`, also quoted in pyproject.toml dependency lists
+    let requirement = REQUIREMENT.get_or_init(|| {
+        Regex::new(r#"^\s*"?([A-Za-z0-9_.\-]+)(?:\[[^\]]*\])?\s*((?:==|>=|~=|<=|!=|<|>)\s*[0-9][^\s;,#"]*)"#)
+            .unwrap()
+    });
+    // `require example.com/mod v1.2.3`, `go 1.22`
+    let go = GO.get_or_init(|| {
+        Regex::new(r"^\s*(?:require\s+)?(go|toolchain|[a-z0-9.\-]+\.[a-z]+/\S+)\s+(v?\d\S*)")
+            .unwrap()
+    });
+    // `gem "rails", "~> 7.1"`
+    let gem = GEM.get_or_init(|| {
+        Regex::new(r#"^\s*gem\s+["']([^"']+)["']\s*,\s*["']([^"']+)["']"#).unwrap()
+    });
+    let version =
+        VERSION.get_or_init(|| Regex::new(r"^[\^~<>=!v\s]*\d+(?:\.[0-9x*]+)*\S*$").unwrap());
+
+    let name = file_name(path);
+    let found = match name {
+        ".nvmrc" | ".node-version" => Some(("node".to_string(), line.trim().to_string())),
+        ".python-version" => Some(("python".to_string(), line.trim().to_string())),
+        ".ruby-version" => Some(("ruby".to_string(), line.trim().to_string())),
+        "rust-toolchain" => Some(("rust".to_string(), line.trim().to_string())),
+        ".tool-versions" => line
+            .split_once(char::is_whitespace)
+            .map(|(tool, v)| (tool.to_string(), v.trim().to_string())),
+        "go.mod" => go
+            .captures(line)
+            .map(|c| (c[1].to_string(), c[2].to_string())),
+        "Gemfile" => gem
+            .captures(line)
+            .map(|c| (c[1].to_string(), c[2].to_string())),
+        _ => requirement
+            .captures(line)
+            .or_else(|| key_value.captures(line))
+            .map(|c| (c[1].to_string(), c[2].trim().to_string())),
+    };
+    found.filter(|(name, v)| !name.is_empty() && version.is_match(v))
+}
+
+/// Search queries for docs mentioning the bumped dependencies or their old versions
+pub fn queries(bumps: &[Bump]) -> Vec<String> {
+    let mut queries: Vec<String> = Vec::new();
+    let mut add = |query: String| {
+        if !queries.contains(&query) {
+            queries.push(query);
+        }
+    };
+    for bump in bumps {
+        if bump.name.len() >= 2 {
+            add(regex::escape(&bump.name));
+        }
+        // Bare "1" or "18" would match everywhere; dotted versions are distinctive
+        if let Some(from) = bump.from.as_deref().map(bare_version) {
+            if from.contains('.') {
+                add(regex::escape(from));
+            }
+        }
+    }
+    for query in VERSION_QUERIES {
+        add(query.to_string());
+    }
+    queries
+}
+
+/// `1.2.3` of `^1.2.3`, `>=1.2.3` or `v1.2.3`
+fn bare_version(version: &str) -> &str {
+    version.trim_start_matches(|c: char| !c.is_ascii_digit())
+}
+
+/// The version changes as a list for the prompt
+pub fn describe(bumps: &[Bump]) -> String {
+    bumps
+        .iter()
+        .map(|bump| {
+            let change = match (&bump.from, &bump.to) {
+                (Some(from), Some(to)) => format!("{} -> {}", from, to),
+                (Some(from), None) => format!("{} -> removed", from),
+                (None, Some(to)) => format!("added at {}", to),
+                (None, None) => "changed".to_string(),
+            };
+            format!("- {} ({}): {}", bump.name, bump.manifest, change)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_dependency_bump() {
+        let diff = "diff --git a/Cargo.toml b/Cargo.toml
+--- a/Cargo.toml
++++ b/Cargo.toml
+@@ -3,5 +3,5 @@
+-rust-version = \"1.70\"
++rust-version = \"1.75\"
+-tokio = { version = \"1.28\", features = [\"full\"] }
++tokio = { version = \"1.36\", features = [\"full\"] }
+ serde = \"1.0\"
+-atty = \"0.2\"
++console = \"0.15\"
+diff --git a/Cargo.lock b/Cargo.lock
+--- a/Cargo.lock
++++ b/Cargo.lock
+@@ -1,4 +1,4 @@
+-version = \"1.28.0\"
++version = \"1.36.0\"
+-checksum = \"aaa\"
++checksum = \"bbb\"
+diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1 +1 @@
+-use atty;
++use console;
+";
+        let split = split(diff).unwrap();
+        assert!(split.manifests.starts_with("diff --git a/Cargo.toml"));
+        assert!(split.rest.starts_with("diff --git a/src/main.rs"));
+        let changes: Vec<(&str, Option<&str>, Option<&str>)> = split
+            .bumps
+            .iter()
+            .map(|b| (b.name.as_str(), b.from.as_deref(), b.to.as_deref()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("rust-version", Some("1.70"), Some("1.75")),
+                ("tokio", Some("1.28"), Some("1.36")),
+                ("atty", Some("0.2"), None),
+                ("console", None, Some("0.15")),
+            ]
+        );
+        assert!(describe(&split.bumps).contains("- tokio (Cargo.toml): 1.28 -> 1.36"));
+        assert_eq!(
+            &queries(&split.bumps)[..4],
+            &["rust\\-version", "1\\.70", "tokio", "1\\.28"]
+        );
+
+        // Mostly code: the usual analysis
+        let code = diff.replace("-use atty;\n+use console;\n", &"+code\n".repeat(20));
+        assert!(super::split(&code).is_none());
+
+        assert_eq!(
+            version_line("package.json", r#"    "react": "^18.2.0","#),
+            Some(("react".to_string(), "^18.2.0".to_string()))
+        );
+        assert_eq!(
+            version_line(
+                "requirements-dev.txt",
+                "requests[socks]>=2.31 ; python_version > '3'"
+            ),
+            Some(("requests".to_string(), ">=2.31".to_string()))
+        );
+        assert_eq!(
+            version_line("go.mod", "\tgolang.org/x/net v0.21.0 // indirect"),
+            Some(("golang.org/x/net".to_string(), "v0.21.0".to_string()))
+        );
+        assert_eq!(version_line("package.json", r#""test": "jest""#), None);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::paths;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// Directory names that usually hold documentation
+const DOC_DIR_NAMES: &[&str] = &[
+    "docs",
+    "doc",
+    "documentation",
+    "wiki",
+    "man",
+    "guide",
+    "guides",
+];
+
+/// Extensions treated as documentation
+const DOC_EXTENSIONS: &[&str] = &["md", "mdx", "rst", "adoc", "txt"];
+
+/// Directories never worth scanning
+const SKIP_DIRS: &[&str] = &[".git", "target", "node_modules", "vendor", "dist", "build"];
+
+/// How deep to look for documentation directories
+const MAX_DEPTH: usize = 3;
+
+/// Scan the repository for likely documentation locations and return glob patterns
+/// relative to `root`, suitable for `docs.paths`.
+pub fn discover_doc_paths(root: &Path) -> Vec<String> {
+    let mut patterns = BTreeSet::new();
+
+    // Top-level markdown files (README, CONTRIBUTING, ...)
+    if let Ok(entries) = fs::read_dir(root) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file() && doc_extension(&path).as_deref() == Some("md") {
+                if let Some(name) = path.file_name() {
+                    let name = name.to_string_lossy();
+                    if !name.eq_ignore_ascii_case("CHANGELOG.md") {
+                        patterns.insert(name.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    scan_dir(root, root, 0, &mut patterns);
+
+    patterns.into_iter().collect()
+}
+
+fn scan_dir(root: &Path, dir: &Path, depth: usize, patterns: &mut BTreeSet<String>) {
+    if depth >= MAX_DEPTH {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if SKIP_DIRS.contains(&name.as_str()) || name.starts_with('.') {
+            continue;
+        }
+
+        if DOC_DIR_NAMES.contains(&name.as_str()) {
+            let rel = paths::to_slash(path.strip_prefix(root).unwrap_or(&path));
+            for ext in collect_extensions(&path, name == "man") {
+                patterns.insert(format!("{}/**/*.{}", rel, ext));
+            }
+            continue;
+        }
+
+        scan_dir(root, &path, depth + 1, patterns);
+    }
+}
+
+/// Collect documentation extensions present anywhere below `dir`
+fn collect_extensions(dir: &Path, man_pages: bool) -> BTreeSet<String> {
+    let mut exts = BTreeSet::new();
+    let mut stack = vec![dir.to_path_buf()];
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if let Some(ext) = doc_extension(&path) {
+                exts.insert(ext);
+            } else if man_pages {
+                // Man pages use the section number as extension (e.g. driftcheck.1)
+                if let Some(ext) = path.extension().map(|e| e.to_string_lossy().to_string()) {
+                    if ext.len() == 1 && ext.chars().all(|c| c.is_ascii_digit()) {
+                        exts.insert(ext);
+                    }
+                }
+            }
+        }
+    }
+
+    exts
+}
+
+fn doc_extension(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    DOC_EXTENSIONS.contains(&ext.as_str()).then_some(ext)
+}
+
+/// Return discovered documentation directories not covered by any configured doc path.
+/// Top-level files are left out since they are usually excluded on purpose.
+pub fn uncovered(discovered: &[String], configured: &[String]) -> Vec<String> {
+    discovered
+        .iter()
+        .filter_map(|p| p.split_once("/**").map(|(dir, _)| (p, dir)))
+        .filter(|(_, dir)| !configured.iter().any(|c| c.starts_with(dir)))
+        .map(|(p, _)| p.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uncovered_skips_configured_dirs() {
+        let discovered = vec![
+            "README.md".to_string(),
+            "docs/**/*.md".to_string(),
+            "website/docs/**/*.mdx".to_string(),
+        ];
+        let configured = vec!["README.md".to_string(), "docs/**/*.md".to_string()];
+
+        assert_eq!(
+            uncovered(&discovered, &configured),
+            vec!["website/docs/**/*.mdx".to_string()]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use crate::llm::{DocChunk, DocKind};
+
+/// Suffix marking a doc path pattern whose files are searched for doc comments only,
+/// e.g. `src/**/*.rs:docstrings`
+pub const SUFFIX: &str = ":docstrings";
+
+/// Extract the doc comments of a source file as chunks, one per comment block, keeping the
+/// original lines (comment markers included) and their 1-based line numbers.
+/// Supports Rust `///` and `//!`, Python docstrings, and JSDoc `/** */` blocks.
+pub fn extract(file: &str, content: &str) -> Vec<DocChunk> {
+    let ext = file.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+    let lines: Vec<&str> = content.lines().collect();
+
+    let blocks = match ext.to_ascii_lowercase().as_str() {
+        "rs" => rust_blocks(&lines),
+        "py" | "pyi" => python_blocks(&lines),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => jsdoc_blocks(&lines),
+        _ => Vec::new(),
+    };
+
+    blocks
+        .into_iter()
+        .map(|(start, end)| DocChunk {
+            file: file.to_string(),
+            start_line: start + 1,
+            end_line: end + 1,
+            content: lines[start..=end].join("\n"),
+            kind: DocKind::Docstring,
+        })
+        .collect()
+}
+
+/// Runs of `///` or `//!` lines (0-based, inclusive)
+fn rust_blocks(lines: &[&str]) -> Vec<(usize, usize)> {
+    let is_doc = |line: &str| {
+        let line = line.trim_start();
+        (line.starts_with("///") && !line.starts_with("////")) || line.starts_with("//!")
+    };
+
+    let mut blocks = Vec::new();
+    let mut start = None;
+    for (i, line) in lines.iter().enumerate() {
+        match (is_doc(line), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                blocks.push((s, i - 1));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        blocks.push((s, lines.len() - 1));
+    }
+    blocks
+}
+
+/// Triple-quoted strings that open a module, or follow a `def`/`class` header
+fn python_blocks(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    // Whether the next statement is the first of a module or of a def/class body
+    let mut expects_docstring = true;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim();
+        if line.is_empty() || line.starts_with('#') {
+            i += 1;
+            continue;
+        }
+
+        let body = line.trim_start_matches(['r', 'R', 'u', 'U']);
+        let quote = ["\"\"\"", "'''"].into_iter().find(|q| body.starts_with(q));
+        if let (true, Some(quote)) = (expects_docstring, quote) {
+            let rest = &body[quote.len()..];
+            let end = if rest.contains(quote) {
+                i
+            } else {
+                (i + 1..lines.len())
+                    .find(|&j| lines[j].contains(quote))
+                    .unwrap_or(lines.len() - 1)
+            };
+            blocks.push((i, end));
+            i = end + 1;
+            expects_docstring = false;
+            continue;
+        }
+
+        expects_docstring = (line.starts_with("def ")
+            || line.starts_with("async def ")
+            || line.starts_with("class "))
+            && line.ends_with(':');
+        // Multi-line signatures end with the colon on a later line
+        if !expects_docstring && line.ends_with(':') {
+            expects_docstring = line.starts_with(')') || line.contains(") ->");
+        }
+        i += 1;
+    }
+    blocks
+}
+
+/// `/** ... */` blocks
+fn jsdoc_blocks(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if line.starts_with("/**") && !line.starts_with("/**/") {
+            let end = (i..lines.len())
+                .find(|&j| {
+                    let from = if j == i { 3 } else { 0 };
+                    lines[j]
+                        .trim_start()
+                        .get(from..)
+                        .is_some_and(|l| l.contains("*/"))
+                })
+                .unwrap_or(lines.len() - 1);
+            blocks.push((i, end));
+            i = end + 1;
+        } else {
+            i += 1;
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(file: &str, content: &str) -> Vec<(usize, usize)> {
+        extract(file, content)
+            .iter()
+            .map(|c| (c.start_line, c.end_line))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_docstrings() {
+        let rust = "//! Crate docs\n\nuse std::fs;\n\n/// Reads the config.\n/// Defaults to 30s.\nfn load() {}\n// plain comment\n";
+        assert_eq!(spans("src/lib.rs", rust), vec![(1, 1), (5, 6)]);
+
+        let python = "\"\"\"Module docs.\"\"\"\n\nimport os\n\ndef load(path):\n    \"\"\"Read the config.\n\n    Defaults to 30s.\n    \"\"\"\n    x = \"\"\"not a docstring\"\"\"\n";
+        assert_eq!(spans("app/config.py", python), vec![(1, 1), (6, 9)]);
+
+        let ts = "/** Single line */\nexport const a = 1;\n\n/**\n * Loads the config.\n * @param path file\n */\nexport function load(path: string) {}\n/* plain */\n";
+        assert_eq!(spans("src/config.ts", ts), vec![(1, 1), (4, 7)]);
+
+        let chunk = &extract("src/lib.rs", rust)[1];
+        assert_eq!(chunk.content, "/// Reads the config.\n/// Defaults to 30s.");
+        assert_eq!(chunk.kind, DocKind::Docstring);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::config::{Config, Corpus};
+use crate::docstrings;
+use crate::error::{DriftcheckError, Result};
+use crate::git;
+use crate::llm::{DocChunk, DocKind, LlmClient};
+use crate::search::sources;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::field::Empty;
+use tracing::{debug, info, warn, Span};
+
+/// Index file inside the cache directory
+const INDEX_FILE: &str = "embeddings.json";
+
+/// Longest chunk, in lines; longer sections are split
+const MAX_CHUNK_LINES: usize = 40;
+
+/// Longest text sent for one embedding, in characters
+const MAX_EMBED_CHARS: usize = 8000;
+
+/// Embedding vectors keyed by chunk (see `chunk_key`)
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+/// Doc chunks of every corpus most similar to the diff, `search.top_k` per corpus,
+/// in file and line order
+#[tracing::instrument(name = "search", skip_all, fields(mode = "embeddings", chunks = Empty))]
+pub async fn retrieve(config: &Config, diff: &str) -> Result<Vec<(Corpus, Vec<DocChunk>)>> {
+    let client = LlmClient::new(&config.llm)?;
+    let model = &config.search.embedding_model;
+
+    let queries: Vec<String> = git::file_diffs(diff)
+        .into_iter()
+        .map(|(_, file_diff)| truncate(&file_diff, MAX_EMBED_CHARS).to_string())
+        .collect();
+    if queries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let query_vectors = client.embed(model, &queries).await?;
+
+    let mut corpora: Vec<(Corpus, Vec<DocChunk>)> = Vec::new();
+    for corpus in config.docs.corpora() {
+        let chunks = corpus_chunks(&config.root, &corpus).await?;
+        corpora.push((corpus, chunks));
+    }
+    let vectors = embed_chunks(config, &client, corpora.iter().flat_map(|(_, c)| c)).await?;
+
+    let mut total = 0;
+    let results = corpora
+        .into_iter()
+        .map(|(corpus, chunks)| {
+            let mut scored: Vec<(f32, DocChunk)> = chunks
+                .into_iter()
+                .filter_map(|chunk| {
+                    let vector = vectors.get(&chunk_key(model, &chunk))?;
+                    let score = query_vectors
+                        .iter()
+                        .map(|q| cosine(q, vector))
+                        .fold(f32::MIN, f32::max);
+                    (score >= config.search.min_similarity).then_some((score, chunk))
+                })
+                .collect();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+            scored.truncate(config.search.top_k);
+            debug!(
+                "Top similarities in {}: {:?}",
+                corpus.name,
+                scored
+                    .iter()
+                    .map(|(score, c)| format!("{}:{} {:.3}", c.file, c.start_line, score))
+                    .collect::<Vec<_>>()
+            );
+
+            let mut chunks: Vec<DocChunk> = scored.into_iter().map(|(_, c)| c).collect();
+            chunks.sort_by(|a, b| a.file.cmp(&b.file).then(a.start_line.cmp(&b.start_line)));
+            total += chunks.len();
+            (corpus, chunks)
+        })
+        .collect();
+
+    Span::current().record("chunks", total);
+    Ok(results)
+}
+
+/// Embed every doc chunk not yet in the index. Returns the number of chunks indexed.
+pub async fn build_index(config: &Config) -> Result<usize> {
+    let client = LlmClient::new(&config.llm)?;
+    let mut chunks = Vec::new();
+    for corpus in config.docs.corpora() {
+        chunks.extend(corpus_chunks(&config.root, &corpus).await?);
+    }
+    Ok(embed_chunks(config, &client, chunks.iter()).await?.len())
+}
+
+/// Vectors for `chunks`, from the index where present. Newly embedded chunks are added
+/// and chunks no longer in the docs dropped before the index is written back.
+async fn embed_chunks<'a>(
+    config: &Config,
+    client: &LlmClient,
+    chunks: impl Iterator<Item = &'a DocChunk>,
+) -> Result<HashMap<String, Vec<f32>>> {
+    let model = &config.search.embedding_model;
+    let path = index_path(config);
+    let mut index = load_index(&path);
+
+    let mut current = HashSet::new();
+    let mut missing: Vec<(String, String)> = Vec::new();
+    for chunk in chunks {
+        let key = chunk_key(model, chunk);
+        if current.insert(key.clone()) && !index.vectors.contains_key(&key) {
+            missing.push((key, embedding_text(chunk)));
+        }
+    }
+
+    let added = missing.len();
+    if !missing.is_empty() {
+        info!("Embedding {} doc chunks with {}", missing.len(), model);
+        let texts: Vec<String> = missing.iter().map(|(_, text)| text.clone()).collect();
+        let vectors = client.embed(model, &texts).await?;
+        index
+            .vectors
+            .extend(missing.into_iter().map(|(key, _)| key).zip(vectors));
+    }
+
+    let before = index.vectors.len();
+    index.vectors.retain(|key, _| current.contains(key));
+    let changed = added > 0 || before != index.vectors.len();
+    if changed && !Config::is_read_only() {
+        if let Err(e) = store_index(&path, &index) {
+            warn!("Failed to write embedding index: {}", e);
+        }
+    }
+    Ok(index.vectors)
+}
+
+/// Chunks of every doc file in a corpus: doc comments for `:docstrings` sources,
+/// heading sections for Markdown, and fixed windows of lines for anything else
+async fn corpus_chunks(root: &Path, corpus: &Corpus) -> Result<Vec<DocChunk>> {
+    let mut chunks = Vec::new();
+    for source in sources::for_corpus(root, &corpus.docs).await? {
+        for file in source.files()? {
+            let content = match source.read(&file) {
+                Ok(content) => content,
+                Err(e) => {
+                    debug!("Skipping unreadable doc file {}: {}", file.display(), e);
+                    continue;
+                }
+            };
+            let name = file.to_string_lossy();
+            if source.docstrings_only(&file) {
+                chunks.extend(docstrings::extract(&name, &content));
+            } else {
+                chunks.extend(chunk_file(&name, &content));
+            }
+        }
+    }
+    Ok(chunks)
+}
+
+/// Split a doc file into chunks of at most `MAX_CHUNK_LINES` lines. Markdown is split
+/// at headings outside code fences first.
+fn chunk_file(file: &str, content: &str) -> Vec<DocChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let kind = DocKind::from_path(file);
+
+    let mut starts = vec![0];
+    if kind == DocKind::Markdown {
+        let mut in_fence = false;
+        for (i, line) in lines.iter().enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            } else if !in_fence && trimmed.starts_with('#') && i > 0 {
+                starts.push(i);
+            }
+        }
+    }
+    starts.push(lines.len());
+
+    let mut chunks = Vec::new();
+    for section in starts.windows(2) {
+        let mut start = section[0];
+        while start < section[1] {
+            let end = (start + MAX_CHUNK_LINES).min(section[1]);
+            let text = lines[start..end].join("\n");
+            if !text.trim().is_empty() {
+                chunks.push(DocChunk {
+                    file: file.to_string(),
+                    start_line: start + 1,
+                    end_line: end,
+                    content: text,
+                    kind,
+                });
+            }
+            start = end;
+        }
+    }
+    chunks
+}
+
+/// Text embedded for a chunk: its path, which often names the topic, and its content
+fn embedding_text(chunk: &DocChunk) -> String {
+    let text = format!("{}\n\n{}", chunk.file, chunk.content);
+    truncate(&text, MAX_EMBED_CHARS).to_string()
+}
+
+/// Index key of a chunk: the model, location and content, so edits re-embed only what changed
+fn chunk_key(model: &str, chunk: &DocChunk) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        model,
+        &chunk.file,
+        &chunk.start_line.to_string(),
+        &chunk.content,
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.finalize()[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+fn index_path(config: &Config) -> PathBuf {
+    config.resolve(&config.cache.dir).join(INDEX_FILE)
+}
+
+fn load_index(path: &Path) -> Index {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn store_index(path: &Path, index: &Index) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    }
+    let content =
+        serde_json::to_string(index).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    fs::write(path, content).map_err(|e| DriftcheckError::CacheError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine() {
+        assert!((cosine(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert_eq!(cosine(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+        assert_eq!(cosine(&[1.0], &[1.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn test_chunk_file_splits_at_headings_outside_fences() {
+        let doc = "# Title\nIntro\n\n## Install\n```sh\n# not a heading\n```\n## Usage\nRun it\n";
+        let chunks = chunk_file("README.md", doc);
+        let spans: Vec<(usize, usize)> =
+            chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(spans, vec![(1, 3), (4, 7), (8, 9)]);
+
+        let long: String = (0..90).map(|i| format!("line {}\n", i)).collect();
+        let spans: Vec<(usize, usize)> = chunk_file("notes.txt", &long)
+            .iter()
+            .map(|c| (c.start_line, c.end_line))
+            .collect();
+        assert_eq!(spans, vec![(1, 40), (41, 80), (81, 90)]);
+    }
+}
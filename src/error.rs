@@ -1,3 +1,4 @@
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -38,20 +39,185 @@ pub enum DriftcheckError {
     #[error("API key not found. Set DRIFTCHECK_API_KEY environment variable.")]
     ApiKeyNotFound,
 
+    #[error("LLM returned an empty response")]
+    LlmEmptyResponse,
+
     #[error("Failed to parse LLM response: {0}")]
     LlmResponseParse(String),
 
+    #[error("LLM context window exceeded even with reduced documentation: {0}")]
+    LlmContextExceeded(String),
+
+    /// The provider answered 429, with how long it asked to wait (milliseconds), if it said
+    #[error("LLM rate limit exceeded: {0}")]
+    LlmRateLimited(String, Option<u64>),
+
     #[error("Cache error: {0}")]
     CacheError(String),
 
     #[error("Hook installation failed: {0}")]
     HookInstallError(String),
 
+    #[error("Failed to write report: {0}")]
+    ReportError(String),
+
+    #[error("Failed to apply patch: {0}")]
+    PatchError(String),
+
     #[error("TUI error: {0}")]
     TuiError(String),
 
+    #[error("Language server error: {0}")]
+    LspError(String),
+
+    #[error("Read-only mode: {0} is not allowed")]
+    ReadOnly(String),
+
     #[error("driftcheck is disabled. Run 'driftcheck enable' to re-enable.")]
     Disabled,
+
+    #[error("Organization policy violation: {0}")]
+    PolicyViolation(String),
+
+    #[error("GitHub API error: {0}")]
+    GithubError(String),
+
+    #[error("Invalid diff: {0}")]
+    DiffInvalid(String),
+}
+
+/// Broad failure class, so wrappers and CI can branch on the kind of error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCategory {
+    Config,
+    Git,
+    Search,
+    Network,
+    Model,
+    Io,
+    Usage,
+}
+
+impl ErrorCategory {
+    /// Process exit code. 1 is reserved for "drift found" and 2 for invalid arguments.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCategory::Config => 3,
+            ErrorCategory::Git => 4,
+            ErrorCategory::Search => 5,
+            ErrorCategory::Network => 6,
+            ErrorCategory::Model => 7,
+            ErrorCategory::Io => 8,
+            ErrorCategory::Usage => 9,
+        }
+    }
+}
+
+/// Machine-readable form of an error, printed with `--error-format json`
+#[derive(Serialize)]
+struct ErrorReport {
+    code: &'static str,
+    category: ErrorCategory,
+    message: String,
+    exit_code: i32,
+}
+
+impl DriftcheckError {
+    /// Stable error code. Codes are never reused or renumbered.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DriftcheckError::ConfigNotFound => "DC001",
+            DriftcheckError::ConfigInvalid(_) => "DC002",
+            DriftcheckError::ConfigRead(_) => "DC003",
+            DriftcheckError::ConfigParse(_) => "DC004",
+            DriftcheckError::NotGitRepo => "DC005",
+            DriftcheckError::GitError(_) => "DC006",
+            DriftcheckError::NoUpstream => "DC007",
+            DriftcheckError::RipgrepNotFound => "DC008",
+            DriftcheckError::SearchError(_) => "DC009",
+            DriftcheckError::LlmError(_) => "DC010",
+            DriftcheckError::LlmTimeout(_) => "DC011",
+            DriftcheckError::ApiKeyNotFound => "DC012",
+            DriftcheckError::LlmEmptyResponse => "DC013",
+            DriftcheckError::LlmResponseParse(_) => "DC014",
+            DriftcheckError::CacheError(_) => "DC015",
+            DriftcheckError::HookInstallError(_) => "DC016",
+            DriftcheckError::ReportError(_) => "DC017",
+            DriftcheckError::PatchError(_) => "DC018",
+            DriftcheckError::TuiError(_) => "DC019",
+            DriftcheckError::ReadOnly(_) => "DC020",
+            DriftcheckError::Disabled => "DC021",
+            DriftcheckError::LspError(_) => "DC022",
+            DriftcheckError::PolicyViolation(_) => "DC023",
+            DriftcheckError::GithubError(_) => "DC024",
+            DriftcheckError::DiffInvalid(_) => "DC025",
+            DriftcheckError::LlmContextExceeded(_) => "DC026",
+            DriftcheckError::LlmRateLimited(..) => "DC027",
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            DriftcheckError::ConfigNotFound
+            | DriftcheckError::ConfigInvalid(_)
+            | DriftcheckError::ConfigRead(_)
+            | DriftcheckError::ConfigParse(_)
+            | DriftcheckError::ApiKeyNotFound
+            | DriftcheckError::PolicyViolation(_) => ErrorCategory::Config,
+            DriftcheckError::NotGitRepo
+            | DriftcheckError::GitError(_)
+            | DriftcheckError::NoUpstream => ErrorCategory::Git,
+            DriftcheckError::RipgrepNotFound | DriftcheckError::SearchError(_) => {
+                ErrorCategory::Search
+            }
+            DriftcheckError::LlmError(_)
+            | DriftcheckError::LlmTimeout(_)
+            | DriftcheckError::LlmRateLimited(..)
+            | DriftcheckError::GithubError(_) => ErrorCategory::Network,
+            DriftcheckError::LlmEmptyResponse
+            | DriftcheckError::LlmResponseParse(_)
+            | DriftcheckError::LlmContextExceeded(_) => ErrorCategory::Model,
+            DriftcheckError::CacheError(_)
+            | DriftcheckError::HookInstallError(_)
+            | DriftcheckError::ReportError(_)
+            | DriftcheckError::PatchError(_)
+            | DriftcheckError::TuiError(_)
+            | DriftcheckError::LspError(_) => ErrorCategory::Io,
+            DriftcheckError::ReadOnly(_)
+            | DriftcheckError::Disabled
+            | DriftcheckError::DiffInvalid(_) => ErrorCategory::Usage,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.category().exit_code()
+    }
+
+    /// Render as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        let report = ErrorReport {
+            code: self.code(),
+            category: self.category(),
+            message: self.to_string(),
+            exit_code: self.exit_code(),
+        };
+        serde_json::to_string(&serde_json::json!({ "error": report })).unwrap_or_default()
+    }
 }
 
 pub type Result<T> = std::result::Result<T, DriftcheckError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&DriftcheckError::LlmTimeout(30).to_json()).unwrap();
+        assert_eq!(json["error"]["code"], "DC011");
+        assert_eq!(json["error"]["category"], "network");
+        assert_eq!(json["error"]["exit_code"], 6);
+    }
+}
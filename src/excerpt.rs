@@ -0,0 +1,178 @@
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::history;
+use crate::output::markdown::locate_excerpt;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Longest excerpt kept on an issue; the full excerpt stays available in `Issue::full_excerpt`
+pub const MAX_EXCERPT_LINES: usize = 12;
+
+/// Shortest line that can anchor a partial excerpt match on its own
+const MIN_ANCHOR_LEN: usize = 10;
+
+/// Files longer than this are sent to fix generation as a window around the issue
+const MAX_FIX_CONTEXT_LINES: usize = 300;
+
+/// Lines of context kept on each side of the excerpt in a fix window
+const FIX_WINDOW_MARGIN: usize = 60;
+
+/// 1-based line range of the excerpt in `content`. Falls back to the longest run of excerpt
+/// lines that appears verbatim (ignoring indentation) when the model padded or paraphrased it.
+pub fn verified_range(content: &str, excerpt: &str) -> Option<(usize, usize)> {
+    if let Some(range) = locate_excerpt(content, excerpt) {
+        return Some(range);
+    }
+
+    let file: Vec<&str> = content.lines().map(str::trim).collect();
+    let needle: Vec<&str> = excerpt.lines().map(str::trim).collect();
+
+    // Longest common run of lines; runs of blank or short lines alone (fences, rules) don't count
+    let mut best: Option<(usize, usize)> = None;
+    let mut best_len = 0;
+    let mut prev = vec![0usize; needle.len() + 1];
+    for (i, line) in file.iter().enumerate() {
+        let mut row = vec![0usize; needle.len() + 1];
+        for (j, want) in needle.iter().enumerate() {
+            if line == want {
+                row[j + 1] = prev[j] + 1;
+                let len = row[j + 1];
+                let start = i + 1 - len;
+                if len > best_len && file[start..=i].iter().any(|l| l.len() >= MIN_ANCHOR_LEN) {
+                    best_len = len;
+                    best = Some((start + 1, i + 1));
+                }
+            }
+        }
+        prev = row;
+    }
+    best
+}
+
+/// Trim the excerpts of all issues against their files under `root`
+pub fn trim_all(root: &Path, issues: &mut [Issue]) {
+    let mut files: HashMap<PathBuf, Option<String>> = HashMap::new();
+    for issue in issues {
+        let content = files
+            .entry(issue.file.clone())
+            .or_insert_with(|| fs::read_to_string(Config::resolve_in(root, &issue.file)).ok());
+        match content {
+            Some(content) => trim(issue, content),
+            None => trim(issue, ""),
+        }
+    }
+}
+
+/// Replace the issue's excerpt with the verified region from the file, capped at
+/// `MAX_EXCERPT_LINES` around the issue's line. The original excerpt is kept in
+/// `full_excerpt` when it changes. Excerpts that can't be found in the file are only capped.
+pub fn trim(issue: &mut Issue, content: &str) {
+    if issue.doc_excerpt.trim().is_empty() {
+        return;
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let trimmed = match verified_range(content, &issue.doc_excerpt) {
+        Some((start, end)) => {
+            if issue.line < start || issue.line > end {
+                issue.line = start;
+            }
+            let (start, end) = cap_range(start, end, issue.line);
+            lines[start - 1..end].join("\n")
+        }
+        None => issue
+            .doc_excerpt
+            .lines()
+            .take(MAX_EXCERPT_LINES)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    let full = std::mem::replace(&mut issue.doc_excerpt, trimmed);
+    if normalize(&full) != normalize(&issue.doc_excerpt) {
+        issue.full_excerpt = Some(full);
+    }
+    issue.fingerprint = history::fingerprint(&issue.file, &issue.description, &issue.doc_excerpt);
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cap a range to `MAX_EXCERPT_LINES`, keeping `focus` inside it
+fn cap_range(start: usize, end: usize, focus: usize) -> (usize, usize) {
+    if end - start < MAX_EXCERPT_LINES {
+        return (start, end);
+    }
+    let window_start = focus
+        .saturating_sub(MAX_EXCERPT_LINES / 2)
+        .clamp(start, end + 1 - MAX_EXCERPT_LINES);
+    (window_start, window_start + MAX_EXCERPT_LINES - 1)
+}
+
+/// 1-based line range of `content` to send for fix generation, or `None` to send the whole file
+pub fn fix_window(content: &str, issue: &Issue) -> Option<(usize, usize)> {
+    let total = content.lines().count();
+    if total <= MAX_FIX_CONTEXT_LINES {
+        return None;
+    }
+
+    let (start, end) = locate_excerpt(content, &issue.doc_excerpt).or_else(|| {
+        (1..=total)
+            .contains(&issue.line)
+            .then_some((issue.line, issue.line))
+    })?;
+    Some((
+        start.saturating_sub(FIX_WINDOW_MARGIN).max(1),
+        (end + FIX_WINDOW_MARGIN).min(total),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    fn issue(line: usize, excerpt: &str) -> Issue {
+        Issue::from(RawIssue {
+            file: "README.md".to_string(),
+            line,
+            description: "Outdated".to_string(),
+            doc_excerpt: excerpt.to_string(),
+            suggested_fix: None,
+            confidence: None,
+            code_symbol: None,
+        })
+    }
+
+    #[test]
+    fn test_verified_range_drops_invented_lines() {
+        let content = "# Usage\n\nRun `tool --fast`\nto start.\n\nMore text.\n";
+        let excerpt = "As the docs say:\nRun `tool --fast`\nto start.\n(end of section)";
+        assert_eq!(verified_range(content, excerpt), Some((3, 4)));
+        assert_eq!(verified_range(content, "nothing here"), None);
+    }
+
+    #[test]
+    fn test_trim_caps_long_excerpts_around_line() {
+        let content: String = (1..=40).map(|i| format!("line {}\n", i)).collect();
+        let mut issue = issue(30, &content);
+        trim(&mut issue, &content);
+
+        assert_eq!(issue.doc_excerpt.lines().count(), MAX_EXCERPT_LINES);
+        assert!(issue.doc_excerpt.contains("line 30"));
+        assert_eq!(issue.full_excerpt.as_deref(), Some(content.as_str()));
+    }
+
+    #[test]
+    fn test_trim_keeps_exact_excerpts() {
+        let content = "# Usage\n\nRun `tool --fast`\n";
+        let mut issue = issue(0, "Run `tool --fast`");
+        trim(&mut issue, content);
+
+        assert_eq!(issue.doc_excerpt, "Run `tool --fast`");
+        assert_eq!(issue.line, 3);
+        assert!(issue.full_excerpt.is_none());
+    }
+}
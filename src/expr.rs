@@ -0,0 +1,368 @@
+//! A small expression language for config rules over issue fields, e.g.
+//! `category == "signature" && path startswith "docs/api"`.
+//!
+//! Comparisons are `==`, `!=`, `<`, `<=`, `>`, `>=`, `startswith`, `endswith`, `contains`
+//! and `matches` (a glob), combined with `&&`, `||`, `!` and parentheses. Values are
+//! quoted strings, numbers and `true`/`false`. A comparison with a field that has no value
+//! for an issue (e.g. `confidence` when the model gave none) is false.
+
+use glob::Pattern;
+use std::fmt;
+
+/// Value of a field or literal
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    StartsWith,
+    EndsWith,
+    Contains,
+    Matches,
+}
+
+#[derive(Debug)]
+enum Node {
+    Compare(String, Op, Value, Option<Pattern>),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+/// A parsed expression
+#[derive(Debug)]
+pub struct Expr(Node);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Expr {
+    /// Parse `source`, accepting only the given field names
+    pub fn parse(source: &str, fields: &[&str]) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            fields,
+        };
+        let node = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(Expr(node)),
+            Some(token) => Err(format!("unexpected {}", describe(token))),
+        }
+    }
+
+    /// Evaluate with `field` giving each field's value, `None` when it has none
+    pub fn eval(&self, field: &dyn Fn(&str) -> Option<Value>) -> bool {
+        eval(&self.0, field)
+    }
+}
+
+fn eval(node: &Node, field: &dyn Fn(&str) -> Option<Value>) -> bool {
+    match node {
+        Node::Not(inner) => !eval(inner, field),
+        Node::And(a, b) => eval(a, field) && eval(b, field),
+        Node::Or(a, b) => eval(a, field) || eval(b, field),
+        Node::Compare(name, op, literal, pattern) => {
+            let Some(value) = field(name) else {
+                return false;
+            };
+            match (op, &value, literal) {
+                (Op::Eq, a, b) => a == b,
+                (Op::Ne, a, b) => a != b,
+                (Op::Lt, Value::Num(a), Value::Num(b)) => a < b,
+                (Op::Le, Value::Num(a), Value::Num(b)) => a <= b,
+                (Op::Gt, Value::Num(a), Value::Num(b)) => a > b,
+                (Op::Ge, Value::Num(a), Value::Num(b)) => a >= b,
+                (Op::StartsWith, Value::Str(a), Value::Str(b)) => a.starts_with(b.as_str()),
+                (Op::EndsWith, Value::Str(a), Value::Str(b)) => a.ends_with(b.as_str()),
+                (Op::Contains, Value::Str(a), Value::Str(b)) => {
+                    a.to_lowercase().contains(&b.to_lowercase())
+                }
+                (Op::Matches, Value::Str(a), _) => pattern.as_ref().is_some_and(|p| p.matches(a)),
+                _ => false,
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    fields: &'a [&'a str],
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            node = Node::And(Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Node::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let node = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(node),
+                    Some(token) => Err(format!("expected ')', found {}", describe(&token))),
+                    None => Err("missing ')'".to_string()),
+                }
+            }
+            Some(Token::Ident(name)) => self.comparison(name),
+            Some(token) => Err(format!("expected a field, found {}", describe(&token))),
+            None => Err("expected a field, found the end".to_string()),
+        }
+    }
+
+    fn comparison(&mut self, name: String) -> Result<Node, String> {
+        if !self.fields.contains(&name.as_str()) {
+            return Err(format!(
+                "unknown field {:?} (fields: {})",
+                name,
+                self.fields.join(", ")
+            ));
+        }
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            Some(token) => {
+                return Err(format!(
+                    "expected an operator after {}, found {}",
+                    name,
+                    describe(&token)
+                ))
+            }
+            None => return Err(format!("expected an operator after {}", name)),
+        };
+        let value = match self.next() {
+            Some(Token::Literal(value)) => value,
+            Some(token) => return Err(format!("expected a value, found {}", describe(&token))),
+            None => return Err("expected a value, found the end".to_string()),
+        };
+
+        let text_op = matches!(
+            op,
+            Op::StartsWith | Op::EndsWith | Op::Contains | Op::Matches
+        );
+        let number_op = matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge);
+        if text_op && !matches!(value, Value::Str(_)) {
+            return Err(format!("{} needs a quoted string", op_name(op)));
+        }
+        if number_op && !matches!(value, Value::Num(_)) {
+            return Err(format!("{} needs a number", op_name(op)));
+        }
+        let pattern = match (&op, &value) {
+            (Op::Matches, Value::Str(glob)) => {
+                Some(Pattern::new(glob).map_err(|e| format!("invalid glob {:?}: {}", glob, e))?)
+            }
+            _ => None,
+        };
+        Ok(Node::Compare(name, op, value, pattern))
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(at, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let two = source.get(at..at + 2).unwrap_or_default();
+        let symbol = match two {
+            "==" => Some(Token::Op(Op::Eq)),
+            "!=" => Some(Token::Op(Op::Ne)),
+            "<=" => Some(Token::Op(Op::Le)),
+            ">=" => Some(Token::Op(Op::Ge)),
+            "&&" => Some(Token::And),
+            "||" => Some(Token::Or),
+            _ => None,
+        };
+        if let Some(token) = symbol {
+            tokens.push(token);
+            chars.next();
+            chars.next();
+            continue;
+        }
+
+        match c {
+            '<' | '>' | '!' | '(' | ')' => {
+                tokens.push(match c {
+                    '<' => Token::Op(Op::Lt),
+                    '>' => Token::Op(Op::Gt),
+                    '!' => Token::Not,
+                    '(' => Token::Open,
+                    _ => Token::Close,
+                });
+                chars.next();
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some((_, quote)) if quote == c => break,
+                        Some((_, other)) => text.push(other),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Literal(Value::Str(text)));
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut number = String::new();
+                while let Some(&(_, d)) = chars.peek() {
+                    if d.is_ascii_digit() || d == '.' || (d == '-' && number.is_empty()) {
+                        number.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let n = number
+                    .parse()
+                    .map_err(|_| format!("invalid number {:?}", number))?;
+                tokens.push(Token::Literal(Value::Num(n)));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&(_, d)) = chars.peek() {
+                    if d.is_alphanumeric() || d == '_' {
+                        word.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(match word.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "startswith" => Token::Op(Op::StartsWith),
+                    "endswith" => Token::Op(Op::EndsWith),
+                    "contains" => Token::Op(Op::Contains),
+                    "matches" => Token::Op(Op::Matches),
+                    _ => Token::Ident(word),
+                });
+            }
+            other => return Err(format!("unexpected character {:?}", other)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn op_name(op: Op) -> &'static str {
+    match op {
+        Op::Eq => "==",
+        Op::Ne => "!=",
+        Op::Lt => "<",
+        Op::Le => "<=",
+        Op::Gt => ">",
+        Op::Ge => ">=",
+        Op::StartsWith => "startswith",
+        Op::EndsWith => "endswith",
+        Op::Contains => "contains",
+        Op::Matches => "matches",
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Ident(name) => format!("field {}", name),
+        Token::Literal(value) => format!("value {}", value),
+        Token::Op(op) => format!("'{}'", op_name(*op)),
+        Token::And => "'&&'".to_string(),
+        Token::Or => "'||'".to_string(),
+        Token::Not => "'!'".to_string(),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[&str] = &["category", "path", "confidence", "corpus"];
+
+    #[test]
+    fn test_parse_and_eval() {
+        let issue = |name: &str| match name {
+            "category" => Some(Value::Str("signature".to_string())),
+            "path" => Some(Value::Str("docs/api/client.md".to_string())),
+            "confidence" => Some(Value::Num(0.8)),
+            _ => None,
+        };
+        let eval = |source: &str| Expr::parse(source, FIELDS).unwrap().eval(&issue);
+
+        assert!(eval(
+            r#"category == "signature" && path startswith "docs/api""#
+        ));
+        assert!(eval(r#"path matches "docs/**/*.md" && confidence >= 0.75"#));
+        assert!(eval(r#"!(category == 'cli') || confidence < 0.5"#));
+        assert!(!eval(r#"path endswith ".rst""#));
+        // No value: every comparison is false, even !=
+        assert!(!eval(r#"corpus != "api""#));
+
+        let error = |source: &str| Expr::parse(source, FIELDS).unwrap_err();
+        assert!(error(r#"kind == "cli""#).contains("unknown field"));
+        assert!(error(r#"confidence > "high""#).contains("needs a number"));
+        assert!(error(r#"(path == "a""#).contains("missing ')'"));
+        assert!(error(r#"path == "a" path"#).contains("unexpected field path"));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::analyzer::Issue;
+use crate::config::{Config, LlmTask, PromptsConfig};
+use crate::error::{DriftcheckError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// An anonymized record of an issue the user dismissed.
+/// Never contains file names, descriptions or doc content.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedbackRecord {
+    pub timestamp: DateTime<Utc>,
+    pub action: String,
+    pub category: String,
+    pub prompt_version: String,
+    pub model: String,
+    pub doc_kind: String,
+}
+
+/// Exported feedback with per-category counts
+#[derive(Debug, Serialize)]
+pub struct FeedbackExport {
+    pub driftcheck_version: &'static str,
+    pub categories: BTreeMap<String, usize>,
+    pub records: Vec<FeedbackRecord>,
+}
+
+fn feedback_path() -> Result<PathBuf> {
+    let git_root = Config::find_git_root()?;
+    Ok(Config::resolve_in(
+        &git_root,
+        ".git/driftcheck/feedback.jsonl",
+    ))
+}
+
+/// Every category `categorize` returns
+pub const CATEGORIES: &[&str] = &[
+    "cli",
+    "config",
+    "signature",
+    "example",
+    "api",
+    "removed_or_renamed",
+    "other",
+];
+
+/// A category as written on the command line or in `general.disabled_categories`: dashes
+/// may stand for underscores and a "-drift" suffix is allowed (e.g. "example-drift")
+pub fn parse_category(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase().replace('-', "_");
+    let name = name.strip_suffix("_drift").unwrap_or(&name);
+    CATEGORIES.iter().copied().find(|c| *c == name)
+}
+
+/// Coarse issue class derived from the description, so records carry no content
+pub fn categorize(description: &str) -> &'static str {
+    let lower = description.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| lower.contains(w));
+
+    if has(&["--", "flag", "option", "subcommand", "command"]) {
+        "cli"
+    } else if has(&[
+        "config",
+        "setting",
+        "default",
+        "environment variable",
+        "env var",
+    ]) {
+        "config"
+    } else if has(&["parameter", "argument", "signature", "return", "type"]) {
+        "signature"
+    } else if has(&["example", "snippet", "sample"]) {
+        "example"
+    } else if has(&["endpoint", "route", "status code", "request", "response"]) {
+        "api"
+    } else if has(&["renamed", "removed", "no longer", "deprecated"]) {
+        "removed_or_renamed"
+    } else {
+        "other"
+    }
+}
+
+/// Identify the analysis prompt without revealing custom prompt text
+fn prompt_version(prompts: &PromptsConfig) -> String {
+    if prompts.analysis == PromptsConfig::default().analysis {
+        format!("default@{}", env!("CARGO_PKG_VERSION"))
+    } else {
+        "custom".to_string()
+    }
+}
+
+/// Record that the user dismissed an issue, if feedback capture is enabled
+pub fn record_dismissal(config: &Config, issue: &Issue) {
+    if !config.general.record_feedback || Config::is_read_only() {
+        return;
+    }
+
+    let record = FeedbackRecord {
+        timestamp: Utc::now(),
+        action: "skipped".to_string(),
+        category: categorize(&issue.description).to_string(),
+        prompt_version: prompt_version(&config.prompts),
+        model: config.llm.task_model(LlmTask::Analysis).to_string(),
+        doc_kind: issue.kind.as_str().to_string(),
+    };
+
+    if let Err(e) = append(&record) {
+        debug!("Failed to record feedback: {}", e);
+    }
+}
+
+fn append(record: &FeedbackRecord) -> Result<()> {
+    Config::ensure_writable("recording feedback")?;
+
+    let path = feedback_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    }
+    let line =
+        serde_json::to_string(record).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    writeln!(file, "{}", line).map_err(|e| DriftcheckError::CacheError(e.to_string()))
+}
+
+/// Load all recorded feedback, skipping malformed lines
+pub fn export() -> Result<FeedbackExport> {
+    let path = feedback_path()?;
+    let content = if path.exists() {
+        fs::read_to_string(&path).map_err(|e| DriftcheckError::CacheError(e.to_string()))?
+    } else {
+        String::new()
+    };
+
+    let records: Vec<FeedbackRecord> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    let mut categories = BTreeMap::new();
+    for record in &records {
+        *categories.entry(record.category.clone()).or_insert(0) += 1;
+    }
+
+    Ok(FeedbackExport {
+        driftcheck_version: env!("CARGO_PKG_VERSION"),
+        categories,
+        records,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize() {
+        assert_eq!(categorize("The `--no-tui` flag was renamed"), "cli");
+        assert_eq!(categorize("Default timeout is now 60"), "config");
+        assert_eq!(
+            categorize("`parse_diff` takes a second parameter"),
+            "signature"
+        );
+        assert_eq!(categorize("The wording is outdated"), "other");
+
+        assert_eq!(parse_category("example-drift"), Some("example"));
+        assert_eq!(
+            parse_category("removed-or-renamed"),
+            Some("removed_or_renamed")
+        );
+        assert_eq!(parse_category("typos"), None);
+    }
+}
@@ -0,0 +1,574 @@
+use crate::analyzer::Issue;
+use crate::config::{Config, LlmTask};
+use crate::error::{DriftcheckError, Result};
+use crate::excerpt;
+use crate::glossary::Glossary;
+use crate::lfs;
+use crate::llm::{parser, FixReview, LlmClient};
+use crate::patch;
+use crate::paths;
+use crate::search;
+use similar::TextDiff;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+/// At most this many other docs are offered for a multi-file fix
+const MAX_RELATED_FILES: usize = 3;
+/// Related docs larger than this are left out of the prompt
+const MAX_RELATED_BYTES: usize = 20_000;
+/// Excerpt lines shorter than this are too generic to identify a related doc
+const MIN_SHARED_LINE_LEN: usize = 12;
+
+/// An edit to another doc that belongs to the same fix
+#[derive(Debug, Clone)]
+pub struct RelatedFix {
+    pub file: PathBuf,
+    pub original_content: String,
+    pub fixed_content: String,
+}
+
+/// Generate a fixed version of the documentation using LLM.
+/// The suggestions prompt may produce a unified diff or the complete file;
+/// diffs are applied to `original_content`. Large files are sent as a window
+/// around the issue, and the result is spliced back into the file.
+pub async fn generate_doc_fix(
+    config: &Config,
+    issue: &Issue,
+    original_content: &str,
+) -> Result<String> {
+    let (fixed, _) = generate_fix_set(config, issue, original_content, &[]).await?;
+    Ok(fixed)
+}
+
+/// Other docs that repeat a distinctive line of the issue's excerpt, and so probably
+/// drifted the same way. Paths are relative to the repository root.
+pub fn related_docs(config: &Config, issue: &Issue) -> Vec<PathBuf> {
+    let shared: Vec<&str> = issue
+        .doc_excerpt
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.len() >= MIN_SHARED_LINE_LEN)
+        .collect();
+    if shared.is_empty() {
+        return Vec::new();
+    }
+
+    let mut files = Vec::new();
+    for corpus in config.docs.corpora() {
+        match search::expand_doc_paths(&config.root, &corpus.docs.paths, &corpus.docs.ignore) {
+            Ok(paths) => files.extend(paths),
+            Err(e) => warn!("Cannot list docs for corpus {}: {}", corpus.name, e),
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    files
+        .into_iter()
+        .filter(|f| f != &issue.file)
+        .filter(|f| {
+            let path = config.resolve(f);
+            !lfs::is_pointer_file(&path)
+                && fs::read_to_string(&path).is_ok_and(|content| {
+                    content.len() <= MAX_RELATED_BYTES
+                        && shared.iter().any(|line| content.contains(line))
+                })
+        })
+        .take(MAX_RELATED_FILES)
+        .collect()
+}
+
+/// Generate a fix that may also edit `related` docs (path and current content).
+/// Returns the fixed content of the issue's file and the edits to related docs
+/// that the model chose to change.
+pub async fn generate_fix_set(
+    config: &Config,
+    issue: &Issue,
+    original_content: &str,
+    related: &[(PathBuf, String)],
+) -> Result<(String, Vec<RelatedFix>)> {
+    let client = LlmClient::new(&config.llm.for_task(LlmTask::Fixes))?;
+    let request = FixRequest::new(
+        issue,
+        original_content,
+        related,
+        glossary_for(config, issue),
+    );
+
+    let response = client
+        .chat(
+            &config.prompts.render(&config.prompts.suggestions),
+            &request.prompt,
+        )
+        .await?;
+
+    request.apply(original_content, related, response)
+}
+
+/// The `docs.glossary` terms a fix for `issue` must use; none for the glossary itself
+fn glossary_for(config: &Config, issue: &Issue) -> Option<Glossary> {
+    Glossary::load(config).filter(|_| !is_glossary(config, issue))
+}
+
+fn is_glossary(config: &Config, issue: &Issue) -> bool {
+    config
+        .docs
+        .glossary
+        .as_deref()
+        .is_some_and(|glossary| search::normalize_pattern(glossary) == paths::to_slash(&issue.file))
+}
+
+/// The suggestions prompt for one issue, and what is needed to apply its response
+struct FixRequest {
+    prompt: String,
+    /// 1-based line range sent instead of the whole file
+    window: Option<(usize, usize)>,
+    /// The file content the model sees: the window, or the whole file
+    context: String,
+    /// Canonical terms the fix must use (`docs.glossary`)
+    glossary: Option<Glossary>,
+}
+
+impl FixRequest {
+    fn new(
+        issue: &Issue,
+        original_content: &str,
+        related: &[(PathBuf, String)],
+        glossary: Option<Glossary>,
+    ) -> Self {
+        let lines: Vec<&str> = original_content.lines().collect();
+        let window = excerpt::fix_window(original_content, issue);
+        let (context, heading) = match window {
+            Some((start, end)) => (
+                lines[start - 1..end].join("\n") + "\n",
+                format!(
+                    "## Current File Content (lines {}-{} of {}; only this part may change)",
+                    start,
+                    end,
+                    lines.len()
+                ),
+            ),
+            None => (
+                original_content.to_string(),
+                "## Current File Content".to_string(),
+            ),
+        };
+
+        let mut prompt = format!(
+            r#"## Issue
+File: {}
+Kind: {}
+Line: {}
+Problem: {}
+
+## Suggested Fix
+{}
+
+{}
+```
+{}
+```
+
+Output the fix:"#,
+            issue.file.display(),
+            issue.kind.as_str(),
+            issue.line,
+            issue.description,
+            issue.suggested_fix.as_deref().unwrap_or("(none)"),
+            heading,
+            context
+        );
+
+        if let Some(note) = issue.kind.guidance() {
+            prompt.push_str(&format!("\n\nNote: {} Keep the file format intact.", note));
+        }
+
+        if !related.is_empty() {
+            prompt.push_str(
+                "\n\n## Other Files With the Same Text\n\
+These files repeat the drifted text. If they need the same correction, output one \
+unified diff per changed file, with `--- a/<path>` and `+++ b/<path>` headers.\n",
+            );
+            for (file, content) in related {
+                prompt.push_str(&format!("\n{}:\n```\n{}\n```\n", file.display(), content));
+            }
+        }
+
+        if let Some(glossary) = &glossary {
+            let mentioned = [
+                context.as_str(),
+                &issue.description,
+                issue.suggested_fix.as_deref().unwrap_or_default(),
+            ]
+            .into_iter()
+            .chain(related.iter().map(|(_, content)| content.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n");
+            if let Some(section) = glossary.prompt_section(&mentioned) {
+                prompt.push_str("\n\n");
+                prompt.push_str(&section);
+            }
+        }
+
+        Self {
+            prompt,
+            window,
+            context,
+            glossary,
+        }
+    }
+
+    /// Apply the model's response, a unified diff or the complete file (or window), with the
+    /// glossary's terms enforced on the lines it adds
+    fn apply(
+        &self,
+        original_content: &str,
+        related: &[(PathBuf, String)],
+        response: String,
+    ) -> Result<(String, Vec<RelatedFix>)> {
+        let (fixed, mut related_fixes) =
+            self.apply_response(original_content, related, response)?;
+        let Some(glossary) = &self.glossary else {
+            return Ok((fixed, related_fixes));
+        };
+
+        let (fixed, mut corrections) = glossary.enforce(original_content, &fixed);
+        for related in &mut related_fixes {
+            let (content, more) =
+                glossary.enforce(&related.original_content, &related.fixed_content);
+            related.fixed_content = content;
+            corrections.extend(more);
+        }
+        for correction in corrections {
+            debug!(
+                "Glossary: replaced \"{}\" with \"{}\" in the fix",
+                correction.found, correction.canonical
+            );
+        }
+        Ok((fixed, related_fixes))
+    }
+
+    fn apply_response(
+        &self,
+        original_content: &str,
+        related: &[(PathBuf, String)],
+        response: String,
+    ) -> Result<(String, Vec<RelatedFix>)> {
+        let diffs = patch::extract_diffs(&response);
+        if diffs.is_empty() {
+            let fixed = match self.window {
+                Some((start, end)) => splice_lines(original_content, start, end, &response),
+                None => response,
+            };
+            return Ok((fixed, Vec::new()));
+        }
+
+        let mut fixed = self.context.clone();
+        let mut related_fixes: Vec<RelatedFix> = Vec::new();
+        for diff in diffs {
+            let sections = patch::split_file_diffs(diff);
+            if sections.is_empty() {
+                fixed = patch::apply_unified_diff(&fixed, diff)?;
+                continue;
+            }
+            for (path, section) in sections {
+                let Some((file, content)) = related.iter().find(|(f, _)| same_path(f, &path))
+                else {
+                    fixed = patch::apply_unified_diff(&fixed, &section)?;
+                    continue;
+                };
+                match related_fixes.iter_mut().find(|r| &r.file == file) {
+                    Some(existing) => {
+                        existing.fixed_content =
+                            patch::apply_unified_diff(&existing.fixed_content, &section)?
+                    }
+                    None => related_fixes.push(RelatedFix {
+                        file: file.clone(),
+                        original_content: content.clone(),
+                        fixed_content: patch::apply_unified_diff(content, &section)?,
+                    }),
+                }
+            }
+        }
+        related_fixes.retain(|r| r.fixed_content != r.original_content);
+
+        let fixed = match self.window {
+            Some((start, end)) => splice_lines(original_content, start, end, &fixed),
+            None => fixed,
+        };
+        Ok((fixed, related_fixes))
+    }
+}
+
+/// Whether a diff header path names `file`
+fn same_path(file: &Path, header: &str) -> bool {
+    let header = header.trim_start_matches("./");
+    paths::to_slash(file) == header
+}
+
+/// Replace 1-based lines `start..=end` of `content` with `replacement`
+fn splice_lines(content: &str, start: usize, end: usize, replacement: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut out: Vec<&str> = lines[..start - 1].to_vec();
+    out.extend(replacement.lines());
+    out.extend(&lines[end..]);
+
+    let mut spliced = out.join("\n");
+    if content.ends_with('\n') {
+        spliced.push('\n');
+    }
+    spliced
+}
+
+/// Ask the LLM whether a generated fix addresses the issue without unrelated edits
+pub async fn review_doc_fix(
+    config: &Config,
+    issue: &Issue,
+    original_content: &str,
+    fixed_content: &str,
+) -> Result<FixReview> {
+    let client = LlmClient::new(&config.llm.for_task(LlmTask::Fixes))?;
+
+    let response = client
+        .chat(
+            &config.prompts.render(&config.prompts.critic),
+            &review_prompt(issue, original_content, fixed_content),
+        )
+        .await?;
+
+    parse_review(&client, &response)
+}
+
+fn review_prompt(issue: &Issue, original_content: &str, fixed_content: &str) -> String {
+    format!(
+        r#"## Issue
+File: {}
+Line: {}
+Problem: {}
+
+## Original File Content
+```
+{}
+```
+
+## Fixed File Content
+```
+{}
+```"#,
+        issue.file.display(),
+        issue.line,
+        issue.description,
+        original_content,
+        fixed_content
+    )
+}
+
+fn parse_review(client: &LlmClient, response: &str) -> Result<FixReview> {
+    let mut review = parser::parse_fix_review(client.parser(), response)?;
+    if review.reason.is_empty() {
+        review.reason = "no reason given".to_string();
+    }
+    Ok(review)
+}
+
+/// Generate a fix for every issue as a unified diff (`--with-fixes`), at most
+/// `llm.max_concurrency` at a time. Issues whose fix fails, or is rejected by the
+/// critic pass, are reported without a patch.
+pub async fn attach_patches(config: &Config, issues: &mut [Issue]) {
+    let config = Arc::new(config.clone());
+    let permits = Arc::new(Semaphore::new(config.llm.max_concurrency.max(1)));
+
+    let mut handles = Vec::new();
+    for issue in issues.iter() {
+        let config = Arc::clone(&config);
+        let permits = Arc::clone(&permits);
+        let issue = issue.clone();
+
+        handles.push(tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await.ok();
+            generate_patch(&config, &issue).await
+        }));
+    }
+
+    for (issue, handle) in issues.iter_mut().zip(handles) {
+        match handle.await {
+            Ok(Ok(patch)) => issue.fix_patch = patch,
+            Ok(Err(e)) => warn!("No fix for {}:{}: {}", issue.file.display(), issue.line, e),
+            Err(e) => warn!("Fix task panicked: {}", e),
+        }
+    }
+}
+
+/// Like `attach_patches`, but every fix (and critic review) is sent as one provider batch
+/// (`--batch`): about half the cost, and may take hours. Providers without a batch API
+/// fall back to `attach_patches`.
+pub async fn attach_patches_batched(config: &Config, issues: &mut [Issue]) -> Result<()> {
+    let client = LlmClient::new(&config.llm.for_task(LlmTask::Fixes))?;
+    if !client.supports_batches() {
+        warn!(
+            "llm.provider \"{}\" has no batch API; generating fixes directly",
+            config.llm.provider
+        );
+        attach_patches(config, issues).await;
+        return Ok(());
+    }
+
+    // Issue index, original content and request of every fix to generate
+    let glossary = Glossary::load(config);
+    let mut pending = Vec::new();
+    for (idx, issue) in issues.iter().enumerate() {
+        let path = config.resolve(&issue.file);
+        let original = match fs::read_to_string(&path) {
+            Ok(original) => original,
+            Err(e) => {
+                warn!("No fix for {}:{}: {}", issue.file.display(), issue.line, e);
+                continue;
+            }
+        };
+        if lfs::is_pointer(&original) {
+            warn!("Skipping fix for Git LFS pointer {}", issue.file.display());
+            continue;
+        }
+        let request = FixRequest::new(
+            issue,
+            &original,
+            &[],
+            glossary.clone().filter(|_| !is_glossary(config, issue)),
+        );
+        pending.push((idx, original, request));
+    }
+
+    let prompts: Vec<String> = pending.iter().map(|(_, _, r)| r.prompt.clone()).collect();
+    let responses = client
+        .chat_batch(
+            &config.prompts.render(&config.prompts.suggestions),
+            &prompts,
+        )
+        .await?;
+
+    let mut fixes = Vec::new();
+    for ((idx, original, request), response) in pending.into_iter().zip(responses) {
+        let issue = &issues[idx];
+        match response.and_then(|r| request.apply(&original, &[], r)) {
+            Ok((fixed, _)) if fixed != original => fixes.push((idx, original, fixed)),
+            Ok(_) => {}
+            Err(e) => warn!("No fix for {}:{}: {}", issue.file.display(), issue.line, e),
+        }
+    }
+
+    if config.tui.critic_pass && !fixes.is_empty() {
+        let prompts: Vec<String> = fixes
+            .iter()
+            .map(|(idx, original, fixed)| review_prompt(&issues[*idx], original, fixed))
+            .collect();
+        let reviews = client
+            .chat_batch(&config.prompts.render(&config.prompts.critic), &prompts)
+            .await?;
+        let mut approved = Vec::new();
+        for (fix, review) in fixes.into_iter().zip(reviews) {
+            let issue = &issues[fix.0];
+            match review.and_then(|r| parse_review(&client, &r)) {
+                Ok(review) if review.approved => approved.push(fix),
+                Ok(review) => warn!(
+                    "Critic rejected the fix for {}:{}: {}",
+                    issue.file.display(),
+                    issue.line,
+                    review.reason
+                ),
+                Err(e) => warn!("No fix for {}:{}: {}", issue.file.display(), issue.line, e),
+            }
+        }
+        fixes = approved;
+    }
+
+    for (idx, original, fixed) in fixes {
+        let issue = &mut issues[idx];
+        issue.fix_patch = Some(unified_patch(&issue.file, &original, &fixed));
+    }
+    Ok(())
+}
+
+/// Generate the fix for an issue as a unified diff against the working tree.
+/// Returns `None` when the fix changes nothing or the critic pass rejects it.
+async fn generate_patch(config: &Config, issue: &Issue) -> Result<Option<String>> {
+    let path = config.resolve(&issue.file);
+    let original = fs::read_to_string(&path)
+        .map_err(|e| DriftcheckError::PatchError(format!("{}: {}", path.display(), e)))?;
+    if lfs::is_pointer(&original) {
+        warn!("Skipping fix for Git LFS pointer {}", issue.file.display());
+        return Ok(None);
+    }
+    let fixed = generate_doc_fix(config, issue, &original).await?;
+
+    if config.tui.critic_pass {
+        let review = review_doc_fix(config, issue, &original, &fixed).await?;
+        if !review.approved {
+            warn!(
+                "Critic rejected the fix for {}:{}: {}",
+                issue.file.display(),
+                issue.line,
+                review.reason
+            );
+            return Ok(None);
+        }
+    }
+
+    Ok((fixed != original).then(|| unified_patch(&issue.file, &original, &fixed)))
+}
+
+/// Open a file in $VISUAL or $EDITOR (vim by default) at `line`, and wait for the editor to exit
+pub fn open_in_editor(path: &Path, line: usize) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vim".to_string());
+    // EDITOR may carry arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vim");
+
+    let mut command = Command::new(program);
+    command.args(parts);
+    if line > 0 {
+        command.arg(format!("+{}", line));
+    }
+    let status = command
+        .arg(path)
+        .status()
+        .map_err(|e| DriftcheckError::TuiError(format!("Failed to open {}: {}", program, e)))?;
+
+    if !status.success() {
+        return Err(DriftcheckError::TuiError(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+    Ok(())
+}
+
+/// Unified diff from `original` to `fixed` with `a/` and `b/` paths, as `git apply` expects
+pub fn unified_patch(file: &Path, original: &str, fixed: &str) -> String {
+    let name = paths::to_slash(file);
+    TextDiff::from_lines(original, fixed)
+        .unified_diff()
+        .header(&format!("a/{}", name), &format!("b/{}", name))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_patch_applies() {
+        let original = "# Guide\n\nTimeout is 30s.\n\nMore text.\n";
+        let fixed = "# Guide\n\nTimeout is 60s.\n\nMore text.\n";
+        let diff = unified_patch(Path::new("docs/guide.md"), original, fixed);
+
+        assert!(diff.starts_with("--- a/docs/guide.md\n+++ b/docs/guide.md\n"));
+        assert_eq!(patch::apply_unified_diff(original, &diff).unwrap(), fixed);
+    }
+}
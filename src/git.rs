@@ -1,7 +1,12 @@
+use crate::config::Config;
 use crate::error::{DriftcheckError, Result};
+use crate::paths;
+use glob::Pattern;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use tracing::warn;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
@@ -12,9 +17,31 @@ const HOOK_SCRIPT: &str = r#"#!/bin/sh
 #   $1 -- Name of the remote to which the push is being done
 #   $2 -- URL to which the push is being done
 
-exec driftcheck hook
+exec driftcheck hook "$@"
 "#;
 
+/// Second line of `HOOK_SCRIPT`, identifying a hook driftcheck wrote
+const HOOK_MARKER: &str = "# driftcheck pre-push hook";
+
+/// Marks the block `HOOK_CHAIN` appends, so chaining twice is a no-op
+const CHAIN_MARKER: &str = "# driftcheck pre-push hook (chained)";
+
+/// Appended to an existing hook (`install-hook --chain`, or a `core.hooksPath` directory
+/// managed by another tool). Skipped where driftcheck is not installed.
+const HOOK_CHAIN: &str = r#"
+# driftcheck pre-push hook (chained)
+if command -v driftcheck >/dev/null 2>&1; then
+    driftcheck hook "$@" || exit 1
+fi
+"#;
+
+/// Where `install_hook` put the hook
+pub enum HookInstall {
+    Written(PathBuf),
+    /// Appended to an existing hook, e.g. one managed by another tool (husky, ...)
+    Chained(PathBuf),
+}
+
 /// Get the diff between upstream and HEAD (or custom range)
 pub fn get_diff(range: &Option<String>) -> Result<String> {
     let range = match range {
@@ -39,6 +66,54 @@ pub fn get_diff(range: &Option<String>) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+/// Get the diff of staged changes (index vs HEAD), for checking before a commit
+pub fn get_staged_diff() -> Result<String> {
+    let output = Command::new("git")
+        .args(["diff", "--cached"])
+        .output()
+        .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DriftcheckError::GitError(stderr.to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// One-line summaries of the commits in a range, oldest first
+pub fn commit_log(range: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["log", "--oneline", "--reverse", range])
+        .output()
+        .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DriftcheckError::GitError(stderr.to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// Name of the checked-out branch
+pub fn current_branch() -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DriftcheckError::GitError(stderr.to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 /// Get the upstream tracking branch
 fn get_upstream() -> Result<String> {
     let output = Command::new("git")
@@ -53,9 +128,109 @@ fn get_upstream() -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-/// Install the pre-push hook
-pub fn install_hook(git_root: &Path, force: bool) -> Result<()> {
-    let hooks_dir = git_root.join(".git/hooks");
+/// Push URL of a remote
+pub fn remote_url(remote: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["remote", "get-url", "--push", remote])
+        .output()
+        .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DriftcheckError::GitError(stderr.trim().to_string()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The git directory of the checkout at `root`: `.git` itself, or the directory a `.git`
+/// file points to with `gitdir:` (worktrees and submodules). `None` if `root` has neither.
+pub fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let target = content
+        .lines()
+        .find_map(|l| l.strip_prefix("gitdir:"))?
+        .trim();
+    let dir = root.join(target);
+    dir.is_dir().then_some(dir)
+}
+
+/// The git directory shared by all worktrees of the repository at `root` (named by the
+/// `commondir` file of a worktree's git directory), where driftcheck keeps its state.
+/// Falls back to `<root>/.git`.
+pub fn common_dir(root: &Path) -> PathBuf {
+    let Some(dir) = git_dir(root) else {
+        return root.join(".git");
+    };
+    match fs::read_to_string(dir.join("commondir")) {
+        Ok(common) => {
+            let common = dir.join(common.trim());
+            common.canonicalize().unwrap_or(common)
+        }
+        Err(_) => dir,
+    }
+}
+
+/// Hooks directory git runs hooks from: `core.hooksPath` if set, else the one shared by
+/// the repository's worktrees
+fn hooks_dir(git_root: &Path) -> PathBuf {
+    if let Some(path) = hooks_path(git_root) {
+        return path;
+    }
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-common-dir"])
+        .current_dir(git_root)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            let common = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            git_root.join(common).join("hooks")
+        }
+        _ => common_dir(git_root).join("hooks"),
+    }
+}
+
+/// `core.hooksPath`, resolved against the repository root. Husky points it at generated
+/// wrappers in `.husky/_` that run the user's hooks in `.husky/`, so those are returned instead.
+fn hooks_path(git_root: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["config", "--get", "core.hooksPath"])
+        .current_dir(git_root)
+        .output()
+        .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || value.is_empty() {
+        return None;
+    }
+    let path = match value.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()?.join(rest),
+        None => git_root.join(&value),
+    };
+    if path.ends_with(".husky/_") {
+        return path.parent().map(Path::to_path_buf);
+    }
+    Some(path)
+}
+
+/// Whether a hook is a shell script that driftcheck can be appended to
+fn is_shell_script(content: &str) -> bool {
+    match content.lines().next() {
+        Some(first) if first.starts_with("#!") => first.contains("sh"),
+        _ => true,
+    }
+}
+
+/// Install the pre-push hook in the directory git runs hooks from. An existing hook gets
+/// driftcheck appended instead of being replaced with `chain`, or when it is in a
+/// `core.hooksPath` directory.
+pub fn install_hook(git_root: &Path, force: bool, chain: bool) -> Result<HookInstall> {
+    Config::ensure_writable("installing the hook")?;
+
+    let hooks_dir = hooks_dir(git_root);
     let hook_path = hooks_dir.join("pre-push");
 
     // Create hooks directory if it doesn't exist
@@ -70,10 +245,32 @@ pub fn install_hook(git_root: &Path, force: bool) -> Result<()> {
         let content = fs::read_to_string(&hook_path)
             .map_err(|e| DriftcheckError::HookInstallError(e.to_string()))?;
 
+        // Another hook keeps its own commands; driftcheck runs after them
+        let ours = content.lines().nth(1) == Some(HOOK_MARKER);
+        let managed = hooks_path(git_root).is_some();
+        let chained = content.contains(CHAIN_MARKER);
+        if !ours && (chain || managed || chained) && is_shell_script(&content) {
+            if !chained && !content.contains("driftcheck hook") {
+                let mut hook = content;
+                if !hook.ends_with('\n') {
+                    hook.push('\n');
+                }
+                hook.push_str(HOOK_CHAIN);
+                fs::write(&hook_path, hook)
+                    .map_err(|e| DriftcheckError::HookInstallError(e.to_string()))?;
+            }
+            return Ok(HookInstall::Chained(hook_path));
+        }
+        if !ours && chain {
+            return Err(DriftcheckError::HookInstallError(format!(
+                "{} is not a shell script; add 'driftcheck hook \"$@\"' to it manually",
+                hook_path.display()
+            )));
+        }
         if !content.contains("driftcheck") {
             return Err(DriftcheckError::HookInstallError(
-                "A pre-push hook already exists. Use --force to overwrite, \
-                 or manually add 'driftcheck hook' to your existing hook."
+                "A pre-push hook already exists. Use --chain to run driftcheck after it, \
+                 --force to overwrite it, or manually add 'driftcheck hook' to it."
                     .to_string(),
             ));
         }
@@ -94,7 +291,44 @@ pub fn install_hook(git_root: &Path, force: bool) -> Result<()> {
             .map_err(|e| DriftcheckError::HookInstallError(e.to_string()))?;
     }
 
-    Ok(())
+    Ok(HookInstall::Written(hook_path))
+}
+
+/// Append entries missing from the repository's .gitignore.
+/// Returns the entries that were added.
+pub fn add_to_gitignore(git_root: &Path, entries: &[String]) -> Result<Vec<String>> {
+    let path = git_root.join(".gitignore");
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let present: Vec<&str> = existing.lines().map(str::trim).collect();
+
+    let missing: Vec<String> = entries
+        .iter()
+        .filter(|e| !present.contains(&e.as_str()))
+        .cloned()
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(missing);
+    }
+
+    Config::ensure_writable("updating .gitignore")?;
+
+    let mut contents = existing;
+    if !contents.is_empty() {
+        if !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push('\n');
+    }
+    contents.push_str("# driftcheck\n");
+    for entry in &missing {
+        contents.push_str(entry);
+        contents.push('\n');
+    }
+
+    fs::write(&path, contents).map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    Ok(missing)
 }
 
 /// Parsed diff - extracts file names from a git diff
@@ -119,3 +353,406 @@ impl ParsedDiff {
         Self { files }
     }
 }
+
+/// Keep only the file sections of a diff whose paths pass the include/exclude globs.
+/// An empty include list keeps every file.
+pub fn filter_diff(diff: &str, include: &[String], exclude: &[String]) -> String {
+    if include.is_empty() && exclude.is_empty() {
+        return diff.to_string();
+    }
+
+    let include = compile_patterns(include);
+    let exclude = compile_patterns(exclude);
+
+    let mut out = String::new();
+    let mut keep = true;
+
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git") {
+            let path = line.trim_end().split(" b/").nth(1).unwrap_or_default();
+            keep = (include.is_empty() || include.iter().any(|p| p.matches(path)))
+                && !exclude.iter().any(|p| p.matches(path));
+        }
+        if keep {
+            out.push_str(line);
+        }
+    }
+
+    out
+}
+
+/// Files tracked in the repository at `root`, relative to it, that pass the include/exclude
+/// globs (as `filter_diff`)
+pub fn tracked_files(root: &Path, include: &[String], exclude: &[String]) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z"])
+        .output()
+        .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DriftcheckError::GitError(stderr.to_string()));
+    }
+
+    let include = compile_patterns(include);
+    let exclude = compile_patterns(exclude);
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .filter(|path| {
+            (include.is_empty() || include.iter().any(|p| p.matches(path)))
+                && !exclude.iter().any(|p| p.matches(path))
+        })
+        .map(str::to_string)
+        .collect())
+}
+
+/// Unix time of the last commit touching each file, from one pass over the history
+pub fn last_changed(root: &Path) -> Result<HashMap<String, i64>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "-c",
+            "core.quotepath=off",
+            "log",
+            "--format=%x00%ct",
+            "--name-only",
+        ])
+        .output()
+        .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DriftcheckError::GitError(stderr.to_string()));
+    }
+
+    // Newest commits come first, so the first time seen is the last change
+    let mut times = HashMap::new();
+    let mut time = 0;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(stamp) = line.strip_prefix('\0') {
+            time = stamp.trim().parse().unwrap_or(0);
+        } else if !line.is_empty() {
+            times.entry(line.to_string()).or_insert(time);
+        }
+    }
+    Ok(times)
+}
+
+/// Split a diff into groups of at most `max_chars`, packing whole files together in diff order.
+/// A file that is larger on its own is split between hunks, repeating its header in each part.
+pub fn split_diff(diff: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces: Vec<String> = Vec::new();
+    for section in file_sections(diff) {
+        if section.len() <= max_chars {
+            pieces.push(section);
+        } else {
+            pieces.extend(split_hunks(&section, max_chars));
+        }
+    }
+
+    let mut groups: Vec<String> = Vec::new();
+    for piece in pieces {
+        match groups.last_mut() {
+            Some(group) if group.len() + piece.len() <= max_chars => group.push_str(&piece),
+            _ => groups.push(piece),
+        }
+    }
+    groups
+}
+
+/// Split a diff into the changed files' paths and their sections of the diff
+pub fn file_diffs(diff: &str) -> Vec<(String, String)> {
+    file_sections(diff)
+        .into_iter()
+        .map(|section| {
+            let header = section.lines().next().unwrap_or_default();
+            let path = header.split(" b/").nth(1).unwrap_or_default().to_string();
+            (path, section)
+        })
+        .collect()
+}
+
+/// Split a diff at each `diff --git` line
+fn file_sections(diff: &str) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        match sections.last_mut() {
+            Some(section) if !line.starts_with("diff --git") => section.push_str(line),
+            _ => sections.push(line.to_string()),
+        }
+    }
+    sections
+}
+
+/// Split one file's diff into parts of whole hunks, each starting with the file header
+fn split_hunks(section: &str, max_chars: usize) -> Vec<String> {
+    let mut header = String::new();
+    let mut hunks: Vec<String> = Vec::new();
+    for line in section.split_inclusive('\n') {
+        if line.starts_with("@@") {
+            hunks.push(line.to_string());
+        } else if let Some(hunk) = hunks.last_mut() {
+            hunk.push_str(line);
+        } else {
+            header.push_str(line);
+        }
+    }
+
+    let mut parts: Vec<String> = Vec::new();
+    for hunk in hunks {
+        match parts.last_mut() {
+            Some(part) if part.len() + hunk.len() <= max_chars => part.push_str(&hunk),
+            _ => parts.push(header.clone() + &hunk),
+        }
+    }
+    if parts.is_empty() {
+        parts.push(header);
+    }
+    parts
+}
+
+/// Where `normalize_patch` is in a patch
+enum PatchState {
+    /// Mail headers, commit message, diffstat, signature: dropped
+    Outside,
+    /// File header lines after `diff --git` (mode, index, `---`/`+++`)
+    Header,
+    /// Inside a hunk, with the old and new lines still to come
+    Hunk(usize, usize),
+}
+
+/// Turn a patch from outside the repository (`git diff` output, a `git format-patch` mail or
+/// plain `diff -u` output) into the `diff --git` sections the analysis expects. Hunk line
+/// counts decide where each hunk ends, so mail text and signatures around it are dropped.
+pub fn normalize_patch(patch: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut state = PatchState::Outside;
+    let mut lines = patch.split_inclusive('\n').peekable();
+
+    while let Some(line) = lines.next() {
+        if let PatchState::Hunk(old, new) = state {
+            let (old, new) = match line.as_bytes().first() {
+                Some(b'+') => (old, new.saturating_sub(1)),
+                Some(b'-') => (old.saturating_sub(1), new),
+                Some(b'\\') => (old, new),
+                _ => (old.saturating_sub(1), new.saturating_sub(1)),
+            };
+            out.push_str(line);
+            state = if old == 0 && new == 0 {
+                PatchState::Outside
+            } else {
+                PatchState::Hunk(old, new)
+            };
+            continue;
+        }
+
+        if line.starts_with("diff --git ") {
+            out.push_str(line);
+            state = PatchState::Header;
+        } else if let Some(counts) = line
+            .strip_prefix("@@ ")
+            .filter(|_| !out.is_empty())
+            .and_then(hunk_counts)
+        {
+            out.push_str(line);
+            state = PatchState::Hunk(counts.0, counts.1);
+        } else if matches!(state, PatchState::Header) {
+            out.push_str(line);
+        } else if line.starts_with("\\ ") && !out.is_empty() {
+            // "\ No newline at end of file" after the last line of a hunk
+            out.push_str(line);
+        } else if line.starts_with("--- ") && lines.peek().is_some_and(|l| l.starts_with("+++ ")) {
+            // A plain unified diff has no `diff --git` line; make one up from the paths
+            let new = lines.next().unwrap_or_default();
+            let path = match patch_path(new) {
+                Some(path) => path,
+                None => patch_path(line).unwrap_or_default(),
+            };
+            out.push_str(&format!("diff --git a/{} b/{}\n", path, path));
+            out.push_str(line);
+            out.push_str(new);
+            state = PatchState::Header;
+        }
+    }
+
+    if !out.contains("diff --git ") {
+        return Err(DriftcheckError::DiffInvalid(
+            "no file changes found (expected a unified diff or a git patch)".to_string(),
+        ));
+    }
+    Ok(out)
+}
+
+/// Old and new line counts of a hunk header, from the part after "@@ "
+fn hunk_counts(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let count = |range: Option<&str>, sign: char| -> Option<usize> {
+        let range = range?.strip_prefix(sign)?;
+        match range.split_once(',') {
+            Some((_, count)) => count.parse().ok(),
+            None => range.parse::<usize>().ok().map(|_| 1),
+        }
+    };
+    Some((count(ranges.next(), '-')?, count(ranges.next(), '+')?))
+}
+
+/// Path of a `---`/`+++` line without its `a/`/`b/` prefix or timestamp; `None` for /dev/null
+fn patch_path(line: &str) -> Option<String> {
+    let path = line[4..].split('\t').next()?.trim();
+    if path == "/dev/null" || path.is_empty() {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+fn compile_patterns(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .filter_map(|p| match Pattern::new(&paths::slash_pattern(p)) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                warn!("Invalid diff filter pattern '{}': {}", p, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs\n+fn a() {}\n\
+diff --git a/tests/fixtures/x.json b/tests/fixtures/x.json\n+{}\n";
+
+    #[test]
+    fn test_normalize_patch() {
+        let mail = "From 1a2b Mon Sep 17 00:00:00 2001\n\
+Subject: [PATCH] Rename flag\n\
+\n\
+---\n\
+ src/cli.rs | 2 +-\n\
+\n\
+diff --git a/src/cli.rs b/src/cli.rs\n\
+index 1a2b..3c4d 100644\n\
+--- a/src/cli.rs\n\
++++ b/src/cli.rs\n\
+@@ -1,2 +1,2 @@\n\
+ // flags\n\
+--- verbose\n\
++-- quiet\n\
+-- \n\
+2.43.0\n";
+        let diff = normalize_patch(mail).unwrap();
+        assert!(diff.starts_with("diff --git a/src/cli.rs b/src/cli.rs\n"));
+        // Removed lines starting with "--" are kept, the mail signature is not
+        assert!(diff.ends_with("--- verbose\n+-- quiet\n"));
+
+        let plain = "Only in new: notes\n\
+--- old/docs/api.md\t2026-10-01 10:00:00\n\
++++ b/docs/api.md\t2026-10-02 10:00:00\n\
+@@ -3 +3 @@\n\
+-GET /v1\n\
++GET /v2\n";
+        let diff = normalize_patch(plain).unwrap();
+        assert_eq!(ParsedDiff::parse(&diff).files, vec!["docs/api.md"]);
+        assert!(diff.ends_with("@@ -3 +3 @@\n-GET /v1\n+GET /v2\n"));
+
+        assert!(normalize_patch("just some text\n").is_err());
+    }
+
+    #[test]
+    fn test_filter_diff_exclude() {
+        let filtered = filter_diff(DIFF, &[], &["tests/fixtures/**".to_string()]);
+        assert_eq!(ParsedDiff::parse(&filtered).files, vec!["src/lib.rs"]);
+    }
+
+    #[test]
+    fn test_filter_diff_include() {
+        let filtered = filter_diff(DIFF, &["tests/**".to_string()], &[]);
+        assert_eq!(
+            ParsedDiff::parse(&filtered).files,
+            vec!["tests/fixtures/x.json"]
+        );
+    }
+
+    #[test]
+    fn test_worktree_git_dirs() {
+        let tmp = std::env::temp_dir().join(format!("driftcheck-worktree-{}", std::process::id()));
+        let common = tmp.join("main/.git");
+        let worktree_git = common.join("worktrees/feature");
+        let worktree = tmp.join("feature");
+        fs::create_dir_all(&worktree_git).unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(worktree_git.join("commondir"), "../..\n").unwrap();
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: ../main/.git/worktrees/feature\n",
+        )
+        .unwrap();
+
+        assert_eq!(git_dir(&tmp.join("main")), Some(common.clone()));
+        assert_eq!(
+            git_dir(&worktree),
+            Some(worktree.join("../main/.git/worktrees/feature"))
+        );
+        assert_eq!(
+            common_dir(&worktree).canonicalize().unwrap(),
+            common.canonicalize().unwrap()
+        );
+        assert_eq!(git_dir(&tmp), None);
+
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_hooks_path() {
+        let tmp = std::env::temp_dir().join(format!("driftcheck-hooks-{}", std::process::id()));
+        fs::create_dir_all(&tmp).unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(&tmp)
+                .output()
+                .unwrap()
+        };
+        git(&["init", "-q"]);
+        assert_eq!(hooks_path(&tmp), None);
+        git(&["config", "core.hooksPath", ".husky/_"]);
+        assert_eq!(hooks_path(&tmp), Some(tmp.join(".husky")));
+        git(&["config", "core.hooksPath", "tools/hooks"]);
+        assert_eq!(hooks_path(&tmp), Some(tmp.join("tools/hooks")));
+
+        assert!(is_shell_script("npm test\n"));
+        assert!(is_shell_script("#!/usr/bin/env bash\nmake lint\n"));
+        assert!(!is_shell_script("#!/usr/bin/env node\nrequire('x')\n"));
+        assert_eq!(HOOK_SCRIPT.lines().nth(1), Some(HOOK_MARKER));
+        assert!(HOOK_CHAIN.contains(CHAIN_MARKER));
+
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_split_diff() {
+        // Small files are packed into one group
+        assert_eq!(split_diff(DIFF, 1000), vec![DIFF.to_string()]);
+        assert_eq!(split_diff(DIFF, 60).len(), 2);
+
+        // A large file is split between hunks, keeping its header
+        let big = "diff --git a/a.rs b/a.rs\n--- a/a.rs\n+++ b/a.rs\n\
+@@ -1 +1 @@\n-old one\n+new one\n\
+@@ -9 +9 @@\n-old two\n+new two\n";
+        let parts = split_diff(big, 70);
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|p| p.starts_with("diff --git a/a.rs")));
+        assert!(parts[1].contains("+new two") && !parts[1].contains("+new one"));
+    }
+}
@@ -0,0 +1,438 @@
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::output::markdown;
+use crate::paths;
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tracing::debug;
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+const API_VERSION: &str = "2022-11-28";
+
+/// Page size for listing comments (the API maximum)
+const PER_PAGE: usize = 100;
+
+/// Marker of the PR conversation comment listing issues that can't be commented inline
+const SUMMARY_MARKER: &str = "<!-- driftcheck:summary -->";
+
+/// The parts of a pull request driftcheck needs
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub head: GitRef,
+    pub base: GitRef,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitRef {
+    #[serde(rename = "ref")]
+    pub name: String,
+    pub sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Comment {
+    id: u64,
+    body: String,
+}
+
+/// What `sync_review_comments` changed on the pull request
+#[derive(Debug, Default)]
+pub struct CommentSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub resolved: usize,
+    /// Issues on lines outside the PR diff, listed in one conversation comment instead
+    pub outside_diff: usize,
+}
+
+/// GitHub REST API client for one repository, authenticated with `GITHUB_TOKEN`
+pub struct GithubClient {
+    client: reqwest::Client,
+    api_url: String,
+    repo: String,
+    token: String,
+}
+
+impl GithubClient {
+    /// `repo` is "owner/name" (default: `GITHUB_REPOSITORY`). Honors `GITHUB_API_URL` for
+    /// GitHub Enterprise.
+    pub fn from_env(repo: Option<String>) -> Result<Self> {
+        let token = env::var("GITHUB_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| DriftcheckError::ConfigInvalid("GITHUB_TOKEN is not set".into()))?;
+        let repo = repo
+            .or_else(|| env::var("GITHUB_REPOSITORY").ok())
+            .filter(|r| r.contains('/'))
+            .ok_or_else(|| {
+                DriftcheckError::ConfigInvalid(
+                    "pass --repo owner/name or set GITHUB_REPOSITORY".into(),
+                )
+            })?;
+        let api_url = env::var("GITHUB_API_URL")
+            .unwrap_or_else(|_| DEFAULT_API_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("driftcheck/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| DriftcheckError::GithubError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            api_url,
+            repo,
+            token,
+        })
+    }
+
+    pub async fn pull_request(&self, pr: u64) -> Result<PullRequest> {
+        let response = self
+            .send(Method::GET, &format!("pulls/{}", pr), None)
+            .await?;
+        parse(response).await
+    }
+
+    /// Send a request for `path` below `/repos/<owner>/<name>/`, failing on error statuses
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<reqwest::Response> {
+        let label = format!("{} {}", method, path);
+        let response = self.request(method, path, body).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(DriftcheckError::GithubError(format!(
+                "{}: HTTP {}: {}",
+                label, status, body
+            )));
+        }
+        Ok(response)
+    }
+
+    /// Send a request for `path` below `/repos/<owner>/<name>/`, whatever the response status
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<reqwest::Response> {
+        let url = format!("{}/repos/{}/{}", self.api_url, self.repo, path);
+        let mut request = self
+            .client
+            .request(method, &url)
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", API_VERSION);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+
+        request
+            .send()
+            .await
+            .map_err(|e| DriftcheckError::GithubError(e.to_string()))
+    }
+
+    /// Every comment on `path` (e.g. `pulls/7/comments`), following pagination
+    async fn list_comments(&self, path: &str) -> Result<Vec<Comment>> {
+        let mut comments = Vec::new();
+        for page in 1.. {
+            let response = self
+                .send(
+                    Method::GET,
+                    &format!("{}?per_page={}&page={}", path, PER_PAGE, page),
+                    None,
+                )
+                .await?;
+            let batch: Vec<Comment> = parse(response).await?;
+            let done = batch.len() < PER_PAGE;
+            comments.extend(batch);
+            if done {
+                break;
+            }
+        }
+        Ok(comments)
+    }
+}
+
+async fn parse<T: for<'de> Deserialize<'de>>(response: reqwest::Response) -> Result<T> {
+    response
+        .json()
+        .await
+        .map_err(|e| DriftcheckError::GithubError(e.to_string()))
+}
+
+/// Post each issue as an inline review comment on its doc line, keyed by the issue fingerprint.
+/// On re-runs, comments of issues still found are updated and those of issues no longer found
+/// are marked resolved. Issues on lines outside the PR diff (where GitHub rejects inline
+/// comments) go into a single conversation comment.
+pub async fn sync_review_comments(
+    github: &GithubClient,
+    pr: u64,
+    head_sha: &str,
+    issues: &[Issue],
+    root: &Path,
+) -> Result<CommentSummary> {
+    let mut summary = CommentSummary::default();
+    let existing = github
+        .list_comments(&format!("pulls/{}/comments", pr))
+        .await?;
+
+    let mut outside_diff = Vec::new();
+    let mut seen = HashSet::new();
+    for issue in issues {
+        if !seen.insert(issue.fingerprint.as_str()) {
+            continue;
+        }
+        let anchor = anchor(issue, root);
+        let body = comment_body(issue, &anchor);
+
+        if let Some(comment) = existing
+            .iter()
+            .find(|c| fingerprint_of(&c.body) == Some(issue.fingerprint.as_str()))
+        {
+            if comment.body != body {
+                github
+                    .send(
+                        Method::PATCH,
+                        &format!("pulls/comments/{}", comment.id),
+                        Some(&json!({ "body": body })),
+                    )
+                    .await?;
+                summary.updated += 1;
+            }
+            continue;
+        }
+
+        let mut request = json!({
+            "body": body,
+            "commit_id": head_sha,
+            "path": paths::to_slash(&issue.file),
+            "line": anchor.end_line,
+            "side": "RIGHT",
+        });
+        if anchor.start_line < anchor.end_line {
+            request["start_line"] = json!(anchor.start_line);
+            request["start_side"] = json!("RIGHT");
+        }
+        let response = github
+            .request(
+                Method::POST,
+                &format!("pulls/{}/comments", pr),
+                Some(&request),
+            )
+            .await?;
+        match response.status() {
+            status if status.is_success() => summary.created += 1,
+            // GitHub only accepts inline comments on lines that are part of the diff
+            StatusCode::UNPROCESSABLE_ENTITY => {
+                debug!(
+                    "Can't comment inline on {}: {}",
+                    issue.file.display(),
+                    response.text().await.unwrap_or_default()
+                );
+                outside_diff.push(issue);
+            }
+            status => {
+                return Err(DriftcheckError::GithubError(format!(
+                    "POST pulls/{}/comments: HTTP {}: {}",
+                    pr,
+                    status,
+                    response.text().await.unwrap_or_default()
+                )));
+            }
+        }
+    }
+
+    for comment in &existing {
+        let Some(fingerprint) = fingerprint_of(&comment.body) else {
+            continue;
+        };
+        if seen.contains(fingerprint) {
+            continue;
+        }
+        github
+            .send(
+                Method::PATCH,
+                &format!("pulls/comments/{}", comment.id),
+                Some(&json!({ "body": resolved_body(&comment.body, fingerprint, head_sha) })),
+            )
+            .await?;
+        summary.resolved += 1;
+    }
+
+    summary.outside_diff = outside_diff.len();
+    sync_summary_comment(github, pr, &outside_diff).await?;
+    Ok(summary)
+}
+
+/// Create, update or clear the conversation comment listing issues outside the diff
+async fn sync_summary_comment(github: &GithubClient, pr: u64, issues: &[&Issue]) -> Result<()> {
+    let existing = github
+        .list_comments(&format!("issues/{}/comments", pr))
+        .await?;
+    let existing = existing.iter().find(|c| c.body.contains(SUMMARY_MARKER));
+
+    let body = if issues.is_empty() {
+        format!(
+            "**driftcheck:** no documentation issues outside this diff.\n\n{}",
+            SUMMARY_MARKER
+        )
+    } else {
+        let mut body = format!(
+            "**driftcheck:** {} documentation issue(s) on lines outside this diff.\n",
+            issues.len()
+        );
+        for issue in issues {
+            body.push_str(&format!(
+                "\n- `{}:{}` ({}): {}",
+                issue.file.display(),
+                issue.line,
+                issue.status_label(),
+                issue.description
+            ));
+        }
+        body.push_str(&format!("\n\n{}", SUMMARY_MARKER));
+        body
+    };
+
+    match existing {
+        Some(comment) if comment.body != body => {
+            github
+                .send(
+                    Method::PATCH,
+                    &format!("issues/comments/{}", comment.id),
+                    Some(&json!({ "body": body })),
+                )
+                .await?;
+        }
+        Some(_) => {}
+        None if !issues.is_empty() => {
+            github
+                .send(
+                    Method::POST,
+                    &format!("issues/{}/comments", pr),
+                    Some(&json!({ "body": body })),
+                )
+                .await?;
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Lines an inline comment is attached to, with the suggestion to render if any
+struct Anchor {
+    start_line: usize,
+    end_line: usize,
+    suggestion: Option<String>,
+}
+
+fn anchor(issue: &Issue, root: &Path) -> Anchor {
+    if let Some(suggestion) = markdown::suggestion_for(issue, root) {
+        return Anchor {
+            start_line: suggestion.start_line,
+            end_line: suggestion.end_line,
+            suggestion: Some(suggestion.replacement),
+        };
+    }
+
+    let range = if issue.line > 0 {
+        None
+    } else {
+        fs::read_to_string(Config::resolve_in(root, &issue.file))
+            .ok()
+            .and_then(|content| markdown::locate_excerpt(&content, &issue.doc_excerpt))
+    };
+    let (start_line, end_line) = range.unwrap_or((issue.line.max(1), issue.line.max(1)));
+    Anchor {
+        start_line,
+        end_line,
+        suggestion: None,
+    }
+}
+
+fn marker(fingerprint: &str) -> String {
+    format!("<!-- driftcheck:{} -->", fingerprint)
+}
+
+/// Fingerprint of the issue an unresolved driftcheck comment belongs to
+fn fingerprint_of(body: &str) -> Option<&str> {
+    let start = body.find("<!-- driftcheck:")? + "<!-- driftcheck:".len();
+    let fingerprint = body[start..].split_once(" -->")?.0;
+    (fingerprint != "summary").then_some(fingerprint)
+}
+
+fn comment_body(issue: &Issue, anchor: &Anchor) -> String {
+    let mut body = format!(
+        "**driftcheck** ({}): {}\n",
+        issue.status_label(),
+        issue.description
+    );
+    if let Some(code) = &issue.code_location {
+        body.push_str(&format!("\nCode: `{}` (`{}`)\n", code, code.symbol));
+    }
+    match (&anchor.suggestion, &issue.suggested_fix) {
+        (Some(suggestion), _) => {
+            body.push_str(&format!("\n```suggestion\n{}\n```\n", suggestion));
+        }
+        (None, Some(fix)) => body.push_str(&format!("\n**Suggested fix:** {}\n", fix)),
+        (None, None) => {}
+    }
+    body.push_str(&format!("\n{}", marker(&issue.fingerprint)));
+    body
+}
+
+/// Strike through a comment whose issue is gone, swapping its marker so later runs skip it
+fn resolved_body(body: &str, fingerprint: &str, head_sha: &str) -> String {
+    let headline = body.lines().next().unwrap_or_default();
+    format!(
+        "~~{}~~\n\nResolved: no longer detected as of {}.\n\n<!-- driftcheck-resolved:{} -->",
+        headline,
+        &head_sha[..head_sha.len().min(7)],
+        fingerprint
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    #[test]
+    fn test_comment_markers() {
+        let issue = Issue::from(RawIssue {
+            file: "docs/usage.md".to_string(),
+            line: 7,
+            description: "The --no-tui flag was renamed".to_string(),
+            doc_excerpt: String::new(),
+            suggested_fix: Some("Use --plain".to_string()),
+            confidence: None,
+            code_symbol: None,
+        });
+        let anchor = anchor(&issue, Path::new(""));
+        assert_eq!((anchor.start_line, anchor.end_line), (7, 7));
+
+        let body = comment_body(&issue, &anchor);
+        assert_eq!(fingerprint_of(&body), Some(issue.fingerprint.as_str()));
+
+        let resolved = resolved_body(&body, &issue.fingerprint, "0123456789abcdef");
+        assert!(resolved.contains("as of 0123456"));
+        assert_eq!(fingerprint_of(&resolved), None);
+        assert_eq!(fingerprint_of(SUMMARY_MARKER), None);
+    }
+}
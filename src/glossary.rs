@@ -0,0 +1,285 @@
+use crate::config::Config;
+use regex::{Regex, RegexBuilder};
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// A canonical term of the project glossary (`docs.glossary`)
+#[derive(Debug, Clone)]
+pub struct Term {
+    pub canonical: String,
+    /// Synonyms and misspellings the glossary says not to use
+    pub avoid: Vec<String>,
+    /// Case-insensitive match of the avoided forms, and of the term itself when it is one
+    /// word; multi-word terms without avoided forms are only listed in the prompt
+    pattern: Option<Regex>,
+}
+
+impl Term {
+    fn new(canonical: String, avoid: Vec<String>) -> Self {
+        let mut forms: Vec<&str> = avoid.iter().map(String::as_str).collect();
+        if !canonical.contains(char::is_whitespace) {
+            forms.push(&canonical);
+        }
+        // Longest first, so "git hub" wins over "git"
+        forms.sort_by_key(|form| std::cmp::Reverse(form.len()));
+        let alternation: Vec<String> = forms.iter().map(|form| regex::escape(form)).collect();
+        let pattern = (!forms.is_empty())
+            .then(|| {
+                RegexBuilder::new(&alternation.join("|"))
+                    .case_insensitive(true)
+                    .build()
+                    .ok()
+            })
+            .flatten();
+        Self {
+            canonical,
+            avoid,
+            pattern,
+        }
+    }
+
+    /// Whether the term or one of its avoided forms occurs in `text`
+    fn mentioned_in(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        text.contains(&self.canonical.to_lowercase())
+            || self
+                .avoid
+                .iter()
+                .any(|form| text.contains(&form.to_lowercase()))
+    }
+}
+
+/// A form of a term that a fix used instead of the canonical one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correction {
+    pub found: String,
+    pub canonical: String,
+}
+
+/// The project's canonical terms, from a Markdown glossary
+#[derive(Debug, Clone, Default)]
+pub struct Glossary {
+    pub terms: Vec<Term>,
+}
+
+impl Glossary {
+    /// The glossary configured in `docs.glossary`, if any. A missing or unreadable file is
+    /// warned about and ignored.
+    pub fn load(config: &Config) -> Option<Self> {
+        let path = config.docs.glossary.as_ref()?;
+        match fs::read_to_string(config.resolve(path)) {
+            Ok(content) => Some(Self::parse(&content)).filter(|g| !g.terms.is_empty()),
+            Err(e) => {
+                warn!("Ignoring docs.glossary {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Terms are bold list entries (`- **GitHub** (not: Github): ...`), `##` headings and the
+    /// first column of tables. "not:" or "avoid:" after the term lists forms not to use.
+    pub fn parse(content: &str) -> Self {
+        static ENTRY: OnceLock<Regex> = OnceLock::new();
+        static HEADING: OnceLock<Regex> = OnceLock::new();
+        static ROW: OnceLock<Regex> = OnceLock::new();
+        static SEPARATOR: OnceLock<Regex> = OnceLock::new();
+        let entry =
+            ENTRY.get_or_init(|| Regex::new(r"^\s*(?:[-*+]\s+)?\*\*([^*]+)\*\*(.*)$").unwrap());
+        let heading = HEADING.get_or_init(|| Regex::new(r"^#{2,6}\s+(.+?)[\s#]*$").unwrap());
+        let row = ROW.get_or_init(|| Regex::new(r"^\s*\|([^|]+)\|(.*)$").unwrap());
+        let separator = SEPARATOR.get_or_init(|| Regex::new(r"^\s*\|[\s:|-]+\|?\s*$").unwrap());
+
+        let lines: Vec<&str> = content.lines().collect();
+        let mut terms: Vec<Term> = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let (term, rest) = if let Some(c) = entry.captures(line) {
+                (c[1].to_string(), c[2].to_string())
+            } else if let Some(c) = heading.captures(line) {
+                (c[1].to_string(), String::new())
+            } else if let Some(c) = row.captures(line) {
+                // Header rows are followed by the separator row
+                let header = lines
+                    .get(i + 1)
+                    .is_some_and(|next| separator.is_match(next));
+                if header || separator.is_match(line) {
+                    continue;
+                }
+                (c[1].to_string(), c[2].to_string())
+            } else {
+                continue;
+            };
+
+            let canonical = term
+                .trim()
+                .trim_matches(|c: char| c == '`' || c == ':')
+                .trim()
+                .to_string();
+            if canonical.is_empty() || terms.iter().any(|t| t.canonical == canonical) {
+                continue;
+            }
+            terms.push(Term::new(canonical, avoided_forms(&rest)));
+        }
+        Self { terms }
+    }
+
+    /// Prompt section listing the terms mentioned in `context`, or `None` if there are none
+    pub fn prompt_section(&self, context: &str) -> Option<String> {
+        let mentioned: Vec<&Term> = self
+            .terms
+            .iter()
+            .filter(|term| term.mentioned_in(context))
+            .collect();
+        if mentioned.is_empty() {
+            return None;
+        }
+
+        let mut section = "## Terminology\nUse these terms exactly as written, with this \
+                           capitalization, and never the forms in parentheses:\n"
+            .to_string();
+        for term in mentioned {
+            section.push_str(&format!("- {}", term.canonical));
+            if !term.avoid.is_empty() {
+                section.push_str(&format!(" (not: {})", term.avoid.join(", ")));
+            }
+            section.push('\n');
+        }
+        Some(section)
+    }
+
+    /// Replace non-canonical forms of glossary terms on the lines `fixed` adds to `original`,
+    /// outside code blocks and inline code. Returns the corrected text and what was replaced.
+    pub fn enforce(&self, original: &str, fixed: &str) -> (String, Vec<Correction>) {
+        let mut corrections = Vec::new();
+        let mut out = String::with_capacity(fixed.len());
+        let mut in_fence = false;
+
+        for change in TextDiff::from_lines(original, fixed).iter_all_changes() {
+            let line = change.value();
+            match change.tag() {
+                ChangeTag::Delete => continue,
+                _ if line.trim_start().starts_with("```") => in_fence = !in_fence,
+                ChangeTag::Insert if !in_fence => {
+                    out.push_str(&self.correct_line(line, &mut corrections));
+                    continue;
+                }
+                _ => {}
+            }
+            out.push_str(line);
+        }
+        (out, corrections)
+    }
+
+    fn correct_line(&self, line: &str, corrections: &mut Vec<Correction>) -> String {
+        let mut line = line.to_string();
+        for term in &self.terms {
+            let Some(pattern) = &term.pattern else {
+                continue;
+            };
+            let code = code_spans(&line);
+            let mut corrected = String::with_capacity(line.len());
+            let mut last = 0;
+            for found in pattern.find_iter(&line) {
+                if found.as_str() == term.canonical
+                    || code.iter().any(|span| span.contains(&found.start()))
+                    || !standalone(&line, found.start(), found.end())
+                {
+                    continue;
+                }
+                corrected.push_str(&line[last..found.start()]);
+                corrected.push_str(&term.canonical);
+                last = found.end();
+                corrections.push(Correction {
+                    found: found.as_str().to_string(),
+                    canonical: term.canonical.clone(),
+                });
+            }
+            if last > 0 {
+                corrected.push_str(&line[last..]);
+                line = corrected;
+            }
+        }
+        line
+    }
+}
+
+/// Forms listed after "not:", "avoid:" or "never:" in the rest of a glossary entry
+fn avoided_forms(rest: &str) -> Vec<String> {
+    static AVOID: OnceLock<Regex> = OnceLock::new();
+    let avoid =
+        AVOID.get_or_init(|| Regex::new(r"(?i)\b(?:not|avoid|never)\b\s*:\s*([^);|]+)").unwrap());
+    avoid
+        .captures(rest)
+        .map(|c| {
+            c[1].split(',')
+                .flat_map(|form| form.split(" or "))
+                .map(|form| {
+                    form.trim()
+                        .trim_matches(|c: char| matches!(c, '"' | '\'' | '`' | '.'))
+                        .to_string()
+                })
+                .filter(|form| !form.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Byte ranges of inline code spans in a line
+fn code_spans(line: &str) -> Vec<std::ops::Range<usize>> {
+    let ticks: Vec<usize> = line.match_indices('`').map(|(i, _)| i).collect();
+    ticks.chunks_exact(2).map(|pair| pair[0]..pair[1]).collect()
+}
+
+/// Whether `line[start..end]` is a word of its own, not part of a longer identifier, path,
+/// URL or domain (`github.com`)
+fn standalone(line: &str, start: usize, end: usize) -> bool {
+    let joined = |c: char| c.is_alphanumeric() || matches!(c, '_' | '/' | '@' | '-');
+    let before = line[..start].chars().next_back();
+    let mut after = line[end..].chars();
+    let next = after.next();
+    let domain = next == Some('.') && after.next().is_some_and(char::is_alphanumeric);
+    !(before.is_some_and(|c| joined(c) || matches!(c, '.' | '#'))
+        || next.is_some_and(joined)
+        || domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enforce_glossary() {
+        let glossary = Glossary::parse(
+            "# Glossary\n\n\
+             - **GitHub** (not: git hub, GH): the code host\n\
+             - **pre-push hook**: runs before a push\n\n\
+             | Term | Meaning |\n\
+             |------|---------|\n\
+             | `driftcheck` | this tool, avoid: Drift Check |\n",
+        );
+        let names: Vec<&str> = glossary
+            .terms
+            .iter()
+            .map(|t| t.canonical.as_str())
+            .collect();
+        assert_eq!(names, vec!["GitHub", "pre-push hook", "driftcheck"]);
+        assert_eq!(glossary.terms[0].avoid, vec!["git hub", "GH"]);
+
+        let section = glossary.prompt_section("Push to github").unwrap();
+        assert!(section.contains("- GitHub (not: git hub, GH)\n"));
+        assert!(!section.contains("driftcheck"));
+
+        let original = "Run Drift Check.\n";
+        let fixed = "Run Drift Check.\nPush to Github or git hub, see https://github.com/x.\n\
+                     ```\ngh pr create # Github\n```\nRun `Drift Check` or Drift Check.\n";
+        let (corrected, corrections) = glossary.enforce(original, fixed);
+        assert_eq!(
+            corrected,
+            "Run Drift Check.\nPush to GitHub or GitHub, see https://github.com/x.\n\
+             ```\ngh pr create # Github\n```\nRun `Drift Check` or driftcheck.\n"
+        );
+        let found: Vec<&str> = corrections.iter().map(|c| c.found.as_str()).collect();
+        assert_eq!(found, vec!["Github", "git hub", "Drift Check"]);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::paths;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Lifecycle state of an issue across runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueState {
+    /// First seen in this run
+    #[default]
+    New,
+    /// Seen in a previous run and still present
+    Recurring,
+    /// No longer detected, or fixed from the TUI
+    Fixed,
+    /// Skipped by the user
+    Dismissed,
+}
+
+impl fmt::Display for IssueState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            IssueState::New => "new",
+            IssueState::Recurring => "recurring",
+            IssueState::Fixed => "fixed",
+            IssueState::Dismissed => "dismissed",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryEntry {
+    file: String,
+    description: String,
+    state: IssueState,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    /// Changed source file the drift came from, if it was located in the diff
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code_file: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IssueHistory {
+    issues: HashMap<String, HistoryEntry>,
+}
+
+/// Compute a stable fingerprint for an issue.
+/// Line numbers and LLM wording drift between runs, so the doc excerpt is
+/// preferred over the description when available.
+pub fn fingerprint(file: &Path, description: &str, doc_excerpt: &str) -> String {
+    let text = if doc_excerpt.trim().is_empty() {
+        description
+    } else {
+        doc_excerpt
+    };
+    let normalized = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    let mut hasher = Sha256::new();
+    hasher.update(paths::to_slash(file).as_bytes());
+    hasher.update([0]);
+    hasher.update(normalized.as_bytes());
+    let result = hasher.finalize();
+    result[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn history_path() -> Result<PathBuf> {
+    let git_root = Config::find_git_root()?;
+    Ok(Config::resolve_in(&git_root, ".git/driftcheck/issues.json"))
+}
+
+fn load() -> Result<IssueHistory> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(IssueHistory::default());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    serde_json::from_str(&content).map_err(|e| DriftcheckError::CacheError(e.to_string()))
+}
+
+fn save(history: &IssueHistory) -> Result<()> {
+    Config::ensure_writable("updating the issue history")?;
+
+    let path = history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    }
+    let content = serde_json::to_string_pretty(history)
+        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    fs::write(&path, content).map_err(|e| DriftcheckError::CacheError(e.to_string()))
+}
+
+/// Lifecycle state of a freshly detected issue, given its history entry
+fn detected_state(entry: Option<&HistoryEntry>) -> IssueState {
+    match entry {
+        Some(entry) if entry.state == IssueState::Dismissed => IssueState::Dismissed,
+        Some(entry) if entry.state != IssueState::Fixed => IssueState::Recurring,
+        _ => IssueState::New,
+    }
+}
+
+/// Set the lifecycle states of freshly detected issues from the history, without recording
+/// them, so severity rules can match on `state` before `track` runs
+pub fn assign_states(issues: &mut [Issue]) -> Result<()> {
+    let history = load()?;
+    for issue in issues.iter_mut() {
+        issue.state = detected_state(history.issues.get(&issue.fingerprint));
+    }
+    Ok(())
+}
+
+/// Assign lifecycle states to freshly detected issues and persist them.
+/// Previously open issues that were not detected again are marked fixed, but only if their
+/// doc file was among the `analyzed` docs: a check of another range says nothing about them.
+pub fn track(issues: &mut [Issue], analyzed: &HashSet<PathBuf>) -> Result<()> {
+    let mut history = load()?;
+    history.update(issues, analyzed, Utc::now());
+
+    if Config::is_read_only() {
+        return Ok(());
+    }
+
+    save(&history)?;
+    debug!("Tracked {} issues in history", issues.len());
+    Ok(())
+}
+
+impl IssueHistory {
+    fn update(&mut self, issues: &mut [Issue], analyzed: &HashSet<PathBuf>, now: DateTime<Utc>) {
+        let mut seen = HashSet::new();
+
+        for issue in issues.iter_mut() {
+            seen.insert(issue.fingerprint.clone());
+
+            let state = detected_state(self.issues.get(&issue.fingerprint));
+            issue.state = state;
+
+            let entry = self
+                .issues
+                .entry(issue.fingerprint.clone())
+                .or_insert_with(|| HistoryEntry {
+                    file: paths::to_slash(&issue.file),
+                    description: issue.description.clone(),
+                    state,
+                    first_seen: now,
+                    last_seen: now,
+                    code_file: None,
+                });
+            entry.state = state;
+            entry.last_seen = now;
+            if let Some(code) = &issue.code_location {
+                entry.code_file = Some(paths::to_slash(&code.file));
+            }
+        }
+
+        for (fp, entry) in self.issues.iter_mut() {
+            if !seen.contains(fp)
+                && entry.state != IssueState::Dismissed
+                && analyzed.contains(Path::new(&entry.file))
+            {
+                entry.state = IssueState::Fixed;
+            }
+        }
+    }
+}
+
+/// Set the states of replayed issues from the history, which may have changed since they
+/// were detected (e.g. fixed or dismissed in an earlier TUI session)
+pub fn refresh(issues: &mut [Issue]) -> Result<()> {
+    let history = load()?;
+    for issue in issues.iter_mut() {
+        if let Some(entry) = history.issues.get(&issue.fingerprint) {
+            issue.state = entry.state;
+        }
+    }
+    Ok(())
+}
+
+/// How many recorded issues each changed source file caused, for drift risk scoring
+pub fn drift_counts() -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    if let Ok(history) = load() {
+        for file in history.issues.values().filter_map(|e| e.code_file.clone()) {
+            *counts.entry(file).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// File and description recorded for an issue, if it was seen before
+pub fn lookup(fingerprint: &str) -> Option<(String, String)> {
+    let history = load().ok()?;
+    let entry = history.issues.get(fingerprint)?;
+    Some((entry.file.clone(), entry.description.clone()))
+}
+
+/// Persist a state change made during review (e.g. dismissed or fixed in the TUI)
+pub fn set_state(fingerprint: &str, state: IssueState) -> Result<()> {
+    let mut history = load()?;
+    if let Some(entry) = history.issues.get_mut(fingerprint) {
+        entry.state = state;
+        save(&history)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_ignores_whitespace_and_case() {
+        let a = fingerprint(Path::new("README.md"), "desc", "Run `foo --bar`");
+        let b = fingerprint(Path::new("README.md"), "other", "  run `foo   --bar`\n");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_fingerprint_differs_per_file() {
+        let a = fingerprint(Path::new("README.md"), "desc", "excerpt");
+        let b = fingerprint(Path::new("docs/guide.md"), "desc", "excerpt");
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_track_keeps_issues_outside_analyzed_docs() {
+        let issue = |file: &str, excerpt: &str| {
+            Issue::from(crate::llm::RawIssue {
+                file: file.to_string(),
+                line: 1,
+                description: "stale".to_string(),
+                doc_excerpt: excerpt.to_string(),
+                suggested_fix: None,
+                confidence: None,
+                code_symbol: None,
+            })
+        };
+        let docs = |files: &[&str]| files.iter().map(PathBuf::from).collect::<HashSet<_>>();
+        let state =
+            |history: &IssueHistory, issue: &Issue| history.issues[&issue.fingerprint].state;
+
+        let mut history = IssueHistory::default();
+        let mut first = vec![issue("README.md", "a"), issue("docs/api.md", "b")];
+        history.update(&mut first, &docs(&["README.md", "docs/api.md"]), Utc::now());
+
+        // Another range, other docs: the first issues are neither seen nor fixed
+        let mut second = vec![issue("docs/cli.md", "c")];
+        history.update(&mut second, &docs(&["docs/cli.md"]), Utc::now());
+        assert!(first.iter().all(|i| state(&history, i) == IssueState::New));
+
+        // README.md analyzed again without its issue: that one is fixed
+        history.update(&mut [], &docs(&["README.md"]), Utc::now());
+        assert_eq!(state(&history, &first[0]), IssueState::Fixed);
+        assert_eq!(state(&history, &first[1]), IssueState::New);
+
+        let mut again = vec![issue("docs/api.md", "b")];
+        history.update(&mut again, &docs(&["docs/api.md"]), Utc::now());
+        assert_eq!(again[0].state, IssueState::Recurring);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// First line of every Git LFS pointer file
+const POINTER_HEADER: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// Pointer files are tiny; anything larger is real content
+const MAX_POINTER_BYTES: u64 = 1024;
+
+/// Directory (relative to the repository root) holding smudged copies of pointer docs
+pub const SMUDGE_DIR: &str = ".git/driftcheck/lfs";
+
+/// Whether `content` is a Git LFS pointer rather than the file itself
+pub fn is_pointer(content: &str) -> bool {
+    content.len() as u64 <= MAX_POINTER_BYTES && content.starts_with(POINTER_HEADER)
+}
+
+/// Whether the file at `path` is an un-smudged Git LFS pointer
+pub fn is_pointer_file(path: &Path) -> bool {
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+    if meta.len() > MAX_POINTER_BYTES {
+        return false;
+    }
+    fs::read_to_string(path).is_ok_and(|content| is_pointer(&content))
+}
+
+/// Write the real content of a pointer doc to `SMUDGE_DIR` with `git lfs smudge`.
+/// Returns the copy's path relative to `root`.
+pub fn smudge(root: &Path, file: &Path) -> Result<PathBuf> {
+    Config::ensure_writable("smudging Git LFS docs")?;
+
+    let pointer =
+        fs::read(root.join(file)).map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+
+    let mut child = Command::new("git")
+        .args(["lfs", "smudge", "--"])
+        .arg(file)
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| DriftcheckError::GitError(format!("git lfs smudge: {}", e)))?;
+
+    // Pointers are tiny, so writing all of stdin before reading stdout can't block
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(&pointer)
+            .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+    if !output.status.success() {
+        return Err(DriftcheckError::GitError(format!(
+            "git lfs smudge {}: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let target = Config::resolve_in(root, Path::new(SMUDGE_DIR).join(file));
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir).map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+    }
+    fs::write(&target, output.stdout).map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+    Ok(target
+        .strip_prefix(root)
+        .map(Path::to_path_buf)
+        .unwrap_or(target))
+}
+
+/// Map a path inside `SMUDGE_DIR` back to the doc it was smudged from. In worktrees and
+/// submodules the copy lives in a git directory outside the root (`../` or absolute).
+pub fn original_path(file: &str) -> &str {
+    if let Some(rest) = file.strip_prefix(SMUDGE_DIR) {
+        return rest.trim_start_matches('/');
+    }
+    let marker = "/driftcheck/lfs/";
+    match file.find(marker) {
+        Some(at) if file.starts_with("../") || Path::new(file).is_absolute() => {
+            &file[at + marker.len()..]
+        }
+        _ => file,
+    }
+}
+
+/// Drop file sections of a diff that only change LFS pointer lines (`version`, `oid`, `size`)
+pub fn strip_pointer_diffs(diff: &str) -> String {
+    let mut out = String::new();
+    let mut section = String::new();
+    let mut pointer_only = true;
+    let mut changed = false;
+
+    let mut flush = |section: &mut String, pointer_only: bool, changed: bool| {
+        if !(pointer_only && changed) {
+            out.push_str(section);
+        }
+        section.clear();
+    };
+
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("diff --git") {
+            flush(&mut section, pointer_only, changed);
+            pointer_only = true;
+            changed = false;
+        } else if (line.starts_with('+') && !line.starts_with("+++"))
+            || (line.starts_with('-') && !line.starts_with("---"))
+        {
+            changed = true;
+            let text = &line[1..];
+            pointer_only &= text.starts_with("version https://git-lfs.github.com/spec/")
+                || text.starts_with("oid sha256:")
+                || text.starts_with("size ");
+        }
+        section.push_str(line);
+    }
+    flush(&mut section, pointer_only, changed);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTER: &str = "version https://git-lfs.github.com/spec/v1\n\
+oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+size 12345\n";
+
+    #[test]
+    fn test_is_pointer() {
+        assert!(is_pointer(POINTER));
+        assert!(!is_pointer(
+            "# Guide\n\nversion https://git-lfs.github.com/spec/v1\n"
+        ));
+    }
+
+    #[test]
+    fn test_strip_pointer_diffs() {
+        let diff = "diff --git a/docs/export.md b/docs/export.md\n\
+--- a/docs/export.md\n+++ b/docs/export.md\n@@ -1,3 +1,3 @@\n \
+version https://git-lfs.github.com/spec/v1\n-oid sha256:aaaa\n+oid sha256:bbbb\n-size 1\n+size 2\n\
+diff --git a/src/lib.rs b/src/lib.rs\n+fn a() {}\n";
+        assert_eq!(
+            strip_pointer_diffs(diff),
+            "diff --git a/src/lib.rs b/src/lib.rs\n+fn a() {}\n"
+        );
+        assert_eq!(
+            original_path(".git/driftcheck/lfs/docs/export.md"),
+            "docs/export.md"
+        );
+        assert_eq!(
+            original_path("../main/.git/driftcheck/lfs/docs/export.md"),
+            "docs/export.md"
+        );
+        assert_eq!(
+            original_path("docs/driftcheck/lfs/a.md"),
+            "docs/driftcheck/lfs/a.md"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! Documentation drift detection for Git.
+//!
+//! The `driftcheck` binary is a thin consumer of this crate. Tools that want the analysis
+//! without shelling out to the binary can load a [`Config`] and call [`analyze`] with a
+//! unified diff:
+//!
+//! ```no_run
+//! # async fn run() -> driftcheck::Result<()> {
+//! let config = driftcheck::Config::load()?;
+//! let diff = std::fs::read_to_string("changes.diff")?;
+//! for issue in driftcheck::analyze(&config, &diff).await?.issues {
+//!     println!("{}:{}: {}", issue.file.display(), issue.line, issue.description);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`analyzer`], [`config`], [`error`], [`llm`] and [`search`] are the public API. The other
+//! modules back the CLI and may change in any release.
+
+pub mod analyzer;
+pub mod config;
+pub mod error;
+pub mod llm;
+pub mod search;
+
+pub use analyzer::{analyze, Analysis, Issue, Severity};
+pub use config::Config;
+pub use error::{DriftcheckError, Result};
+pub use history::IssueState;
+pub use llm::{DocChunk, DocKind, LlmClient};
+
+#[doc(hidden)]
+pub mod baseline;
+#[doc(hidden)]
+pub mod cache;
+#[doc(hidden)]
+pub mod consensus;
+mod deps;
+#[doc(hidden)]
+pub mod discover;
+mod docstrings;
+mod embeddings;
+mod excerpt;
+mod expr;
+#[doc(hidden)]
+pub mod feedback;
+#[doc(hidden)]
+pub mod fix;
+#[doc(hidden)]
+pub mod git;
+#[doc(hidden)]
+pub mod github;
+#[doc(hidden)]
+pub mod glossary;
+#[doc(hidden)]
+pub mod history;
+mod lfs;
+#[doc(hidden)]
+pub mod lsp;
+#[doc(hidden)]
+pub mod metrics;
+#[doc(hidden)]
+pub mod onboarding;
+#[doc(hidden)]
+pub mod output;
+mod patch;
+mod paths;
+mod policy;
+mod preview;
+mod progress;
+#[doc(hidden)]
+pub mod push;
+#[doc(hidden)]
+pub mod release;
+#[doc(hidden)]
+pub mod risk;
+#[doc(hidden)]
+pub mod runs;
+mod suppress;
+#[doc(hidden)]
+pub mod sweep;
+mod symbols;
+#[doc(hidden)]
+pub mod telemetry;
+#[doc(hidden)]
+pub mod term;
+#[doc(hidden)]
+pub mod tui;
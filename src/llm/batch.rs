@@ -0,0 +1,444 @@
+use super::{endpoint, AnthropicResponse, ChatResponse, LlmClient, Provider, ANTHROPIC_VERSION};
+use crate::error::{DriftcheckError, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::field::Empty;
+use tracing::{debug, info, Span};
+
+/// Time between batch status checks
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Longest wait for a batch; providers finish within 24 hours or expire the batch
+const MAX_WAIT: Duration = Duration::from_secs(25 * 60 * 60);
+
+const MULTIPART_BOUNDARY: &str = "driftcheck-batch-boundary";
+
+#[derive(Debug, Deserialize)]
+struct OpenAiFile {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiBatch {
+    id: String,
+    status: String,
+    #[serde(default)]
+    output_file_id: Option<String>,
+    #[serde(default)]
+    error_file_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiBatchLine {
+    custom_id: String,
+    #[serde(default)]
+    response: Option<OpenAiBatchResponse>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiBatchResponse {
+    status_code: u16,
+    body: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicBatch {
+    id: String,
+    processing_status: String,
+    #[serde(default)]
+    results_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicBatchLine {
+    custom_id: String,
+    result: AnthropicBatchResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicBatchResult {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    message: Option<AnthropicResponse>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+/// Token counts summed over a batch's results
+#[derive(Default)]
+struct Usage {
+    input: u64,
+    output: u64,
+}
+
+impl LlmClient {
+    /// Whether the provider has a batch API (`chat_batch`)
+    pub fn supports_batches(&self) -> bool {
+        self.provider != Provider::Ollama
+    }
+
+    /// Send every message with the same system prompt as one provider batch (OpenAI Batch
+    /// API or Anthropic Message Batches), wait for it to finish, and return the responses in
+    /// order. Batches cost about half as much but may take hours; each request fails on its own.
+    #[tracing::instrument(
+        name = "llm.batch",
+        skip_all,
+        fields(
+            gen_ai.system = self.config.provider.as_str(),
+            gen_ai.request.model = self.config.model.as_str(),
+            requests = user_messages.len(),
+            batch_id = Empty,
+            gen_ai.usage.input_tokens = Empty,
+            gen_ai.usage.output_tokens = Empty,
+            error = Empty,
+        )
+    )]
+    pub async fn chat_batch(
+        &self,
+        system_prompt: &str,
+        user_messages: &[String],
+    ) -> Result<Vec<Result<String>>> {
+        if user_messages.is_empty() {
+            return Ok(Vec::new());
+        }
+        let result = match self.provider {
+            Provider::OpenAi => self.openai_batch(system_prompt, user_messages).await,
+            Provider::Anthropic => self.anthropic_batch(system_prompt, user_messages).await,
+            Provider::Ollama => Err(DriftcheckError::ConfigInvalid(
+                "llm.provider \"ollama\" has no batch API".to_string(),
+            )),
+        };
+        let (mut responses, usage) = match result {
+            Ok(results) => results,
+            Err(e) => {
+                Span::current().record("error", e.to_string());
+                return Err(e);
+            }
+        };
+        self.config.usage.record_batch(
+            &self.config.model,
+            user_messages.len() as u64,
+            usage.input,
+            usage.output,
+        );
+        Span::current().record("gen_ai.usage.input_tokens", usage.input);
+        Span::current().record("gen_ai.usage.output_tokens", usage.output);
+
+        Ok((0..user_messages.len())
+            .map(|i| {
+                responses.remove(&custom_id(i)).unwrap_or_else(|| {
+                    Err(DriftcheckError::LlmError(
+                        "no result for this request in the batch".to_string(),
+                    ))
+                })
+            })
+            .collect())
+    }
+
+    async fn openai_batch(
+        &self,
+        system_prompt: &str,
+        user_messages: &[String],
+    ) -> Result<(HashMap<String, Result<String>>, Usage)> {
+        let base = self.config.base_url.trim_end_matches('/');
+
+        let mut input = String::new();
+        for (i, user_message) in user_messages.iter().enumerate() {
+            let line = json!({
+                "custom_id": custom_id(i),
+                "method": "POST",
+                "url": "/v1/chat/completions",
+                "body": self.chat_request(system_prompt, user_message, None),
+            });
+            input.push_str(&line.to_string());
+            input.push('\n');
+        }
+
+        let file: OpenAiFile = self
+            .send_json(
+                self.authorized(&format!("{}/files", base))
+                    .header(
+                        "content-type",
+                        format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY),
+                    )
+                    .body(multipart_jsonl(&input)),
+            )
+            .await?;
+        let mut batch: OpenAiBatch = self
+            .send_json(self.authorized(&format!("{}/batches", base)).json(&json!({
+                "input_file_id": file.id,
+                "endpoint": "/v1/chat/completions",
+                "completion_window": "24h",
+            })))
+            .await?;
+        Span::current().record("batch_id", batch.id.as_str());
+        info!(
+            "Submitted batch {} with {} requests",
+            batch.id,
+            user_messages.len()
+        );
+
+        let started = tokio::time::Instant::now();
+        while !matches!(
+            batch.status.as_str(),
+            "completed" | "failed" | "expired" | "cancelled"
+        ) {
+            wait_for_batch(&batch.id, &batch.status, started).await?;
+            batch = self
+                .send_json(self.authenticated_get(&format!("{}/batches/{}", base, batch.id)))
+                .await?;
+        }
+        if batch.status != "completed" && batch.output_file_id.is_none() {
+            return Err(DriftcheckError::LlmError(format!(
+                "batch {} {}",
+                batch.id, batch.status
+            )));
+        }
+
+        let mut results = HashMap::new();
+        let mut usage = Usage::default();
+        for file_id in [&batch.output_file_id, &batch.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            let content = self
+                .send_text(self.authenticated_get(&format!("{}/files/{}/content", base, file_id)))
+                .await?;
+            results.extend(parse_openai_results(&content, &mut usage)?);
+        }
+        Ok((results, usage))
+    }
+
+    async fn anthropic_batch(
+        &self,
+        system_prompt: &str,
+        user_messages: &[String],
+    ) -> Result<(HashMap<String, Result<String>>, Usage)> {
+        let url = format!(
+            "{}/batches",
+            endpoint(Provider::Anthropic, &self.config.base_url)
+        );
+
+        let requests: Vec<Value> = user_messages
+            .iter()
+            .enumerate()
+            .map(|(i, user_message)| {
+                json!({
+                    "custom_id": custom_id(i),
+                    "params": self.anthropic_request(system_prompt, user_message, None),
+                })
+            })
+            .collect();
+
+        let mut batch: AnthropicBatch = self
+            .send_json(
+                self.anthropic(self.client.post(&url))
+                    .json(&json!({ "requests": requests })),
+            )
+            .await?;
+        Span::current().record("batch_id", batch.id.as_str());
+        info!(
+            "Submitted batch {} with {} requests",
+            batch.id,
+            user_messages.len()
+        );
+
+        let started = tokio::time::Instant::now();
+        while batch.processing_status != "ended" {
+            wait_for_batch(&batch.id, &batch.processing_status, started).await?;
+            batch = self
+                .send_json(self.anthropic(self.client.get(format!("{}/{}", url, batch.id))))
+                .await?;
+        }
+        let Some(results_url) = batch.results_url else {
+            return Err(DriftcheckError::LlmError(format!(
+                "batch {} ended without results",
+                batch.id
+            )));
+        };
+
+        let content = self
+            .send_text(self.anthropic(self.client.get(&results_url)))
+            .await?;
+        let mut usage = Usage::default();
+        let results = parse_anthropic_results(&content, &mut usage)?;
+        Ok((results, usage))
+    }
+
+    fn authenticated_get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.get(url);
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    fn anthropic(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request
+            .header("x-api-key", self.api_key.as_deref().unwrap_or_default())
+            .header("anthropic-version", ANTHROPIC_VERSION)
+    }
+
+    async fn send_text(&self, request: reqwest::RequestBuilder) -> Result<String> {
+        let response = request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                DriftcheckError::LlmTimeout(self.config.timeout)
+            } else {
+                DriftcheckError::LlmError(e.to_string())
+            }
+        })?;
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(DriftcheckError::LlmError(format!(
+                "HTTP {}: {}",
+                status, body
+            )));
+        }
+        Ok(body)
+    }
+
+    async fn send_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let body = self.send_text(request).await?;
+        serde_json::from_str(&body).map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))
+    }
+}
+
+/// Sleep until the next status check, or fail once the batch has run for `MAX_WAIT`
+async fn wait_for_batch(id: &str, status: &str, started: tokio::time::Instant) -> Result<()> {
+    if started.elapsed() > MAX_WAIT {
+        return Err(DriftcheckError::LlmError(format!(
+            "batch {} still {} after {} hours",
+            id,
+            status,
+            MAX_WAIT.as_secs() / 3600
+        )));
+    }
+    debug!("Batch {} is {}", id, status);
+    tokio::time::sleep(POLL_INTERVAL).await;
+    Ok(())
+}
+
+/// Responses by custom id from an OpenAI batch output or error file (JSONL)
+fn parse_openai_results(
+    content: &str,
+    usage: &mut Usage,
+) -> Result<HashMap<String, Result<String>>> {
+    let mut results = HashMap::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let line: OpenAiBatchLine = serde_json::from_str(line)
+            .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))?;
+        let result = match (line.response, line.error) {
+            (Some(response), _) if response.status_code == 200 => {
+                serde_json::from_value::<ChatResponse>(response.body)
+                    .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))
+                    .and_then(|chat| {
+                        if let Some(u) = &chat.usage {
+                            usage.input += u.prompt_tokens.unwrap_or_default();
+                            usage.output += u.completion_tokens.unwrap_or_default();
+                        }
+                        chat.choices
+                            .into_iter()
+                            .next()
+                            .map(|c| c.message.content)
+                            .ok_or_else(|| {
+                                DriftcheckError::LlmResponseParse("No response choices".to_string())
+                            })
+                    })
+            }
+            (Some(response), _) => Err(DriftcheckError::LlmError(format!(
+                "HTTP {}: {}",
+                response.status_code, response.body
+            ))),
+            (None, error) => Err(DriftcheckError::LlmError(
+                error.map_or_else(|| "unknown error".to_string(), |e| e.to_string()),
+            )),
+        };
+        results.insert(line.custom_id, result);
+    }
+    Ok(results)
+}
+
+/// Responses by custom id from Anthropic batch results (JSONL)
+fn parse_anthropic_results(
+    content: &str,
+    usage: &mut Usage,
+) -> Result<HashMap<String, Result<String>>> {
+    let mut results = HashMap::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let line: AnthropicBatchLine = serde_json::from_str(line)
+            .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))?;
+        let result = match line.result.message {
+            Some(message) if line.result.kind == "succeeded" => {
+                if let Some(u) = &message.usage {
+                    usage.input += u.input_tokens.unwrap_or_default();
+                    usage.output += u.output_tokens.unwrap_or_default();
+                }
+                Ok(message
+                    .content
+                    .into_iter()
+                    .filter(|block| block.kind == "text")
+                    .map(|block| block.text)
+                    .collect::<Vec<_>>()
+                    .join(""))
+            }
+            _ => Err(DriftcheckError::LlmError(format!(
+                "{}: {}",
+                line.result.kind,
+                line.result.error.unwrap_or_default()
+            ))),
+        };
+        results.insert(line.custom_id, result);
+    }
+    Ok(results)
+}
+
+fn custom_id(index: usize) -> String {
+    format!("request-{}", index)
+}
+
+/// `multipart/form-data` body uploading `jsonl` as a batch input file
+fn multipart_jsonl(jsonl: &str) -> String {
+    format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"purpose\"\r\n\r\nbatch\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"driftcheck-batch.jsonl\"\r\n\
+         Content-Type: application/jsonl\r\n\r\n{jsonl}\r\n--{b}--\r\n",
+        b = MULTIPART_BOUNDARY,
+        jsonl = jsonl
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch_results() {
+        let openai = r#"{"custom_id": "request-1", "response": {"status_code": 200, "body": {"choices": [{"message": {"role": "assistant", "content": "fixed"}}], "usage": {"prompt_tokens": 100, "completion_tokens": 20}}}, "error": null}
+{"custom_id": "request-0", "response": {"status_code": 429, "body": {"error": "rate limited"}}, "error": null}
+"#;
+        let mut usage = Usage::default();
+        let results = parse_openai_results(openai, &mut usage).unwrap();
+        assert_eq!(results["request-1"].as_deref().unwrap(), "fixed");
+        assert!(results["request-0"].is_err());
+        assert_eq!((usage.input, usage.output), (100, 20));
+
+        let anthropic = r#"{"custom_id": "request-0", "result": {"type": "succeeded", "message": {"content": [{"type": "text", "text": "fixed"}], "usage": {"input_tokens": 50, "output_tokens": 5}}}}
+{"custom_id": "request-1", "result": {"type": "expired"}}
+"#;
+        let mut usage = Usage::default();
+        let results = parse_anthropic_results(anthropic, &mut usage).unwrap();
+        assert_eq!(results["request-0"].as_deref().unwrap(), "fixed");
+        assert!(results["request-1"].is_err());
+        assert_eq!((usage.input, usage.output), (50, 5));
+    }
+}
@@ -0,0 +1,46 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Set by `check --capture-prompts`
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static EXCHANGES: Mutex<Vec<Exchange>> = Mutex::new(Vec::new());
+
+/// The exact prompt sent and raw response received for one pipeline stage
+#[derive(Debug, Clone, Serialize)]
+pub struct Exchange {
+    pub stage: &'static str,
+    pub system_prompt: String,
+    pub user_message: String,
+    pub response: String,
+}
+
+/// Start recording exchanges for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn record(stage: &'static str, system_prompt: &str, user_message: &str, response: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let exchange = Exchange {
+        stage,
+        system_prompt: system_prompt.to_string(),
+        user_message: user_message.to_string(),
+        response: response.to_string(),
+    };
+    if let Ok(mut exchanges) = EXCHANGES.lock() {
+        exchanges.push(exchange);
+    }
+}
+
+/// Take all recorded exchanges, in the order they completed
+pub fn take() -> Vec<Exchange> {
+    EXCHANGES
+        .lock()
+        .map(|mut exchanges| std::mem::take(&mut *exchanges))
+        .unwrap_or_default()
+}
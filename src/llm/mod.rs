@@ -0,0 +1,1415 @@
+mod batch;
+pub mod capture;
+pub mod parser;
+mod rate_limit;
+mod schema;
+pub mod stream;
+pub mod usage;
+
+use crate::config::{Config, LlmConfig, LlmTask};
+use crate::error::{DriftcheckError, Result};
+use parser::ResponseParser;
+pub use schema::Answer;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::time::Duration;
+use tracing::field::Empty;
+use tracing::{debug, warn, Span};
+
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<JsonSchema>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonSchema {
+    name: &'static str,
+    strict: bool,
+    schema: Value,
+}
+
+/// Ask for token counts in the last chunk of a streamed response
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatUsage {
+    prompt_tokens: Option<u64>,
+    completion_tokens: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+/// Anthropic requires an explicit output limit; fixes may rewrite whole doc files
+const ANTHROPIC_MAX_TOKENS: u32 = 8192;
+
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Serialize)]
+struct AnthropicRequest {
+    model: String,
+    max_tokens: u32,
+    system: String,
+    messages: Vec<Message>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ToolChoice>,
+}
+
+/// A tool whose input is the structured answer; Anthropic has no response format setting
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: &'static str,
+    description: &'static str,
+    input_schema: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolChoice {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicUsage {
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+    /// Arguments of a `tool_use` block
+    #[serde(default)]
+    input: Option<Value>,
+}
+
+const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+#[derive(Debug, Serialize)]
+struct OllamaRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    options: OllamaOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaOptions {
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+/// JSON mode and schemas only allow an object, so array answers are wrapped in one; the
+/// parsers find the array inside
+const JSON_OBJECT_NOTE: &str =
+    "\n\nRespond with a JSON object whose \"items\" key holds the JSON array.";
+
+/// A full response, or one line of a streamed response (the last line carries the counts)
+#[derive(Debug, Deserialize)]
+struct OllamaResponse {
+    message: Message,
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    eval_count: Option<u64>,
+}
+
+/// Embeddings request, for both OpenAI (`/embeddings`) and Ollama (`/api/embed`)
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbeddings {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbedding {
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddings {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Texts per embeddings request
+const EMBEDDING_BATCH: usize = 64;
+
+/// 429 responses one call gives up after, however short the waits they ask for
+const MAX_RATE_LIMITED: u32 = 10;
+
+/// Wire protocol spoken by the configured endpoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Provider {
+    OpenAi,
+    Anthropic,
+    Ollama,
+}
+
+impl Provider {
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "openai" => Ok(Provider::OpenAi),
+            "anthropic" => Ok(Provider::Anthropic),
+            "ollama" => Ok(Provider::Ollama),
+            other => Err(DriftcheckError::ConfigInvalid(format!(
+                "Unknown llm.provider '{}' (expected \"openai\", \"anthropic\" or \"ollama\")",
+                other
+            ))),
+        }
+    }
+}
+
+/// Whether requests can be made without an API key (local servers)
+fn api_key_optional(provider: Provider, base_url: &str) -> bool {
+    let url = base_url.to_lowercase();
+    provider == Provider::Ollama
+        || url.contains("://localhost")
+        || url.contains("://127.0.0.1")
+        || url.contains("://[::1]")
+}
+
+/// Request URL for the provider. The default OpenAI base URL is swapped for
+/// the provider's own when only `provider` is set.
+fn endpoint(provider: Provider, base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    match provider {
+        Provider::OpenAi => format!("{}/chat/completions", base),
+        Provider::Ollama => format!("{}/api/chat", ollama_base(base)),
+        Provider::Anthropic => {
+            let base = if base.contains("api.openai.com") {
+                ANTHROPIC_BASE_URL
+            } else {
+                base
+            };
+            if base.ends_with("/v1") {
+                format!("{}/messages", base)
+            } else {
+                format!("{}/v1/messages", base)
+            }
+        }
+    }
+}
+
+/// Embeddings URL for the provider; Anthropic has no embeddings API
+fn embeddings_endpoint(provider: Provider, base_url: &str) -> Option<String> {
+    let base = base_url.trim_end_matches('/');
+    match provider {
+        Provider::OpenAi => Some(format!("{}/embeddings", base)),
+        Provider::Ollama => Some(format!("{}/api/embed", ollama_base(base))),
+        Provider::Anthropic => None,
+    }
+}
+
+fn ollama_base(base: &str) -> &str {
+    if base.contains("api.openai.com") {
+        OLLAMA_BASE_URL
+    } else {
+        // Accept the OpenAI-compatible URL (".../v1") users may already have
+        base.trim_end_matches("/v1")
+    }
+}
+
+pub struct LlmClient {
+    client: reqwest::Client,
+    config: LlmConfig,
+    provider: Provider,
+    api_key: Option<String>,
+    parser: Box<dyn ResponseParser>,
+}
+
+impl LlmClient {
+    pub fn new(config: &LlmConfig) -> Result<Self> {
+        let provider = Provider::from_name(&config.provider)?;
+        let key = match &config.api_key_env {
+            Some(var) => std::env::var(var).map_err(|_| DriftcheckError::ApiKeyNotFound),
+            None => Config::get_api_key(),
+        };
+        let api_key = match key {
+            Ok(key) => Some(key),
+            Err(_) if api_key_optional(provider, &config.base_url) => None,
+            Err(e) => return Err(e),
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .build()
+            .map_err(|e| DriftcheckError::LlmError(e.to_string()))?;
+
+        let parser = parser::parser_for(config);
+        debug!("Using {} response parser", parser.name());
+
+        Ok(Self {
+            client,
+            config: config.clone(),
+            provider,
+            api_key,
+            parser,
+        })
+    }
+
+    /// Parser matching the configured provider's response style
+    pub fn parser(&self) -> &dyn ResponseParser {
+        self.parser.as_ref()
+    }
+
+    #[tracing::instrument(
+        name = "llm.chat",
+        skip_all,
+        fields(
+            gen_ai.system = self.config.provider.as_str(),
+            gen_ai.request.model = self.config.model.as_str(),
+            attempts = Empty,
+            gen_ai.usage.input_tokens = Empty,
+            gen_ai.usage.output_tokens = Empty,
+            error = Empty,
+        )
+    )]
+    pub async fn chat(&self, system_prompt: &str, user_message: &str) -> Result<String> {
+        self.chat_with(system_prompt, user_message, None).await
+    }
+
+    /// `chat`, asking for an `answer` shaped JSON output when `llm.json_mode` or
+    /// `llm.structured_output` is on
+    async fn chat_with(
+        &self,
+        system_prompt: &str,
+        user_message: &str,
+        answer: Option<Answer>,
+    ) -> Result<String> {
+        let json = answer.filter(|_| self.config.json_mode || self.config.structured_output);
+        let system_prompt = &self.json_system_prompt(system_prompt, json);
+        let url = endpoint(self.provider, &self.config.base_url);
+
+        debug!("LLM request to: {}", url);
+        debug!("LLM model: {}", self.config.model);
+        debug!("System prompt: {}", &system_prompt);
+        debug!("User message: {}", &user_message);
+        debug!("User message length: {} chars", user_message.len());
+
+        let (mut attempts, mut retries, mut rate_limited) = (0, 0, 0);
+        let mut rate_limit_wait = Duration::ZERO;
+
+        // Rough estimate: 4 chars per token
+        let estimated_tokens = (system_prompt.len() + user_message.len()) / 4;
+
+        loop {
+            attempts += 1;
+            Span::current().record("attempts", attempts);
+            stream::restart();
+            rate_limit::acquire(
+                &self.config.base_url,
+                self.config.requests_per_minute,
+                self.config.tokens_per_minute,
+                estimated_tokens,
+            )
+            .await;
+
+            match self
+                .make_request(&url, system_prompt, user_message, json)
+                .await
+            {
+                Ok(response) if response.trim().is_empty() => {
+                    // Not a transport problem, so retrying the same request won't help
+                    self.config.usage.record_call(&self.config.model);
+                    Span::current().record("error", DriftcheckError::LlmEmptyResponse.code());
+                    return Err(DriftcheckError::LlmEmptyResponse);
+                }
+                Ok(response) => {
+                    debug!("LLM response: {}", &response[..response.len().min(500)]);
+                    self.config.usage.record_call(&self.config.model);
+                    return Ok(response);
+                }
+                Err(e @ DriftcheckError::LlmContextExceeded(_)) => {
+                    // The same request would overflow again; the caller has to shrink it
+                    Span::current().record("error", e.code());
+                    return Err(e);
+                }
+                Err(DriftcheckError::LlmRateLimited(message, retry_after)) => {
+                    // Wait as long as the provider asks, holding back concurrent calls too
+                    rate_limited += 1;
+                    let wait = rate_limited_wait(retry_after, rate_limited);
+                    rate_limit_wait += wait;
+                    if rate_limited > MAX_RATE_LIMITED
+                        || rate_limit_wait > Duration::from_secs(self.config.max_rate_limit_wait)
+                    {
+                        let error = DriftcheckError::LlmRateLimited(message, retry_after);
+                        Span::current().record("error", error.code());
+                        return Err(error);
+                    }
+                    warn!("LLM rate limit hit, retrying in {:?}", wait);
+                    rate_limit::back_off(wait);
+                }
+                Err(e) => {
+                    warn!("LLM request attempt {} failed: {}", attempts, e);
+                    if retries == self.config.max_retries {
+                        Span::current().record("error", e.to_string());
+                        return Err(e);
+                    }
+                    retries += 1;
+                    let delay = backoff(retries);
+                    debug!("Retrying LLM request after {:?}", delay);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Like `chat`, but for prompts that expect a JSON array shaped like `answer`.
+    /// An empty response is retried once with a nudge towards the expected format.
+    pub async fn chat_json(
+        &self,
+        system_prompt: &str,
+        user_message: &str,
+        answer: Answer,
+    ) -> Result<String> {
+        match self
+            .chat_with(system_prompt, user_message, Some(answer))
+            .await
+        {
+            Err(DriftcheckError::LlmEmptyResponse) => {
+                warn!("LLM returned an empty response, retrying with a nudge");
+                let nudged = format!(
+                    "{}\n\nYour previous response was empty. Respond with a JSON array.",
+                    user_message
+                );
+                self.chat_with(system_prompt, &nudged, Some(answer)).await
+            }
+            result => result,
+        }
+    }
+
+    /// Embed `inputs` with `model`, one vector per input, in batches of `EMBEDDING_BATCH`
+    #[tracing::instrument(
+        name = "llm.embed",
+        skip_all,
+        fields(gen_ai.request.model = model, inputs = inputs.len(), error = Empty)
+    )]
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let Some(url) = embeddings_endpoint(self.provider, &self.config.base_url) else {
+            return Err(DriftcheckError::ConfigInvalid(format!(
+                "llm.provider \"{}\" has no embeddings API; use search.mode = \"ripgrep\", \
+                 or an openai-compatible or ollama provider",
+                self.config.provider
+            )));
+        };
+
+        let mut vectors = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(EMBEDDING_BATCH) {
+            let mut last_error = None;
+            for attempt in 0..=self.config.max_retries {
+                if attempt > 0 {
+                    tokio::time::sleep(backoff(attempt)).await;
+                }
+                let tokens = batch.iter().map(String::len).sum::<usize>() / 4;
+                rate_limit::acquire(
+                    &self.config.base_url,
+                    self.config.requests_per_minute,
+                    self.config.tokens_per_minute,
+                    tokens,
+                )
+                .await;
+
+                match self.embed_batch(&url, model, batch).await {
+                    Ok(batch_vectors) if batch_vectors.len() == batch.len() => {
+                        vectors.extend(batch_vectors);
+                        last_error = None;
+                        break;
+                    }
+                    Ok(batch_vectors) => {
+                        last_error = Some(DriftcheckError::LlmResponseParse(format!(
+                            "expected {} embeddings, got {}",
+                            batch.len(),
+                            batch_vectors.len()
+                        )));
+                    }
+                    Err(e) => {
+                        warn!("Embeddings request attempt {} failed: {}", attempt + 1, e);
+                        if let DriftcheckError::LlmRateLimited(_, Some(ms)) = &e {
+                            rate_limit::back_off(Duration::from_millis(*ms));
+                        }
+                        last_error = Some(e);
+                    }
+                }
+            }
+            if let Some(e) = last_error {
+                Span::current().record("error", e.to_string());
+                return Err(e);
+            }
+        }
+        Ok(vectors)
+    }
+
+    async fn embed_batch(&self, url: &str, model: &str, batch: &[String]) -> Result<Vec<Vec<f32>>> {
+        let response = self
+            .authorized(url)
+            .json(&EmbeddingRequest {
+                model,
+                input: batch,
+            })
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    DriftcheckError::LlmTimeout(self.config.timeout)
+                } else {
+                    DriftcheckError::LlmError(e.to_string())
+                }
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = rate_limit::retry_after(
+                response.headers(),
+                chrono::Utc::now(),
+                Duration::from_secs(self.config.max_rate_limit_wait),
+            );
+            let body = response.text().await.unwrap_or_default();
+            return Err(response_error(status, retry_after, &body));
+        }
+
+        match self.provider {
+            Provider::Ollama => response
+                .json::<OllamaEmbeddings>()
+                .await
+                .map(|r| r.embeddings)
+                .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string())),
+            _ => response
+                .json::<OpenAiEmbeddings>()
+                .await
+                .map(|r| r.data.into_iter().map(|d| d.embedding).collect())
+                .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string())),
+        }
+    }
+
+    /// POST request with bearer auth when an API key is available
+    fn authorized(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.post(url);
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    /// The system prompt, asking for an object around the array in OpenAI's JSON mode and
+    /// with structured output
+    fn json_system_prompt<'a>(&self, system_prompt: &'a str, json: Option<Answer>) -> Cow<'a, str> {
+        let wrapped = self.config.structured_output || self.provider == Provider::OpenAi;
+        if json.is_some() && wrapped {
+            Cow::Owned(format!("{}{}", system_prompt, JSON_OBJECT_NOTE))
+        } else {
+            Cow::Borrowed(system_prompt)
+        }
+    }
+
+    /// The schema to constrain `json` answers to, with `llm.structured_output`
+    fn structured(&self, json: Option<Answer>) -> Option<Answer> {
+        json.filter(|_| self.config.structured_output)
+    }
+
+    /// OpenAI chat request with the configured sampling parameters
+    fn chat_request(
+        &self,
+        system_prompt: &str,
+        user_message: &str,
+        json: Option<Answer>,
+    ) -> ChatRequest {
+        ChatRequest {
+            model: self.config.model.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: system_prompt.to_string(),
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: user_message.to_string(),
+                },
+            ],
+            temperature: self.config.temperature,
+            max_tokens: self.config.max_tokens,
+            top_p: self.config.top_p,
+            seed: self.config.seed,
+            response_format: json.map(|answer| match self.structured(Some(answer)) {
+                Some(answer) => ResponseFormat {
+                    kind: "json_schema",
+                    json_schema: Some(JsonSchema {
+                        name: answer.name(),
+                        strict: true,
+                        schema: answer.schema(),
+                    }),
+                },
+                None => ResponseFormat {
+                    kind: "json_object",
+                    json_schema: None,
+                },
+            }),
+            stream: false,
+            stream_options: None,
+        }
+    }
+
+    /// Anthropic Messages request with the configured sampling parameters (it has no seed);
+    /// a structured answer is requested as a forced tool call
+    fn anthropic_request(
+        &self,
+        system_prompt: &str,
+        user_message: &str,
+        json: Option<Answer>,
+    ) -> AnthropicRequest {
+        let structured = self.structured(json);
+        AnthropicRequest {
+            model: self.config.model.clone(),
+            max_tokens: self.config.max_tokens.unwrap_or(ANTHROPIC_MAX_TOKENS),
+            system: system_prompt.to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: user_message.to_string(),
+            }],
+            temperature: self.config.temperature,
+            top_p: self.config.top_p,
+            stream: false,
+            tools: structured
+                .map(|answer| AnthropicTool {
+                    name: answer.name(),
+                    description: "Report the answer",
+                    input_schema: answer.schema(),
+                })
+                .into_iter()
+                .collect(),
+            tool_choice: structured.map(|answer| ToolChoice {
+                kind: "tool",
+                name: answer.name(),
+            }),
+        }
+    }
+
+    async fn make_request(
+        &self,
+        url: &str,
+        system_prompt: &str,
+        user_message: &str,
+        json: Option<Answer>,
+    ) -> Result<String> {
+        let streaming = self.config.stream;
+        let request = match self.provider {
+            Provider::OpenAi => self.authorized(url).json(&ChatRequest {
+                stream: streaming,
+                stream_options: streaming.then_some(StreamOptions {
+                    include_usage: true,
+                }),
+                ..self.chat_request(system_prompt, user_message, json)
+            }),
+            Provider::Anthropic => self
+                .client
+                .post(url)
+                .header("x-api-key", self.api_key.as_deref().unwrap_or_default())
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .json(&AnthropicRequest {
+                    stream: streaming,
+                    ..self.anthropic_request(system_prompt, user_message, json)
+                }),
+            Provider::Ollama => self.authorized(url).json(&OllamaRequest {
+                model: self.config.model.clone(),
+                messages: vec![
+                    Message {
+                        role: "system".to_string(),
+                        content: system_prompt.to_string(),
+                    },
+                    Message {
+                        role: "user".to_string(),
+                        content: user_message.to_string(),
+                    },
+                ],
+                stream: streaming,
+                format: json.map(|answer| match self.structured(Some(answer)) {
+                    Some(answer) => answer.schema(),
+                    None => Value::from("json"),
+                }),
+                options: OllamaOptions {
+                    temperature: self.config.temperature,
+                    top_p: self.config.top_p,
+                    seed: self.config.seed,
+                    num_predict: self.config.max_tokens,
+                },
+            }),
+        };
+
+        let response = request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                DriftcheckError::LlmTimeout(self.config.timeout)
+            } else {
+                DriftcheckError::LlmError(e.to_string())
+            }
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = rate_limit::retry_after(
+                response.headers(),
+                chrono::Utc::now(),
+                Duration::from_secs(self.config.max_rate_limit_wait),
+            );
+            let body = response.text().await.unwrap_or_default();
+            return Err(response_error(status, retry_after, &body));
+        }
+
+        if streaming {
+            return self.read_stream(response).await;
+        }
+
+        match self.provider {
+            Provider::OpenAi => {
+                let chat_response: ChatResponse = response
+                    .json()
+                    .await
+                    .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))?;
+                if let Some(usage) = &chat_response.usage {
+                    record_usage(&self.config, usage.prompt_tokens, usage.completion_tokens);
+                }
+
+                chat_response
+                    .choices
+                    .first()
+                    .map(|c| c.message.content.clone())
+                    .ok_or_else(|| {
+                        DriftcheckError::LlmResponseParse("No response choices".to_string())
+                    })
+            }
+            Provider::Anthropic => {
+                let anthropic_response: AnthropicResponse = response
+                    .json()
+                    .await
+                    .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))?;
+                if let Some(usage) = &anthropic_response.usage {
+                    record_usage(&self.config, usage.input_tokens, usage.output_tokens);
+                }
+
+                // Join text blocks and structured answers; other block types (e.g. thinking)
+                // are not part of the answer
+                Ok(anthropic_response
+                    .content
+                    .into_iter()
+                    .filter_map(|block| match block.kind.as_str() {
+                        "text" => Some(block.text),
+                        "tool_use" => block.input.map(|input| input.to_string()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(""))
+            }
+            Provider::Ollama => {
+                let body = response
+                    .text()
+                    .await
+                    .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))?;
+                parse_ollama_body(&self.config, &body)
+            }
+        }
+    }
+
+    /// Read a streamed response as it arrives, passing text to the current `stream::Live`
+    async fn read_stream(&self, mut response: reqwest::Response) -> Result<String> {
+        let mut lines = stream::Lines::default();
+        let mut text = String::new();
+        let (mut input, mut output) = (None, None);
+
+        let mut handle = |line: &str| -> Result<()> {
+            let event = match self.provider {
+                Provider::Ollama => stream::ollama_event(line),
+                _ => stream::sse_event(line),
+            };
+            let Some(event) = event else {
+                return Ok(());
+            };
+            if let Some(error) = event.error {
+                return Err(if exceeds_context(&error) {
+                    DriftcheckError::LlmContextExceeded(error)
+                } else {
+                    DriftcheckError::LlmError(error)
+                });
+            }
+            if let Some(delta) = event.text {
+                stream::push(&delta);
+                text.push_str(&delta);
+            }
+            // Ollama and OpenAI report totals once; Anthropic sends input and output separately
+            input = event.input_tokens.or(input);
+            output = event.output_tokens.or(output);
+            Ok(())
+        };
+
+        while let Some(chunk) = response.chunk().await.map_err(|e| {
+            if e.is_timeout() {
+                DriftcheckError::LlmTimeout(self.config.timeout)
+            } else {
+                DriftcheckError::LlmError(e.to_string())
+            }
+        })? {
+            for line in lines.feed(&chunk) {
+                handle(&line)?;
+            }
+        }
+        if let Some(line) = lines.finish() {
+            handle(&line)?;
+        }
+
+        if input.is_some() || output.is_some() {
+            record_usage(&self.config, input, output);
+        }
+        Ok(text)
+    }
+}
+
+/// Phrases providers use when a request doesn't fit the model's context window
+const CONTEXT_EXCEEDED_MARKERS: &[&str] = &[
+    "context_length_exceeded",
+    "maximum context length",
+    "context window",
+    "prompt is too long",
+    "reduce the length",
+];
+
+fn exceeds_context(message: &str) -> bool {
+    let message = message.to_lowercase();
+    CONTEXT_EXCEEDED_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+/// Delay before the `retry`th retry (1-based): 0.5s, 1s, 2s, ...
+fn backoff(retry: u32) -> Duration {
+    Duration::from_millis(500 * 2u64.pow(retry.saturating_sub(1).min(10)))
+}
+
+/// Wait before resending a rate-limited request: what the provider asked for in milliseconds,
+/// but at least the backoff, so `Retry-After: 0` can't turn the retries into a busy loop
+fn rate_limited_wait(retry_after: Option<u64>, attempt: u32) -> Duration {
+    retry_after
+        .map(Duration::from_millis)
+        .unwrap_or_default()
+        .max(backoff(attempt))
+}
+
+/// The error for a failed chat request, telling rate limits (with the wait the provider asked
+/// for) and context-length errors apart, so they can be retried differently
+fn response_error(
+    status: reqwest::StatusCode,
+    retry_after: Option<Duration>,
+    body: &str,
+) -> DriftcheckError {
+    let message = format!("HTTP {}: {}", status, body);
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        DriftcheckError::LlmRateLimited(message, retry_after.map(|d| d.as_millis() as u64))
+    } else if status == reqwest::StatusCode::PAYLOAD_TOO_LARGE || exceeds_context(body) {
+        DriftcheckError::LlmContextExceeded(message)
+    } else {
+        DriftcheckError::LlmError(message)
+    }
+}
+
+/// Parse an Ollama chat response. Proxies may stream even when `stream: false`
+/// was requested, in which case the body is one JSON object per line.
+fn parse_ollama_body(config: &LlmConfig, body: &str) -> Result<String> {
+    if let Ok(response) = serde_json::from_str::<OllamaResponse>(body) {
+        record_usage(config, response.prompt_eval_count, response.eval_count);
+        return Ok(response.message.content);
+    }
+
+    body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str::<OllamaResponse>(line)
+                .map(|chunk| {
+                    record_usage(config, chunk.prompt_eval_count, chunk.eval_count);
+                    chunk.message.content
+                })
+                .map_err(|e| DriftcheckError::LlmResponseParse(e.to_string()))
+        })
+        .collect()
+}
+
+/// Record the token usage a provider reported on the current `llm.chat` span and in the
+/// totals of `config` (`llm.usage`)
+fn record_usage(config: &LlmConfig, input: Option<u64>, output: Option<u64>) {
+    config.usage.record_tokens(
+        &config.model,
+        input.unwrap_or_default(),
+        output.unwrap_or_default(),
+    );
+    let span = Span::current();
+    if let Some(input) = input {
+        span.record("gen_ai.usage.input_tokens", input);
+    }
+    if let Some(output) = output {
+        span.record("gen_ai.usage.output_tokens", output);
+    }
+}
+
+/// Generate search queries from a diff
+pub async fn generate_search_queries(config: &Config, diff: &str) -> Result<Vec<String>> {
+    let client = LlmClient::new(&config.llm.for_task(LlmTask::Queries))?;
+
+    let system_prompt = config.prompts.render(&config.prompts.search_queries);
+    let response = match client
+        .chat_json(&system_prompt, diff, Answer::Queries)
+        .await
+    {
+        Err(DriftcheckError::LlmEmptyResponse) if config.llm.empty_response_as_no_issues => {
+            warn!("LLM kept returning empty responses; generating no search queries");
+            return Ok(vec![]);
+        }
+        result => result?,
+    };
+    capture::record("search_queries", &system_prompt, diff, &response);
+
+    // Parse JSON array of queries
+    parser::parse_search_queries(client.parser(), &response)
+}
+
+/// Ask for new search queries after a search that found little: `missed` matched no
+/// documentation, `matched` did. Queries already tried are dropped from the result.
+pub async fn refine_search_queries(
+    config: &Config,
+    diff: &str,
+    missed: &[String],
+    matched: &[String],
+) -> Result<Vec<String>> {
+    let client = LlmClient::new(&config.llm.for_task(LlmTask::Queries))?;
+
+    let list = |queries: &[String]| {
+        if queries.is_empty() {
+            "(none)".to_string()
+        } else {
+            queries
+                .iter()
+                .map(|q| format!("- {}", q))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+    let user_message = format!(
+        "{}\n\n## Previous Search Results\n\
+These queries matched no documentation:\n{}\n\n\
+These queries matched documentation:\n{}\n\n\
+Suggest different queries that are more likely to appear in user-facing documentation: \
+broader terms, synonyms, shorter identifiers, command or option names as users would \
+type them. Do not repeat earlier queries.",
+        diff,
+        list(missed),
+        list(matched)
+    );
+
+    let system_prompt = config.prompts.render(&config.prompts.search_queries);
+    let response = match client
+        .chat_json(&system_prompt, &user_message, Answer::Queries)
+        .await
+    {
+        Err(DriftcheckError::LlmEmptyResponse) => return Ok(vec![]),
+        result => result?,
+    };
+    capture::record("refine_queries", &system_prompt, &user_message, &response);
+
+    let mut queries = parser::parse_search_queries(client.parser(), &response)?;
+    queries.retain(|q| !missed.contains(q) && !matched.contains(q));
+    queries.dedup();
+    Ok(queries)
+}
+
+/// Analyze consistency between diff and documentation
+pub async fn analyze_consistency(
+    config: &Config,
+    diff: &str,
+    doc_chunks: &[DocChunk],
+) -> Result<Vec<RawIssue>> {
+    if doc_chunks.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let user_message = consistency_message(diff, doc_chunks);
+    let system_prompt = config.prompts.render(&config.prompts.analysis);
+    request_issues(config, "analysis", &system_prompt, &user_message).await
+}
+
+/// User message of a consistency analysis call
+pub(crate) fn consistency_message(diff: &str, doc_chunks: &[DocChunk]) -> String {
+    format!(
+        "## Code Diff (changes being pushed)\n```diff\n{}\n```\n\n{}",
+        diff,
+        docs_section(doc_chunks)
+    )
+}
+
+/// Check that release-facing docs reflect the changes in a tag-to-tag range
+pub async fn analyze_release(
+    config: &Config,
+    range: &str,
+    commits: &[String],
+    diff: &str,
+    doc_chunks: &[DocChunk],
+) -> Result<Vec<RawIssue>> {
+    if doc_chunks.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let user_message = format!(
+        "## Release Range\n{}\n\n## Commits\n{}\n\n## Code Diff\n```diff\n{}\n```\n\n{}",
+        range,
+        commits.join("\n"),
+        diff,
+        docs_section(doc_chunks)
+    );
+
+    let system_prompt = config.prompts.render(&config.prompts.release);
+    request_issues(config, "release", &system_prompt, &user_message).await
+}
+
+/// Check docs against the dependency and toolchain versions a diff changes
+pub async fn analyze_dependencies(
+    config: &Config,
+    bumps: &str,
+    diff: &str,
+    doc_chunks: &[DocChunk],
+) -> Result<Vec<RawIssue>> {
+    if doc_chunks.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let user_message = format!(
+        "## Version Changes\n{}\n\n## Manifest Diff\n```diff\n{}\n```\n\n{}",
+        bumps,
+        diff,
+        docs_section(doc_chunks)
+    );
+
+    let system_prompt = config.prompts.render(&config.prompts.dependencies);
+    request_issues(config, "dependencies", &system_prompt, &user_message).await
+}
+
+/// Verify a doc section against excerpts of the current code (`driftcheck sweep`)
+pub async fn analyze_sweep(
+    config: &Config,
+    code_excerpts: &str,
+    missing: &[String],
+    doc_chunks: &[DocChunk],
+) -> Result<Vec<RawIssue>> {
+    if doc_chunks.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let missing = if missing.is_empty() {
+        "(none)".to_string()
+    } else {
+        missing.join("\n")
+    };
+    let user_message = format!(
+        "## Code Excerpts\n{}\n\n## Names Not Found in the Code\n{}\n\n{}",
+        code_excerpts,
+        missing,
+        docs_section(doc_chunks)
+    );
+
+    let system_prompt = config.prompts.render(&config.prompts.sweep);
+    request_issues(config, "sweep", &system_prompt, &user_message).await
+}
+
+/// Format doc chunks for the prompt, with notes for the kinds of documentation present
+fn docs_section(doc_chunks: &[DocChunk]) -> String {
+    let docs_context = doc_chunks
+        .iter()
+        .map(|c| {
+            format!(
+                "--- {} ({}, lines {}-{}) ---\n{}",
+                c.file,
+                c.kind.as_str(),
+                c.start_line,
+                c.end_line,
+                c.content
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mut section = format!("## Documentation Excerpts\n{}", docs_context);
+
+    // Specialize the prompt for the kinds of documentation present
+    let mut kinds: Vec<DocKind> = Vec::new();
+    for chunk in doc_chunks {
+        if !kinds.contains(&chunk.kind) {
+            kinds.push(chunk.kind);
+        }
+    }
+    let guidance: Vec<&str> = kinds.iter().filter_map(|k| k.guidance()).collect();
+    if !guidance.is_empty() {
+        section.push_str("\n\n## Notes\n");
+        for note in guidance {
+            section.push_str(&format!("- {}\n", note));
+        }
+    }
+
+    section
+}
+
+async fn request_issues(
+    config: &Config,
+    stage: &'static str,
+    system_prompt: &str,
+    user_message: &str,
+) -> Result<Vec<RawIssue>> {
+    let client = LlmClient::new(&config.llm.for_task(LlmTask::Analysis))?;
+
+    let response = match client
+        .chat_json(system_prompt, user_message, Answer::Issues)
+        .await
+    {
+        Err(DriftcheckError::LlmEmptyResponse) if config.llm.empty_response_as_no_issues => {
+            warn!("LLM kept returning empty responses; treating as no issues");
+            return Ok(vec![]);
+        }
+        result => result?,
+    };
+    capture::record(stage, system_prompt, user_message, &response);
+
+    parser::parse_issues(client.parser(), &response)
+}
+
+/// Verdict returned by the critic pass for a generated fix
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixReview {
+    pub approved: bool,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// The kind of documentation source a chunk or issue comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocKind {
+    #[default]
+    Markdown,
+    /// Doc comments inside source files
+    Docstring,
+    /// OpenAPI / Swagger specs
+    OpenApi,
+    /// Jupyter notebooks
+    Notebook,
+    /// Anything else (plain text, reStructuredText, man pages, ...)
+    Text,
+}
+
+impl DocKind {
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_lowercase();
+        let file_name = lower.rsplit('/').next().unwrap_or(&lower);
+        let ext = file_name.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+
+        match ext {
+            "md" | "mdx" | "markdown" => DocKind::Markdown,
+            "ipynb" => DocKind::Notebook,
+            "yaml" | "yml" | "json"
+                if file_name.contains("openapi") || file_name.contains("swagger") =>
+            {
+                DocKind::OpenApi
+            }
+            "rs" | "py" | "pyi" | "js" | "jsx" | "mjs" | "ts" | "tsx" | "go" | "java" | "kt"
+            | "rb" | "c" | "h" | "cpp" | "cs" | "swift" => DocKind::Docstring,
+            _ => DocKind::Text,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocKind::Markdown => "markdown",
+            DocKind::Docstring => "docstring",
+            DocKind::OpenApi => "openapi",
+            DocKind::Notebook => "notebook",
+            DocKind::Text => "text",
+        }
+    }
+
+    /// Extra instructions for prompts dealing with this kind of documentation
+    pub fn guidance(&self) -> Option<&'static str> {
+        match self {
+            DocKind::Markdown | DocKind::Text => None,
+            DocKind::Docstring => Some(
+                "Docstring excerpts are doc comments in source files; only the comment text is documentation.",
+            ),
+            DocKind::OpenApi => Some(
+                "OpenAPI excerpts describe an HTTP API; check paths, parameters, schemas and status codes against the code.",
+            ),
+            DocKind::Notebook => Some(
+                "Notebook excerpts are raw .ipynb JSON; only markdown cells and code examples are documentation.",
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DocChunk {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub content: String,
+    pub kind: DocKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawIssue {
+    pub file: String,
+    #[serde(default)]
+    pub line: usize,
+    pub description: String,
+    #[serde(default)]
+    pub doc_excerpt: String,
+    pub suggested_fix: Option<String>,
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// Function, flag or config key in the diff that the docs contradict
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_symbol: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint() {
+        assert_eq!(
+            endpoint(Provider::OpenAi, "https://api.openai.com/v1/"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert_eq!(
+            endpoint(Provider::Anthropic, "https://api.openai.com/v1"),
+            "https://api.anthropic.com/v1/messages"
+        );
+        assert_eq!(
+            endpoint(Provider::Anthropic, "https://proxy.example.com/v1"),
+            "https://proxy.example.com/v1/messages"
+        );
+        assert_eq!(
+            endpoint(Provider::Ollama, "http://localhost:11434/v1"),
+            "http://localhost:11434/api/chat"
+        );
+    }
+
+    #[test]
+    fn test_sampling_parameters() {
+        let config = LlmConfig {
+            base_url: "http://localhost:4000".to_string(),
+            temperature: 0.7,
+            max_tokens: Some(2048),
+            seed: Some(42),
+            json_mode: true,
+            ..LlmConfig::default()
+        };
+        let client = LlmClient::new(&config).unwrap();
+
+        let request =
+            serde_json::to_value(client.chat_request("system", "diff", Some(Answer::Issues)))
+                .unwrap();
+        assert_eq!(request["max_tokens"], 2048);
+        assert_eq!(request["seed"], 42);
+        assert_eq!(request["response_format"]["type"], "json_object");
+        assert!(request.get("top_p").is_none() && request.get("stream").is_none());
+        assert!((request["temperature"].as_f64().unwrap() - 0.7).abs() < 1e-6);
+        assert!(client
+            .json_system_prompt("system", Some(Answer::Issues))
+            .ends_with(JSON_OBJECT_NOTE));
+
+        let plain = serde_json::to_value(client.chat_request("system", "fix", None)).unwrap();
+        assert!(plain.get("response_format").is_none());
+    }
+
+    #[test]
+    fn test_structured_output_requests() {
+        let config = LlmConfig {
+            base_url: "http://localhost:4000".to_string(),
+            structured_output: true,
+            ..LlmConfig::default()
+        };
+        let client = LlmClient::new(&config).unwrap();
+
+        let request =
+            serde_json::to_value(client.chat_request("system", "diff", Some(Answer::Queries)))
+                .unwrap();
+        let format = &request["response_format"];
+        assert_eq!(format["type"], "json_schema");
+        assert_eq!(format["json_schema"]["name"], "search_queries");
+        assert_eq!(
+            format["json_schema"]["schema"]["properties"]["items"]["items"]["type"],
+            "string"
+        );
+
+        let request =
+            serde_json::to_value(client.anthropic_request("system", "diff", Some(Answer::Issues)))
+                .unwrap();
+        assert_eq!(request["tool_choice"]["name"], "documentation_issues");
+        assert_eq!(
+            request["tools"][0]["input_schema"]["properties"]["items"]["type"],
+            "array"
+        );
+        let fix = serde_json::to_value(client.anthropic_request("system", "fix", None)).unwrap();
+        assert!(fix.get("tools").is_none() && fix.get("tool_choice").is_none());
+
+        let response: AnthropicResponse = serde_json::from_str(
+            r#"{"content":[{"type":"tool_use","id":"t","name":"search_queries","input":{"items":["a"]}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response.content[0].input.as_ref().unwrap().to_string(),
+            r#"{"items":["a"]}"#
+        );
+    }
+
+    #[test]
+    fn test_context_exceeded() {
+        let openai = r#"{"error":{"message":"This model's maximum context length is 128000 tokens.","code":"context_length_exceeded"}}"#;
+        assert!(matches!(
+            response_error(reqwest::StatusCode::BAD_REQUEST, None, openai),
+            DriftcheckError::LlmContextExceeded(_)
+        ));
+        let anthropic = r#"{"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long: 210000 tokens > 200000 maximum"}}"#;
+        assert_eq!(
+            response_error(reqwest::StatusCode::BAD_REQUEST, None, anthropic).code(),
+            "DC026"
+        );
+        assert!(matches!(
+            response_error(
+                reqwest::StatusCode::TOO_MANY_REQUESTS,
+                Some(Duration::from_secs(2)),
+                "maximum context length is reached"
+            ),
+            DriftcheckError::LlmRateLimited(_, Some(2000))
+        ));
+    }
+
+    #[test]
+    fn test_rate_limited_wait() {
+        assert_eq!(
+            rate_limited_wait(Some(5000), 1),
+            Duration::from_millis(5000)
+        );
+        // Zero or missing waits back off exponentially instead of resending at once
+        assert_eq!(rate_limited_wait(Some(0), 1), Duration::from_millis(500));
+        assert_eq!(rate_limited_wait(Some(0), 3), Duration::from_millis(2000));
+        assert_eq!(rate_limited_wait(None, 2), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_api_key_optional() {
+        assert!(api_key_optional(Provider::Ollama, "http://gpu-box:11434"));
+        assert!(api_key_optional(Provider::OpenAi, "http://localhost:4000"));
+        assert!(!api_key_optional(
+            Provider::OpenAi,
+            "https://api.openai.com/v1"
+        ));
+    }
+
+    #[test]
+    fn test_parse_ollama_body() {
+        let config = LlmConfig {
+            model: "llama3".to_string(),
+            ..LlmConfig::default()
+        };
+        let full =
+            r#"{"model":"llama3","message":{"role":"assistant","content":"[\"a\"]"},"done":true}"#;
+        assert_eq!(parse_ollama_body(&config, full).unwrap(), r#"["a"]"#);
+
+        let streamed = concat!(
+            r#"{"message":{"role":"assistant","content":"[\"a\", "},"done":false}"#,
+            "\n",
+            r#"{"message":{"role":"assistant","content":"\"b\"]"},"done":true}"#,
+            "\n"
+        );
+        assert_eq!(
+            parse_ollama_body(&config, streamed).unwrap(),
+            r#"["a", "b"]"#
+        );
+    }
+
+    #[test]
+    fn test_doc_kind_from_path() {
+        assert_eq!(DocKind::from_path("docs/guide.md"), DocKind::Markdown);
+        assert_eq!(DocKind::from_path("api/openapi.yaml"), DocKind::OpenApi);
+        assert_eq!(DocKind::from_path("config.yaml"), DocKind::Text);
+        assert_eq!(
+            DocKind::from_path("notebooks/Intro.ipynb"),
+            DocKind::Notebook
+        );
+        assert_eq!(DocKind::from_path("src/lib.rs"), DocKind::Docstring);
+        assert_eq!(DocKind::from_path("man/driftcheck.1"), DocKind::Text);
+    }
+}
@@ -0,0 +1,405 @@
+use super::{FixReview, RawIssue};
+use crate::config::LlmConfig;
+use crate::error::{DriftcheckError, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Shape of the JSON payload expected in a response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonKind {
+    Array,
+    Object,
+}
+
+/// Locates the JSON payload inside a raw LLM response.
+/// Providers wrap JSON differently (markdown fences, leading prose, XML tags,
+/// reasoning blocks), so each implementation yields the text regions most likely
+/// to contain the payload, in priority order.
+pub trait ResponseParser: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn candidates<'a>(&self, response: &'a str) -> Vec<&'a str>;
+}
+
+/// OpenAI-compatible APIs: usually bare JSON, sometimes in a ```json fence
+pub struct OpenAiParser;
+
+/// Anthropic models: often answer inside XML tags, preceded by prose
+pub struct AnthropicParser;
+
+/// Local models via Ollama: may emit `<think>` reasoning before the answer
+pub struct OllamaParser;
+
+/// Tries every known wrapping, for unknown providers
+pub struct GenericParser;
+
+impl ResponseParser for OpenAiParser {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn candidates<'a>(&self, response: &'a str) -> Vec<&'a str> {
+        let mut out = fenced_blocks(response);
+        out.push(response);
+        out
+    }
+}
+
+impl ResponseParser for AnthropicParser {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn candidates<'a>(&self, response: &'a str) -> Vec<&'a str> {
+        let mut out = Vec::new();
+        for tagged in tagged_blocks(response) {
+            out.extend(fenced_blocks(tagged));
+            out.push(tagged);
+        }
+        out.extend(fenced_blocks(response));
+        out.push(response);
+        out
+    }
+}
+
+impl ResponseParser for OllamaParser {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn candidates<'a>(&self, response: &'a str) -> Vec<&'a str> {
+        let answer = strip_reasoning(response);
+        let mut out = fenced_blocks(answer);
+        out.push(answer);
+        out
+    }
+}
+
+impl ResponseParser for GenericParser {
+    fn name(&self) -> &'static str {
+        "generic"
+    }
+
+    fn candidates<'a>(&self, response: &'a str) -> Vec<&'a str> {
+        let answer = strip_reasoning(response);
+        let mut out = Vec::new();
+        for tagged in tagged_blocks(answer) {
+            out.extend(fenced_blocks(tagged));
+            out.push(tagged);
+        }
+        out.extend(fenced_blocks(answer));
+        out.push(answer);
+        out
+    }
+}
+
+/// Pick a parser from `llm.parser`, inferring the provider from the endpoint when "auto"
+pub fn parser_for(config: &LlmConfig) -> Box<dyn ResponseParser> {
+    let name = match config.parser.as_str() {
+        "auto" => infer_provider(config),
+        other => other,
+    };
+
+    match name {
+        "openai" => Box::new(OpenAiParser),
+        "anthropic" => Box::new(AnthropicParser),
+        "ollama" => Box::new(OllamaParser),
+        _ => Box::new(GenericParser),
+    }
+}
+
+fn infer_provider(config: &LlmConfig) -> &'static str {
+    let url = config.base_url.to_lowercase();
+    let model = config.model.to_lowercase();
+
+    if config.provider == "anthropic" || url.contains("anthropic") || model.contains("claude") {
+        "anthropic"
+    } else if config.provider == "ollama" || url.contains("ollama") || url.contains(":11434") {
+        "ollama"
+    } else if url.contains("api.openai.com") {
+        "openai"
+    } else {
+        "generic"
+    }
+}
+
+/// Find the first JSON value of the requested kind in the parser's candidate regions
+pub fn extract_json(parser: &dyn ResponseParser, response: &str, kind: JsonKind) -> Option<Value> {
+    if kind == JsonKind::Array {
+        if let Some(items) = structured_array(response) {
+            return Some(items);
+        }
+    }
+    parser
+        .candidates(response.trim())
+        .into_iter()
+        .find_map(|candidate| first_json_value(candidate, kind))
+}
+
+/// The array of a response that is JSON as a whole: a bare array, or the `{"items": [...]}`
+/// object of JSON mode and structured output. No scanning, so brackets inside strings
+/// can't be mistaken for the answer.
+fn structured_array(response: &str) -> Option<Value> {
+    match serde_json::from_str::<Value>(response.trim()).ok()? {
+        Value::Array(items) => Some(Value::Array(items)),
+        Value::Object(mut object) => object.remove("items").filter(Value::is_array),
+        _ => None,
+    }
+}
+
+/// Scan for the first position where a complete JSON value of `kind` parses.
+/// Trailing prose after the value is ignored.
+fn first_json_value(text: &str, kind: JsonKind) -> Option<Value> {
+    let open = match kind {
+        JsonKind::Array => '[',
+        JsonKind::Object => '{',
+    };
+
+    text.match_indices(open).find_map(|(i, _)| {
+        let mut stream = serde_json::Deserializer::from_str(&text[i..]).into_iter::<Value>();
+        match stream.next() {
+            Some(Ok(value)) if matches!(kind, JsonKind::Array) == value.is_array() => Some(value),
+            _ => None,
+        }
+    })
+}
+
+/// Contents of ``` fenced code blocks, in order
+fn fenced_blocks(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("```") {
+        let after = &rest[start + 3..];
+        // Skip the info string (e.g. "json")
+        let body_start = after.find('\n').map(|i| i + 1).unwrap_or(after.len());
+        let body = &after[body_start..];
+        match body.find("```") {
+            Some(end) => {
+                out.push(&body[..end]);
+                rest = &body[end + 3..];
+            }
+            None => {
+                // Unterminated fence, common when the response is cut off
+                out.push(body);
+                break;
+            }
+        }
+    }
+
+    out
+}
+
+/// Contents of XML-style answer tags such as `<json>...</json>`
+fn tagged_blocks(text: &str) -> Vec<&str> {
+    const TAGS: &[&str] = &["json", "answer", "output", "result", "issues"];
+
+    let mut out = Vec::new();
+    for tag in TAGS {
+        let open = format!("<{}>", tag);
+        let close = format!("</{}>", tag);
+        if let Some(start) = text.find(&open) {
+            let body = &text[start + open.len()..];
+            if let Some(end) = body.find(&close) {
+                out.push(&body[..end]);
+            }
+        }
+    }
+    out
+}
+
+/// Drop `<think>...</think>` reasoning emitted by some local models
+fn strip_reasoning(text: &str) -> &str {
+    match text.rfind("</think>") {
+        Some(end) => &text[end + "</think>".len()..],
+        None => text,
+    }
+}
+
+fn from_value<T: DeserializeOwned>(value: Value, what: &str) -> Result<T> {
+    serde_json::from_value(value)
+        .map_err(|e| DriftcheckError::LlmResponseParse(format!("Failed to parse {}: {}", what, e)))
+}
+
+/// Parse a JSON array of search queries
+pub fn parse_search_queries(parser: &dyn ResponseParser, response: &str) -> Result<Vec<String>> {
+    let value = extract_json(parser, response, JsonKind::Array).ok_or_else(|| {
+        DriftcheckError::LlmResponseParse("No JSON array found in response".to_string())
+    })?;
+    from_value(value, "search queries")
+}
+
+/// Parse a JSON array of issues; prose saying there are no issues counts as none
+pub fn parse_issues(parser: &dyn ResponseParser, response: &str) -> Result<Vec<RawIssue>> {
+    match extract_json(parser, response, JsonKind::Array) {
+        Some(value) => from_value(value, "issues"),
+        None => {
+            let lower = response.to_lowercase();
+            if lower.contains("no issues") || lower.contains("no documentation") {
+                return Ok(vec![]);
+            }
+            Err(DriftcheckError::LlmResponseParse(
+                "Could not parse issues from response".to_string(),
+            ))
+        }
+    }
+}
+
+/// Parse the critic's JSON verdict
+pub fn parse_fix_review(parser: &dyn ResponseParser, response: &str) -> Result<FixReview> {
+    let value = extract_json(parser, response, JsonKind::Object).ok_or_else(|| {
+        DriftcheckError::LlmResponseParse("No JSON object found in review".to_string())
+    })?;
+    from_value(value, "review")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPENAI_BARE: &str = r#"["generate_search_queries", "--no-tui", "max_context_tokens"]"#;
+
+    const OPENAI_FENCED: &str = "```json\n[\n  \"install_hook\",\n  \"pre-push\"\n]\n```";
+
+    const ANTHROPIC_TAGGED: &str = r#"Looking at the diff, the `timeout` default changed from 30 to 60 seconds [see config.rs].
+
+<json>
+[
+  {
+    "file": "README.md",
+    "line": 212,
+    "description": "Default timeout is documented as 30 but is now 60",
+    "doc_excerpt": "timeout = 30",
+    "suggested_fix": "timeout = 60"
+  }
+]
+</json>"#;
+
+    const ANTHROPIC_PROSE: &str = r#"I reviewed the documentation excerpts [README.md, docs/usage.md] against the diff.
+
+```json
+[]
+```
+
+No documentation is factually wrong after this change."#;
+
+    const OLLAMA_THINKING: &str = r#"<think>
+The user wants search patterns. Candidates: ["foo", maybe "bar"]. Let me output JSON.
+</think>
+
+["parse_diff", "ParsedDiff"]"#;
+
+    const OLLAMA_TRUNCATED_FENCE: &str = "Here you go:\n```json\n[\"cache_key\", \"ttl\"]\n";
+
+    const CRITIC_WITH_PROSE: &str = r#"Verdict: the fix is minimal {mostly}.
+{"approved": true, "reason": "only the flag name changed"}"#;
+
+    fn queries(parser: &dyn ResponseParser, response: &str) -> Vec<String> {
+        parse_search_queries(parser, response).unwrap()
+    }
+
+    #[test]
+    fn test_openai_bare_and_fenced() {
+        assert_eq!(queries(&OpenAiParser, OPENAI_BARE).len(), 3);
+        assert_eq!(
+            queries(&OpenAiParser, OPENAI_FENCED),
+            vec!["install_hook", "pre-push"]
+        );
+    }
+
+    #[test]
+    fn test_json_mode_object() {
+        // OpenAI's JSON mode wraps the array in an object
+        let response = r#"{"items": [{"file": "README.md", "description": "stale [flag]"}]}"#;
+        let issues = parse_issues(&OpenAiParser, response).unwrap();
+        assert_eq!(issues[0].description, "stale [flag]");
+        assert!(parse_issues(&OpenAiParser, r#"{"items": []}"#)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_structured_output() {
+        // Optional fields come back as null
+        let response = r#"{"items": [{"file": "[draft] notes.md", "line": 3, "description": "d", "doc_excerpt": "", "suggested_fix": null, "confidence": null, "code_symbol": null}]}"#;
+        let issues = parse_issues(&GenericParser, response).unwrap();
+        assert_eq!(issues[0].file, "[draft] notes.md");
+        assert_eq!(issues[0].suggested_fix, None);
+        assert_eq!(
+            parse_search_queries(&AnthropicParser, r#"{"items":["timeout"]}"#).unwrap(),
+            vec!["timeout"]
+        );
+    }
+
+    #[test]
+    fn test_anthropic_prefers_tagged_json_over_prose_brackets() {
+        let issues = parse_issues(&AnthropicParser, ANTHROPIC_TAGGED).unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 212);
+        assert_eq!(issues[0].suggested_fix.as_deref(), Some("timeout = 60"));
+    }
+
+    #[test]
+    fn test_anthropic_fenced_empty_array_after_prose() {
+        assert!(parse_issues(&AnthropicParser, ANTHROPIC_PROSE)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_ollama_skips_reasoning_block() {
+        assert_eq!(
+            queries(&OllamaParser, OLLAMA_THINKING),
+            vec!["parse_diff", "ParsedDiff"]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_fence() {
+        assert_eq!(
+            queries(&OllamaParser, OLLAMA_TRUNCATED_FENCE),
+            vec!["cache_key", "ttl"]
+        );
+    }
+
+    #[test]
+    fn test_generic_handles_all_samples() {
+        assert_eq!(queries(&GenericParser, OPENAI_FENCED).len(), 2);
+        assert_eq!(queries(&GenericParser, OLLAMA_THINKING).len(), 2);
+        assert_eq!(
+            parse_issues(&GenericParser, ANTHROPIC_TAGGED)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_no_issues_prose() {
+        let issues = parse_issues(&GenericParser, "No issues found.").unwrap();
+        assert!(issues.is_empty());
+        assert!(parse_issues(&GenericParser, "Something went wrong").is_err());
+    }
+
+    #[test]
+    fn test_fix_review_skips_non_json_braces() {
+        let review = parse_fix_review(&GenericParser, CRITIC_WITH_PROSE).unwrap();
+        assert!(review.approved);
+        assert_eq!(review.reason, "only the flag name changed");
+    }
+
+    #[test]
+    fn test_infer_provider() {
+        let mut config = LlmConfig::default();
+        assert_eq!(parser_for(&config).name(), "openai");
+
+        config.base_url = "http://localhost:11434/v1".to_string();
+        assert_eq!(parser_for(&config).name(), "ollama");
+
+        config.model = "claude-sonnet-4".to_string();
+        assert_eq!(parser_for(&config).name(), "anthropic");
+
+        config.parser = "generic".to_string();
+        assert_eq!(parser_for(&config).name(), "generic");
+    }
+}
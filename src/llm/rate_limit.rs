@@ -0,0 +1,360 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// One limiter per LLM endpoint, shared by every client and task calling it
+static LIMITERS: OnceLock<std::sync::Mutex<HashMap<String, Endpoint>>> = OnceLock::new();
+
+/// Until when every call waits after the provider answered 429, so concurrent calls don't
+/// keep hitting the limit
+static BACKOFF: std::sync::Mutex<Option<Instant>> = std::sync::Mutex::new(None);
+
+/// Token bucket refilled continuously at `capacity` units per minute
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn per_minute(capacity: u32, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            available: capacity as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Time until `amount` units are available (zero if they already are).
+    /// Requests larger than the bucket are clamped so they can still proceed.
+    fn wait_for(&self, amount: f64) -> Duration {
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((amount - self.available) * 60.0 / self.capacity)
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.available -= amount.min(self.capacity);
+    }
+}
+
+/// The limiter of one endpoint and the limits it was configured with
+struct Endpoint {
+    limits: (Option<u32>, Option<u32>),
+    /// A caller with other limits was warned about
+    warned: bool,
+    limiter: Arc<Mutex<RateLimiter>>,
+}
+
+/// Requests-per-minute and tokens-per-minute budget
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Self {
+        let now = Instant::now();
+        Self {
+            requests: requests_per_minute
+                .filter(|n| *n > 0)
+                .map(|n| TokenBucket::per_minute(n, now)),
+            tokens: tokens_per_minute
+                .filter(|n| *n > 0)
+                .map(|n| TokenBucket::per_minute(n, now)),
+        }
+    }
+
+    /// Try to reserve one request and `tokens` tokens, returning how long to wait otherwise
+    fn try_acquire(&mut self, tokens: usize, now: Instant) -> Duration {
+        let tokens = tokens as f64;
+        let mut wait = Duration::ZERO;
+
+        if let Some(bucket) = self.requests.as_mut() {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_for(1.0));
+        }
+        if let Some(bucket) = self.tokens.as_mut() {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_for(tokens));
+        }
+
+        if wait.is_zero() {
+            if let Some(bucket) = self.requests.as_mut() {
+                bucket.take(1.0);
+            }
+            if let Some(bucket) = self.tokens.as_mut() {
+                bucket.take(tokens);
+            }
+        }
+
+        wait
+    }
+}
+
+/// Wait until the budget of `endpoint` allows a request of roughly `tokens` tokens, and until
+/// any `back_off` has passed. Each endpoint's limiter is configured by its first caller.
+pub async fn acquire(
+    endpoint: &str,
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+    tokens: usize,
+) {
+    let until = BACKOFF.lock().ok().and_then(|until| *until);
+    if let Some(wait) = until.map(|until| until.saturating_duration_since(Instant::now())) {
+        if !wait.is_zero() {
+            debug!("Backing off after a 429 response, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    if requests_per_minute.is_none() && tokens_per_minute.is_none() {
+        return;
+    }
+
+    let limiter = limiter(endpoint, requests_per_minute, tokens_per_minute);
+    loop {
+        let wait = limiter.lock().await.try_acquire(tokens, Instant::now());
+        if wait.is_zero() {
+            return;
+        }
+        debug!("Rate limit reached, waiting {:?}", wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// The limiter of `endpoint`, created with these limits on its first use. Callers of the same
+/// endpoint with other limits share it, with a warning.
+fn limiter(
+    endpoint: &str,
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+) -> Arc<Mutex<RateLimiter>> {
+    let limits = (requests_per_minute, tokens_per_minute);
+    let mut limiters = LIMITERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let entry = limiters
+        .entry(endpoint.to_string())
+        .or_insert_with(|| Endpoint {
+            limits,
+            warned: false,
+            limiter: Arc::new(Mutex::new(RateLimiter::new(
+                requests_per_minute,
+                tokens_per_minute,
+            ))),
+        });
+    if entry.limits != limits && !entry.warned {
+        entry.warned = true;
+        warn!(
+            "{} is already rate limited at {:?} requests and {:?} tokens per minute; \
+             ignoring {:?} and {:?} from another model config",
+            endpoint, entry.limits.0, entry.limits.1, requests_per_minute, tokens_per_minute
+        );
+    }
+    entry.limiter.clone()
+}
+
+/// Hold every call back for `wait` after the provider rate-limited one
+pub fn back_off(wait: Duration) {
+    if let Ok(mut until) = BACKOFF.lock() {
+        let end = Instant::now() + wait;
+        *until = Some(until.map_or(end, |until| until.max(end)));
+    }
+}
+
+/// How long a 429 response asks to wait: `retry-after-ms`, `Retry-After` (seconds or an HTTP
+/// date), OpenAI's `x-ratelimit-reset-*` durations (e.g. "6m0s") or Anthropic's
+/// `anthropic-ratelimit-*-reset` timestamps. The longest of the reset headers wins. Waits are
+/// capped at `max`, and values that are not a number of seconds (`inf`, `NaN`) are ignored.
+pub fn retry_after(headers: &HeaderMap, now: DateTime<Utc>, max: Duration) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+    };
+    let until = |date: DateTime<Utc>| (date - now).to_std().ok().map(|wait| wait.min(max));
+    let seconds = |seconds: f64| capped_secs(seconds, max);
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return seconds(ms / 1000.0);
+    }
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return seconds(secs);
+        }
+        if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+            return Some(until(date.with_timezone(&Utc)).unwrap_or_default());
+        }
+    }
+
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?.trim();
+            match name.as_str() {
+                "x-ratelimit-reset-requests" | "x-ratelimit-reset-tokens" => {
+                    go_duration(value).and_then(seconds)
+                }
+                name if name.starts_with("anthropic-ratelimit-") && name.ends_with("-reset") => {
+                    DateTime::parse_from_rfc3339(value)
+                        .ok()
+                        .and_then(|date| until(date.with_timezone(&Utc)))
+                }
+                _ => None,
+            }
+        })
+        .max()
+}
+
+/// `seconds` as a duration of at most `max`; `None` for NaN and infinities
+fn capped_secs(seconds: f64, max: Duration) -> Option<Duration> {
+    if !seconds.is_finite() {
+        return None;
+    }
+    Some(
+        Duration::try_from_secs_f64(seconds.max(0.0))
+            .unwrap_or(max)
+            .min(max),
+    )
+}
+
+/// Seconds of a duration like "1m30s", "250ms" or "0.5s"
+fn go_duration(text: &str) -> Option<f64> {
+    let mut total = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..split].parse().ok()?;
+        rest = &rest[split..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        total += number
+            * match &rest[..unit_len] {
+                "h" => 3600.0,
+                "m" => 60.0,
+                "s" => 1.0,
+                "ms" => 0.001,
+                _ => return None,
+            };
+        rest = &rest[unit_len..];
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_budget_exhausts_and_refills() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(Some(2), None);
+
+        assert!(limiter.try_acquire(0, start).is_zero());
+        assert!(limiter.try_acquire(0, start).is_zero());
+
+        let wait = limiter.try_acquire(0, start);
+        assert_eq!(wait, Duration::from_secs(30));
+
+        assert!(limiter
+            .try_acquire(0, start + Duration::from_secs(30))
+            .is_zero());
+    }
+
+    #[test]
+    fn test_limiter_per_endpoint() {
+        let a = limiter("https://a.test/v1", Some(10), None);
+        assert!(Arc::ptr_eq(
+            &a,
+            &limiter("https://a.test/v1", Some(20), None)
+        ));
+        assert!(!Arc::ptr_eq(
+            &a,
+            &limiter("https://b.test/v1", Some(10), None)
+        ));
+    }
+
+    #[test]
+    fn test_oversized_request_is_clamped() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(None, Some(1000));
+
+        assert!(limiter.try_acquire(5000, start).is_zero());
+        assert_eq!(limiter.try_acquire(500, start), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_retry_after_headers() {
+        let now = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(*name, value.parse().unwrap());
+            }
+            map
+        };
+
+        let max = Duration::from_secs(300);
+        let wait = |pairs: &[(&'static str, &str)]| retry_after(&headers(pairs), now, max);
+        assert_eq!(
+            wait(&[("retry-after", "20")]),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(
+            wait(&[("retry-after", "Wed, 01 Jan 2025 00:00:05 GMT")]),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            wait(&[("retry-after-ms", "1500"), ("retry-after", "2")]),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            wait(&[
+                ("x-ratelimit-reset-requests", "1m30s"),
+                ("x-ratelimit-reset-tokens", "250ms")
+            ]),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            wait(&[("anthropic-ratelimit-tokens-reset", "2025-01-01T00:00:12Z")]),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(wait(&[]), None);
+
+        // Values from misbehaving servers or proxies never panic and never exceed the cap
+        assert_eq!(wait(&[("retry-after", "inf")]), None);
+        assert_eq!(wait(&[("retry-after", "1e300")]), Some(max));
+        assert_eq!(wait(&[("retry-after", "NaN")]), None);
+        assert_eq!(wait(&[("retry-after-ms", "-5")]), Some(Duration::ZERO));
+        assert_eq!(
+            wait(&[("retry-after", "Fri, 01 Jan 2100 00:00:00 GMT")]),
+            Some(max)
+        );
+        assert_eq!(
+            wait(&[("x-ratelimit-reset-tokens", "99999999h")]),
+            Some(max)
+        );
+    }
+}
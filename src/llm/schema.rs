@@ -0,0 +1,57 @@
+use serde_json::{json, Value};
+
+/// Shape of a JSON answer, constrained by a schema with `llm.structured_output`.
+/// Schemas need an object at the top, so the array is wrapped as `{"items": [...]}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// Search queries: an array of strings
+    Queries,
+    /// Documentation issues: an array of `RawIssue` objects
+    Issues,
+}
+
+impl Answer {
+    /// Name of the schema (OpenAI `json_schema.name`, Anthropic tool name)
+    pub fn name(self) -> &'static str {
+        match self {
+            Answer::Queries => "search_queries",
+            Answer::Issues => "documentation_issues",
+        }
+    }
+
+    /// JSON schema of the answer, in the strict form OpenAI requires: every property is
+    /// required, optional ones are nullable, and no other properties are allowed
+    pub fn schema(self) -> Value {
+        let items = match self {
+            Answer::Queries => json!({ "type": "string" }),
+            Answer::Issues => json!({
+                "type": "object",
+                "properties": {
+                    "file": { "type": "string" },
+                    "line": { "type": "integer" },
+                    "description": { "type": "string" },
+                    "doc_excerpt": { "type": "string" },
+                    "suggested_fix": { "type": ["string", "null"] },
+                    "confidence": { "type": ["number", "null"] },
+                    "code_symbol": { "type": ["string", "null"] }
+                },
+                "required": [
+                    "file",
+                    "line",
+                    "description",
+                    "doc_excerpt",
+                    "suggested_fix",
+                    "confidence",
+                    "code_symbol"
+                ],
+                "additionalProperties": false
+            }),
+        };
+        json!({
+            "type": "object",
+            "properties": { "items": { "type": "array", "items": items } },
+            "required": ["items"],
+            "additionalProperties": false
+        })
+    }
+}
@@ -0,0 +1,208 @@
+use serde_json::Value;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    static LIVE: Live;
+}
+
+/// Text of LLM responses as they stream in (`llm.stream`), for showing progress. Calls made
+/// inside [`scope`] append to it; each call starts over, so `text` is the latest response.
+#[derive(Debug, Clone, Default)]
+pub struct Live(Arc<Mutex<LiveText>>);
+
+#[derive(Debug, Default)]
+struct LiveText {
+    text: String,
+    received: usize,
+}
+
+impl Live {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The response streaming in, or the last one
+    pub fn text(&self) -> String {
+        self.0
+            .lock()
+            .map(|live| live.text.clone())
+            .unwrap_or_default()
+    }
+
+    /// Characters received by every call in the scope so far
+    pub fn received(&self) -> usize {
+        self.0.lock().map(|live| live.received).unwrap_or(0)
+    }
+}
+
+/// Run `future` with its LLM calls streaming into `live`
+pub async fn scope<F: Future>(live: Live, future: F) -> F::Output {
+    LIVE.scope(live, future).await
+}
+
+/// The `Live` of the current scope, to carry into spawned tasks
+pub fn current() -> Option<Live> {
+    LIVE.try_with(Live::clone).ok()
+}
+
+/// Run `future` in `live`'s scope, if there is one
+pub async fn scope_opt<F: Future>(live: Option<Live>, future: F) -> F::Output {
+    match live {
+        Some(live) => scope(live, future).await,
+        None => future.await,
+    }
+}
+
+/// A new response (or retry) starts
+pub(super) fn restart() {
+    let _ = LIVE.try_with(|live| {
+        if let Ok(mut live) = live.0.lock() {
+            live.text.clear();
+        }
+    });
+}
+
+pub(super) fn push(delta: &str) {
+    let _ = LIVE.try_with(|live| {
+        if let Ok(mut live) = live.0.lock() {
+            live.text.push_str(delta);
+            live.received += delta.chars().count();
+        }
+    });
+}
+
+/// Splits a streamed body into lines as bytes arrive; a line is only decoded once complete,
+/// so multi-byte characters split across chunks survive
+#[derive(Default)]
+pub(super) struct Lines {
+    pending: Vec<u8>,
+}
+
+impl Lines {
+    pub(super) fn feed(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(end) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+        lines
+    }
+
+    /// The last line, if the body didn't end with a newline
+    pub(super) fn finish(&mut self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.pending).trim().to_string();
+        self.pending.clear();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// One parsed event of a streamed response
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct Event {
+    pub text: Option<String>,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+    /// An error the provider sent mid-stream
+    pub error: Option<String>,
+}
+
+/// An OpenAI or Anthropic server-sent event line (`data: {...}`); other lines and the final
+/// `data: [DONE]` give nothing
+pub(super) fn sse_event(line: &str) -> Option<Event> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data == "[DONE]" {
+        return None;
+    }
+    let value: Value = serde_json::from_str(data).ok()?;
+    let number = |v: &Value| v.as_u64();
+
+    match value["type"].as_str() {
+        // Anthropic Messages API; a structured answer streams as tool input JSON
+        Some("content_block_delta") => Some(Event {
+            text: value["delta"]["text"]
+                .as_str()
+                .or_else(|| value["delta"]["partial_json"].as_str())
+                .map(str::to_string),
+            ..Event::default()
+        }),
+        Some("message_start") => Some(Event {
+            input_tokens: number(&value["message"]["usage"]["input_tokens"]),
+            ..Event::default()
+        }),
+        Some("message_delta") => Some(Event {
+            output_tokens: number(&value["usage"]["output_tokens"]),
+            ..Event::default()
+        }),
+        Some("error") => Some(Event {
+            error: Some(
+                value["error"]["message"]
+                    .as_str()
+                    .unwrap_or(data)
+                    .to_string(),
+            ),
+            ..Event::default()
+        }),
+        Some(_) => None,
+        // OpenAI chat completion chunks; usage comes last with `include_usage`
+        None => Some(Event {
+            text: value["choices"][0]["delta"]["content"]
+                .as_str()
+                .map(str::to_string),
+            input_tokens: number(&value["usage"]["prompt_tokens"]),
+            output_tokens: number(&value["usage"]["completion_tokens"]),
+            error: value["error"]["message"].as_str().map(str::to_string),
+        }),
+    }
+}
+
+/// An Ollama stream line (one JSON object per line)
+pub(super) fn ollama_event(line: &str) -> Option<Event> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    Some(Event {
+        text: value["message"]["content"].as_str().map(str::to_string),
+        input_tokens: value["prompt_eval_count"].as_u64(),
+        output_tokens: value["eval_count"].as_u64(),
+        error: value["error"].as_str().map(str::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream_events() {
+        let mut lines = Lines::default();
+        let body = "data: {\"choices\":[{\"delta\":{\"content\":\"Hé\"}}]}\n\ndata: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":3}}\ndata: [DONE]";
+        let bytes = body.as_bytes();
+        // Split inside the two-byte 'é'
+        let split = body.find('é').unwrap() + 1;
+        let mut parsed = lines.feed(&bytes[..split]);
+        parsed.extend(lines.feed(&bytes[split..]));
+        parsed.extend(lines.finish());
+        let events: Vec<Event> = parsed.iter().filter_map(|l| sse_event(l)).collect();
+        assert_eq!(events[0].text.as_deref(), Some("Hé"));
+        assert_eq!(
+            (events[1].input_tokens, events[1].output_tokens),
+            (Some(12), Some(3))
+        );
+        assert_eq!(events.len(), 2);
+
+        let anthropic = r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"fixed"}}"#;
+        assert_eq!(sse_event(anthropic).unwrap().text.as_deref(), Some("fixed"));
+        let ollama = r#"{"message":{"role":"assistant","content":""},"done":true,"prompt_eval_count":5,"eval_count":2}"#;
+        assert_eq!(ollama_event(ollama).unwrap().output_tokens, Some(2));
+
+        let live = Live::new();
+        scope(live.clone(), async {
+            push("partial");
+            restart();
+            push("retry");
+        })
+        .await;
+        assert_eq!((live.text(), live.received()), ("retry".to_string(), 12));
+        push("outside a scope is dropped");
+    }
+}
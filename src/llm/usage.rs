@@ -0,0 +1,192 @@
+use crate::config::ModelPrice;
+use glob::Pattern;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Share of the regular price batch APIs charge
+const BATCH_DISCOUNT: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Tokens {
+    calls: u64,
+    input: u64,
+    output: u64,
+    batch_input: u64,
+    batch_output: u64,
+}
+
+/// Token usage and estimated cost of a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunUsage {
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Estimated cost in USD, if every model used has an `[llm.pricing]` entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    pub models: Vec<ModelUsage>,
+}
+
+/// Token usage of one model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+/// Tokens used per model by the LLM calls of one configuration and its clones: clients record
+/// into the `llm.usage` of the configuration they were created from
+#[derive(Debug, Clone, Default)]
+pub struct Usage(Arc<Mutex<BTreeMap<String, Tokens>>>);
+
+impl Usage {
+    /// Count a completed chat call
+    pub fn record_call(&self, model: &str) {
+        self.update(model, |tokens| tokens.calls += 1);
+    }
+
+    /// Add the tokens a provider reported for a chat call
+    pub fn record_tokens(&self, model: &str, input: u64, output: u64) {
+        self.update(model, |tokens| {
+            tokens.input += input;
+            tokens.output += output;
+        });
+    }
+
+    /// Add the calls and tokens of a batch, billed at the batch discount
+    pub fn record_batch(&self, model: &str, calls: u64, input: u64, output: u64) {
+        self.update(model, |tokens| {
+            tokens.calls += calls;
+            tokens.batch_input += input;
+            tokens.batch_output += output;
+        });
+    }
+
+    fn update(&self, model: &str, apply: impl FnOnce(&mut Tokens)) {
+        if let Ok(mut usage) = self.0.lock() {
+            apply(usage.entry(model.to_string()).or_default());
+        }
+    }
+
+    /// Usage of every call so far, priced with `pricing` (model globs to prices), or `None`
+    /// when no LLM call was made
+    pub fn total(&self, pricing: &BTreeMap<String, ModelPrice>) -> Option<RunUsage> {
+        let usage = self.0.lock().ok()?.clone();
+        summarize(&usage, pricing)
+    }
+}
+
+fn summarize(
+    usage: &BTreeMap<String, Tokens>,
+    pricing: &BTreeMap<String, ModelPrice>,
+) -> Option<RunUsage> {
+    if usage.values().all(|tokens| tokens.calls == 0) {
+        return None;
+    }
+
+    let models: Vec<ModelUsage> = usage
+        .iter()
+        .map(|(model, tokens)| {
+            let cost = price(model, pricing).map(|price| {
+                let per_token = |tokens: u64, usd_per_million: f64| {
+                    tokens as f64 * usd_per_million / 1_000_000.0
+                };
+                per_token(tokens.input, price.input)
+                    + per_token(tokens.output, price.output)
+                    + BATCH_DISCOUNT
+                        * (per_token(tokens.batch_input, price.input)
+                            + per_token(tokens.batch_output, price.output))
+            });
+            ModelUsage {
+                model: model.clone(),
+                calls: tokens.calls,
+                prompt_tokens: tokens.input + tokens.batch_input,
+                completion_tokens: tokens.output + tokens.batch_output,
+                cost_usd: cost,
+            }
+        })
+        .collect();
+
+    Some(RunUsage {
+        calls: models.iter().map(|m| m.calls).sum(),
+        prompt_tokens: models.iter().map(|m| m.prompt_tokens).sum(),
+        completion_tokens: models.iter().map(|m| m.completion_tokens).sum(),
+        cost_usd: models.iter().map(|m| m.cost_usd).sum(),
+        models,
+    })
+}
+
+/// Price of `model`: an exact `[llm.pricing]` key, else the first glob key matching it
+fn price<'a>(model: &str, pricing: &'a BTreeMap<String, ModelPrice>) -> Option<&'a ModelPrice> {
+    pricing.get(model).or_else(|| {
+        pricing
+            .iter()
+            .find(|(key, _)| Pattern::new(key).is_ok_and(|p| p.matches(model)))
+            .map(|(_, price)| price)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let usage = BTreeMap::from([
+            (
+                "gpt-4o-mini".to_string(),
+                Tokens {
+                    calls: 3,
+                    input: 1_000_000,
+                    output: 100_000,
+                    batch_input: 2_000_000,
+                    batch_output: 0,
+                },
+            ),
+            (
+                "llama3".to_string(),
+                Tokens {
+                    calls: 1,
+                    input: 500,
+                    output: 50,
+                    ..Tokens::default()
+                },
+            ),
+        ]);
+        let pricing = BTreeMap::from([(
+            "gpt-4o*".to_string(),
+            ModelPrice {
+                input: 0.15,
+                output: 0.6,
+            },
+        )]);
+
+        let total = summarize(&usage, &pricing).unwrap();
+        assert_eq!((total.calls, total.prompt_tokens), (4, 3_000_500));
+        assert_eq!(total.completion_tokens, 100_050);
+        // llama3 has no price, so the run has no total
+        assert_eq!(total.cost_usd, None);
+        let cost = total.models[0].cost_usd.unwrap();
+        assert!((cost - (0.15 + 0.06 + 0.15)).abs() < 1e-9);
+
+        assert_eq!(summarize(&BTreeMap::new(), &pricing), None);
+    }
+
+    #[test]
+    fn test_usage_is_per_handle() {
+        let pricing = BTreeMap::new();
+        let usage = Usage::default();
+        let shared = usage.clone();
+        shared.record_call("gpt-4o");
+        shared.record_tokens("gpt-4o", 100, 10);
+
+        let total = usage.total(&pricing).unwrap();
+        assert_eq!((total.calls, total.prompt_tokens), (1, 100));
+        assert_eq!(Usage::default().total(&pricing), None);
+    }
+}
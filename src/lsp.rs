@@ -0,0 +1,612 @@
+use crate::analyzer::{self, Issue, Severity};
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::fix;
+use crate::git;
+use crate::history::{self, IssueState};
+use crate::output::markdown::locate_excerpt;
+use crate::runs;
+use crate::search::remote;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, warn};
+
+/// Command that analyzes the current diff (upstream to `HEAD`)
+const CHECK_COMMAND: &str = "driftcheck.check";
+
+/// Longest header line accepted before the message is considered garbage
+const MAX_HEADER_LEN: u64 = 4096;
+/// Largest message body accepted; full document sync sends whole files
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// JSON-RPC error code of unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code of requests with invalid parameters
+const INVALID_PARAMS: i64 = -32602;
+/// LSP error code of requests that were valid but failed
+const REQUEST_FAILED: i64 = -32803;
+
+/// Serve the issues of the last recorded run as diagnostics over stdio, with a `quickfix`
+/// code action per issue that applies a generated fix as a workspace edit.
+/// `driftcheck.check` runs a new analysis.
+pub async fn run(config: Config) -> Result<()> {
+    let mut server = Server::new(config);
+    server.load_last_run();
+
+    let mut stdin = tokio::io::BufReader::new(tokio::io::stdin());
+    let mut stdout = tokio::io::stdout();
+    while let Some(message) = read_message(&mut stdin).await? {
+        for reply in server.handle(message).await {
+            write_message(&mut stdout, &reply).await?;
+        }
+        if server.exit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Read one `Content-Length` framed message; `None` at the end of the input
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Value>> {
+    let io = |e: std::io::Error| DriftcheckError::LspError(e.to_string());
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        let read = (&mut *reader)
+            .take(MAX_HEADER_LEN)
+            .read_line(&mut line)
+            .await
+            .map_err(io)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if !line.ends_with('\n') {
+            return Err(DriftcheckError::LspError(format!(
+                "header line longer than {} bytes",
+                MAX_HEADER_LEN
+            )));
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length
+        .ok_or_else(|| DriftcheckError::LspError("message without Content-Length".to_string()))?;
+    if length > MAX_MESSAGE_LEN {
+        return Err(DriftcheckError::LspError(format!(
+            "message of {} bytes exceeds the limit of {} bytes",
+            length, MAX_MESSAGE_LEN
+        )));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.map_err(io)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| DriftcheckError::LspError(format!("invalid message: {}", e)))
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let io = |e: std::io::Error| DriftcheckError::LspError(e.to_string());
+    writer.write_all(framed.as_bytes()).await.map_err(io)?;
+    writer.flush().await.map_err(io)
+}
+
+struct Server {
+    config: Config,
+    issues: Vec<Issue>,
+    /// Text of the documents open in the editor, by URI
+    open: HashMap<String, String>,
+    /// URIs diagnostics were published for, so they can be cleared
+    published: Vec<String>,
+    /// Generated fixes by fingerprint: the text they were generated from, and the fixed text
+    fixes: HashMap<String, (String, String)>,
+    exit: bool,
+}
+
+impl Server {
+    fn new(config: Config) -> Self {
+        Self {
+            config,
+            issues: Vec::new(),
+            open: HashMap::new(),
+            published: Vec::new(),
+            fixes: HashMap::new(),
+            exit: false,
+        }
+    }
+
+    /// Start from the open issues of the last `check` or hook run, as `driftcheck review --last`
+    fn load_last_run(&mut self) {
+        let mut issues = match runs::latest(&self.config) {
+            Ok(run) => run.issues,
+            Err(e) => {
+                debug!("No recorded run to serve: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = history::refresh(&mut issues) {
+            debug!("Failed to read issue history: {}", e);
+        }
+        issues.retain(|issue| issue.state != IssueState::Fixed && !remote::is_remote(&issue.file));
+        self.issues = issues;
+    }
+
+    /// Handle one message; returns the response and notifications to send
+    async fn handle(&mut self, message: Value) -> Vec<Value> {
+        let id = message.get("id").cloned();
+        let method = message["method"].as_str().unwrap_or_default().to_string();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method.as_str() {
+            "initialize" => Ok(capabilities()),
+            "initialized" => return self.publish_all(),
+            "shutdown" => Ok(Value::Null),
+            "exit" => {
+                self.exit = true;
+                return vec![];
+            }
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                let uri = document["uri"].as_str().unwrap_or_default().to_string();
+                let text = document["text"].as_str().unwrap_or_default().to_string();
+                self.open.insert(uri.clone(), text);
+                return self.publish(&uri).into_iter().collect();
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                // Full sync: the last change is the whole document
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.open.insert(uri.clone(), text.to_string());
+                }
+                return self.publish(&uri).into_iter().collect();
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.open.remove(uri);
+                }
+                return vec![];
+            }
+            "textDocument/codeAction" => Ok(self.code_actions(&params)),
+            "codeAction/resolve" => self.resolve(params).await,
+            "workspace/executeCommand" => match params["command"].as_str() {
+                Some(CHECK_COMMAND) => match self.check().await {
+                    Ok(()) => {
+                        let mut out = self.publish_all();
+                        out.push(show_message(
+                            3,
+                            &format!("driftcheck: {} issue(s)", self.issues.len()),
+                        ));
+                        out.extend(id.map(|id| response(id, Ok(Value::Null))));
+                        return out;
+                    }
+                    Err(e) => Err(e),
+                },
+                command => {
+                    let message = format!("unknown command {}", command.unwrap_or("(none)"));
+                    return id
+                        .map(|id| error(id, INVALID_PARAMS, &message))
+                        .into_iter()
+                        .collect();
+                }
+            },
+            _ if id.is_none() => return vec![],
+            _ => {
+                let message = format!("unknown method {}", method);
+                return id
+                    .map(|id| error(id, METHOD_NOT_FOUND, &message))
+                    .into_iter()
+                    .collect();
+            }
+        };
+
+        let Some(id) = id else {
+            return vec![];
+        };
+        match result {
+            Err(e) => {
+                warn!("{} failed: {}", method, e);
+                let message = show_message(1, &format!("driftcheck: {}", e));
+                vec![response(id, Err(e)), message]
+            }
+            result => vec![response(id, result)],
+        }
+    }
+
+    /// Analyze the current diff and replace the served issues
+    async fn check(&mut self) -> Result<()> {
+        let diff = git::get_diff(&None)?;
+        let mut issues = if diff.is_empty() {
+            vec![]
+        } else {
+            analyzer::analyze(&self.config, &diff).await?.issues
+        };
+        // Remote pages have no file for the editor to open
+        issues.retain(|issue| !remote::is_remote(&issue.file));
+        self.issues = issues;
+        Ok(())
+    }
+
+    /// Current text of a doc file: the editor's buffer if it is open, else the file on disk
+    fn text(&self, file: &Path) -> Option<String> {
+        let path = self.config.resolve(file);
+        self.open
+            .get(&file_uri(&path))
+            .cloned()
+            .or_else(|| fs::read_to_string(path).ok())
+    }
+
+    /// Diagnostics for every file with issues, and empty ones for files that no longer have any
+    fn publish_all(&mut self) -> Vec<Value> {
+        let mut uris: Vec<String> = self
+            .issues
+            .iter()
+            .map(|issue| file_uri(&self.config.resolve(&issue.file)))
+            .collect();
+        uris.extend(std::mem::take(&mut self.published));
+        uris.sort();
+        uris.dedup();
+        uris.iter().filter_map(|uri| self.publish(uri)).collect()
+    }
+
+    fn publish(&mut self, uri: &str) -> Option<Value> {
+        let file = self.relative(uri)?;
+        let text = self.text(&file).unwrap_or_default();
+        let diagnostics: Vec<Value> = self
+            .issues
+            .iter()
+            .filter(|issue| issue.file == file)
+            .filter_map(|issue| diagnostic(issue, &text))
+            .collect();
+        if !self.published.iter().any(|u| u == uri) {
+            self.published.push(uri.to_string());
+        }
+        Some(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    /// Repository-relative path of a `file://` URI inside the repository
+    fn relative(&self, uri: &str) -> Option<PathBuf> {
+        uri_path(uri)?
+            .strip_prefix(&self.config.root)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    /// A `quickfix` per issue whose diagnostic overlaps the requested range. Fixes generated
+    /// for the current text carry their edit; the others are generated on `codeAction/resolve`.
+    fn code_actions(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(file) = self.relative(uri) else {
+            return json!([]);
+        };
+        let text = self.text(&file).unwrap_or_default();
+        let first = params["range"]["start"]["line"].as_u64().unwrap_or(0) as usize;
+        let last = params["range"]["end"]["line"].as_u64().unwrap_or(0) as usize;
+
+        let actions: Vec<Value> = self
+            .issues
+            .iter()
+            .filter(|issue| issue.file == file)
+            .filter_map(|issue| {
+                let diagnostic = diagnostic(issue, &text)?;
+                let start = diagnostic["range"]["start"]["line"].as_u64()? as usize;
+                let end = diagnostic["range"]["end"]["line"].as_u64()? as usize;
+                if start > last || end < first {
+                    return None;
+                }
+                let mut action = json!({
+                    "title": format!("Fix documentation drift: {}", issue.description),
+                    "kind": "quickfix",
+                    "diagnostics": [diagnostic],
+                    "isPreferred": true,
+                    "data": { "fingerprint": issue.fingerprint },
+                });
+                if let Some((original, fixed)) = self.fixes.get(&issue.fingerprint) {
+                    if *original == text {
+                        action["edit"] = workspace_edit(uri, original, fixed);
+                    }
+                }
+                Some(action)
+            })
+            .collect();
+        json!(actions)
+    }
+
+    /// Add the edit to a code action, generating the fix for the current text of its file
+    async fn resolve(&mut self, mut action: Value) -> Result<Value> {
+        if action.get("edit").is_some() {
+            return Ok(action);
+        }
+        let fingerprint = action["data"]["fingerprint"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let Some(issue) = self
+            .issues
+            .iter()
+            .find(|issue| issue.fingerprint == fingerprint)
+        else {
+            return Err(DriftcheckError::LspError(
+                "the issue is no longer reported; run driftcheck.check".to_string(),
+            ));
+        };
+
+        let original = self.text(&issue.file).ok_or_else(|| {
+            DriftcheckError::PatchError(format!("cannot read {}", issue.file.display()))
+        })?;
+        let fixed = match self.fixes.get(&fingerprint) {
+            Some((base, fixed)) if *base == original => fixed.clone(),
+            _ => fix::generate_doc_fix(&self.config, issue, &original).await?,
+        };
+        let uri = file_uri(&self.config.resolve(&issue.file));
+        action["edit"] = workspace_edit(&uri, &original, &fixed);
+        self.fixes.insert(fingerprint, (original, fixed));
+        Ok(action)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": { "openClose": true, "change": 1 },
+            "codeActionProvider": { "codeActionKinds": ["quickfix"], "resolveProvider": true },
+            "executeCommandProvider": { "commands": [CHECK_COMMAND] },
+        },
+        "serverInfo": { "name": "driftcheck", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn response(id: Value, result: Result<Value>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error(id, REQUEST_FAILED, &format!("[{}] {}", e.code(), e)),
+    }
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// `window/showMessage` of `kind` (1 error, 2 warning, 3 info)
+fn show_message(kind: u8, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "window/showMessage",
+        "params": { "type": kind, "message": message },
+    })
+}
+
+/// The issue as a diagnostic over its excerpt in `text`, or at its line when the excerpt is not
+/// found. Issues whose excerpt is gone from a non-empty text (fixed by hand) have none.
+fn diagnostic(issue: &Issue, text: &str) -> Option<Value> {
+    let (start, end) = match locate_excerpt(text, &issue.doc_excerpt) {
+        Some(range) => range,
+        None if !text.is_empty() && !issue.doc_excerpt.trim().is_empty() => return None,
+        None => (issue.line.max(1), issue.line.max(1)),
+    };
+    let mut message = issue.description.clone();
+    if let Some(fix) = &issue.suggested_fix {
+        message.push_str(&format!("\nSuggested fix: {}", fix));
+    }
+    Some(json!({
+        "range": {
+            "start": { "line": start - 1, "character": 0 },
+            "end": { "line": end, "character": 0 },
+        },
+        "severity": match issue.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "code": issue.fingerprint,
+        "source": "driftcheck",
+        "message": message,
+    }))
+}
+
+/// A workspace edit replacing the lines that differ between `original` and `fixed`
+fn workspace_edit(uri: &str, original: &str, fixed: &str) -> Value {
+    let mut changes = serde_json::Map::new();
+    if let Some(edit) = text_edit(original, fixed) {
+        changes.insert(uri.to_string(), json!([edit]));
+    }
+    json!({ "changes": changes })
+}
+
+/// One edit replacing the lines that differ between `original` and `fixed`
+fn text_edit(original: &str, fixed: &str) -> Option<Value> {
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = fixed.split_inclusive('\n').collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == old.len() && prefix == new.len() {
+        return None;
+    }
+    Some(json!({
+        "range": {
+            "start": { "line": prefix, "character": 0 },
+            "end": { "line": old.len() - suffix, "character": 0 },
+        },
+        "newText": new[prefix..new.len() - suffix].concat(),
+    }))
+}
+
+/// `file://` URI of an absolute path, with `/` separators and reserved bytes percent-encoded
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        // Windows drive paths: file:///C:/repo
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            b':' if uri.len() == "file:///".len() + 1 => uri.push(':'),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Path of a `file://` URI
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(rest.len());
+    let mut iter = rest.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex: String = iter.by_ref().take(2).map(char::from).collect();
+            bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // file:///C:/repo is C:/repo on Windows
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    #[test]
+    fn test_code_action_edit() {
+        let root = std::env::temp_dir().join(format!("driftcheck-lsp-{}", std::process::id()));
+        fs::create_dir_all(root.join("docs")).unwrap();
+        let original = "# Usage\n\nRun with --no-tui to skip the interface.\n\nMore.\n";
+        fs::write(root.join("docs/usage.md"), original).unwrap();
+
+        let issue = Issue::from(RawIssue {
+            file: "docs/usage.md".to_string(),
+            line: 3,
+            description: "--no-tui was renamed to --plain".to_string(),
+            doc_excerpt: "Run with --no-tui to skip the interface.".to_string(),
+            suggested_fix: None,
+            confidence: None,
+            code_symbol: None,
+        });
+        let fixed = original.replace("--no-tui", "--plain");
+        let mut server = Server::new(Config {
+            root: root.clone(),
+            ..Config::default()
+        });
+        server.fixes.insert(
+            issue.fingerprint.clone(),
+            (original.to_string(), fixed.clone()),
+        );
+        server.issues = vec![issue];
+        let uri = file_uri(&root.join("docs/usage.md"));
+        assert_eq!(server.relative(&uri), Some(PathBuf::from("docs/usage.md")));
+
+        let actions = server.code_actions(&json!({
+            "textDocument": { "uri": uri },
+            "range": { "start": { "line": 2, "character": 4 }, "end": { "line": 2, "character": 4 } },
+        }));
+        let action = &actions[0];
+        assert_eq!(action["kind"], "quickfix");
+        assert_eq!(action["diagnostics"][0]["range"]["start"]["line"], 2);
+        let edit = &action["edit"]["changes"][&uri][0];
+        assert_eq!(edit["range"]["start"], json!({ "line": 2, "character": 0 }));
+        assert_eq!(edit["range"]["end"], json!({ "line": 3, "character": 0 }));
+        assert_eq!(edit["newText"], "Run with --plain to skip the interface.\n");
+
+        // Outside the diagnostic: no action
+        let none = server.code_actions(&json!({
+            "textDocument": { "uri": uri },
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+        }));
+        assert_eq!(none, json!([]));
+
+        // Fixed by hand in the editor: the diagnostic goes away
+        server.open.insert(uri.clone(), fixed);
+        let published = server.publish(&uri).unwrap();
+        assert_eq!(published["params"]["diagnostics"], json!([]));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_messages() {
+        let framed = b"Content-Length: 2\r\n\r\n{}Content-Length: 99999999999\r\n\r\n";
+        let mut reader = &framed[..];
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(json!({})));
+        assert!(read_message(&mut reader).await.is_err());
+        let long = format!("X-Padding: {}\r\n", "x".repeat(MAX_HEADER_LEN as usize));
+        assert!(read_message(&mut long.as_bytes()).await.is_err());
+        assert_eq!(read_message(&mut &b""[..]).await.unwrap(), None);
+
+        let mut server = Server::new(Config::default());
+        let unknown_command = server
+            .handle(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "workspace/executeCommand",
+                "params": { "command": "other.command" },
+            }))
+            .await;
+        assert_eq!(unknown_command[0]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(
+            unknown_command[0]["error"]["message"],
+            "unknown command other.command"
+        );
+        let unknown_method = server
+            .handle(json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover" }))
+            .await;
+        assert_eq!(unknown_method[0]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_file_uri_round_trip() {
+        let path = Path::new("/repo/docs/getting started.md");
+        let uri = file_uri(path);
+        assert_eq!(uri, "file:///repo/docs/getting%20started.md");
+        assert_eq!(uri_path(&uri), Some(path.to_path_buf()));
+        assert_eq!(
+            file_uri(Path::new(r"C:\repo\README.md")),
+            "file:///C:/repo/README.md"
+        );
+        assert_eq!(
+            uri_path("file:///c%3A/repo/README.md"),
+            Some(PathBuf::from("c:/repo/README.md"))
+        );
+    }
+}
@@ -1,23 +1,28 @@
-mod analyzer;
-mod cache;
 mod cli;
-mod config;
-mod error;
-mod git;
-mod llm;
-mod output;
-mod progress;
-mod search;
-mod tui;
-
-use clap::Parser;
-use cli::{CacheAction, Cli, Commands};
-use config::Config;
-use error::{DriftcheckError, Result};
+
+use clap::{CommandFactory, FromArgMatches};
+use cli::{CacheAction, Cli, Commands, ConfigAction, ErrorFormat, FeedbackAction, OutputFormat};
+use driftcheck::config::Config;
+use driftcheck::error::{DriftcheckError, Result};
+use driftcheck::git::HookInstall;
+use driftcheck::history::IssueState;
+use driftcheck::metrics::PassThrough;
+use driftcheck::onboarding::FirstRunPolicy;
+use driftcheck::push::{HookAction, PushTarget};
+use driftcheck::{
+    analyzer, baseline, cache, discover, feedback, fix, git, github, history, llm, lsp, metrics,
+    onboarding, output, release, runs, search, sweep, telemetry, term, tui,
+};
 use std::env;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
 use std::process;
-use tracing::{error, info};
-use tracing_subscriber::EnvFilter;
+use term::{ColorSupport, TermCaps};
+use tracing::{debug, error, info, info_span, Instrument};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
 #[tokio::main]
 async fn main() {
@@ -35,34 +40,148 @@ async fn main() {
         EnvFilter::new("warn")
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_target(false)
+    let caps = TermCaps::detect();
+    if caps.color == ColorSupport::None {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+
+    let matches = Cli::command().get_matches();
+    let command = matches.subcommand_name().unwrap_or_default().to_string();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // The language server speaks its protocol on stdout, so it logs to stderr
+    let writer = if command == "lsp" {
+        BoxMakeWriter::new(io::stderr)
+    } else {
+        BoxMakeWriter::new(io::stdout)
+    };
+    // Logs honor the debug filter; spans go to the OTLP exporter when one is configured
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .with_ansi(caps.color != ColorSupport::None)
+                .with_writer(writer)
+                .with_filter(filter),
+        )
+        .with(telemetry::layer())
         .init();
 
-    if let Err(e) = run().await {
+    let error_format = cli.error_format;
+
+    let span = info_span!(
+        "driftcheck",
+        command = command.as_str(),
+        error = tracing::field::Empty
+    );
+    if let Err(e) = run(cli).instrument(span.clone()).await {
+        span.record("error", e.code());
+        drop(span);
         error!("{}", e);
-        eprintln!("Error: {}", e);
-        process::exit(1);
+        match error_format {
+            ErrorFormat::Text => eprintln!("Error [{}]: {}", e.code(), e),
+            ErrorFormat::Json => eprintln!("{}", e.to_json()),
+        }
+        exit(e.exit_code());
     }
+    drop(span);
+    telemetry::shutdown();
+}
+
+/// Exit after exporting traces, which `process::exit` would otherwise lose
+fn exit(code: i32) -> ! {
+    telemetry::shutdown();
+    process::exit(code)
 }
 
-async fn run() -> Result<()> {
-    let cli = Cli::parse();
+async fn run(cli: Cli) -> Result<()> {
+    if cli.read_only {
+        Config::set_read_only();
+    }
+    if let Some(profile) = &cli.profile {
+        Config::set_profile(profile);
+    }
 
     match cli.command {
-        Commands::Init { force } => cmd_init(force).await,
-        Commands::Check { range, no_tui } => cmd_check(range, no_tui).await,
-        Commands::Config { edit, path } => cmd_config(edit, path),
-        Commands::Enable => cmd_enable(),
-        Commands::Disable => cmd_disable(),
-        Commands::Cache { action } => cmd_cache(action),
-        Commands::InstallHook { force } => cmd_install_hook(force),
-        Commands::Hook => cmd_hook().await,
+        Commands::Init { force, gitignore } => cmd_init(force, gitignore).await,
+        Commands::Check {
+            range,
+            staged,
+            diff_file,
+            stdin,
+            no_tui,
+            format,
+            output,
+            explain_queries,
+            dry_run,
+            all,
+            capture_prompts,
+            with_fixes,
+            batch,
+        } => {
+            let source = match (diff_file, stdin) {
+                (Some(path), _) => DiffSource::File(path),
+                (None, true) => DiffSource::Stdin,
+                (None, false) if staged => DiffSource::Staged,
+                (None, false) => DiffSource::Range(range),
+            };
+            // The patch isn't applied here, so fixes and history would not match the tree
+            if matches!(source, DiffSource::File(_) | DiffSource::Stdin) {
+                Config::set_read_only();
+            }
+            if explain_queries {
+                cmd_explain_queries(&source).await
+            } else if dry_run {
+                cmd_dry_run(&source).await
+            } else {
+                let report = ReportOptions {
+                    format,
+                    output,
+                    capture_prompts,
+                    with_fixes,
+                    batch,
+                };
+                cmd_check(source, no_tui, all, report).await
+            }
+        }
+        Commands::Ci { range, github } => cmd_ci(range, github).await,
+        Commands::Review { last, run } => cmd_review(last, run).await,
+        Commands::Comment { pr, range, repo } => cmd_comment(pr, range, repo).await,
+        Commands::ReleaseCheck { range, format } => cmd_release_check(range, format).await,
+        Commands::Sweep {
+            sample,
+            format,
+            output,
+        } => cmd_sweep(sample, format, output).await,
+        Commands::Config {
+            action: Some(ConfigAction::Schema),
+            ..
+        } => cmd_config_schema(),
+        Commands::Config {
+            action: None,
+            edit,
+            path,
+            check,
+        } => cmd_config(edit, path, check),
+        Commands::Enable { category, all } => cmd_enable(&category, all),
+        Commands::Disable { category } => cmd_disable(&category),
+        Commands::Cache { action } => cmd_cache(action).await,
+        Commands::Baseline { range } => cmd_baseline(range).await,
+        Commands::Snooze {
+            fingerprint,
+            duration,
+        } => cmd_snooze(&fingerprint, &duration),
+        Commands::DiscoverDocs { apply } => cmd_discover_docs(apply),
+        Commands::Feedback { action } => cmd_feedback(action),
+        Commands::InstallHook { force, chain } => cmd_install_hook(force, chain),
+        Commands::Lsp => cmd_lsp().await,
+        Commands::SimulatePush { remote, range } => cmd_simulate_push(remote, range).await,
+        Commands::Hook { remote, url } => cmd_hook(None, PushTarget { remote, url }).await,
     }
 }
 
-async fn cmd_init(force: bool) -> Result<()> {
+async fn cmd_init(force: bool, gitignore: bool) -> Result<()> {
     let git_root = Config::find_git_root()?;
     let config_path = git_root.join(".driftcheck.toml");
 
@@ -75,14 +194,29 @@ async fn cmd_init(force: bool) -> Result<()> {
         return Ok(());
     }
 
-    // Create default config
-    let config = Config::default();
+    // Create default config, seeded with discovered documentation
+    let mut config = Config::default();
+    let discovered = discover::discover_doc_paths(&git_root);
+    if !discovered.is_empty() {
+        config.docs.paths = discovered;
+    }
     config.save_to_path(&config_path)?;
     println!("Created configuration file: {}", config_path.display());
+    println!("Documentation paths:");
+    for path in &config.docs.paths {
+        println!("  {}", path);
+    }
 
     // Install hook
-    git::install_hook(&git_root, force)?;
-    println!("Installed pre-push hook");
+    report_hook_install(git::install_hook(&git_root, force, false)?);
+
+    // Keep local state and secrets out of version control
+    let entries = gitignore_entries(&config);
+    if gitignore || confirm(&format!("Add {} to .gitignore?", entries.join(", "))) {
+        for entry in git::add_to_gitignore(&git_root, &entries)? {
+            println!("Added {} to .gitignore", entry);
+        }
+    }
 
     println!("\ndriftcheck initialized successfully!");
     println!("\nNext steps:");
@@ -93,17 +227,130 @@ async fn cmd_init(force: bool) -> Result<()> {
     Ok(())
 }
 
-async fn cmd_check(range: Option<String>, no_tui: bool) -> Result<()> {
+/// Local files driftcheck creates that should not be committed.
+/// Everything under .git/ is already private to the clone.
+fn gitignore_entries(config: &Config) -> Vec<String> {
+    let mut entries = vec![".env".to_string()];
+    if !config.cache.dir.starts_with(".git/") {
+        entries.push(format!("{}/", config.cache.dir.trim_end_matches('/')));
+    }
+    entries
+}
+
+/// Ask a yes/no question on the terminal (defaults to yes, no without a TTY)
+fn confirm(question: &str) -> bool {
+    if !atty::is(atty::Stream::Stdin) {
+        return false;
+    }
+
+    print!("{} [Y/n] ", question);
+    let _ = io::stdout().flush();
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes")
+}
+
+/// How `check` reports its results
+struct ReportOptions {
+    format: OutputFormat,
+    output: Option<PathBuf>,
+    capture_prompts: bool,
+    with_fixes: bool,
+    /// Generate fixes through the provider's batch API
+    batch: bool,
+}
+
+/// Where `check` reads its diff from
+enum DiffSource {
+    /// A commit range, `@{u}..HEAD` by default
+    Range(Option<String>),
+    /// The index (`--staged`)
+    Staged,
+    /// A patch file (`--diff-file`)
+    File(PathBuf),
+    /// A patch on stdin (`--stdin`)
+    Stdin,
+}
+
+impl DiffSource {
+    fn read(&self) -> Result<String> {
+        let patch = match self {
+            DiffSource::Range(range) => return git::get_diff(range),
+            DiffSource::Staged => return git::get_staged_diff(),
+            DiffSource::File(path) => std::fs::read_to_string(path)
+                .map_err(|e| DriftcheckError::DiffInvalid(format!("{}: {}", path.display(), e)))?,
+            DiffSource::Stdin => {
+                let mut patch = String::new();
+                io::stdin()
+                    .read_to_string(&mut patch)
+                    .map_err(|e| DriftcheckError::DiffInvalid(format!("stdin: {}", e)))?;
+                patch
+            }
+        };
+        if patch.trim().is_empty() {
+            return Ok(String::new());
+        }
+        git::normalize_patch(&patch)
+    }
+
+    /// Name of the checked changes in reports and run records
+    fn label(&self) -> Option<String> {
+        match self {
+            DiffSource::Range(range) => range.clone(),
+            DiffSource::Staged => Some("staged".to_string()),
+            DiffSource::File(path) => Some(path.display().to_string()),
+            DiffSource::Stdin => Some("stdin".to_string()),
+        }
+    }
+}
+
+async fn cmd_check(
+    source: DiffSource,
+    no_tui: bool,
+    all: bool,
+    report: ReportOptions,
+) -> Result<()> {
     let config = Config::load()?;
+    let ReportOptions {
+        format,
+        output,
+        capture_prompts,
+        with_fixes,
+        batch,
+    } = report;
+
+    if output.is_some() && format == OutputFormat::Text {
+        return Err(DriftcheckError::ReportError(
+            "--output requires a non-text --format".to_string(),
+        ));
+    }
+
+    if capture_prompts {
+        if format != OutputFormat::Json {
+            return Err(DriftcheckError::ReportError(
+                "--capture-prompts requires --format json".to_string(),
+            ));
+        }
+        llm::capture::enable();
+    }
+
+    if with_fixes && !matches!(format, OutputFormat::Json | OutputFormat::Sarif) {
+        return Err(DriftcheckError::ReportError(
+            "--with-fixes requires --format json or sarif".to_string(),
+        ));
+    }
 
     if !config.is_enabled() {
         return Err(DriftcheckError::Disabled);
     }
 
     // Get the diff
-    let diff = git::get_diff(&range)?;
+    let diff = source.read()?;
 
-    if diff.is_empty() {
+    if diff.is_empty() && format == OutputFormat::Text {
         println!("No changes to check.");
         return Ok(());
     }
@@ -111,27 +358,328 @@ async fn cmd_check(range: Option<String>, no_tui: bool) -> Result<()> {
     info!("Analyzing diff ({} bytes)", diff.len());
 
     // Run analysis
-    let issues = analyzer::analyze(&config, &diff).await?;
+    let analysis = if diff.is_empty() {
+        analyzer::Analysis::default()
+    } else {
+        analyzer::analyze(&config, &diff).await?
+    };
+    let mut issues = analysis.issues;
+    if with_fixes && batch {
+        fix::attach_patches_batched(&config, &mut issues).await?;
+    } else if with_fixes {
+        fix::attach_patches(&config, &mut issues).await;
+    }
+
+    let range = source.label();
+    record_run(&config, &range, &diff, &issues);
+    let meta = output::ReportMeta {
+        risk: analysis.risk,
+        consensus: analysis.consensus,
+        ..output::ReportMeta::new(&config, &range)
+    };
+    let report = match format {
+        OutputFormat::Text => None,
+        OutputFormat::Html => Some(output::html::render(&issues, &meta)),
+        OutputFormat::Markdown => Some(output::markdown::render(&issues, &config.root)),
+        OutputFormat::Json => {
+            let exchanges = capture_prompts.then(llm::capture::take);
+            Some(output::json::render(&issues, &meta, exchanges.as_deref()))
+        }
+        OutputFormat::Sarif => Some(output::sarif::render(&issues, &meta)),
+        OutputFormat::Codeclimate => Some(output::codeclimate::render(&issues, &config.root)),
+    };
+
+    if let Some(report) = report {
+        output::write_report(&report, output.as_deref())?;
+        if issues.iter().any(analyzer::Issue::is_blocking) {
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    output::print_usage(&config);
+
+    // Point out documentation that docs.paths misses
+    let discovered = discover::discover_doc_paths(&Config::find_git_root()?);
+    let missing = discover::uncovered(&discovered, &config.docs.all_paths());
+    if !missing.is_empty() {
+        eprintln!(
+            "hint: documentation not covered by docs.paths: {} (see `driftcheck discover-docs`)",
+            missing.join(", ")
+        );
+    }
 
     if issues.is_empty() {
         println!("No documentation issues detected.");
         return Ok(());
     }
 
+    let hidden = if all {
+        0
+    } else {
+        analyzer::cap_issues(&mut issues, config.general.max_issues)
+    };
+
     // Determine output mode
     let use_tui = !no_tui && atty::is(atty::Stream::Stdout);
 
     if use_tui {
-        tui::run(&config, issues).await?;
+        tui::run(&config, issues, hidden, Some(&diff)).await?;
     } else {
-        output::print_issues(&issues);
-        process::exit(1);
+        output::print_issues(&issues, hidden);
+        if issues.iter().any(analyzer::Issue::is_blocking) {
+            exit(1);
+        }
     }
 
     Ok(())
 }
 
-fn cmd_config(edit: bool, show_path: bool) -> Result<()> {
+async fn cmd_ci(range: Option<String>, github: bool) -> Result<()> {
+    let config = Config::load()?;
+    if !config.is_enabled() {
+        return Err(DriftcheckError::Disabled);
+    }
+    let github = github || output::github::is_github_actions();
+
+    // Pull request workflows know their base branch; push workflows fall back to the upstream
+    let range = range.or_else(|| {
+        env::var("GITHUB_BASE_REF")
+            .ok()
+            .filter(|base| !base.is_empty())
+            .map(|base| format!("origin/{}..HEAD", base))
+    });
+
+    let diff = git::get_diff(&range)?;
+    let issues = if diff.is_empty() {
+        vec![]
+    } else {
+        analyzer::analyze(&config, &diff).await?.issues
+    };
+    output::print_usage(&config);
+
+    if github {
+        print!(
+            "{}",
+            output::github::render_annotations(&issues, &config.root)
+        );
+        output::github::write_step_summary(&output::markdown::render(&issues, &config.root))?;
+        if issues.is_empty() {
+            println!("No documentation issues detected.");
+        }
+    } else if issues.is_empty() {
+        println!("No documentation issues detected.");
+    } else {
+        output::print_issues(&issues, 0);
+    }
+
+    if issues.iter().any(analyzer::Issue::is_blocking) {
+        exit(1);
+    }
+    Ok(())
+}
+
+async fn cmd_comment(pr: u64, range: Option<String>, repo: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    if !config.is_enabled() {
+        return Err(DriftcheckError::Disabled);
+    }
+    let github = github::GithubClient::from_env(repo)?;
+    let pull = github.pull_request(pr).await?;
+
+    let range = range.unwrap_or_else(|| format!("origin/{}..HEAD", pull.base.name));
+    let diff = git::get_diff(&Some(range))?;
+    let issues = if diff.is_empty() {
+        vec![]
+    } else {
+        analyzer::analyze(&config, &diff).await?.issues
+    };
+
+    let summary =
+        github::sync_review_comments(&github, pr, &pull.head.sha, &issues, &config.root).await?;
+    println!(
+        "driftcheck: {} issue(s) on PR #{}: {} comment(s) created, {} updated, {} resolved, {} outside the diff",
+        issues.len(),
+        pr,
+        summary.created,
+        summary.updated,
+        summary.resolved,
+        summary.outside_diff
+    );
+
+    if issues.iter().any(analyzer::Issue::is_blocking) {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// Keep the issues of a run for `driftcheck review`
+fn record_run(config: &Config, range: &Option<String>, diff: &str, issues: &[analyzer::Issue]) {
+    if issues.is_empty() {
+        return;
+    }
+    let range = range.as_deref().unwrap_or("@{u}..HEAD");
+    if let Err(e) = runs::record(config, range, issues) {
+        debug!("Failed to record the run: {}", e);
+    }
+    if let Err(e) = output::write_last_annotated(&config.root, diff, issues) {
+        debug!("Failed to write the annotated diff: {}", e);
+    }
+}
+
+async fn cmd_review(last: bool, run: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+
+    let run = match (last, run) {
+        (true, _) => runs::latest(&config)?,
+        (false, Some(id)) => runs::load(&config, &id)?,
+        (false, None) => {
+            let ids = runs::ids(&config)?;
+            if ids.is_empty() {
+                println!(
+                    "No recorded runs. Runs with issues are recorded by `check` and the hook."
+                );
+            }
+            for id in ids {
+                let run = runs::load(&config, &id)?;
+                println!("{}  {}  {} issue(s)", run.id, run.range, run.issues.len());
+            }
+            return Ok(());
+        }
+    };
+
+    // Issues fixed since the run (from the TUI or by a later run) need no review
+    let mut issues = run.issues;
+    history::refresh(&mut issues)?;
+    issues.retain(|issue| issue.state != IssueState::Fixed);
+    if issues.is_empty() {
+        println!("All issues of run {} have been fixed.", run.id);
+        return Ok(());
+    }
+
+    let hidden = analyzer::cap_issues(&mut issues, config.general.max_issues);
+    if atty::is(atty::Stream::Stdout) {
+        tui::run(&config, issues, hidden, None).await
+    } else {
+        output::print_issues(&issues, hidden);
+        Ok(())
+    }
+}
+
+async fn cmd_explain_queries(source: &DiffSource) -> Result<()> {
+    let config = Config::load()?;
+    let diff = source.read()?;
+
+    if diff.is_empty() {
+        println!("No changes to check.");
+        return Ok(());
+    }
+
+    let explanation = analyzer::explain(&config, &diff).await?;
+    output::print_explanation(&explanation);
+    Ok(())
+}
+
+async fn cmd_dry_run(source: &DiffSource) -> Result<()> {
+    let config = Config::load()?;
+    let diff = source.read()?;
+
+    if diff.is_empty() {
+        println!("No changes to check.");
+        return Ok(());
+    }
+
+    let dry_run = analyzer::dry_run(&config, &diff).await?;
+    output::print_dry_run(&dry_run);
+    Ok(())
+}
+
+async fn cmd_release_check(range: String, format: OutputFormat) -> Result<()> {
+    let config = Config::load()?;
+    let report = release::check(&config, &range).await?;
+
+    match format {
+        OutputFormat::Text => output::print_release_report(&report),
+        OutputFormat::Html => print!(
+            "{}",
+            output::html::render(
+                &report.issues,
+                &output::ReportMeta::new(&config, &Some(range.clone()))
+            )
+        ),
+        OutputFormat::Markdown => print!(
+            "{}",
+            output::markdown::render_release(&report, &config.root)
+        ),
+        OutputFormat::Sarif => print!(
+            "{}",
+            output::sarif::render(
+                &report.issues,
+                &output::ReportMeta::new(&config, &Some(range.clone()))
+            )
+        ),
+        OutputFormat::Codeclimate => print!(
+            "{}",
+            output::codeclimate::render(&report.issues, &config.root)
+        ),
+        OutputFormat::Json => output::print_json(
+            &report.issues,
+            &output::ReportMeta::new(&config, &Some(range.clone())),
+            None,
+        ),
+    }
+
+    if !report.is_ready() {
+        exit(1);
+    }
+    Ok(())
+}
+
+async fn cmd_sweep(
+    sample: Option<usize>,
+    format: OutputFormat,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let config = Config::load()?;
+    if output.is_some() && format == OutputFormat::Text {
+        return Err(DriftcheckError::ReportError(
+            "--output requires a non-text --format".to_string(),
+        ));
+    }
+    if !config.is_enabled() {
+        return Err(DriftcheckError::Disabled);
+    }
+
+    let report = sweep::run(&config, sample.unwrap_or(config.sweep.sample)).await?;
+    output::print_usage(&config);
+
+    let meta = output::ReportMeta::new(&config, &Some("HEAD (sweep)".to_string()));
+    let rendered = match format {
+        OutputFormat::Text => None,
+        OutputFormat::Html => Some(output::html::render(&report.issues, &meta)),
+        OutputFormat::Markdown => Some(output::markdown::render(&report.issues, &config.root)),
+        OutputFormat::Json => Some(output::json::render(&report.issues, &meta, None)),
+        OutputFormat::Sarif => Some(output::sarif::render(&report.issues, &meta)),
+        OutputFormat::Codeclimate => {
+            Some(output::codeclimate::render(&report.issues, &config.root))
+        }
+    };
+    match rendered {
+        Some(rendered) => output::write_report(&rendered, output.as_deref())?,
+        None => output::print_sweep_report(&report),
+    }
+
+    if !report.is_clean() {
+        exit(1);
+    }
+    Ok(())
+}
+
+fn cmd_config(edit: bool, show_path: bool, check: bool) -> Result<()> {
+    if check {
+        return cmd_config_check();
+    }
+
     if show_path {
         match Config::find_config_path() {
             Ok(path) => println!("{}", path.display()),
@@ -168,23 +716,127 @@ fn cmd_config(edit: bool, show_path: bool) -> Result<()> {
     Ok(())
 }
 
-fn cmd_enable() -> Result<()> {
+fn cmd_config_schema() -> Result<()> {
+    let schema = serde_json::to_string_pretty(&Config::schema())
+        .map_err(|e| DriftcheckError::ConfigInvalid(e.to_string()))?;
+    println!("{}", schema);
+    Ok(())
+}
+
+/// Load the configuration (which validates every glob) and report doc patterns that match
+/// no files; exits 1 when any pattern is unmatched. `release.paths` is a list of candidates,
+/// so it is only validated.
+fn cmd_config_check() -> Result<()> {
+    let config = Config::load()?;
+
+    let mut lists = vec![
+        ("docs.paths".to_string(), &config.docs.paths),
+        ("docs.ignore".to_string(), &config.docs.ignore),
+    ];
+    for (name, corpus) in &config.docs.corpora {
+        lists.push((format!("docs.{}.paths", name), &corpus.paths));
+        lists.push((format!("docs.{}.ignore", name), &corpus.ignore));
+    }
+
+    let mut unmatched = 0;
+    for (key, patterns) in lists {
+        for pattern in search::unmatched_patterns(&config.root, patterns) {
+            println!("{}: \"{}\" matches no files", key, pattern);
+            unmatched += 1;
+        }
+    }
+
+    if unmatched > 0 {
+        exit(1);
+    }
+    println!("Configuration is valid; every doc pattern matches at least one file.");
+    Ok(())
+}
+
+fn cmd_enable(categories: &[String], all: bool) -> Result<()> {
     let mut config = Config::load()?;
+    if !categories.is_empty() {
+        let categories = parse_categories(categories)?;
+        config.general.disabled_categories.retain(|name| {
+            feedback::parse_category(name).is_some_and(|c| !categories.contains(&c))
+        });
+        config.save()?;
+        println!("Reporting {} issues again.", categories.join(", "));
+        return Ok(());
+    }
+
     config.general.enabled = true;
+    if all {
+        config.general.disabled_categories.clear();
+    }
     config.save()?;
-    println!("driftcheck enabled.");
+    if all {
+        println!("driftcheck enabled for every category.");
+    } else {
+        println!("driftcheck enabled.");
+    }
     Ok(())
 }
 
-fn cmd_disable() -> Result<()> {
+fn cmd_disable(categories: &[String]) -> Result<()> {
     let mut config = Config::load()?;
+    if !categories.is_empty() {
+        if let Some(level) = &config.policy().min_severity {
+            return Err(DriftcheckError::PolicyViolation(format!(
+                "disabling categories is forbidden (min_severity = \"{}\")",
+                level
+            )));
+        }
+        let categories = parse_categories(categories)?;
+        for category in &categories {
+            if !config
+                .general
+                .disabled_categories
+                .iter()
+                .any(|name| feedback::parse_category(name) == Some(category))
+            {
+                config
+                    .general
+                    .disabled_categories
+                    .push(category.to_string());
+            }
+        }
+        config.save()?;
+        println!(
+            "No longer reporting {} issues (`driftcheck enable --category` to undo).",
+            categories.join(", ")
+        );
+        return Ok(());
+    }
+
+    if config.policy().forbid_disable {
+        return Err(DriftcheckError::PolicyViolation(
+            "disabling driftcheck is forbidden".to_string(),
+        ));
+    }
     config.general.enabled = false;
     config.save()?;
     println!("driftcheck disabled.");
     Ok(())
 }
 
-fn cmd_cache(action: CacheAction) -> Result<()> {
+/// Categories named with `--category`, as `feedback::categorize` returns them
+fn parse_categories(names: &[String]) -> Result<Vec<&'static str>> {
+    names
+        .iter()
+        .map(|name| {
+            feedback::parse_category(name).ok_or_else(|| {
+                DriftcheckError::ConfigInvalid(format!(
+                    "unknown category {:?} (categories: {})",
+                    name,
+                    feedback::CATEGORIES.join(", ")
+                ))
+            })
+        })
+        .collect()
+}
+
+async fn cmd_cache(action: CacheAction) -> Result<()> {
     match action {
         CacheAction::Clear => {
             cache::clear()?;
@@ -193,23 +845,207 @@ fn cmd_cache(action: CacheAction) -> Result<()> {
         CacheAction::Stats => {
             let stats = cache::stats()?;
             println!("Cache statistics:");
-            println!("  Entries: {}", stats.entries);
+            println!("  Search query entries: {}", stats.entries);
+            println!("  Analysis entries: {}", stats.analysis_entries);
             println!("  Size: {} bytes", stats.size_bytes);
             println!("  Location: {}", stats.path.display());
         }
+        CacheAction::Warm { range } => {
+            let config = Config::load()?;
+            let diff = git::get_diff(&range)?;
+            let warmup = analyzer::warm(&config, &diff).await?;
+
+            if let Some(chunks) = warmup.indexed_chunks {
+                println!("Embedding index up to date ({} doc chunks).", chunks);
+            } else if warmup.changed_files == 0 {
+                println!("No changes to warm.");
+            } else if warmup.already_cached {
+                println!(
+                    "Search queries already cached ({} queries, {} changed file(s)).",
+                    warmup.queries, warmup.changed_files
+                );
+            } else {
+                println!(
+                    "Cached {} search queries for {} changed file(s); {} doc file(s) in scope.",
+                    warmup.queries, warmup.changed_files, warmup.doc_files
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_feedback(action: FeedbackAction) -> Result<()> {
+    match action {
+        FeedbackAction::Export { output } => {
+            let export = feedback::export()?;
+            let json = serde_json::to_string_pretty(&export)
+                .map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+
+            match output {
+                Some(path) => {
+                    Config::ensure_writable("writing the feedback export")?;
+                    std::fs::write(&path, json + "\n")
+                        .map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+                    eprintln!(
+                        "Exported {} feedback records to {}",
+                        export.records.len(),
+                        path.display()
+                    );
+                }
+                None => println!("{}", json),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_discover_docs(apply: bool) -> Result<()> {
+    let git_root = Config::find_git_root()?;
+    let discovered = discover::discover_doc_paths(&git_root);
+
+    if discovered.is_empty() {
+        println!("No documentation found.");
+        return Ok(());
+    }
+
+    if !apply {
+        println!("Discovered documentation paths:\n");
+        println!("[docs]");
+        println!("paths = [");
+        for path in &discovered {
+            println!("    \"{}\",", path);
+        }
+        println!("]");
+        println!("\nRun `driftcheck discover-docs --apply` to add them to your configuration.");
+        return Ok(());
+    }
+
+    let path = Config::find_config_path()?;
+    let mut config = Config::load_from_path(&path)?;
+    if !config.docs.corpora.is_empty() {
+        return Err(DriftcheckError::ConfigInvalid(
+            "named [docs.<corpus>] tables are configured; add the discovered paths to a corpus by hand"
+                .to_string(),
+        ));
+    }
+    let mut added = 0;
+    for pattern in discovered {
+        if !config.docs.paths.contains(&pattern) {
+            println!("  + {}", pattern);
+            config.docs.paths.push(pattern);
+            added += 1;
+        }
     }
+    config.save_to_path(&path)?;
+    println!(
+        "Added {} documentation path(s) to {}",
+        added,
+        path.display()
+    );
+
+    Ok(())
+}
+
+async fn cmd_baseline(range: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let mut baseline = baseline::load(&config)?;
+
+    let diff = git::get_diff(&range)?;
+    // Issues already in the baseline are filtered out here and stay in it
+    let issues = if diff.is_empty() {
+        vec![]
+    } else {
+        analyzer::analyze(&config, &diff).await?.issues
+    };
+
+    let added = baseline.extend(&issues);
+    baseline::save(&config, &baseline)?;
+    println!(
+        "Baselined {} new issue(s); {} total in {}.",
+        added,
+        baseline.issues.len(),
+        baseline::BASELINE_FILE
+    );
+    println!("Commit it so everyone shares the baseline.");
+    Ok(())
+}
+
+fn cmd_snooze(fingerprint: &str, duration: &str) -> Result<()> {
+    let config = Config::load()?;
+    let until = chrono::Utc::now() + baseline::parse_duration(duration)?;
+    let (file, description) = history::lookup(fingerprint).unwrap_or_default();
+
+    let mut baseline = baseline::load(&config)?;
+    baseline.snooze(baseline::Snooze {
+        fingerprint: fingerprint.to_string(),
+        file: file.clone(),
+        description,
+        until,
+    });
+    baseline::save(&config, &baseline)?;
+
+    let target = if file.is_empty() {
+        format!("issue {}", fingerprint)
+    } else {
+        format!("issue {} in {}", fingerprint, file)
+    };
+    println!(
+        "Snoozed {} until {}; it is reported again after that.",
+        target,
+        until.format("%Y-%m-%d %H:%M UTC")
+    );
+    println!("Commit {} to share the snooze.", baseline::BASELINE_FILE);
     Ok(())
 }
 
-fn cmd_install_hook(force: bool) -> Result<()> {
+fn cmd_install_hook(force: bool, chain: bool) -> Result<()> {
     let git_root = Config::find_git_root()?;
-    git::install_hook(&git_root, force)?;
-    println!("Pre-push hook installed.");
+    report_hook_install(git::install_hook(&git_root, force, chain)?);
     Ok(())
 }
 
-async fn cmd_hook() -> Result<()> {
-    // This is called by the git pre-push hook
+fn report_hook_install(install: HookInstall) {
+    match install {
+        HookInstall::Written(path) => println!("Installed pre-push hook: {}", path.display()),
+        HookInstall::Chained(path) => {
+            println!(
+                "Added driftcheck to the existing pre-push hook: {}",
+                path.display()
+            )
+        }
+    }
+}
+
+async fn cmd_lsp() -> Result<()> {
+    let config = Config::load()?;
+    if !config.is_enabled() {
+        return Err(DriftcheckError::Disabled);
+    }
+    lsp::run(config).await
+}
+
+async fn cmd_simulate_push(remote: Option<String>, range: Option<String>) -> Result<()> {
+    let range = match (range, &remote) {
+        (Some(range), _) => Some(range),
+        (None, Some(remote)) => Some(format!("{}/{}..HEAD", remote, git::current_branch()?)),
+        (None, None) => None,
+    };
+    // Pass the remote's URL like git does, so `[[hook.remotes]]` conditions apply
+    let url = remote.as_deref().map(git::remote_url).transpose()?;
+
+    eprintln!(
+        "driftcheck: simulating pre-push hook for {}",
+        range.as_deref().unwrap_or("@{u}..HEAD")
+    );
+    // Blocked pushes exit 1 from inside the hook, exactly as a real push would
+    cmd_hook(range, PushTarget { remote, url }).await?;
+    eprintln!("driftcheck: simulated push would proceed");
+    Ok(())
+}
+
+async fn cmd_hook(range: Option<String>, target: PushTarget) -> Result<()> {
+    // This is called by the git pre-push hook (with no range) and by simulate-push
     // Behavior: analyze and block if issues found (unless allow_push_on_error)
 
     let config = match Config::load() {
@@ -222,22 +1058,60 @@ async fn cmd_hook() -> Result<()> {
     };
 
     if !config.is_enabled() {
+        metrics::report_pass_through(PassThrough::Disabled, "analysis skipped");
         return Ok(());
     }
 
-    let diff = match git::get_diff(&None) {
+    let action = HookAction::for_push(&config, &target)?;
+    if action == HookAction::Skip {
+        metrics::report_pass_through(
+            PassThrough::Remote,
+            &format!(
+                "analysis skipped for {}",
+                target.remote.as_deref().unwrap_or("this remote")
+            ),
+        );
+        return Ok(());
+    }
+
+    // A first-time contributor shouldn't have a push blocked by a tool they've never heard of
+    let mut config = config;
+    let mut guarded = false;
+    if onboarding::is_first_run(&config) {
+        match FirstRunPolicy::from_config(&config)? {
+            FirstRunPolicy::Normal => {}
+            FirstRunPolicy::Skip => {
+                metrics::report_pass_through(PassThrough::FirstRun, "analysis skipped");
+                return Ok(());
+            }
+            FirstRunPolicy::Guarded => {
+                onboarding::print_welcome(&config);
+                config.llm.timeout = config.llm.timeout.min(onboarding::FIRST_RUN_TIMEOUT);
+                guarded = true;
+            }
+        }
+    }
+    let pass_on_error = |e: &DriftcheckError| {
+        let reason = if guarded {
+            PassThrough::FirstRun
+        } else if config.general.allow_push_on_error {
+            PassThrough::ErrorAllowed
+        } else {
+            return false;
+        };
+        metrics::report_pass_through(reason, &e.to_string());
+        true
+    };
+
+    let diff = match git::get_diff(&range) {
         Ok(d) => d,
         Err(DriftcheckError::NoUpstream) => {
             // No upstream, likely first push, allow
+            metrics::report_pass_through(PassThrough::NoUpstream, "analysis skipped");
             return Ok(());
         }
-        Err(e) => {
-            if config.general.allow_push_on_error {
-                eprintln!("driftcheck warning: {}", e);
-                return Ok(());
-            }
-            return Err(e);
-        }
+        Err(e) if pass_on_error(&e) => return Ok(()),
+        Err(e) => return Err(e),
     };
 
     if diff.is_empty() {
@@ -245,29 +1119,62 @@ async fn cmd_hook() -> Result<()> {
     }
 
     let issues = match analyzer::analyze(&config, &diff).await {
-        Ok(i) => i,
-        Err(e) => {
-            if config.general.allow_push_on_error {
-                eprintln!("driftcheck warning: {}", e);
-                return Ok(());
-            }
-            return Err(e);
-        }
+        Ok(analysis) => analysis.issues,
+        Err(e) if pass_on_error(&e) => return Ok(()),
+        Err(e) => return Err(e),
     };
+    output::print_usage(&config);
 
     if issues.is_empty() {
         return Ok(());
     }
+    record_run(&config, &range, &diff, &issues);
+
+    // Warn-only issues are shown but never block the push (exit 0); errors exit 1
+    let mut issues = issues;
+    let blocking = issues.iter().filter(|i| i.is_blocking()).count();
+    if action == HookAction::Warn {
+        for issue in &mut issues {
+            issue.severity = analyzer::Severity::Warning;
+        }
+    }
+    if !issues.iter().any(analyzer::Issue::is_blocking) {
+        eprintln!("{}", output::hook_verdict(&issues));
+        let detail = if blocking > 0 {
+            format!(
+                "{} error(s) downgraded to warnings for {}",
+                blocking,
+                target.remote.as_deref().unwrap_or("this remote")
+            )
+        } else {
+            format!("{} warn-only issue(s)", issues.len())
+        };
+        metrics::report_pass_through(PassThrough::Warn, &detail);
+        let hidden = analyzer::cap_issues(&mut issues, config.general.max_issues);
+        output::print_issues(&issues, hidden);
+        return Ok(());
+    }
 
     // We have issues!
     if atty::is(atty::Stream::Stdout) {
-        tui::run(&config, issues).await?;
+        let hidden = analyzer::cap_issues(&mut issues, config.general.max_issues);
+        tui::run(&config, issues, hidden, Some(&diff)).await?;
+    } else if let Some(client) = output::detect_gui_client() {
+        eprintln!("{}", output::hook_verdict(&issues));
+        let report = Config::find_git_root()
+            .and_then(|root| output::write_last_report(&root, &issues))
+            .map_err(|e| debug!("Failed to write report: {}", e))
+            .ok();
+        output::print_gui_summary(client, &issues, report.as_deref());
+        exit(1);
     } else {
-        output::print_issues(&issues);
+        eprintln!("{}", output::hook_verdict(&issues));
+        let hidden = analyzer::cap_issues(&mut issues, config.general.max_issues);
+        output::print_issues(&issues, hidden);
         eprintln!("\nPush blocked. Run `git push` from a terminal to review and fix issues,");
-        eprintln!("or run `driftcheck check` to see details.");
+        eprintln!("or run `driftcheck review --last` to review them without re-analyzing.");
         eprintln!("\nTo bypass (not recommended): git push --no-verify");
-        process::exit(1);
+        exit(1);
     }
 
     Ok(())
@@ -0,0 +1,93 @@
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// Why the hook let a push through without a blocking analysis
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassThrough {
+    /// driftcheck is disabled via config or DRIFTCHECK_DISABLED
+    Disabled,
+    /// No upstream branch, so there is nothing to diff against
+    NoUpstream,
+    /// Analysis failed and `allow_push_on_error` is set
+    ErrorAllowed,
+    /// First run in this clone, skipped or failed under `first_run_policy`
+    FirstRun,
+    /// A `[[hook.remotes]]` entry (or `hook.default_action`) skips pushes to this remote
+    Remote,
+    /// Issues were found but none blocks: all are warn-only, or the remote's action is "warn"
+    Warn,
+}
+
+impl PassThrough {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PassThrough::Disabled => "disabled",
+            PassThrough::NoUpstream => "no_upstream",
+            PassThrough::ErrorAllowed => "error_allowed",
+            PassThrough::FirstRun => "first_run",
+            PassThrough::Remote => "remote",
+            PassThrough::Warn => "warn",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Event<'a> {
+    timestamp: DateTime<Utc>,
+    event: &'static str,
+    reason: PassThrough,
+    detail: &'a str,
+}
+
+fn events_path() -> Result<PathBuf> {
+    let git_root = Config::find_git_root()?;
+    Ok(Config::resolve_in(
+        &git_root,
+        ".git/driftcheck/events.jsonl",
+    ))
+}
+
+/// Emit a one-line summary to stderr and record the pass-through in the event log
+pub fn report_pass_through(reason: PassThrough, detail: &str) {
+    eprintln!(
+        "driftcheck: pass-through reason={} blocking=false detail={:?}",
+        reason.as_str(),
+        detail
+    );
+
+    if let Err(e) = record(reason, detail) {
+        debug!("Failed to record pass-through: {}", e);
+    }
+}
+
+fn record(reason: PassThrough, detail: &str) -> Result<()> {
+    Config::ensure_writable("recording metrics")?;
+
+    let path = events_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    }
+
+    let event = Event {
+        timestamp: Utc::now(),
+        event: "pass_through",
+        reason,
+        detail,
+    };
+    let line =
+        serde_json::to_string(&event).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    writeln!(file, "{}", line).map_err(|e| DriftcheckError::CacheError(e.to_string()))
+}
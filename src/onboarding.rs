@@ -0,0 +1,74 @@
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+
+/// LLM timeout (seconds) for the first hook run in a clone, so an unfamiliar tool never stalls a push for long
+pub const FIRST_RUN_TIMEOUT: u64 = 15;
+
+/// How the hook behaves the first time it runs in a clone (`general.first_run_policy`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirstRunPolicy {
+    /// Explain what driftcheck does, use a shorter timeout and never block on errors
+    Guarded,
+    /// Behave like any other run
+    Normal,
+    /// Let the push through without analyzing it
+    Skip,
+}
+
+impl FirstRunPolicy {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.general.first_run_policy.as_str() {
+            "guarded" => Ok(FirstRunPolicy::Guarded),
+            "normal" => Ok(FirstRunPolicy::Normal),
+            "skip" => Ok(FirstRunPolicy::Skip),
+            other => Err(DriftcheckError::ConfigInvalid(format!(
+                "general.first_run_policy must be \"guarded\", \"normal\" or \"skip\", got {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Whether driftcheck has never run in this clone: no issue history, events or cache yet
+pub fn is_first_run(config: &Config) -> bool {
+    !config.resolve(".git/driftcheck").exists() && !config.resolve(&config.cache.dir).exists()
+}
+
+/// Tell a contributor who may never have heard of driftcheck why it is running
+pub fn print_welcome(config: &Config) {
+    eprintln!("driftcheck: first run in this clone.");
+    eprintln!("  driftcheck checks that the documentation still matches the code you push,");
+    eprintln!(
+        "  by sending the diff and related docs to {} (configured in .driftcheck.toml).",
+        config.llm.model
+    );
+    eprintln!(
+        "  This run uses a {}s timeout and lets the push through if the analysis cannot run.",
+        config.llm.timeout.min(FIRST_RUN_TIMEOUT)
+    );
+    eprintln!("  Set DRIFTCHECK_DISABLED=1 to skip it.");
+    eprintln!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_run_policy() {
+        let mut config = Config::default();
+        assert_eq!(
+            FirstRunPolicy::from_config(&config).unwrap(),
+            FirstRunPolicy::Guarded
+        );
+
+        config.general.first_run_policy = "skip".to_string();
+        assert_eq!(
+            FirstRunPolicy::from_config(&config).unwrap(),
+            FirstRunPolicy::Skip
+        );
+
+        config.general.first_run_policy = "lenient".to_string();
+        assert!(FirstRunPolicy::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::analyzer::{Issue, Severity};
+use console::Style;
+use std::path::Path;
+
+/// Prefix of annotation lines; not a valid diff line, so diff viewers show it as-is
+const PREFIX: &str = "#";
+
+/// Render `diff` with a comment block under the line each issue's code location points at
+/// (or at the end of its hunk). Issues without a location in the diff are listed at the
+/// top. With `color`, blocks are red for errors and yellow for warnings.
+pub fn render(diff: &str, issues: &[Issue], color: bool) -> String {
+    let mut placed = vec![false; issues.len()];
+    let mut body = String::new();
+
+    let mut file = String::new();
+    // Issues of the current hunk not yet written, and the next new-file line number
+    let mut pending: Vec<usize> = Vec::new();
+    let mut new_line = 0;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") || line.starts_with("@@") {
+            flush(&mut body, &mut pending, issues, color);
+        }
+
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = path.strip_prefix("b/").unwrap_or(path).to_string();
+        } else if let Some((start, count)) = hunk_range(line) {
+            new_line = start;
+            for (i, issue) in issues.iter().enumerate() {
+                let Some(code) = &issue.code_location else {
+                    continue;
+                };
+                if !placed[i]
+                    && code.file == Path::new(&file)
+                    && (start..start + count.max(1)).contains(&code.line)
+                {
+                    placed[i] = true;
+                    pending.push(i);
+                }
+            }
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        }
+
+        body.push_str(line);
+        body.push('\n');
+
+        if !pending.is_empty() && (line.starts_with(' ') || line.starts_with('+')) {
+            let (here, later): (Vec<usize>, Vec<usize>) = pending.iter().partition(|&&i| {
+                issues[i].code_location.as_ref().map(|c| c.line) == Some(new_line)
+            });
+            pending = later;
+            for i in here {
+                push_block(&mut body, &issues[i], color);
+            }
+            new_line += 1;
+        }
+    }
+    flush(&mut body, &mut pending, issues, color);
+
+    let mut out = String::new();
+    let unplaced: Vec<&Issue> = issues
+        .iter()
+        .zip(&placed)
+        .filter(|(_, placed)| !**placed)
+        .map(|(issue, _)| issue)
+        .collect();
+    if !unplaced.is_empty() {
+        out.push_str(&format!(
+            "{} driftcheck: {} issue(s) not tied to a changed line\n",
+            PREFIX,
+            unplaced.len()
+        ));
+        for issue in unplaced {
+            push_block(&mut out, issue, color);
+        }
+        out.push('\n');
+    }
+    out.push_str(&body);
+    out
+}
+
+/// Start and line count of the new side of a hunk header (`@@ -a,b +c,d @@`)
+fn hunk_range(line: &str) -> Option<(usize, usize)> {
+    let new = line
+        .strip_prefix("@@ ")?
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))?;
+    let mut parts = new.splitn(2, ',');
+    let start = parts.next()?.parse().ok()?;
+    let count = parts.next().map_or(Some(1), |c| c.parse().ok())?;
+    Some((start, count))
+}
+
+fn flush(out: &mut String, pending: &mut Vec<usize>, issues: &[Issue], color: bool) {
+    for i in pending.drain(..) {
+        push_block(out, &issues[i], color);
+    }
+}
+
+fn push_block(out: &mut String, issue: &Issue, color: bool) {
+    let style = match issue.severity {
+        Severity::Error => Style::new().red(),
+        Severity::Warning => Style::new().yellow(),
+    }
+    .force_styling(color);
+
+    let mut lines = vec![format!(
+        "{} driftcheck {}: {}:{}: {}",
+        PREFIX,
+        issue.severity,
+        issue.file.display(),
+        issue.line,
+        issue.description
+    )];
+    if let Some(excerpt) = issue.doc_excerpt.lines().find(|l| !l.trim().is_empty()) {
+        lines.push(format!("{}   Docs say: {}", PREFIX, excerpt.trim()));
+    }
+    if let Some(fix) = &issue.suggested_fix {
+        lines.push(format!("{}   Suggested fix: {}", PREFIX, fix));
+    }
+    for line in lines {
+        out.push_str(&style.apply_to(line).to_string());
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::CodeLocation;
+    use crate::llm::RawIssue;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_render_annotated_diff() {
+        let diff = "diff --git a/src/cli.rs b/src/cli.rs\n--- a/src/cli.rs\n+++ b/src/cli.rs\n@@ -10,3 +10,3 @@ fn args()\n     let a = 1;\n-    flag(\"--no-tui\");\n+    flag(\"--plain\");\n     let b = 2;\n";
+        let issue = |description: &str, line: Option<usize>| Issue {
+            code_location: line.map(|line| CodeLocation {
+                file: PathBuf::from("src/cli.rs"),
+                line,
+                symbol: "--plain".to_string(),
+            }),
+            ..Issue::from(RawIssue {
+                file: "README.md".to_string(),
+                line: 7,
+                description: description.to_string(),
+                doc_excerpt: "Run with --no-tui\n".to_string(),
+                suggested_fix: None,
+                confidence: None,
+                code_symbol: None,
+            })
+        };
+
+        let out = render(
+            diff,
+            &[issue("renamed flag", Some(11)), issue("elsewhere", None)],
+            false,
+        );
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            "# driftcheck: 1 issue(s) not tied to a changed line"
+        );
+        assert_eq!(lines[1], "# driftcheck error: README.md:7: elsewhere");
+        let flag = lines
+            .iter()
+            .position(|l| *l == "+    flag(\"--plain\");")
+            .unwrap();
+        assert_eq!(
+            lines[flag + 1],
+            "# driftcheck error: README.md:7: renamed flag"
+        );
+        assert_eq!(lines[flag + 2], "#   Docs say: Run with --no-tui");
+        assert!(!out.contains('\u{1b}'));
+
+        assert!(render(diff, &[issue("renamed flag", Some(11))], true).contains('\u{1b}'));
+    }
+}
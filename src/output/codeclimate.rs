@@ -0,0 +1,72 @@
+use super::sarif;
+use crate::analyzer::{Issue, Severity};
+use crate::feedback;
+use crate::paths;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Render issues as a GitLab Code Quality report (a subset of the Code Climate format),
+/// so they show up in the merge request widget. Issue paths are resolved against `root`.
+pub fn render(issues: &[Issue], root: &Path) -> String {
+    let report: Vec<Value> = issues.iter().map(|issue| entry(issue, root)).collect();
+
+    // Only plain data, so serialization cannot fail
+    let mut out = serde_json::to_string_pretty(&report).unwrap_or_default();
+    out.push('\n');
+    out
+}
+
+fn entry(issue: &Issue, root: &Path) -> Value {
+    let mut description = issue.description.clone();
+    if let Some(code) = &issue.code_location {
+        description.push_str(&format!(" Code: {} ({}).", code, code.symbol));
+    }
+    if let Some(fix) = &issue.suggested_fix {
+        description.push_str(&format!(" Suggested fix: {}", fix));
+    }
+
+    json!({
+        "type": "issue",
+        "check_name": format!("driftcheck/{}", feedback::categorize(&issue.description)),
+        "description": description,
+        "categories": ["Documentation"],
+        // GitLab matches issues across pipelines by fingerprint, which ignores line shifts
+        "fingerprint": issue.fingerprint,
+        "severity": match issue.severity {
+            Severity::Error => "major",
+            Severity::Warning => "minor",
+        },
+        "location": {
+            "path": paths::to_slash(&issue.file),
+            "lines": { "begin": sarif::start_line(issue, root).unwrap_or(1) },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    #[test]
+    fn test_render() {
+        let issue = Issue::from(RawIssue {
+            file: "docs/usage.md".to_string(),
+            line: 7,
+            description: "The --no-tui flag was renamed".to_string(),
+            doc_excerpt: String::new(),
+            suggested_fix: None,
+            confidence: None,
+            code_symbol: None,
+        });
+        let fingerprint = issue.fingerprint.clone();
+
+        let report: Value = serde_json::from_str(&render(&[issue], Path::new(""))).unwrap();
+        let entry = &report[0];
+        assert_eq!(entry["check_name"], "driftcheck/cli");
+        assert_eq!(entry["fingerprint"], fingerprint.as_str());
+        assert_eq!(entry["severity"], "major");
+        assert_eq!(entry["location"]["path"], "docs/usage.md");
+        assert_eq!(entry["location"]["lines"]["begin"], 7);
+    }
+}
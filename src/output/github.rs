@@ -0,0 +1,111 @@
+use super::sarif;
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::feedback;
+use crate::paths;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+/// Render issues as GitHub Actions workflow commands (`::error file=...,line=...::message`),
+/// which show up as annotations on the PR diff. Warnings use `::warning`.
+pub fn render_annotations(issues: &[Issue], root: &Path) -> String {
+    let mut out = String::new();
+
+    for issue in issues {
+        let level = if issue.is_blocking() {
+            "error"
+        } else {
+            "warning"
+        };
+        let file = paths::to_slash(&issue.file);
+        let mut properties = format!("file={}", escape_property(&file));
+        if let Some(line) = sarif::start_line(issue, root) {
+            properties.push_str(&format!(",line={}", line));
+        }
+        properties.push_str(&format!(
+            ",title={}",
+            escape_property(&format!(
+                "Documentation drift ({})",
+                feedback::categorize(&issue.description).replace('_', " ")
+            ))
+        ));
+
+        let mut message = issue.description.clone();
+        if let Some(code) = &issue.code_location {
+            message.push_str(&format!("\n\nCode: {} ({})", code, code.symbol));
+        }
+        if let Some(fix) = &issue.suggested_fix {
+            message.push_str(&format!("\n\nSuggested fix: {}", fix));
+        }
+
+        out.push_str(&format!(
+            "::{} {}::{}\n",
+            level,
+            properties,
+            escape_data(&message)
+        ));
+    }
+
+    out
+}
+
+/// Append `summary` to the job summary file in `$GITHUB_STEP_SUMMARY`, if set
+pub fn write_step_summary(summary: &str) -> Result<()> {
+    let Some(path) = env::var_os("GITHUB_STEP_SUMMARY").filter(|p| !p.is_empty()) else {
+        return Ok(());
+    };
+    Config::ensure_writable("writing the job summary")?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+    file.write_all(summary.as_bytes())
+        .map_err(|e| DriftcheckError::ReportError(e.to_string()))
+}
+
+/// Whether driftcheck runs inside GitHub Actions
+pub fn is_github_actions() -> bool {
+    env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+/// Escape a workflow command message
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a workflow command property value, which additionally ends at `:` or `,`
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    #[test]
+    fn test_render_annotations() {
+        let issue = Issue::from(RawIssue {
+            file: "docs/usage.md".to_string(),
+            line: 7,
+            description: "The --no-tui flag was renamed, 100% of examples break".to_string(),
+            doc_excerpt: String::new(),
+            suggested_fix: Some("Use --plain".to_string()),
+            confidence: None,
+            code_symbol: None,
+        });
+
+        assert_eq!(
+            render_annotations(&[issue], Path::new("")),
+            "::error file=docs/usage.md,line=7,title=Documentation drift (cli)::\
+The --no-tui flag was renamed, 100%25 of examples break%0A%0ASuggested fix: Use --plain\n"
+        );
+    }
+}
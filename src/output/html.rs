@@ -0,0 +1,329 @@
+use super::ReportMeta;
+use crate::analyzer::Issue;
+use crate::patch;
+use similar::{ChangeTag, TextDiff};
+
+/// Share of words a suggested fix must have in common with the excerpt to be shown as its
+/// replacement
+const MIN_REPLACEMENT_SIMILARITY: f32 = 0.5;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2rem auto; max-width: 1100px; color: #1f2328; padding: 0 1rem; }
+h1 { font-size: 1.6rem; margin-bottom: 0.25rem; }
+table.meta { border-collapse: collapse; margin: 1rem 0 2rem; font-size: 0.9rem; }
+table.meta td { padding: 0.2rem 1rem 0.2rem 0; }
+table.meta td:first-child { color: #656d76; }
+table.risk { border-collapse: collapse; margin: 0 0 2rem; font-size: 0.9rem; }
+table.risk th, table.risk td { text-align: left; padding: 0.2rem 1rem 0.2rem 0; }
+table.risk th { color: #656d76; font-weight: normal; }
+details { border: 1px solid #d0d7de; border-radius: 6px; margin-bottom: 1rem; }
+summary { cursor: pointer; padding: 0.6rem 1rem; background: #f6f8fa; font-weight: 600; }
+summary code { font-weight: normal; }
+.body { padding: 0.5rem 1rem 1rem; }
+.badge { font-size: 0.75rem; text-transform: uppercase; padding: 0.1rem 0.4rem; border-radius: 1rem; background: #eaeef2; color: #656d76; }
+.badge.new { background: #fff8c5; color: #9a6700; }
+.badge.warning { background: #ddf4ff; color: #0969da; }
+.empty { color: #1a7f37; font-weight: 600; }
+table.diff { width: 100%; border-collapse: collapse; table-layout: fixed; font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: 0.85rem; }
+table.diff th { text-align: left; color: #656d76; font-weight: normal; padding: 0.3rem 0.5rem; border-bottom: 1px solid #d0d7de; }
+table.diff td { vertical-align: top; padding: 0.1rem 0.5rem; white-space: pre-wrap; word-break: break-word; }
+td.del { background: #ffebe9; }
+td.ins { background: #e6ffec; }
+pre.excerpt { background: #f6f8fa; border-radius: 6px; padding: 0.5rem; white-space: pre-wrap; word-break: break-word; font-size: 0.85rem; }
+.label { color: #656d76; font-size: 0.9rem; margin-bottom: 0.25rem; }
+"#;
+
+/// Render a standalone, self-contained HTML report
+pub fn render(issues: &[Issue], meta: &ReportMeta) -> String {
+    let mut out = String::new();
+
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>driftcheck report</title>\n");
+    out.push_str(&format!("<style>{}</style>\n", STYLE));
+    out.push_str("</head>\n<body>\n<h1>driftcheck report</h1>\n");
+
+    out.push_str("<table class=\"meta\">\n");
+    for (key, value) in [
+        ("Generated", meta.generated_at.to_rfc3339()),
+        ("Range", meta.range.clone()),
+        ("Model", meta.model.clone()),
+        ("driftcheck", meta.version.to_string()),
+        ("Issues", issues.len().to_string()),
+    ] {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            key,
+            escape(&value)
+        ));
+    }
+    out.push_str("</table>\n");
+
+    if !meta.risk.is_empty() {
+        out.push_str("<h2>Drift risk</h2>\n<table class=\"risk\">\n");
+        out.push_str(
+            "<tr><th>File</th><th>Score</th><th>API changes</th><th>Doc references</th><th>Past issues</th></tr>\n",
+        );
+        for r in &meta.risk {
+            out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(&r.file),
+                r.score,
+                r.api_changes,
+                r.doc_references,
+                r.past_drift
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    if issues.is_empty() {
+        out.push_str("<p class=\"empty\">No documentation issues detected.</p>\n");
+    }
+
+    for (i, issue) in issues.iter().enumerate() {
+        out.push_str(&format!(
+            "<details open>\n<summary>Issue {}: <code>{}:{}</code> <span class=\"badge {}\">{}</span>{}</summary>\n<div class=\"body\">\n",
+            i + 1,
+            escape(&issue.file.display().to_string()),
+            issue.line,
+            issue.state,
+            issue.state,
+            if issue.is_blocking() {
+                ""
+            } else {
+                " <span class=\"badge warning\">warning</span>"
+            }
+        ));
+        out.push_str(&format!("<p>{}</p>\n", escape(&issue.description)));
+        if let Some(code) = &issue.code_location {
+            out.push_str(&format!(
+                "<p class=\"code\">Code: <code>{}</code> (<code>{}</code>)</p>\n",
+                escape(&code.to_string()),
+                escape(&code.symbol)
+            ));
+        }
+
+        out.push_str(&render_fix(issue));
+
+        out.push_str("</div>\n</details>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// The generated fix as side-by-side diffs, one per hunk; else the suggestion as a diff when
+/// it rewrites the excerpt, or the excerpt followed by the suggestion as prose
+fn render_fix(issue: &Issue) -> String {
+    if let Some(patch) = &issue.fix_patch {
+        let hunks = patch_hunks(patch);
+        if !hunks.is_empty() {
+            return hunks
+                .iter()
+                .map(|(before, after)| render_diff(before, after))
+                .collect();
+        }
+    }
+
+    let excerpt = issue.doc_excerpt.trim_end();
+    let suggestion = issue.suggested_fix.as_deref().map(str::trim);
+    if let Some(fix) = suggestion.filter(|fix| is_replacement(excerpt, fix)) {
+        return render_diff(&format!("{}\n", excerpt), &format!("{}\n", fix));
+    }
+
+    let mut out = String::new();
+    if !excerpt.is_empty() {
+        out.push_str(&format!(
+            "<p class=\"label\">Documentation says</p>\n<pre class=\"excerpt\">{}</pre>\n",
+            escape(excerpt)
+        ));
+    }
+    if let Some(fix) = suggestion.filter(|fix| !fix.is_empty()) {
+        out.push_str(&format!(
+            "<p><strong>Suggestion:</strong> {}</p>\n",
+            escape(fix)
+        ));
+    }
+    out
+}
+
+/// Whether a suggested fix is replacement text for the excerpt rather than advice about it:
+/// most of its words are the excerpt's
+fn is_replacement(excerpt: &str, fix: &str) -> bool {
+    !excerpt.is_empty()
+        && fix != excerpt
+        && TextDiff::from_words(excerpt, fix).ratio() >= MIN_REPLACEMENT_SIMILARITY
+}
+
+/// Old and new text of each hunk of a unified diff, which may touch several files
+fn patch_hunks(patch: &str) -> Vec<(String, String)> {
+    let files = patch::split_file_diffs(patch);
+    let sections: Vec<&str> = if files.is_empty() {
+        vec![patch]
+    } else {
+        files.iter().map(|(_, diff)| diff.as_str()).collect()
+    };
+
+    let mut hunks: Vec<(String, String)> = Vec::new();
+    for section in sections {
+        hunks.extend(file_hunks(section));
+    }
+    hunks.retain(|(before, after)| before != after);
+    hunks
+}
+
+/// Old and new text of each hunk of a single file's diff; header lines before the first hunk
+/// are skipped
+fn file_hunks(diff: &str) -> Vec<(String, String)> {
+    let mut hunks: Vec<(String, String)> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            hunks.push((String::new(), String::new()));
+            continue;
+        }
+        let Some((before, after)) = hunks.last_mut() else {
+            continue;
+        };
+        if let Some(text) = line.strip_prefix('-') {
+            before.push_str(&format!("{}\n", text));
+        } else if let Some(text) = line.strip_prefix('+') {
+            after.push_str(&format!("{}\n", text));
+        } else if let Some(text) = line.strip_prefix(' ').or(line.is_empty().then_some("")) {
+            before.push_str(&format!("{}\n", text));
+            after.push_str(&format!("{}\n", text));
+        }
+    }
+    hunks
+}
+
+/// Render a side-by-side line diff between the doc excerpt and the suggested fix
+fn render_diff(before: &str, after: &str) -> String {
+    let mut out = String::from(
+        "<table class=\"diff\">\n<tr><th>Documentation says</th><th>Suggested fix</th></tr>\n",
+    );
+
+    let diff = TextDiff::from_lines(before, after);
+    let mut deleted: Vec<String> = Vec::new();
+    let mut inserted: Vec<String> = Vec::new();
+
+    for change in diff.iter_all_changes() {
+        let line = change.value().trim_end_matches('\n').to_string();
+        match change.tag() {
+            ChangeTag::Delete => deleted.push(line),
+            ChangeTag::Insert => inserted.push(line),
+            ChangeTag::Equal => {
+                flush_rows(&mut out, &mut deleted, &mut inserted);
+                let line = escape(&line);
+                out.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", line, line));
+            }
+        }
+    }
+    flush_rows(&mut out, &mut deleted, &mut inserted);
+
+    out.push_str("</table>\n");
+    out
+}
+
+/// Pair up pending deletions and insertions into side-by-side rows
+fn flush_rows(out: &mut String, deleted: &mut Vec<String>, inserted: &mut Vec<String>) {
+    let rows = deleted.len().max(inserted.len());
+    for i in 0..rows {
+        let cell = |lines: &[String], class: &str| match lines.get(i) {
+            Some(line) => format!("<td class=\"{}\">{}</td>", class, escape(line)),
+            None => "<td></td>".to_string(),
+        };
+        out.push_str(&format!(
+            "<tr>{}{}</tr>\n",
+            cell(deleted, "del"),
+            cell(inserted, "ins")
+        ));
+    }
+    deleted.clear();
+    inserted.clear();
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    fn issue(excerpt: &str, fix: Option<&str>) -> Issue {
+        Issue::from(RawIssue {
+            file: "README.md".to_string(),
+            line: 3,
+            description: "flag renamed".to_string(),
+            doc_excerpt: excerpt.to_string(),
+            suggested_fix: fix.map(str::to_string),
+            confidence: None,
+            code_symbol: None,
+        })
+    }
+
+    #[test]
+    fn test_render_fix() {
+        // No fix: the excerpt alone, never a diff that looks like "no change needed"
+        let out = render_fix(&issue("Run with --no-tui\n", None));
+        assert!(!out.contains("table class=\"diff\""));
+        assert!(out.contains("<pre class=\"excerpt\">Run with --no-tui</pre>"));
+
+        // Advice is printed as text
+        let out = render_fix(&issue(
+            "Run with --no-tui\n",
+            Some("Mention the new --plain flag instead."),
+        ));
+        assert!(!out.contains("table class=\"diff\""));
+        assert!(out.contains("<strong>Suggestion:</strong> Mention the new --plain flag instead."));
+
+        // Replacement text is diffed against the excerpt
+        let out = render_fix(&issue(
+            "Run driftcheck with --no-tui to skip the interface\n",
+            Some("Run driftcheck with --plain to skip the interface"),
+        ));
+        assert!(out.contains("<td class=\"del\">Run driftcheck with --no-tui"));
+        assert!(out.contains("<td class=\"ins\">Run driftcheck with --plain"));
+
+        // A generated patch wins over the suggestion
+        let out = render_fix(&Issue {
+            fix_patch: Some(
+                "--- a/README.md\n+++ b/README.md\n@@ -3,2 +3,2 @@\n Usage:\n-Run with --no-tui\n+Run with --plain\n"
+                    .to_string(),
+            ),
+            ..issue("Run with --no-tui\n", Some("Use the new flag."))
+        });
+        assert!(out.contains("<td class=\"ins\">Run with --plain</td>"));
+        assert!(!out.contains("Suggestion:"));
+    }
+
+    #[test]
+    fn test_patch_hunks_across_files() {
+        let patch = "diff --git a/README.md b/README.md\n--- a/README.md\n+++ b/README.md\n\
+                     @@ -3,1 +3,1 @@\n-Run with --no-tui\n+Run with --plain\n\
+                     diff --git a/docs/cli.md b/docs/cli.md\n--- a/docs/cli.md\n+++ b/docs/cli.md\n\
+                     @@ -7,1 +7,1 @@\n-`--no-tui`\n+`--plain`\n";
+        assert_eq!(
+            patch_hunks(patch),
+            vec![
+                (
+                    "Run with --no-tui\n".to_string(),
+                    "Run with --plain\n".to_string()
+                ),
+                ("`--no-tui`\n".to_string(), "`--plain`\n".to_string()),
+            ]
+        );
+    }
+}
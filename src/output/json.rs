@@ -0,0 +1,134 @@
+use super::ReportMeta;
+use crate::analyzer::{CodeLocation, Issue, Severity};
+use crate::consensus::ProviderIssues;
+use crate::history::IssueState;
+use crate::llm::capture::Exchange;
+use crate::llm::usage::RunUsage;
+use crate::llm::DocKind;
+use crate::paths;
+use crate::risk::FileRisk;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Report<'a> {
+    generated_at: DateTime<Utc>,
+    range: &'a str,
+    model: &'a str,
+    version: &'static str,
+    issues: Vec<IssueEntry<'a>>,
+    /// Drift risk of each changed source file, riskiest first
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    risk: &'a [FileRisk],
+    /// LLM calls, tokens and estimated cost of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<&'a RunUsage>,
+    /// Issues each provider found before merging, with `[llm.consensus]`
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    consensus: &'a [ProviderIssues],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exchanges: Option<&'a [Exchange]>,
+}
+
+#[derive(Serialize)]
+struct IssueEntry<'a> {
+    file: String,
+    line: usize,
+    description: &'a str,
+    doc_excerpt: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    full_excerpt: Option<&'a str>,
+    suggested_fix: Option<&'a str>,
+    confidence: Option<f32>,
+    kind: DocKind,
+    fingerprint: &'a str,
+    state: IssueState,
+    severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    corpus: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix_patch: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_location: Option<&'a CodeLocation>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    snooze_expired: bool,
+}
+
+/// Render issues as a JSON report.
+/// `exchanges` holds the captured prompts and responses when `--capture-prompts` is set.
+pub fn render(issues: &[Issue], meta: &ReportMeta, exchanges: Option<&[Exchange]>) -> String {
+    let report = Report {
+        generated_at: meta.generated_at,
+        range: &meta.range,
+        model: &meta.model,
+        version: meta.version,
+        issues: issues
+            .iter()
+            .map(|issue| IssueEntry {
+                file: paths::to_slash(&issue.file),
+                line: issue.line,
+                description: &issue.description,
+                doc_excerpt: &issue.doc_excerpt,
+                full_excerpt: issue.full_excerpt.as_deref(),
+                suggested_fix: issue.suggested_fix.as_deref(),
+                confidence: issue.confidence,
+                kind: issue.kind,
+                fingerprint: &issue.fingerprint,
+                state: issue.state,
+                severity: issue.severity,
+                corpus: issue.corpus.as_deref(),
+                fix_patch: issue.fix_patch.as_deref(),
+                code_location: issue.code_location.as_ref(),
+                snooze_expired: issue.snooze_expired,
+            })
+            .collect(),
+        risk: &meta.risk,
+        usage: meta.usage.as_ref(),
+        consensus: &meta.consensus,
+        exchanges,
+    };
+
+    // Only plain data, so serialization cannot fail
+    let mut out = serde_json::to_string_pretty(&report).unwrap_or_default();
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    #[test]
+    fn test_render_issue_fields() {
+        let issue = Issue::from(RawIssue {
+            file: "README.md".to_string(),
+            line: 12,
+            description: "Flag was renamed".to_string(),
+            doc_excerpt: "--no-tui".to_string(),
+            suggested_fix: Some("--plain".to_string()),
+            confidence: None,
+            code_symbol: None,
+        });
+        let meta = ReportMeta {
+            generated_at: Utc::now(),
+            range: "@{u}..HEAD".to_string(),
+            model: "gpt-4o".to_string(),
+            version: "0.0.0",
+            root: std::path::PathBuf::new(),
+            risk: Vec::new(),
+            usage: None,
+            consensus: Vec::new(),
+        };
+
+        let value: serde_json::Value =
+            serde_json::from_str(&render(&[issue], &meta, None)).unwrap();
+        let issue = &value["issues"][0];
+        assert_eq!(issue["file"], "README.md");
+        assert_eq!(issue["line"], 12);
+        assert_eq!(issue["doc_excerpt"], "--no-tui");
+        assert_eq!(issue["suggested_fix"], "--plain");
+        assert_eq!(issue["kind"], "markdown");
+        assert!(value.get("exchanges").is_none());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::release::ReleaseReport;
+use similar::TextDiff;
+use std::fs;
+use std::path::Path;
+
+/// Largest fix (in lines) rendered as a one-click suggestion
+const MAX_SUGGESTION_LINES: usize = 10;
+
+/// Minimum similarity between excerpt and fix; below this the fix is likely prose
+const MIN_SUGGESTION_SIMILARITY: f32 = 0.5;
+
+/// A replacement for a line range of a documentation file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub replacement: String,
+}
+
+/// Render issues as a markdown report. Issue paths are resolved against `root`.
+pub fn render(issues: &[Issue], root: &Path) -> String {
+    let mut out = String::new();
+
+    out.push_str("# driftcheck report\n\n");
+    out.push_str(&format!(
+        "{} documentation issue(s) detected.\n",
+        issues.len()
+    ));
+    push_issues(&mut out, issues, root);
+
+    out
+}
+
+/// Render a release readiness report as markdown
+pub fn render_release(report: &ReleaseReport, root: &Path) -> String {
+    let mut out = String::new();
+
+    out.push_str(&format!("# Release readiness: `{}`\n\n", report.range));
+    out.push_str(&format!(
+        "**Status:** {}\n\n",
+        if report.is_ready() {
+            "ready".to_string()
+        } else {
+            format!("not ready ({} issue(s))", report.issues.len())
+        }
+    ));
+    out.push_str(&format!(
+        "{} commit(s), {} changed file(s). Docs checked: {}\n",
+        report.commits.len(),
+        report.changed_files,
+        if report.docs.is_empty() {
+            "none".to_string()
+        } else {
+            report
+                .docs
+                .iter()
+                .map(|d| format!("`{}`", d))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    ));
+    push_issues(&mut out, &report.issues, root);
+
+    out
+}
+
+fn push_issues(out: &mut String, issues: &[Issue], root: &Path) {
+    for (i, issue) in issues.iter().enumerate() {
+        out.push_str(&format!(
+            "\n## Issue {}: `{}:{}` ({}{})\n\n{}\n",
+            i + 1,
+            issue.file.display(),
+            issue.line,
+            issue.state,
+            if issue.is_blocking() { "" } else { ", warning" },
+            issue.description
+        ));
+        if let Some(code) = &issue.code_location {
+            out.push_str(&format!("\n**Code:** `{}` (`{}`)\n", code, code.symbol));
+        }
+
+        if !issue.doc_excerpt.is_empty() {
+            out.push_str("\n**Documentation says:**\n\n");
+            for line in issue.doc_excerpt.lines() {
+                out.push_str(&format!("> {}\n", line));
+            }
+        }
+
+        if let Some(suggestion) = suggestion_for(issue, root) {
+            out.push_str(&format!(
+                "\n**Suggested fix** (`{}` lines {}-{}):\n\n```suggestion\n{}\n```\n",
+                issue.file.display(),
+                suggestion.start_line,
+                suggestion.end_line,
+                suggestion.replacement
+            ));
+        } else if let Some(ref fix) = issue.suggested_fix {
+            out.push_str(&format!("\n**Suggested fix:** {}\n", fix));
+        }
+    }
+}
+
+/// Turn a small, single-hunk fix into a GitHub suggestion anchored to the excerpt's lines.
+/// Returns `None` when the fix reads like prose or the excerpt can't be located.
+pub fn suggestion_for(issue: &Issue, root: &Path) -> Option<Suggestion> {
+    let fix = issue.suggested_fix.as_deref()?.trim_end();
+    if issue.doc_excerpt.trim().is_empty() || fix.lines().count() > MAX_SUGGESTION_LINES {
+        return None;
+    }
+
+    let diff = TextDiff::from_lines(issue.doc_excerpt.trim_end(), fix);
+    if diff.ratio() < MIN_SUGGESTION_SIMILARITY || diff.grouped_ops(0).len() != 1 {
+        return None;
+    }
+
+    let content = fs::read_to_string(Config::resolve_in(root, &issue.file)).ok()?;
+    let (start_line, end_line) = locate_excerpt(&content, &issue.doc_excerpt)?;
+
+    Some(Suggestion {
+        start_line,
+        end_line,
+        replacement: fix.to_string(),
+    })
+}
+
+/// Find the 1-based line range of `excerpt` in `content`, ignoring surrounding whitespace
+pub fn locate_excerpt(content: &str, excerpt: &str) -> Option<(usize, usize)> {
+    let needle: Vec<&str> = excerpt.trim_matches('\n').lines().map(str::trim).collect();
+    if needle.is_empty() {
+        return None;
+    }
+
+    let haystack: Vec<&str> = content.lines().map(str::trim).collect();
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle.as_slice())
+        .map(|i| (i + 1, i + needle.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_excerpt() {
+        let content = "# Title\n\nRun `tool --fast`\nto start.\n";
+        assert_eq!(
+            locate_excerpt(content, "  Run `tool --fast`\nto start."),
+            Some((3, 4))
+        );
+        assert_eq!(locate_excerpt(content, "missing"), None);
+    }
+}
@@ -0,0 +1,510 @@
+pub mod annotate;
+pub mod codeclimate;
+pub mod github;
+pub mod html;
+pub mod json;
+pub mod markdown;
+pub mod sarif;
+
+use crate::analyzer::{DryRun, Explanation, Issue};
+use crate::config::{self, Config};
+use crate::consensus::ProviderIssues;
+use crate::error::{DriftcheckError, Result};
+use crate::llm::capture::Exchange;
+use crate::llm::usage::RunUsage;
+use crate::release::ReleaseReport;
+use crate::risk::FileRisk;
+use crate::sweep::SweepReport;
+use crate::term::{ColorSupport, TermCaps};
+use chrono::{DateTime, Utc};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Metadata about an analysis run, included in reports
+#[derive(Debug, Clone)]
+pub struct ReportMeta {
+    pub generated_at: DateTime<Utc>,
+    pub range: String,
+    pub model: String,
+    pub version: &'static str,
+    /// Repository root that issue paths are relative to
+    pub root: PathBuf,
+    /// Drift risk of each changed source file, riskiest first
+    pub risk: Vec<FileRisk>,
+    /// LLM calls, tokens and estimated cost of the run, if it made any calls
+    pub usage: Option<RunUsage>,
+    /// What each provider found, with `[llm.consensus]`
+    pub consensus: Vec<ProviderIssues>,
+}
+
+impl ReportMeta {
+    /// Metadata of a report on `range`; `risk` and `consensus` are left for the caller to fill
+    /// in from the `analyzer::Analysis` the report is about
+    pub fn new(config: &Config, range: &Option<String>) -> Self {
+        Self {
+            generated_at: Utc::now(),
+            range: range.clone().unwrap_or_else(|| "@{u}..HEAD".to_string()),
+            model: config.llm.task_model(config::LlmTask::Analysis).to_string(),
+            version: env!("CARGO_PKG_VERSION"),
+            root: config.root.clone(),
+            risk: Vec::new(),
+            usage: config.llm.usage.total(&config.llm.pricing),
+            consensus: Vec::new(),
+        }
+    }
+}
+
+/// Print issues in a non-TTY friendly format
+pub fn print_issues(issues: &[Issue], hidden: usize) {
+    let rule = TermCaps::detect().symbols().rule.repeat(72);
+    eprintln!();
+    eprintln!("driftcheck: Documentation drift detected!");
+    eprintln!();
+    eprintln!("{}", rule);
+    eprintln!();
+
+    for (i, issue) in issues.iter().enumerate() {
+        eprintln!(
+            "Issue {} [{}]: {}:{}",
+            i + 1,
+            issue.status_label(),
+            issue.file.display(),
+            issue.line
+        );
+        eprintln!("  {}", issue.description);
+        if let Some(code) = &issue.code_location {
+            eprintln!("  Code: {} ({})", code, code.symbol);
+        }
+        eprintln!("  Fingerprint: {}", issue.fingerprint);
+
+        if !issue.doc_excerpt.is_empty() {
+            eprintln!();
+            eprintln!("  Documentation says:");
+            for line in issue.doc_excerpt.lines().take(5) {
+                eprintln!("    {}", line);
+            }
+        }
+
+        if let Some(ref fix) = issue.suggested_fix {
+            eprintln!();
+            eprintln!("  Suggested fix: {}", fix);
+        }
+
+        eprintln!();
+    }
+
+    if hidden > 0 {
+        eprintln!("{}", overflow_summary(hidden));
+        eprintln!();
+    }
+
+    eprintln!("{}", rule);
+}
+
+/// Print the LLM calls, tokens and estimated cost of the run so far on stderr, e.g.
+/// "driftcheck: 4 LLM calls, 12,340 prompt + 810 completion tokens, ~$0.0024"
+pub fn print_usage(config: &Config) {
+    if let Some(usage) = config.llm.usage.total(&config.llm.pricing) {
+        eprintln!("{}", usage_line(&usage));
+    }
+}
+
+fn usage_line(usage: &RunUsage) -> String {
+    let mut line = format!(
+        "driftcheck: {} LLM call{}, {} prompt + {} completion tokens",
+        usage.calls,
+        if usage.calls == 1 { "" } else { "s" },
+        thousands(usage.prompt_tokens),
+        thousands(usage.completion_tokens)
+    );
+    match usage.cost_usd {
+        Some(cost) => line.push_str(&format!(", ~${:.4}", cost)),
+        None => line.push_str(" (no [llm.pricing] entry for the model)"),
+    }
+    line
+}
+
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Write a rendered report to `path`, or to stdout when no path is given
+pub fn write_report(report: &str, path: Option<&Path>) -> Result<()> {
+    match path {
+        Some(path) => {
+            Config::ensure_writable("writing the report")?;
+            fs::write(path, report).map_err(|e| DriftcheckError::ReportError(e.to_string()))
+        }
+        None => {
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// Print issues as a machine-readable JSON report on stdout
+pub fn print_json(issues: &[Issue], meta: &ReportMeta, exchanges: Option<&[Exchange]>) {
+    print!("{}", json::render(issues, meta, exchanges));
+}
+
+/// Print a release readiness report in a non-TTY friendly format
+pub fn print_release_report(report: &ReleaseReport) {
+    println!("Release readiness: {}", report.range);
+    println!(
+        "  {} commits, {} changed files",
+        report.commits.len(),
+        report.changed_files
+    );
+    if report.docs.is_empty() {
+        println!("  No release docs found (see `release.paths`)");
+    } else {
+        println!("  Docs checked: {}", report.docs.join(", "));
+    }
+    println!();
+
+    if report.is_ready() {
+        println!("Ready: release docs reflect the changes in this range.");
+    } else {
+        println!("Not ready: {} issue(s) found.", report.issues.len());
+        print_issues(&report.issues, 0);
+    }
+}
+
+pub fn print_sweep_report(report: &SweepReport) {
+    println!(
+        "Drift sweep: {} of {} doc sections verified ({} doc files)",
+        report.sampled.len(),
+        report.sections,
+        report.docs
+    );
+    let stale: Vec<_> = report
+        .sampled
+        .iter()
+        .filter(|s| !s.missing.is_empty())
+        .take(10)
+        .collect();
+    if !stale.is_empty() {
+        println!();
+        println!("Sections referring to names not found in the code:");
+        for section in stale {
+            println!(
+                "  {}:{}-{}  {}{}",
+                section.file,
+                section.start_line,
+                section.end_line,
+                section.missing.join(", "),
+                section
+                    .age_days
+                    .map(|days| format!(" (unchanged for {} days)", days))
+                    .unwrap_or_default()
+            );
+        }
+    }
+    println!();
+
+    if report.issues.is_empty() {
+        println!("No stale documentation found in the sampled sections.");
+    } else {
+        println!(
+            "{} issue(s) found, most important first.",
+            report.issues.len()
+        );
+        print_issues(&report.issues, 0);
+    }
+}
+
+/// Summary line for issues dropped by `general.max_issues`
+pub fn overflow_summary(hidden: usize) -> String {
+    format!(
+        "plus {} lower-confidence finding{} (run `driftcheck check --all` to see {})",
+        hidden,
+        if hidden == 1 { "" } else { "s" },
+        if hidden == 1 { "it" } else { "them" }
+    )
+}
+
+/// Print how documentation was selected for the diff (`check --explain-queries`)
+pub fn print_explanation(explanation: &Explanation) {
+    if explanation.changed_files == 0 {
+        println!("No files left to analyze after diff_include/diff_exclude filtering.");
+        return;
+    }
+
+    println!(
+        "Search queries ({}{}):",
+        explanation.queries.len(),
+        if explanation.queries_cached {
+            ", cached"
+        } else {
+            ""
+        }
+    );
+    for query in &explanation.queries {
+        println!();
+        println!("  {:?}", query.query);
+        if query.files.is_empty() {
+            println!("    (no matches)");
+        }
+        for (file, count) in &query.files {
+            println!(
+                "    {} ({} match{})",
+                file,
+                count,
+                if *count == 1 { "" } else { "es" }
+            );
+        }
+    }
+
+    let named = explanation.corpora.len() > 1
+        || explanation
+            .corpora
+            .iter()
+            .any(|c| c.name != config::DEFAULT_CORPUS);
+    let mut truncated = false;
+    for corpus in &explanation.corpora {
+        let selected: Vec<_> = corpus.chunks.iter().filter(|c| c.batch.is_some()).collect();
+        let selected_tokens: usize = selected.iter().map(|c| c.tokens).sum();
+        truncated |= selected.len() < corpus.chunks.len();
+
+        println!();
+        println!(
+            "Chunks{} ({} of {} selected, ~{} tokens in {} batch{} of up to {}):",
+            if named {
+                format!(" in {}", corpus.name)
+            } else {
+                String::new()
+            },
+            selected.len(),
+            corpus.chunks.len(),
+            selected_tokens,
+            corpus.batches,
+            if corpus.batches == 1 { "" } else { "es" },
+            corpus.max_context_tokens
+        );
+        println!();
+        for chunk in &corpus.chunks {
+            println!(
+                "  {} {}:{}-{} ({}, ~{} tokens{})",
+                if chunk.batch.is_some() { "+" } else { "-" },
+                chunk.file,
+                chunk.start_line,
+                chunk.end_line,
+                chunk.kind.as_str(),
+                chunk.tokens,
+                match chunk.batch {
+                    Some(batch) if corpus.batches > 1 => format!(", batch {}", batch),
+                    _ => String::new(),
+                }
+            );
+        }
+    }
+    if truncated {
+        println!();
+        println!(
+            "Chunks marked '-' do not fit in docs.max_batches batches of the corpus token budget \
+             and are not sent to the LLM."
+        );
+    }
+}
+
+/// Print what `check --dry-run` found: the doc selection, then the LLM calls a check would
+/// make with their prompt templates and estimated input tokens
+pub fn print_dry_run(dry_run: &DryRun) {
+    if dry_run.embeddings {
+        println!(
+            "search.mode is \"embeddings\": selecting docs needs embedding API calls, so the dry run \
+             stops here. Use `check --explain-queries` to see the selection."
+        );
+        return;
+    }
+
+    print_explanation(&dry_run.explanation);
+    if dry_run.explanation.changed_files == 0 {
+        return;
+    }
+    if dry_run.heuristic_files > 0 {
+        println!();
+        println!(
+            "Queries for {} file(s) were guessed from names in the diff; a real run asks the model, \
+             so its matches may differ.",
+            dry_run.heuristic_files
+        );
+    }
+
+    println!();
+    println!("LLM calls (dry run, nothing was sent):");
+    println!();
+    let mut calls = 0;
+    let mut tokens = 0;
+    for stage in &dry_run.stages {
+        let sent = stage.calls - stage.cached;
+        calls += sent;
+        tokens += stage.tokens;
+        println!(
+            "  {:<17} prompts.{:<16} {}{} call(s){}, ~{} input tokens",
+            stage.stage,
+            stage.prompt,
+            if stage.conditional { "up to " } else { "" },
+            sent,
+            if stage.cached > 0 {
+                format!(" ({} more cached)", stage.cached)
+            } else {
+                String::new()
+            },
+            stage.tokens
+        );
+    }
+    println!();
+    println!(
+        "Total: up to {} call(s), ~{} input tokens (at about 4 characters per token)",
+        calls, tokens
+    );
+}
+
+/// Detect git GUI clients that run hooks without a TTY and show stderr in a small dialog
+pub fn detect_gui_client() -> Option<&'static str> {
+    if env::var_os("VSCODE_GIT_IPC_HANDLE").is_some()
+        || env::var("TERM_PROGRAM")
+            .map(|v| v == "vscode")
+            .unwrap_or(false)
+    {
+        return Some("VS Code");
+    }
+
+    let bundle = env::var("__CFBundleIdentifier").unwrap_or_default();
+    let askpass = env::var("GIT_ASKPASS").unwrap_or_default();
+
+    if bundle.starts_with("com.DanPristupov.Fork") || askpass.contains("Fork") {
+        return Some("Fork");
+    }
+    if bundle.starts_with("com.fournova.Tower") || askpass.contains("Tower") {
+        return Some("Tower");
+    }
+    if bundle.starts_with("com.torusknot.SourceTree") || askpass.contains("Sourcetree") {
+        return Some("Sourcetree");
+    }
+    if askpass.contains("GitHub Desktop") {
+        return Some("GitHub Desktop");
+    }
+
+    None
+}
+
+/// Write the full report to `.git/driftcheck/last-report.md`
+pub fn write_last_report(git_root: &Path, issues: &[Issue]) -> Result<PathBuf> {
+    Config::ensure_writable("writing the report")?;
+
+    let dir = Config::resolve_in(git_root, ".git/driftcheck");
+    fs::create_dir_all(&dir).map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+
+    let path = dir.join("last-report.md");
+    fs::write(&path, markdown::render(issues, git_root))
+        .map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+
+    Ok(path)
+}
+
+/// Write the analyzed diff with the issues annotated inline to
+/// `.git/driftcheck/last-annotated.diff`, colored unless colors are disabled
+pub fn write_last_annotated(git_root: &Path, diff: &str, issues: &[Issue]) -> Result<PathBuf> {
+    Config::ensure_writable("writing the annotated diff")?;
+
+    let dir = Config::resolve_in(git_root, ".git/driftcheck");
+    fs::create_dir_all(&dir).map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+
+    let path = dir.join("last-annotated.diff");
+    let color = TermCaps::detect().color != ColorSupport::None;
+    fs::write(&path, annotate::render(diff, issues, color))
+        .map_err(|e| DriftcheckError::ReportError(e.to_string()))?;
+
+    Ok(path)
+}
+
+/// One-line outcome printed by the hook before any details,
+/// e.g. "driftcheck: 2 errors, 3 warnings — push blocked"
+pub fn hook_verdict(issues: &[Issue]) -> String {
+    let errors = issues.iter().filter(|i| i.is_blocking()).count();
+    let warnings = issues.len() - errors;
+    verdict(errors, warnings, TermCaps::detect().symbols().dash)
+}
+
+fn verdict(errors: usize, warnings: usize, dash: &str) -> String {
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    format!(
+        "driftcheck: {}, {} {} push {}",
+        plural(errors, "error"),
+        plural(warnings, "warning"),
+        dash,
+        if errors > 0 { "blocked" } else { "allowed" }
+    )
+}
+
+/// Print a compact, single-screen summary for GUI git clients, below the verdict line
+pub fn print_gui_summary(client: &str, issues: &[Issue], report: Option<&Path>) {
+    for issue in issues.iter().take(5) {
+        eprintln!(
+            "  - [{}] {}:{} {}",
+            issue.state,
+            issue.file.display(),
+            issue.line,
+            first_line(&issue.description)
+        );
+    }
+    if issues.len() > 5 {
+        eprintln!("  ... and {} more", issues.len() - 5);
+    }
+
+    match report {
+        Some(path) => eprintln!("Full report: file://{}", path.display()),
+        None => eprintln!("Run `driftcheck check` for details."),
+    }
+    eprintln!(
+        "Push from a terminal to review fixes ({} has no TTY).",
+        client
+    );
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict() {
+        assert_eq!(
+            verdict(2, 1, "-"),
+            "driftcheck: 2 errors, 1 warning - push blocked"
+        );
+        assert_eq!(
+            verdict(0, 3, "-"),
+            "driftcheck: 0 errors, 3 warnings - push allowed"
+        );
+    }
+
+    #[test]
+    fn test_usage_line() {
+        let usage = RunUsage {
+            calls: 4,
+            prompt_tokens: 1_234_567,
+            completion_tokens: 810,
+            cost_usd: Some(0.19),
+            models: Vec::new(),
+        };
+        assert_eq!(
+            usage_line(&usage),
+            "driftcheck: 4 LLM calls, 1,234,567 prompt + 810 completion tokens, ~$0.1900"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use super::markdown;
+use super::ReportMeta;
+use crate::analyzer::{Issue, Severity};
+use crate::config::Config;
+use crate::feedback;
+use crate::paths;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Render issues as a SARIF 2.1.0 log for code scanning integrations.
+/// Each issue category becomes a rule (e.g. `driftcheck/cli`).
+pub fn render(issues: &[Issue], meta: &ReportMeta) -> String {
+    let mut rules: Vec<&str> = Vec::new();
+    for issue in issues {
+        let category = feedback::categorize(&issue.description);
+        if !rules.contains(&category) {
+            rules.push(category);
+        }
+    }
+
+    let log = json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "driftcheck",
+                    "version": meta.version,
+                    "informationUri": "https://github.com/deichrenner/driftcheck",
+                    "rules": rules.iter().map(|category| rule(category)).collect::<Vec<_>>(),
+                }
+            },
+            "properties": {
+                "range": meta.range,
+                "model": meta.model,
+            },
+            "results": issues.iter().map(|issue| result(issue, &meta.root)).collect::<Vec<_>>(),
+        }]
+    });
+
+    // Only plain data, so serialization cannot fail
+    let mut out = serde_json::to_string_pretty(&log).unwrap_or_default();
+    out.push('\n');
+    out
+}
+
+fn rule_id(category: &str) -> String {
+    format!("driftcheck/{}", category)
+}
+
+fn rule(category: &str) -> Value {
+    json!({
+        "id": rule_id(category),
+        "shortDescription": {
+            "text": format!("Documentation drift ({})", category.replace('_', " ")),
+        },
+        "defaultConfiguration": { "level": "error" },
+    })
+}
+
+fn result(issue: &Issue, root: &Path) -> Value {
+    let uri = paths::to_slash(&issue.file);
+
+    let mut location = json!({
+        "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" },
+    });
+    if let Some(line) = start_line(issue, root) {
+        location["region"] = json!({ "startLine": line });
+    }
+
+    let mut message = issue.description.clone();
+    if let Some(fix) = &issue.suggested_fix {
+        message.push_str(&format!("\n\nSuggested fix: {}", fix));
+    }
+
+    let mut result = json!({
+        "ruleId": rule_id(feedback::categorize(&issue.description)),
+        "level": match issue.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        },
+        "message": { "text": message },
+        "locations": [{ "physicalLocation": location }],
+        "partialFingerprints": { "driftcheck/v1": issue.fingerprint },
+    });
+
+    if let Some(corpus) = &issue.corpus {
+        result["properties"]["corpus"] = json!(corpus);
+    }
+    if let Some(fix_patch) = &issue.fix_patch {
+        result["properties"]["fixPatch"] = json!(fix_patch);
+    }
+    if let Some(code) = &issue.code_location {
+        result["relatedLocations"] = json!([{
+            "id": 1,
+            "physicalLocation": {
+                "artifactLocation": {
+                    "uri": paths::to_slash(&code.file),
+                    "uriBaseId": "%SRCROOT%",
+                },
+                "region": { "startLine": code.line },
+            },
+            "message": { "text": format!("Changed code: {}", code.symbol) },
+        }]);
+    }
+
+    if let Some(suggestion) = markdown::suggestion_for(issue, root) {
+        result["fixes"] = json!([{
+            "description": { "text": "Apply driftcheck's suggested fix" },
+            "artifactChanges": [{
+                "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" },
+                "replacements": [{
+                    "deletedRegion": {
+                        "startLine": suggestion.start_line,
+                        "endLine": suggestion.end_line,
+                    },
+                    "insertedContent": { "text": suggestion.replacement },
+                }],
+            }],
+        }]);
+    }
+
+    result
+}
+
+/// SARIF lines are 1-based; fall back to locating the excerpt when the LLM gave no line
+pub(super) fn start_line(issue: &Issue, root: &Path) -> Option<usize> {
+    if issue.line > 0 {
+        return Some(issue.line);
+    }
+    let content = fs::read_to_string(Config::resolve_in(root, &issue.file)).ok()?;
+    markdown::locate_excerpt(&content, &issue.doc_excerpt).map(|(start, _)| start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+    use chrono::Utc;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_render_result() {
+        let issue = Issue::from(RawIssue {
+            file: "docs/usage.md".to_string(),
+            line: 7,
+            description: "The --no-tui flag was renamed".to_string(),
+            doc_excerpt: String::new(),
+            suggested_fix: None,
+            confidence: None,
+            code_symbol: None,
+        });
+        let meta = ReportMeta {
+            generated_at: Utc::now(),
+            range: "@{u}..HEAD".to_string(),
+            model: "gpt-4o".to_string(),
+            version: "0.0.0",
+            root: PathBuf::new(),
+            risk: Vec::new(),
+            usage: None,
+            consensus: Vec::new(),
+        };
+
+        let log: Value = serde_json::from_str(&render(&[issue], &meta)).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "driftcheck/cli");
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "driftcheck/cli");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "docs/usage.md");
+        assert_eq!(location["region"]["startLine"], 7);
+    }
+}
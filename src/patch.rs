@@ -0,0 +1,390 @@
+use crate::error::{DriftcheckError, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How far (in lines) a hunk may have drifted from its header position
+const MAX_HUNK_DRIFT: usize = 200;
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Result of merging a fix into a file that may have changed since the fix was generated
+#[derive(Debug, PartialEq)]
+pub enum Merge {
+    Clean(String),
+    /// Merged content with conflict markers, and the number of conflicts
+    Conflicts(String, usize),
+}
+
+/// Part of a three-way merge: lines both sides agree on, or a conflict between them
+#[derive(Debug, Clone, PartialEq)]
+pub enum Region {
+    Clean(String),
+    Conflict {
+        /// The lines when the fix was generated
+        base: String,
+        /// The lines as edited since
+        current: String,
+        /// The lines as the fix changes them
+        fix: String,
+    },
+}
+
+#[derive(Debug)]
+struct Hunk {
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// Extract every unified diff from an LLM response: each fenced diff, or the whole response
+/// when it has no fenced diff
+pub fn extract_diffs(response: &str) -> Vec<&str> {
+    let mut fenced = vec![];
+    let mut rest = response;
+    while let Some(start) = rest.find("```") {
+        let after = &rest[start + 3..];
+        let body_start = after.find('\n').map(|i| i + 1).unwrap_or(after.len());
+        let body = &after[body_start..];
+        let end = body.find("```").unwrap_or(body.len());
+        if is_unified_diff(&body[..end]) {
+            fenced.push(&body[..end]);
+        }
+        rest = &body[(end + 3).min(body.len())..];
+    }
+
+    if fenced.is_empty() && is_unified_diff(response) {
+        fenced.push(response);
+    }
+    fenced
+}
+
+/// Split a diff touching several files into one diff per file, keyed by the path of its
+/// `+++` header (without the `b/` prefix)
+pub fn split_file_diffs(diff: &str) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = Vec::new();
+    let mut pending_old: Option<&str> = None;
+
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("--- ") && pending_old.is_none() {
+            pending_old = Some(line);
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("+++ ") {
+            let path = path.trim_end();
+            let path = path.strip_prefix("b/").unwrap_or(path);
+            files.push((
+                path.to_string(),
+                format!("{}{}", pending_old.take().unwrap_or_default(), line),
+            ));
+            continue;
+        }
+        if let Some(old) = pending_old.take() {
+            // A removed line that merely starts with "-- "
+            if let Some((_, section)) = files.last_mut() {
+                section.push_str(old);
+            }
+        }
+        if let Some((_, section)) = files.last_mut() {
+            section.push_str(line);
+        }
+    }
+    files
+}
+
+fn is_unified_diff(text: &str) -> bool {
+    let mut has_header = false;
+    let mut has_hunk = false;
+    for line in text.lines() {
+        has_header |= line.starts_with("--- ") || line.starts_with("+++ ");
+        has_hunk |= line.starts_with("@@");
+    }
+    has_header && has_hunk
+}
+
+fn parse_hunks(diff: &str) -> Result<Vec<Hunk>> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            hunks.extend(current.take());
+            current = Some(Hunk {
+                old_start: parse_old_start(line).unwrap_or(1),
+                old: vec![],
+                new: vec![],
+            });
+            continue;
+        }
+
+        let Some(hunk) = current.as_mut() else {
+            // File headers and other preamble
+            continue;
+        };
+
+        if let Some(text) = line.strip_prefix('-') {
+            hunk.old.push(text.to_string());
+        } else if let Some(text) = line.strip_prefix('+') {
+            hunk.new.push(text.to_string());
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
+        } else {
+            // Context line; models often drop the leading space on blank lines
+            let text = line.strip_prefix(' ').unwrap_or(line);
+            hunk.old.push(text.to_string());
+            hunk.new.push(text.to_string());
+        }
+    }
+    hunks.extend(current);
+
+    if hunks.is_empty() {
+        return Err(DriftcheckError::PatchError(
+            "Patch contains no hunks".to_string(),
+        ));
+    }
+    Ok(hunks)
+}
+
+/// Parse the old start line from "@@ -12,7 +12,8 @@"
+fn parse_old_start(header: &str) -> Option<usize> {
+    let old = header.split_whitespace().nth(1)?.strip_prefix('-')?;
+    old.split(',').next()?.parse().ok()
+}
+
+/// Apply a unified diff to `original`.
+/// Hunk headers from LLMs are often off, so each hunk is located by its context
+/// and removed lines, searching outwards from the header position.
+pub fn apply_unified_diff(original: &str, diff: &str) -> Result<String> {
+    let hunks = parse_hunks(diff)?;
+    let mut lines: Vec<String> = original.lines().map(|l| l.to_string()).collect();
+    // Offset between positions in the original and in `lines` as hunks are applied
+    let mut shift: isize = 0;
+    let mut min_pos = 0;
+
+    for hunk in &hunks {
+        let expected = (hunk.old_start.saturating_sub(1) as isize + shift).max(0) as usize;
+        let pos = if hunk.old.is_empty() {
+            expected.min(lines.len())
+        } else {
+            find_hunk(&lines, &hunk.old, expected, min_pos).ok_or_else(|| {
+                DriftcheckError::PatchError(format!(
+                    "Hunk at line {} does not match the file",
+                    hunk.old_start
+                ))
+            })?
+        };
+
+        lines.splice(pos..pos + hunk.old.len(), hunk.new.iter().cloned());
+        shift += hunk.new.len() as isize - hunk.old.len() as isize;
+        min_pos = pos + hunk.new.len();
+    }
+
+    let mut out = lines.join("\n");
+    if original.ends_with('\n') || original.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn find_hunk(lines: &[String], old: &[String], expected: usize, min_pos: usize) -> Option<usize> {
+    let matches_at = |pos: usize| {
+        pos + old.len() <= lines.len()
+            && lines[pos..pos + old.len()]
+                .iter()
+                .zip(old)
+                .all(|(a, b)| a.trim_end() == b.trim_end())
+    };
+
+    (0..=MAX_HUNK_DRIFT).find_map(|offset| {
+        [expected.checked_add(offset), expected.checked_sub(offset)]
+            .into_iter()
+            .flatten()
+            .find(|&pos| pos >= min_pos && matches_at(pos))
+    })
+}
+
+/// Three-way merge a fix (`patched`, derived from `base`) into the file's `current` content
+/// with `git merge-file`, so edits made since the fix was generated are kept
+pub fn merge3(base: &str, current: &str, patched: &str) -> Result<Merge> {
+    if current == base {
+        return Ok(Merge::Clean(patched.to_string()));
+    }
+
+    let (merged, conflicts) = merge_file(base, current, patched, false)?;
+    Ok(match conflicts {
+        0 => Merge::Clean(merged),
+        n => Merge::Conflicts(merged, n),
+    })
+}
+
+/// Three-way merge like `merge3`, split into clean regions and conflicts that keep the base,
+/// current and fix lines apart, for resolving them one by one
+pub fn merge3_regions(base: &str, current: &str, patched: &str) -> Result<Vec<Region>> {
+    if current == base {
+        return Ok(vec![Region::Clean(patched.to_string())]);
+    }
+    let (merged, _) = merge_file(base, current, patched, true)?;
+
+    let mut regions = Vec::new();
+    let mut clean = String::new();
+    // Conflict being read: the sides seen so far, and the one being filled
+    let mut sides: Option<([String; 3], usize)> = None;
+    for line in merged.split_inclusive('\n') {
+        let marker = line.trim_end_matches(['\n', '\r']);
+        match sides.as_mut() {
+            None if marker == "<<<<<<< current" => {
+                if !clean.is_empty() {
+                    regions.push(Region::Clean(std::mem::take(&mut clean)));
+                }
+                sides = Some((Default::default(), 1));
+            }
+            None => clean.push_str(line),
+            Some((_, side)) if marker == "||||||| base" => *side = 0,
+            Some((_, side)) if marker == "=======" => *side = 2,
+            Some(_) if marker == ">>>>>>> fix" => {
+                if let Some(([base, current, fix], _)) = sides.take() {
+                    regions.push(Region::Conflict { base, current, fix });
+                }
+            }
+            Some((text, side)) => text[*side].push_str(line),
+        }
+    }
+    if !clean.is_empty() {
+        regions.push(Region::Clean(clean));
+    }
+    Ok(regions)
+}
+
+/// Run `git merge-file` on the three versions; returns the merged content with conflict
+/// markers (including the base lines with `diff3`) and the number of conflicts
+fn merge_file(base: &str, current: &str, patched: &str, diff3: bool) -> Result<(String, usize)> {
+    let current_path = write_temp("current", current)?;
+    let base_path = write_temp("base", base)?;
+    let patched_path = write_temp("fix", patched)?;
+
+    let output = Command::new("git")
+        .args([
+            "merge-file",
+            "-p",
+            "-L",
+            "current",
+            "-L",
+            "base",
+            "-L",
+            "fix",
+        ])
+        .args(diff3.then_some("--diff3"))
+        .arg(&current_path)
+        .arg(&base_path)
+        .arg(&patched_path)
+        .output();
+
+    for path in [&current_path, &base_path, &patched_path] {
+        let _ = fs::remove_file(path);
+    }
+
+    let output = output.map_err(|e| DriftcheckError::GitError(e.to_string()))?;
+    let merged = String::from_utf8_lossy(&output.stdout).to_string();
+
+    // Exit code is the number of conflicts; negative (>127) on error
+    match output.status.code() {
+        Some(n) if (0..128).contains(&n) => Ok((merged, n as usize)),
+        _ => Err(DriftcheckError::GitError(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )),
+    }
+}
+
+fn write_temp(label: &str, content: &str) -> Result<PathBuf> {
+    let path = std::env::temp_dir().join(format!(
+        "driftcheck-{}-{}-{}",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+        label
+    ));
+    fs::write(&path, content).map_err(|e| DriftcheckError::PatchError(e.to_string()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Usage\n\nRun `driftcheck check`.\n\nTimeout is 30 seconds.\n";
+
+    #[test]
+    fn test_extract_diff_from_fence() {
+        let response = "Here is the patch:\n```diff\n--- a/README.md\n+++ b/README.md\n@@ -5 +5 @@\n-Timeout is 30 seconds.\n+Timeout is 60 seconds.\n```\n";
+        assert!(extract_diffs(response)[0].starts_with("--- a/README.md"));
+        assert!(extract_diffs(DOC).is_empty());
+    }
+
+    #[test]
+    fn test_split_file_diffs() {
+        let response = "```diff\n--- a/README.md\n+++ b/README.md\n@@ -1 +1 @@\n-a\n+b\n```\n\
+```diff\n--- a/docs/tutorial.md\n+++ b/docs/tutorial.md\n@@ -3 +3 @@\n--- old\n+b\n```\n";
+        let files: Vec<(String, String)> = extract_diffs(response)
+            .into_iter()
+            .flat_map(split_file_diffs)
+            .collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "README.md");
+        assert_eq!(files[1].0, "docs/tutorial.md");
+        assert!(files[1].1.ends_with("@@ -3 +3 @@\n--- old\n+b\n"));
+    }
+
+    #[test]
+    fn test_apply_with_wrong_line_numbers() {
+        let diff = "--- a/README.md\n+++ b/README.md\n@@ -40,3 +40,3 @@\n \n-Timeout is 30 seconds.\n+Timeout is 60 seconds.\n";
+        let patched = apply_unified_diff(DOC, diff).unwrap();
+        assert_eq!(
+            patched,
+            "# Usage\n\nRun `driftcheck check`.\n\nTimeout is 60 seconds.\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_rejects_mismatched_hunk() {
+        let diff =
+            "--- a/README.md\n+++ b/README.md\n@@ -1 +1 @@\n-Not in the file\n+Replacement\n";
+        assert!(apply_unified_diff(DOC, diff).is_err());
+    }
+
+    #[test]
+    fn test_merge3_keeps_unrelated_edits() {
+        let patched = DOC.replace("30", "60");
+        let current = DOC.replace("# Usage", "# Getting started");
+        let merged = merge3(DOC, &current, &patched).unwrap();
+        assert_eq!(
+            merged,
+            Merge::Clean(
+                "# Getting started\n\nRun `driftcheck check`.\n\nTimeout is 60 seconds.\n"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge3_reports_conflicts() {
+        let patched = DOC.replace("30", "60");
+        let current = DOC.replace("30", "45");
+        assert!(matches!(
+            merge3(DOC, &current, &patched).unwrap(),
+            Merge::Conflicts(_, 1)
+        ));
+
+        let regions = merge3_regions(DOC, &current, &patched).unwrap();
+        assert_eq!(
+            regions,
+            vec![
+                Region::Clean("# Usage\n\nRun `driftcheck check`.\n\n".to_string()),
+                Region::Conflict {
+                    base: "Timeout is 30 seconds.\n".to_string(),
+                    current: "Timeout is 45 seconds.\n".to_string(),
+                    fix: "Timeout is 60 seconds.\n".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+
+/// Windows verbatim prefix of canonicalized paths, which globs don't understand
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+/// `path` with `/` separators, as git, doc globs and reports use on every platform.
+/// A Windows verbatim prefix (`\\?\C:\repo`) is dropped.
+pub fn to_slash(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    let text = text.strip_prefix(VERBATIM_PREFIX).unwrap_or(&text);
+    text.replace('\\', "/")
+}
+
+/// `to_slash` as a path, for paths that are compared or hashed: Windows accepts `/` too
+pub fn normalize(path: &Path) -> PathBuf {
+    PathBuf::from(to_slash(path))
+}
+
+/// A glob (rules, diff filters) with `/` separators; glob patterns never use `\`
+pub fn slash_pattern(pattern: &str) -> String {
+    pattern.replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_slash() {
+        assert_eq!(
+            to_slash(Path::new(r"docs\guide\setup.md")),
+            "docs/guide/setup.md"
+        );
+        assert_eq!(
+            to_slash(Path::new(r"\\?\C:\repo\README.md")),
+            "C:/repo/README.md"
+        );
+        assert_eq!(
+            normalize(Path::new(r"docs\api.md")),
+            PathBuf::from("docs/api.md")
+        );
+        assert_eq!(slash_pattern(r"docs\**\*.md"), "docs/**/*.md");
+    }
+}
@@ -0,0 +1,278 @@
+use crate::config::{Config, PolicyConfig};
+use crate::error::{DriftcheckError, Result};
+use reqwest::Url;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// File name of the organization policy in the user config directory
+const POLICY_FILE: &str = "policy.toml";
+
+#[derive(Deserialize)]
+struct PolicyFile {
+    #[serde(default)]
+    policy: PolicyConfig,
+}
+
+/// Path of the organization policy: DRIFTCHECK_POLICY, else `<config dir>/driftcheck/policy.toml`
+fn policy_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("DRIFTCHECK_POLICY") {
+        return Some(PathBuf::from(path));
+    }
+    dirs::config_dir().map(|dir| dir.join("driftcheck").join(POLICY_FILE))
+}
+
+/// Load the `[policy]` section of the organization policy file, if one exists.
+/// A DRIFTCHECK_POLICY that points nowhere is an error, so a broken rollout doesn't go unnoticed.
+pub fn load_org_policy() -> Result<Option<PolicyConfig>> {
+    let Some(path) = policy_path() else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        if env::var_os("DRIFTCHECK_POLICY").is_some() {
+            return Err(DriftcheckError::ConfigInvalid(format!(
+                "policy file {} (DRIFTCHECK_POLICY) not found",
+                path.display()
+            )));
+        }
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)?;
+    let file: PolicyFile = toml::from_str(&contents)?;
+    Ok(Some(file.policy))
+}
+
+/// Fail with every setting of `config` that the policy in effect forbids
+pub fn enforce(config: &Config) -> Result<()> {
+    let violations = violations(config)?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(DriftcheckError::PolicyViolation(violations.join("; ")))
+}
+
+fn violations(config: &Config) -> Result<Vec<String>> {
+    let policy = config.policy();
+    let mut violations = Vec::new();
+
+    // "error" keeps every issue blocking; "warning" only forbids dropping issues
+    let forbid_warnings = match policy.min_severity.as_deref() {
+        None => None,
+        Some("error") => Some(true),
+        Some("warning") => Some(false),
+        Some(other) => {
+            return Err(DriftcheckError::ConfigInvalid(format!(
+                "policy.min_severity must be \"error\" or \"warning\", got \"{}\"",
+                other
+            )));
+        }
+    };
+    if let Some(forbid_warnings) = forbid_warnings {
+        let level = policy.min_severity.as_deref().unwrap_or_default();
+        if forbid_warnings {
+            if !config.rules.warn_only.is_empty() {
+                violations.push(format!(
+                    "rules.warn_only is not allowed (min_severity = \"{}\")",
+                    level
+                ));
+            }
+            if config
+                .rules
+                .severity
+                .iter()
+                .any(|r| r.severity == "warning")
+            {
+                violations.push(format!(
+                    "rules.severity may not lower issues to \"warning\" (min_severity = \"{}\")",
+                    level
+                ));
+            }
+            for corpus in config.docs.corpora() {
+                if corpus.severity == "warning" {
+                    violations.push(format!(
+                        "docs.{} may not use severity = \"warning\" (min_severity = \"{}\")",
+                        corpus.name, level
+                    ));
+                }
+            }
+        }
+        if !config.rules.ignore.is_empty() {
+            violations.push(format!(
+                "rules.ignore is not allowed (min_severity = \"{}\")",
+                level
+            ));
+        }
+        if !config.general.disabled_categories.is_empty() {
+            violations.push(format!(
+                "general.disabled_categories is not allowed (min_severity = \"{}\")",
+                level
+            ));
+        }
+    }
+
+    if let Some(max) = policy.max_min_confidence {
+        if config.docs.min_confidence > max {
+            violations.push(format!(
+                "docs.min_confidence = {} exceeds the allowed maximum of {}",
+                config.docs.min_confidence, max
+            ));
+        }
+    }
+
+    if policy.forbid_disable && !config.general.enabled {
+        violations.push("general.enabled = false is not allowed".into());
+    }
+
+    // Remote conditions may not skip or downgrade what the policy pins
+    let hook_actions = std::iter::once(("hook.default_action", &config.hook.default_action)).chain(
+        config
+            .hook
+            .remotes
+            .iter()
+            .map(|rule| ("hook.remotes.action", &rule.action)),
+    );
+    for (key, action) in hook_actions {
+        if policy.forbid_disable && action == "skip" {
+            violations.push(format!("{} = \"skip\" is not allowed", key));
+        }
+        if forbid_warnings == Some(true) && action == "warn" {
+            violations.push(format!(
+                "{} = \"warn\" is not allowed (min_severity = \"error\")",
+                key
+            ));
+        }
+    }
+
+    if !policy.allowed_endpoints.is_empty() {
+        let llm = &config.llm;
+        let endpoints = std::iter::once(("llm.base_url".to_string(), &llm.base_url))
+            .chain(llm.profiles.iter().filter_map(|(name, profile)| {
+                let key = format!("llm.profiles.{}.base_url", name);
+                profile.base_url.as_ref().map(|url| (key, url))
+            }))
+            .chain(
+                llm.consensus
+                    .iter()
+                    .map(|consensus| ("llm.consensus.base_url".to_string(), &consensus.base_url)),
+            );
+        for (key, url) in endpoints {
+            if !policy
+                .allowed_endpoints
+                .iter()
+                .any(|endpoint| endpoint_allows(endpoint, url))
+            {
+                violations.push(format!(
+                    "{} {} is not an approved endpoint ({})",
+                    key,
+                    url,
+                    policy.allowed_endpoints.join(", ")
+                ));
+            }
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Whether `url` is `endpoint` or below it: same scheme, host and port, and a path under the
+/// endpoint's path on a segment boundary
+fn endpoint_allows(endpoint: &str, url: &str) -> bool {
+    let (Ok(endpoint), Ok(url)) = (Url::parse(endpoint), Url::parse(url)) else {
+        return false;
+    };
+    let prefix = endpoint.path().trim_end_matches('/');
+    let rest = url.path().strip_prefix(prefix);
+    endpoint.scheme() == url.scheme()
+        && endpoint.host_str() == url.host_str()
+        && endpoint.port_or_known_default() == url.port_or_known_default()
+        && rest.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LlmProfile;
+
+    #[test]
+    fn test_violations() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [general]
+            enabled = false
+
+            [llm]
+            base_url = "https://api.openai.com/v1"
+
+            [rules]
+            warn_only = ["docs/internal/**"]
+
+            [policy]
+            min_severity = "error"
+            forbid_disable = true
+            allowed_endpoints = ["https://llm-proxy.example.com/"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(violations(&config).unwrap().len(), 3);
+
+        // Every endpoint a task or the consensus provider can reach is checked
+        config.general.enabled = true;
+        config.rules.warn_only.clear();
+        config.llm.base_url = "https://llm-proxy.example.com/v1".to_string();
+        assert!(violations(&config).unwrap().is_empty());
+        config.llm.profiles.insert(
+            "direct".to_string(),
+            LlmProfile {
+                base_url: Some("https://api.anthropic.com/v1".to_string()),
+                ..LlmProfile::default()
+            },
+        );
+        let found = violations(&config).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("llm.profiles.direct.base_url"));
+
+        // The organization policy replaces the local one
+        config.org_policy = Some(PolicyConfig::default());
+        assert!(enforce(&config).is_ok());
+    }
+
+    #[test]
+    fn test_endpoint_allows() {
+        let allows = endpoint_allows;
+        assert!(allows(
+            "https://proxy.example.com",
+            "https://proxy.example.com/v1"
+        ));
+        assert!(allows(
+            "https://proxy.example.com/llm/",
+            "https://proxy.example.com/llm"
+        ));
+        assert!(allows(
+            "https://proxy.example.com:443/llm",
+            "https://proxy.example.com/llm/v1"
+        ));
+        assert!(!allows(
+            "https://proxy.example.com",
+            "https://proxy.example.com.evil.io/v1"
+        ));
+        assert!(!allows(
+            "https://proxy.example.com",
+            "https://proxy.example.com@evil.io/v1"
+        ));
+        assert!(!allows(
+            "https://proxy.example.com/llm",
+            "https://proxy.example.com/llm-other"
+        ));
+        assert!(!allows(
+            "https://proxy.example.com",
+            "http://proxy.example.com/v1"
+        ));
+        assert!(!allows(
+            "https://proxy.example.com",
+            "https://proxy.example.com:8443/v1"
+        ));
+        assert!(!allows("https://proxy.example.com", "not a url"));
+    }
+}
@@ -0,0 +1,204 @@
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::fix;
+use crate::patch::{self, Merge};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Combined patch of every previewed fix, relative to the files the fixes were generated from
+pub const PATCH_FILE: &str = "changes.patch";
+
+/// Copies of the working-tree files as they were before their first preview, used as merge bases
+const ORIG_DIR: &str = ".orig";
+
+/// A preview directory (`.git/driftcheck/preview/<run-id>/`) that receives fixes instead of
+/// the working tree. Files mirror the repository layout; `apply` merges them in.
+#[derive(Debug, Clone)]
+pub struct Preview {
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+/// Outcome of applying a preview to the working tree
+#[derive(Debug, Default)]
+pub struct Applied {
+    pub files: usize,
+    /// Files written with conflict markers
+    pub conflicts: Vec<PathBuf>,
+    /// Structured files left untouched because a merge would break them
+    pub skipped: Vec<PathBuf>,
+}
+
+impl Preview {
+    /// Create a fresh preview directory for this run
+    pub fn create(config: &Config) -> Result<Self> {
+        Config::ensure_writable("writing fix previews")?;
+
+        let run_id = format!(
+            "{}-{}",
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        );
+        let dir = config.resolve(".git/driftcheck/preview").join(run_id);
+        fs::create_dir_all(&dir).map_err(|e| preview_error(&dir, e))?;
+
+        Ok(Self {
+            root: config.root.clone(),
+            dir,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Content a new fix for `file` should start from: the previewed version if there is one
+    pub fn current(&self, file: &Path) -> Result<String> {
+        let previewed = self.dir.join(file);
+        let path = if previewed.exists() {
+            previewed
+        } else {
+            self.root.join(file)
+        };
+        fs::read_to_string(&path).map_err(|e| preview_error(&path, e))
+    }
+
+    /// Write a fixed version of `file` into the preview and refresh the combined patch
+    pub fn write(&self, file: &Path, content: &str) -> Result<()> {
+        let orig = self.dir.join(ORIG_DIR).join(file);
+        if !orig.exists() {
+            let working = self.root.join(file);
+            let base = fs::read_to_string(&working).map_err(|e| preview_error(&working, e))?;
+            write_file(&orig, &base)?;
+        }
+        write_file(&self.dir.join(file), content)?;
+        self.write_patch()
+    }
+
+    /// Repository-relative paths of every previewed file
+    pub fn files(&self) -> Vec<PathBuf> {
+        let orig_dir = self.dir.join(ORIG_DIR);
+        let mut files = Vec::new();
+        collect_files(&orig_dir, &orig_dir, &mut files);
+        files.sort();
+        files
+    }
+
+    fn write_patch(&self) -> Result<()> {
+        let mut combined = String::new();
+        for file in self.files() {
+            let (orig, previewed) = self.read_pair(&file)?;
+            combined.push_str(&fix::unified_patch(&file, &orig, &previewed));
+        }
+        write_file(&self.dir.join(PATCH_FILE), &combined)
+    }
+
+    fn read_pair(&self, file: &Path) -> Result<(String, String)> {
+        let orig_path = self.dir.join(ORIG_DIR).join(file);
+        let orig = fs::read_to_string(&orig_path).map_err(|e| preview_error(&orig_path, e))?;
+        let preview_path = self.dir.join(file);
+        let previewed =
+            fs::read_to_string(&preview_path).map_err(|e| preview_error(&preview_path, e))?;
+        Ok((orig, previewed))
+    }
+
+    /// Merge every previewed file into the working tree, keeping edits made since the preview
+    pub fn apply(&self) -> Result<Applied> {
+        Config::ensure_writable("applying previewed fixes")?;
+
+        let mut applied = Applied::default();
+        for file in self.files() {
+            let (orig, previewed) = self.read_pair(&file)?;
+            let working_path = self.root.join(&file);
+            let working =
+                fs::read_to_string(&working_path).map_err(|e| preview_error(&working_path, e))?;
+
+            let merged = match patch::merge3(&orig, &working, &previewed)? {
+                Merge::Clean(merged) => merged,
+                Merge::Conflicts(_, _) if is_json(&file) => {
+                    applied.skipped.push(file);
+                    continue;
+                }
+                Merge::Conflicts(merged, _) => {
+                    applied.conflicts.push(file.clone());
+                    merged
+                }
+            };
+            fs::write(&working_path, merged).map_err(|e| preview_error(&working_path, e))?;
+            applied.files += 1;
+        }
+        Ok(applied)
+    }
+}
+
+fn is_json(file: &Path) -> bool {
+    file.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("ipynb"))
+}
+
+fn collect_files(base: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(base, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(base) {
+            files.push(relative.to_path_buf());
+        }
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| preview_error(parent, e))?;
+    }
+    fs::write(path, content).map_err(|e| preview_error(path, e))
+}
+
+fn preview_error(path: &Path, e: std::io::Error) -> DriftcheckError {
+    DriftcheckError::PatchError(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_write_and_apply() {
+        let root = std::env::temp_dir().join(format!("driftcheck-preview-{}", std::process::id()));
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/guide.md"), "# Guide\n\nTimeout is 30s.\n").unwrap();
+
+        let preview = Preview {
+            root: root.clone(),
+            dir: root.join(".git/driftcheck/preview/test"),
+        };
+        let file = Path::new("docs/guide.md");
+        preview.write(file, "# Guide\n\nTimeout is 60s.\n").unwrap();
+
+        // The working tree is untouched until the preview is applied
+        assert_eq!(
+            fs::read_to_string(root.join(file)).unwrap(),
+            "# Guide\n\nTimeout is 30s.\n"
+        );
+        assert_eq!(
+            preview.current(file).unwrap(),
+            "# Guide\n\nTimeout is 60s.\n"
+        );
+        assert_eq!(preview.files(), vec![file.to_path_buf()]);
+        let combined = fs::read_to_string(preview.dir().join(PATCH_FILE)).unwrap();
+        assert!(combined.contains("+++ b/docs/guide.md"));
+        assert!(combined.contains("+Timeout is 60s."));
+
+        let applied = preview.apply().unwrap();
+        let result = fs::read_to_string(root.join(file)).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(applied.files, 1);
+        assert!(applied.conflicts.is_empty());
+        assert_eq!(result, "# Guide\n\nTimeout is 60s.\n");
+    }
+}
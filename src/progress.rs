@@ -1,3 +1,4 @@
+use crate::term::{ColorSupport, TermCaps};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
 
@@ -10,11 +11,20 @@ impl Progress {
     /// Create a new progress indicator (only shows if stdout is a TTY)
     pub fn new() -> Self {
         let bar = if atty::is(atty::Stream::Stdout) {
+            let caps = TermCaps::detect();
+            let template = if caps.color == ColorSupport::None {
+                "{spinner} {msg}"
+            } else {
+                "{spinner:.cyan} {msg}"
+            };
+            // The final tick char is shown once the spinner finishes
+            let tick_chars = format!("{} ", caps.symbols().spinner.concat());
+
             let pb = ProgressBar::new_spinner();
             pb.set_style(
                 ProgressStyle::default_spinner()
-                    .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏")
-                    .template("{spinner:.cyan} {msg}")
+                    .tick_chars(&tick_chars)
+                    .template(template)
                     .unwrap(),
             );
             pb.enable_steady_tick(Duration::from_millis(80));
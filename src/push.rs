@@ -0,0 +1,107 @@
+use crate::config::{Config, RemoteRule};
+use crate::error::{DriftcheckError, Result};
+use glob::Pattern;
+
+/// The remote a push goes to, as git passes it to the pre-push hook (`$1` and `$2`)
+#[derive(Debug, Clone, Default)]
+pub struct PushTarget {
+    pub remote: Option<String>,
+    pub url: Option<String>,
+}
+
+/// What the hook does for a push (`hook.default_action` and `[[hook.remotes]]`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookAction {
+    /// Block the push on blocking issues
+    Block,
+    /// Report every issue as a warning and never block
+    Warn,
+    /// Let the push through without analyzing it
+    Skip,
+}
+
+impl HookAction {
+    pub fn parse(key: &str, value: &str) -> Result<Self> {
+        match value {
+            "block" => Ok(HookAction::Block),
+            "warn" => Ok(HookAction::Warn),
+            "skip" => Ok(HookAction::Skip),
+            other => Err(DriftcheckError::ConfigInvalid(format!(
+                "{} must be \"block\", \"warn\" or \"skip\", got {:?}",
+                key, other
+            ))),
+        }
+    }
+
+    /// Action for a push to `target`: the first `[[hook.remotes]]` entry that matches it,
+    /// else `hook.default_action`
+    pub fn for_push(config: &Config, target: &PushTarget) -> Result<Self> {
+        for rule in &config.hook.remotes {
+            if rule_matches(rule, target)? {
+                return Self::parse("hook.remotes.action", &rule.action);
+            }
+        }
+        Self::parse("hook.default_action", &config.hook.default_action)
+    }
+}
+
+/// A rule matches when each glob it sets matches the corresponding part of the push.
+/// Pushes without a known remote or URL never match a rule that sets that glob.
+fn rule_matches(rule: &RemoteRule, target: &PushTarget) -> Result<bool> {
+    let matches = |pattern: &Option<String>, value: &Option<String>| -> Result<bool> {
+        let Some(pattern) = pattern else {
+            return Ok(true);
+        };
+        let pattern = Pattern::new(pattern).map_err(|e| {
+            DriftcheckError::ConfigInvalid(format!("hook.remotes pattern {:?}: {}", pattern, e))
+        })?;
+        Ok(value.as_deref().is_some_and(|value| pattern.matches(value)))
+    };
+    Ok(matches(&rule.name, &target.remote)? && matches(&rule.url, &target.url)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_push() {
+        let config: Config = toml::from_str(
+            r#"
+            [hook]
+            default_action = "warn"
+
+            [[hook.remotes]]
+            name = "mirror*"
+            action = "skip"
+
+            [[hook.remotes]]
+            url = "*github.com*acme/*"
+            action = "block"
+            "#,
+        )
+        .unwrap();
+        let push = |remote: &str, url: &str| PushTarget {
+            remote: Some(remote.to_string()),
+            url: Some(url.to_string()),
+        };
+
+        assert_eq!(
+            HookAction::for_push(&config, &push("origin", "git@github.com:acme/app.git")).unwrap(),
+            HookAction::Block
+        );
+        assert_eq!(
+            HookAction::for_push(&config, &push("mirror-eu", "https://github.com/acme/app"))
+                .unwrap(),
+            HookAction::Skip
+        );
+        assert_eq!(
+            HookAction::for_push(&config, &push("origin", "https://gitlab.com/acme/app")).unwrap(),
+            HookAction::Warn
+        );
+        assert_eq!(
+            HookAction::for_push(&config, &PushTarget::default()).unwrap(),
+            HookAction::Warn
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::analyzer::{self, Issue};
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::excerpt;
+use crate::git::{self, ParsedDiff};
+use crate::llm::{self, DocChunk, DocKind};
+use crate::search;
+use crate::symbols;
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// Only the newest entries of a changelog matter for a release
+const CHANGELOG_HEAD_LINES: usize = 200;
+
+/// Result of checking release-facing docs against a tag-to-tag range
+#[derive(Debug)]
+pub struct ReleaseReport {
+    pub range: String,
+    pub commits: Vec<String>,
+    pub changed_files: usize,
+    pub docs: Vec<String>,
+    pub issues: Vec<Issue>,
+}
+
+impl ReleaseReport {
+    pub fn is_ready(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Verify that changelog, migration guides and README reflect the changes in `range`
+pub async fn check(config: &Config, range: &str) -> Result<ReleaseReport> {
+    if !range.contains("..") {
+        return Err(DriftcheckError::GitError(format!(
+            "Expected a range like v1.2.0..v1.3.0, got '{}'",
+            range
+        )));
+    }
+
+    let commits = git::commit_log(range)?;
+    let diff = git::get_diff(&Some(range.to_string()))?;
+    let diff = git::filter_diff(
+        &diff,
+        &config.general.diff_include,
+        &config.general.diff_exclude,
+    );
+    let changed_files = ParsedDiff::parse(&diff).files.len();
+
+    let chunks = release_docs(config)?;
+    let docs = chunks.iter().map(|c| c.file.clone()).collect();
+
+    let mut report = ReleaseReport {
+        range: range.to_string(),
+        commits,
+        changed_files,
+        docs,
+        issues: vec![],
+    };
+
+    if changed_files == 0 || chunks.is_empty() {
+        debug!("Nothing to check for release");
+        return Ok(report);
+    }
+
+    info!(
+        "Checking {} release docs against {} commits",
+        chunks.len(),
+        report.commits.len()
+    );
+
+    let total = chunks.len();
+    let (batches, dropped) = analyzer::partition_to_budget(
+        chunks,
+        config.docs.max_context_tokens,
+        config.docs.max_batches,
+    );
+    if dropped > 0 {
+        warn!(
+            "Checking {} of {} release docs; {} did not fit in {} batch(es) of {} tokens \
+             (raise docs.max_batches or docs.max_context_tokens)",
+            total - dropped,
+            total,
+            dropped,
+            batches.len(),
+            config.docs.max_context_tokens
+        );
+    }
+
+    let file_diffs = git::file_diffs(&diff);
+    for chunks in batches {
+        let raw_issues =
+            llm::analyze_release(config, range, &report.commits, &diff, &chunks).await?;
+        report
+            .issues
+            .extend(raw_issues.into_iter().map(|raw| Issue {
+                code_location: symbols::resolve(
+                    &file_diffs,
+                    raw.code_symbol.as_deref(),
+                    &raw.description,
+                ),
+                ..Issue::from(raw)
+            }));
+    }
+    excerpt::trim_all(&config.root, &mut report.issues);
+
+    Ok(report)
+}
+
+/// Load each release-facing doc as a single chunk
+fn release_docs(config: &Config) -> Result<Vec<DocChunk>> {
+    let mut files =
+        search::expand_doc_paths(&config.root, &config.release.paths, &config.docs.ignore)?;
+    files.sort();
+
+    let mut chunks = Vec::new();
+    for path in files {
+        let content = match fs::read_to_string(config.resolve(&path)) {
+            Ok(c) => c,
+            Err(e) => {
+                debug!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let content = release_excerpt(&path, &content);
+        let file = path.to_string_lossy().to_string();
+        chunks.push(DocChunk {
+            kind: DocKind::from_path(&file),
+            start_line: 1,
+            end_line: content.lines().count().max(1),
+            content,
+            file,
+        });
+    }
+
+    Ok(chunks)
+}
+
+/// Keep only the head of changelogs (newest entries); other docs are used whole
+fn release_excerpt(path: &Path, content: &str) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_uppercase())
+        .unwrap_or_default();
+
+    if name.starts_with("CHANGELOG") || name.starts_with("CHANGES") || name.starts_with("HISTORY") {
+        content
+            .lines()
+            .take(CHANGELOG_HEAD_LINES)
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        content.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_excerpt_keeps_changelog_head() {
+        let content: String = (0..500).map(|i| format!("line {}\n", i)).collect();
+
+        let changelog = release_excerpt(Path::new("CHANGELOG.md"), &content);
+        assert_eq!(changelog.lines().count(), CHANGELOG_HEAD_LINES);
+        assert!(changelog.starts_with("line 0"));
+
+        let readme = release_excerpt(Path::new("README.md"), &content);
+        assert_eq!(readme.lines().count(), 500);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::llm::DocChunk;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Points per changed line of public API, per doc chunk mentioning a changed name and per
+/// past issue caused by the file
+const API_WEIGHT: f32 = 3.0;
+const REFERENCE_WEIGHT: f32 = 1.0;
+const DRIFT_WEIGHT: f32 = 2.0;
+
+/// Points at which the score reaches about 63 of 100
+const SCALE: f32 = 12.0;
+
+/// How likely a changed source file is to have made docs drift
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileRisk {
+    pub file: String,
+    /// 0 to 100
+    pub score: u8,
+    /// Added or removed lines declaring public items, CLI flags or exports
+    pub api_changes: usize,
+    /// Retrieved doc chunks mentioning a name the file's diff declares
+    pub doc_references: usize,
+    /// Issues the file caused in earlier runs (issue history)
+    pub past_drift: usize,
+}
+
+/// Score every changed file of the diff (`git::file_diffs`) against the retrieved doc chunks
+/// and the number of issues each file caused before, riskiest first
+pub fn assess(
+    files: &[(String, String)],
+    chunks: &[&DocChunk],
+    drift_counts: &HashMap<String, usize>,
+) -> Vec<FileRisk> {
+    let mut scores: Vec<FileRisk> = files
+        .iter()
+        .map(|(file, file_diff)| {
+            let (api_changes, names) = public_changes(file_diff);
+            let doc_references = chunks
+                .iter()
+                .filter(|chunk| names.iter().any(|name| chunk.content.contains(name)))
+                .count();
+            let past_drift = drift_counts.get(file).copied().unwrap_or(0);
+            let points = API_WEIGHT * api_changes as f32
+                + REFERENCE_WEIGHT * doc_references as f32
+                + DRIFT_WEIGHT * past_drift as f32;
+            FileRisk {
+                file: file.clone(),
+                score: (100.0 * (1.0 - (-points / SCALE).exp())).round() as u8,
+                api_changes,
+                doc_references,
+                past_drift,
+            }
+        })
+        .collect();
+    scores.sort_by_key(|risk| std::cmp::Reverse(risk.score));
+    scores
+}
+
+/// Order doc chunks so the ones mentioning names from the riskiest files come first; the
+/// order is otherwise kept. Used when a corpus's token budget can't fit every chunk.
+pub fn prioritize(
+    chunks: Vec<DocChunk>,
+    files: &[(String, String)],
+    scores: &[FileRisk],
+) -> Vec<DocChunk> {
+    let names: Vec<(u8, HashSet<String>)> = files
+        .iter()
+        .map(|(file, file_diff)| {
+            let score = scores
+                .iter()
+                .find(|risk| &risk.file == file)
+                .map_or(0, |risk| risk.score);
+            (score, public_changes(file_diff).1)
+        })
+        .collect();
+
+    let mut ranked: Vec<(u8, DocChunk)> = chunks
+        .into_iter()
+        .map(|chunk| {
+            let score = names
+                .iter()
+                .filter(|(_, names)| names.iter().any(|name| chunk.content.contains(name)))
+                .map(|(score, _)| *score)
+                .max()
+                .unwrap_or(0);
+            (score, chunk)
+        })
+        .collect();
+    ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    ranked.into_iter().map(|(_, chunk)| chunk).collect()
+}
+
+/// Changed lines of a file's diff that declare public API, and the names they declare
+fn public_changes(file_diff: &str) -> (usize, HashSet<String>) {
+    static PUBLIC: OnceLock<Regex> = OnceLock::new();
+    static NAME: OnceLock<Regex> = OnceLock::new();
+    static FLAG: OnceLock<Regex> = OnceLock::new();
+    let public = PUBLIC.get_or_init(|| {
+        Regex::new(
+            r#"^\s*(pub(\([a-z]+\))?\s|export\s|public\s|module\.exports)|^(def|class|func|function)\s+[A-Za-z]|long\s*=|add_argument\(|\.option\(|--[a-z][a-z0-9-]+"#,
+        )
+        .unwrap()
+    });
+    let name = NAME.get_or_init(|| {
+        Regex::new(
+            r"\b(?:fn|def|class|function|func|struct|enum|trait|interface|type|const|static|mod)\s+([A-Za-z_][A-Za-z0-9_]*)",
+        )
+        .unwrap()
+    });
+    let flag = FLAG.get_or_init(|| Regex::new(r"--[a-z][a-z0-9-]+").unwrap());
+
+    let mut count = 0;
+    let mut names = HashSet::new();
+    for line in file_diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        }
+        let Some(text) = line.strip_prefix('+').or_else(|| line.strip_prefix('-')) else {
+            continue;
+        };
+        if !public.is_match(text) {
+            continue;
+        }
+        count += 1;
+        names.extend(
+            name.captures_iter(text)
+                .map(|c| c[1].to_string())
+                .filter(|n| n.len() >= 3),
+        );
+        names.extend(flag.find_iter(text).map(|m| m.as_str().to_string()));
+    }
+    (count, names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git;
+    use crate::llm::DocKind;
+
+    const DIFF: &str = "diff --git a/src/cli.rs b/src/cli.rs
+--- a/src/cli.rs
++++ b/src/cli.rs
+@@ -1,3 +1,3 @@
+-    #[arg(long = \"no-color\")]
++    #[arg(long = \"plain\")]
+-pub fn render_colored() {}
++pub fn render_plain() {}
+diff --git a/src/util.rs b/src/util.rs
+--- a/src/util.rs
++++ b/src/util.rs
+@@ -1,2 +1,2 @@
+-fn helper() -> u32 { 1 }
++fn helper() -> u32 { 2 }
+";
+
+    fn chunk(content: &str) -> DocChunk {
+        DocChunk {
+            file: "README.md".to_string(),
+            start_line: 1,
+            end_line: 1,
+            content: content.to_string(),
+            kind: DocKind::Markdown,
+        }
+    }
+
+    #[test]
+    fn test_assess_and_prioritize() {
+        let files = git::file_diffs(DIFF);
+        let chunks = [
+            chunk("Call `helper` for details."),
+            chunk("Use `render_colored` to print."),
+        ];
+        let drift = HashMap::from([("src/util.rs".to_string(), 1)]);
+        let scores = assess(&files, &chunks.iter().collect::<Vec<_>>(), &drift);
+
+        assert_eq!(scores[0].file, "src/cli.rs");
+        assert_eq!((scores[0].api_changes, scores[0].doc_references), (4, 1));
+        assert_eq!(scores[1].file, "src/util.rs");
+        assert_eq!(
+            (
+                scores[1].api_changes,
+                scores[1].doc_references,
+                scores[1].past_drift
+            ),
+            (0, 0, 1)
+        );
+        assert!(scores[0].score > scores[1].score);
+
+        let ordered = prioritize(chunks.to_vec(), &files, &scores);
+        assert!(ordered[0].content.contains("render_colored"));
+    }
+}
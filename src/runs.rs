@@ -0,0 +1,127 @@
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// Directory (relative to the repository root) holding one JSON file per recorded run
+const RUNS_DIR: &str = ".git/driftcheck/runs";
+
+/// Runs kept for `driftcheck review`; older ones are deleted when a new run is recorded
+const MAX_RUNS: usize = 20;
+
+/// The issues of one `check` or hook run, replayed by `driftcheck review`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Run {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub range: String,
+    pub issues: Vec<Issue>,
+}
+
+fn run_path(config: &Config, id: &str) -> PathBuf {
+    config.resolve(RUNS_DIR).join(format!("{}.json", id))
+}
+
+/// Store the issues of a run and prune old runs. Returns the run id.
+pub fn record(config: &Config, range: &str, issues: &[Issue]) -> Result<String> {
+    Config::ensure_writable("recording the run")?;
+
+    let dir = config.resolve(RUNS_DIR);
+    fs::create_dir_all(&dir).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+
+    // Fixed-width timestamps, so ids sort chronologically
+    let created_at = Utc::now();
+    let id = created_at.format("%Y%m%d-%H%M%S-%3f").to_string();
+
+    let run = Run {
+        id: id.clone(),
+        created_at,
+        range: range.to_string(),
+        issues: issues.to_vec(),
+    };
+    let content = serde_json::to_string_pretty(&run)
+        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    fs::write(run_path(config, &id), content)
+        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+
+    for old in ids(config)?.into_iter().skip(MAX_RUNS) {
+        debug!("Pruning recorded run {}", old);
+        let _ = fs::remove_file(run_path(config, &old));
+    }
+    Ok(id)
+}
+
+/// Ids of the recorded runs, newest first
+pub fn ids(config: &Config) -> Result<Vec<String>> {
+    let dir = config.resolve(RUNS_DIR);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut ids: Vec<String> = fs::read_dir(&dir)
+        .map_err(|e| DriftcheckError::CacheError(e.to_string()))?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_suffix(".json").map(str::to_string)
+        })
+        .collect();
+    ids.sort_by(|a, b| b.cmp(a));
+    Ok(ids)
+}
+
+pub fn load(config: &Config, id: &str) -> Result<Run> {
+    let path = run_path(config, id);
+    if !path.exists() {
+        return Err(DriftcheckError::CacheError(format!(
+            "no recorded run {:?} (see `driftcheck review`)",
+            id
+        )));
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| DriftcheckError::CacheError(e.to_string()))?;
+    serde_json::from_str(&content).map_err(|e| DriftcheckError::CacheError(e.to_string()))
+}
+
+/// The most recently recorded run
+pub fn latest(config: &Config) -> Result<Run> {
+    match ids(config)?.first() {
+        Some(id) => load(config, id),
+        None => Err(DriftcheckError::CacheError(
+            "no recorded runs yet; runs with issues are recorded by `check` and the hook".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::RawIssue;
+
+    #[test]
+    fn test_run_roundtrip() {
+        let issue = Issue::from(RawIssue {
+            file: "docs/usage.md".to_string(),
+            line: 7,
+            description: "The --no-tui flag was renamed".to_string(),
+            doc_excerpt: "Run `tool --no-tui`".to_string(),
+            suggested_fix: None,
+            confidence: Some(0.9),
+            code_symbol: None,
+        });
+        let run = Run {
+            id: "20261015-142301-123".to_string(),
+            created_at: Utc::now(),
+            range: "@{u}..HEAD".to_string(),
+            issues: vec![issue.clone()],
+        };
+
+        let loaded: Run = serde_json::from_str(&serde_json::to_string(&run).unwrap()).unwrap();
+        assert_eq!(loaded.issues[0].fingerprint, issue.fingerprint);
+        assert_eq!(loaded.issues[0].confidence, Some(0.9));
+    }
+}
@@ -0,0 +1,851 @@
+use crate::config::{Config, DocsConfig};
+use crate::docstrings;
+use crate::error::{DriftcheckError, Result};
+use crate::lfs;
+use crate::llm::{DocChunk, DocKind};
+use crate::paths;
+use glob::glob;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use tracing::field::Empty;
+use tracing::{debug, warn, Span};
+
+pub mod remote;
+pub mod sources;
+
+/// Lines of context kept around each match, as `rg -C`
+const CONTEXT_LINES: usize = 3;
+
+/// Check if ripgrep is installed
+pub fn check_ripgrep() -> Result<()> {
+    which::which("rg").map_err(|_| DriftcheckError::RipgrepNotFound)?;
+    Ok(())
+}
+
+/// Whether searches run through ripgrep; without it the slower built-in search is used
+pub fn ripgrep_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let available = check_ripgrep().is_ok();
+        if !available {
+            debug!("ripgrep (rg) not found, using the built-in search");
+        }
+        available
+    })
+}
+
+/// How doc chunks are retrieved for a diff (`search.mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// LLM-generated search queries run through ripgrep
+    Ripgrep,
+    /// Cosine similarity between embeddings of the diff and of doc chunks
+    Embeddings,
+}
+
+impl SearchMode {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.search.mode.as_str() {
+            "ripgrep" => Ok(SearchMode::Ripgrep),
+            "embeddings" => Ok(SearchMode::Embeddings),
+            other => Err(DriftcheckError::ConfigInvalid(format!(
+                "search.mode must be \"ripgrep\" or \"embeddings\", got {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+/// How search queries are generated in ripgrep mode (`search.query_mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryMode {
+    /// Asked from the LLM, per changed file
+    Llm,
+    /// Extracted from the diff with regexes, without an API call
+    Heuristic,
+}
+
+impl QueryMode {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.search.query_mode.as_str() {
+            "llm" => Ok(QueryMode::Llm),
+            "heuristic" => Ok(QueryMode::Heuristic),
+            other => Err(DriftcheckError::ConfigInvalid(format!(
+                "search.query_mode must be \"llm\" or \"heuristic\", got {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Raw matches for a single search query, before deduplication and merging
+#[derive(Debug, Clone)]
+pub struct QueryMatches {
+    pub query: String,
+    pub chunks: Vec<DocChunk>,
+}
+
+/// Find relevant documentation based on search queries
+pub async fn find_relevant_docs(
+    root: &Path,
+    config: &DocsConfig,
+    queries: &[String],
+) -> Result<Vec<DocChunk>> {
+    let matches = search_queries(root, config, queries).await?;
+    Ok(merge_matches(matches))
+}
+
+/// Run every query against the configured doc files, keeping matches per query
+#[tracing::instrument(
+    name = "search",
+    skip_all,
+    fields(queries = queries.len(), files = Empty, chunks = Empty)
+)]
+pub async fn search_queries(
+    root: &Path,
+    config: &DocsConfig,
+    queries: &[String],
+) -> Result<Vec<QueryMatches>> {
+    let sources = sources::for_corpus(root, config).await?;
+    let mut doc_files = Vec::new();
+    for source in &sources {
+        doc_files.extend(source.files()?);
+    }
+
+    Span::current().record("files", doc_files.len());
+    if doc_files.is_empty() {
+        debug!("No documentation files found");
+        return Ok(vec![]);
+    }
+
+    debug!(
+        "Searching {} doc files with {} queries",
+        doc_files.len(),
+        queries.len()
+    );
+    debug!("Doc files: {:?}", doc_files);
+    debug!("Search queries: {:?}", queries);
+
+    // Run searches in parallel
+    let mut handles = Vec::new();
+
+    for query in queries {
+        let query = query.clone();
+        let sources = sources.clone();
+
+        handles.push(tokio::spawn(async move {
+            let mut chunks = Vec::new();
+            for source in &sources {
+                match source.search(&query) {
+                    Ok(found) => chunks.extend(found),
+                    Err(e) => return (query, Err(e)),
+                }
+            }
+            (query, Ok(chunks))
+        }));
+    }
+
+    // Collect results
+    let mut results = Vec::new();
+
+    for handle in handles {
+        match handle.await {
+            Ok((query, Ok(chunks))) => {
+                results.push(QueryMatches { query, chunks });
+            }
+            Ok((_, Err(e))) => {
+                warn!("Search query failed: {}", e);
+            }
+            Err(e) => {
+                warn!("Search task panicked: {}", e);
+            }
+        }
+    }
+
+    Span::current().record(
+        "chunks",
+        results.iter().map(|m| m.chunks.len()).sum::<usize>(),
+    );
+    Ok(results)
+}
+
+/// Deduplicate matches across queries and merge adjacent chunks
+pub fn merge_matches(matches: Vec<QueryMatches>) -> Vec<DocChunk> {
+    let mut all_chunks = Vec::new();
+    let mut seen: HashSet<(String, usize)> = HashSet::new();
+
+    for chunk in matches.into_iter().flat_map(|m| m.chunks) {
+        // Deduplicate by file:line
+        let key = (chunk.file.clone(), chunk.start_line);
+        if seen.insert(key) {
+            all_chunks.push(chunk);
+        }
+    }
+
+    // Sort by file and line
+    all_chunks.sort_by(|a, b| a.file.cmp(&b.file).then(a.start_line.cmp(&b.start_line)));
+
+    // Merge adjacent chunks in the same file
+    merge_adjacent_chunks(all_chunks)
+}
+
+/// Expand doc globs relative to `root`. Returned paths are relative to `root`.
+/// Patterns support `{a,b}` alternatives, and a leading `!` removes matches of earlier
+/// patterns: `!docs/drafts/**` in `paths` excludes drafts, and in `ignore` keeps them.
+pub fn expand_doc_paths(root: &Path, paths: &[String], ignore: &[String]) -> Result<Vec<PathBuf>> {
+    let ignored = match_patterns(root, ignore, true);
+
+    Ok(match_patterns(root, paths, false)
+        .into_iter()
+        .filter(|path| !ignored.contains(path) && root.join(path).is_file())
+        .collect())
+}
+
+/// Paths matched by `patterns`, minus those matched by their `!` negations.
+/// Directories matched by a negation (or by any pattern with `excluding`) stand for
+/// every file below them, so `docs/archive/**` covers the archived files.
+fn match_patterns(root: &Path, patterns: &[String], excluding: bool) -> HashSet<PathBuf> {
+    let mut included = HashSet::new();
+    let mut excluded = HashSet::new();
+
+    for pattern in patterns {
+        let pattern = normalize_pattern(pattern);
+        let pattern = pattern.trim_end_matches(docstrings::SUFFIX);
+        let (set, pattern, whole_dirs) = match pattern.strip_prefix('!') {
+            Some(negated) => (&mut excluded, negated, true),
+            None => (&mut included, pattern, excluding),
+        };
+
+        for mut alternative in expand_braces(pattern) {
+            // A trailing `**` only yields directories; mean everything below them
+            if alternative.ends_with("/**") || alternative == "**" {
+                alternative.push_str("/*");
+            }
+            let matches = match glob_in(root, &alternative) {
+                Ok(matches) => matches,
+                Err(e) => {
+                    warn!("Invalid glob pattern '{}': {}", alternative, e);
+                    continue;
+                }
+            };
+            for path in matches {
+                if whole_dirs && root.join(&path).is_dir() {
+                    let below = format!("{}/**/*", glob::Pattern::escape(&paths::to_slash(&path)));
+                    set.extend(glob_in(root, &below).unwrap_or_default());
+                }
+                set.insert(path);
+            }
+        }
+    }
+
+    included.retain(|path| !excluded.contains(path));
+    included
+}
+
+/// Canonical form of a doc glob: trimmed, `/` separators, and no leading `./`
+pub fn normalize_pattern(pattern: &str) -> String {
+    let pattern = pattern.trim().replace('\\', "/");
+    let (negation, pattern) = match pattern.strip_prefix('!') {
+        Some(rest) => ("!", rest.trim_start()),
+        None => ("", pattern.as_str()),
+    };
+    let mut pattern = pattern;
+    while let Some(rest) = pattern.strip_prefix("./") {
+        pattern = rest;
+    }
+    format!("{}{}", negation, pattern)
+}
+
+/// Expand `{a,b}` alternatives (nested braces included) into plain globs
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+
+    let mut depth = 0;
+    let mut close = None;
+    let mut splits = Vec::new();
+    for (i, c) in pattern[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(open + i);
+                    break;
+                }
+            }
+            ',' if depth == 1 => splits.push(open + i),
+            _ => {}
+        }
+    }
+    let Some(close) = close else {
+        return vec![pattern.to_string()];
+    };
+
+    let mut bounds = vec![open];
+    bounds.extend(splits);
+    bounds.push(close);
+    let (prefix, suffix) = (&pattern[..open], &pattern[close + 1..]);
+
+    bounds
+        .windows(2)
+        .flat_map(|w| expand_braces(&format!("{}{}{}", prefix, &pattern[w[0] + 1..w[1]], suffix)))
+        .collect()
+}
+
+/// Check that a doc glob is valid, describing the first problem with its column
+pub fn check_pattern(pattern: &str) -> std::result::Result<(), String> {
+    let normalized = normalize_pattern(pattern);
+    let body = normalized.trim_end_matches(docstrings::SUFFIX);
+    let body = body.strip_prefix('!').unwrap_or(body);
+    if body.is_empty() {
+        return Err("empty pattern".to_string());
+    }
+
+    let mut depth: i32 = 0;
+    for (i, c) in body.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Err(format!("unmatched `}}` at column {}", i + 1)),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err("unclosed `{`".to_string());
+    }
+
+    for alternative in expand_braces(body) {
+        glob::Pattern::new(&alternative)
+            .map_err(|e| format!("{} at column {} of `{}`", e.msg, e.pos + 1, alternative))?;
+    }
+    Ok(())
+}
+
+/// Patterns (as written) that match no file under `root`; `!` negations are not checked
+pub fn unmatched_patterns(root: &Path, patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .filter(|p| !normalize_pattern(p).starts_with('!'))
+        .filter(|p| match_patterns(root, std::slice::from_ref(p), false).is_empty())
+        .cloned()
+        .collect()
+}
+
+/// Source files matched by `:docstrings` patterns, whose doc comments are searched instead
+/// of the whole file. Returned paths are relative to `root`.
+pub fn expand_docstring_paths(
+    root: &Path,
+    paths: &[String],
+    ignore: &[String],
+) -> Result<Vec<PathBuf>> {
+    let paths: Vec<String> = paths.iter().map(|p| normalize_pattern(p)).collect();
+    if !paths.iter().any(|p| p.ends_with(docstrings::SUFFIX)) {
+        return Ok(Vec::new());
+    }
+    // Negations apply to docstring patterns too
+    let patterns: Vec<String> = paths
+        .into_iter()
+        .filter(|p| p.starts_with('!') || p.ends_with(docstrings::SUFFIX))
+        .collect();
+    expand_doc_paths(root, &patterns, ignore)
+}
+
+/// Replace matches in `:docstrings` source files with the doc comment blocks they touch,
+/// dropping matches in code
+pub(crate) fn restrict_to_docstrings(
+    root: &Path,
+    chunks: Vec<DocChunk>,
+    sources: &HashSet<PathBuf>,
+) -> Vec<DocChunk> {
+    if sources.is_empty() {
+        return chunks;
+    }
+
+    let mut blocks: HashMap<String, Vec<DocChunk>> = HashMap::new();
+    let mut restricted = Vec::new();
+    for chunk in chunks {
+        if !sources.contains(Path::new(&chunk.file)) {
+            restricted.push(chunk);
+            continue;
+        }
+        let file_blocks = blocks.entry(chunk.file.clone()).or_insert_with(|| {
+            std::fs::read_to_string(root.join(&chunk.file))
+                .map(|content| docstrings::extract(&chunk.file, &content))
+                .unwrap_or_default()
+        });
+        restricted.extend(
+            file_blocks
+                .iter()
+                .filter(|b| b.start_line <= chunk.end_line && chunk.start_line <= b.end_line)
+                .cloned(),
+        );
+    }
+    restricted
+}
+
+/// Replace Git LFS pointer docs with smudged copies when `smudge` is set, or drop them,
+/// so pointer text never ends up in doc chunks
+pub(crate) fn resolve_lfs_pointers(root: &Path, files: Vec<PathBuf>, smudge: bool) -> Vec<PathBuf> {
+    files
+        .into_iter()
+        .filter_map(|file| {
+            if !lfs::is_pointer_file(&root.join(&file)) {
+                return Some(file);
+            }
+            if !smudge {
+                debug!(
+                    "Skipping Git LFS pointer {} (set docs.smudge_lfs to search it)",
+                    file.display()
+                );
+                return None;
+            }
+            match lfs::smudge(root, &file) {
+                Ok(copy) => Some(copy),
+                Err(e) => {
+                    warn!("Skipping Git LFS pointer {}: {}", file.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Glob `pattern` under `root`, returning matches relative to `root` with `/` separators.
+/// The root is globbed with `/` separators too, so Windows roots (`C:\repo`, `\\?\C:\repo`)
+/// work.
+fn glob_in(root: &Path, pattern: &str) -> std::result::Result<Vec<PathBuf>, glob::PatternError> {
+    let root = paths::normalize(root);
+    let prefix = glob::Pattern::escape(&paths::to_slash(&root));
+    let full = if root.as_os_str().is_empty() {
+        pattern.to_string()
+    } else {
+        format!("{}/{}", prefix.trim_end_matches('/'), pattern)
+    };
+
+    Ok(glob(&full)?
+        .flatten()
+        .map(|path| paths::normalize(path.strip_prefix(&root).unwrap_or(&path)))
+        .collect())
+}
+
+pub(crate) fn search_query(root: &Path, query: &str, files: &[PathBuf]) -> Result<Vec<DocChunk>> {
+    if ripgrep_available() {
+        ripgrep_query(root, query, files)
+    } else {
+        builtin_query(root, query, files)
+    }
+}
+
+fn ripgrep_query(root: &Path, query: &str, files: &[PathBuf]) -> Result<Vec<DocChunk>> {
+    let file_args: Vec<String> = files.iter().map(|p| paths::to_slash(p)).collect();
+
+    // Run from the root so reported paths stay repository-relative
+    let mut command = Command::new("rg");
+    if !root.as_os_str().is_empty() {
+        command.current_dir(root);
+    }
+    let output = command
+        .args(["--json", "-C", &CONTEXT_LINES.to_string(), "--", query])
+        .args(&file_args)
+        .output()
+        .map_err(|e| DriftcheckError::SearchError(e.to_string()))?;
+
+    // ripgrep returns exit code 1 if no matches (which is fine)
+    if !output.status.success() && output.status.code() != Some(1) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DriftcheckError::SearchError(stderr.to_string()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_ripgrep_json(&stdout)
+}
+
+/// Search without ripgrep, producing the same chunks: the query is a regex as for `rg`,
+/// binary files are skipped, and matches with overlapping context form one chunk
+fn builtin_query(root: &Path, query: &str, files: &[PathBuf]) -> Result<Vec<DocChunk>> {
+    let pattern = compile_query(query)?;
+
+    let mut chunks = Vec::new();
+    for file in files {
+        let Ok(bytes) = std::fs::read(root.join(file)) else {
+            debug!("Skipping unreadable doc file {}", file.display());
+            continue;
+        };
+        if bytes.contains(&0) {
+            continue;
+        }
+        let content = String::from_utf8_lossy(&bytes);
+        chunks.extend(match_chunks(&pattern, &paths::to_slash(file), &content));
+    }
+    Ok(chunks)
+}
+
+pub(crate) fn compile_query(query: &str) -> Result<Regex> {
+    Regex::new(query)
+        .map_err(|e| DriftcheckError::SearchError(format!("invalid query {:?}: {}", query, e)))
+}
+
+/// Chunks of `content` around lines matching `pattern`, with `CONTEXT_LINES` of context;
+/// matches with overlapping context form one chunk
+pub(crate) fn match_chunks(pattern: &Regex, file: &str, content: &str) -> Vec<DocChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut block: Vec<(usize, String)> = Vec::new();
+    let mut block_end = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if !pattern.is_match(line) {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + CONTEXT_LINES + 1).min(lines.len());
+        if !block.is_empty() && start > block_end {
+            chunks.push(create_chunk(file.to_string(), &block));
+            block.clear();
+        }
+        let from = if block.is_empty() { start } else { block_end };
+        block.extend((from..end).map(|n| (n + 1, lines[n].to_string())));
+        block_end = end;
+    }
+    if !block.is_empty() {
+        chunks.push(create_chunk(file.to_string(), &block));
+    }
+    chunks
+}
+
+/// One line of `rg --json` output. Only `match` and `context` events carry lines.
+#[derive(Deserialize)]
+struct RgEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct RgLine {
+    path: RgData,
+    lines: RgData,
+    line_number: Option<usize>,
+}
+
+/// Text, or base64 `bytes` when it is not valid UTF-8
+#[derive(Deserialize)]
+struct RgData {
+    text: Option<String>,
+    bytes: Option<String>,
+}
+
+impl RgData {
+    fn into_string(self) -> String {
+        match (self.text, self.bytes) {
+            (Some(text), _) => text,
+            (None, Some(bytes)) => String::from_utf8_lossy(&decode_base64(&bytes)).into_owned(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// Group `rg --json` match and context lines into chunks of consecutive lines per file
+fn parse_ripgrep_json(output: &str) -> Result<Vec<DocChunk>> {
+    let mut chunks = Vec::new();
+    let mut current_file: Option<String> = None;
+    let mut current_lines: Vec<(usize, String)> = Vec::new();
+
+    for line in output.lines().filter(|l| !l.trim().is_empty()) {
+        let unexpected = |e: serde_json::Error| {
+            DriftcheckError::SearchError(format!("unexpected rg output: {}", e))
+        };
+        let event: RgEvent = serde_json::from_str(line).map_err(unexpected)?;
+        if event.kind != "match" && event.kind != "context" {
+            continue;
+        }
+        let rg_line: RgLine = serde_json::from_value(event.data).map_err(unexpected)?;
+        let Some(line_num) = rg_line.line_number else {
+            continue;
+        };
+        // ripgrep prints `\` separators on Windows
+        let file = rg_line.path.into_string().replace('\\', "/");
+        let content = rg_line.lines.into_string();
+        let content = content.strip_suffix('\n').unwrap_or(&content);
+        let content = content.strip_suffix('\r').unwrap_or(content);
+
+        // A new file, or a gap between context windows, starts a new chunk
+        let continues = current_file.as_ref() == Some(&file)
+            && current_lines
+                .last()
+                .is_some_and(|(n, _)| *n + 1 == line_num);
+        if !continues {
+            if let Some(f) = current_file.take() {
+                if !current_lines.is_empty() {
+                    chunks.push(create_chunk(f, &current_lines));
+                    current_lines.clear();
+                }
+            }
+            current_file = Some(file);
+        }
+        current_lines.push((line_num, content.to_string()));
+    }
+
+    if let Some(file) = current_file {
+        if !current_lines.is_empty() {
+            chunks.push(create_chunk(file, &current_lines));
+        }
+    }
+
+    Ok(chunks)
+}
+
+/// Decode standard base64, as used by `rg --json` for non-UTF-8 data
+fn decode_base64(input: &str) -> Vec<u8> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let mut out = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for sextet in input.bytes().filter_map(value) {
+        buffer = (buffer << 6) | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    out
+}
+
+fn create_chunk(file: String, lines: &[(usize, String)]) -> DocChunk {
+    // Matches in smudged LFS copies belong to the original doc
+    let file = lfs::original_path(&file).to_string();
+    let start_line = lines.first().map(|(n, _)| *n).unwrap_or(1);
+    let end_line = lines.last().map(|(n, _)| *n).unwrap_or(1);
+    let content = lines
+        .iter()
+        .map(|(_, c)| c.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    DocChunk {
+        kind: DocKind::from_path(&file),
+        file,
+        start_line,
+        end_line,
+        content,
+    }
+}
+
+fn merge_adjacent_chunks(chunks: Vec<DocChunk>) -> Vec<DocChunk> {
+    if chunks.is_empty() {
+        return chunks;
+    }
+
+    let mut merged: Vec<DocChunk> = Vec::new();
+
+    for chunk in chunks {
+        if let Some(last) = merged.last_mut() {
+            // Merge if same file and lines are close (within 5 lines)
+            if last.file == chunk.file && chunk.start_line <= last.end_line + 5 {
+                last.end_line = chunk.end_line;
+                last.content.push_str("\n...\n");
+                last.content.push_str(&chunk.content);
+                continue;
+            }
+        }
+        merged.push(chunk);
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_doc_paths_relative_to_root() {
+        let root = std::env::temp_dir().join(format!("driftcheck-search-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        for file in ["README.md", "docs/guide.md", "docs/skip.md"] {
+            std::fs::write(root.join(file), "# Doc\n").unwrap();
+        }
+
+        // The test process runs from the crate directory, not from `root`
+        let mut files = expand_doc_paths(
+            &root,
+            &["README.md".to_string(), "docs/*.md".to_string()],
+            &["docs/skip.md".to_string()],
+        )
+        .unwrap();
+        files.sort();
+
+        // A canonicalized root is a `\\?\C:\...` path on Windows; Windows-style patterns
+        // match too, and results always use `/`
+        let mut windows_style = expand_doc_paths(
+            &root.canonicalize().unwrap(),
+            &[r"docs\**\*.md".to_string()],
+            &[r".\docs\skip.md".to_string()],
+        )
+        .unwrap();
+        windows_style.sort();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            files,
+            vec![PathBuf::from("README.md"), PathBuf::from("docs/guide.md")]
+        );
+        assert_eq!(windows_style, vec![PathBuf::from("docs/guide.md")]);
+    }
+
+    #[test]
+    fn test_glob_patterns() {
+        assert_eq!(
+            expand_braces("docs/{guide,api/{v1,v2}}/*.md"),
+            vec!["docs/guide/*.md", "docs/api/v1/*.md", "docs/api/v2/*.md"]
+        );
+        assert_eq!(
+            normalize_pattern(" ! ./docs\\drafts/** "),
+            "!docs/drafts/**"
+        );
+
+        assert!(check_pattern("src/**/*.rs:docstrings").is_ok());
+        assert!(check_pattern("!docs/{a,b}/*.md").is_ok());
+        assert_eq!(check_pattern("docs/{a,b/*.md").unwrap_err(), "unclosed `{`");
+        assert!(check_pattern("docs/[.md").unwrap_err().contains("column 6"));
+        assert!(check_pattern("  ").is_err());
+    }
+
+    #[test]
+    fn test_expand_doc_paths_negation() {
+        let root = std::env::temp_dir().join(format!("driftcheck-negate-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("docs/drafts")).unwrap();
+        for file in [
+            "docs/guide.md",
+            "docs/api.md",
+            "docs/drafts/new.md",
+            "docs/drafts/keep.md",
+        ] {
+            std::fs::write(root.join(file), "# Doc\n").unwrap();
+        }
+
+        let mut files = expand_doc_paths(
+            &root,
+            &[
+                "docs/{*,drafts/*}.md".to_string(),
+                "!docs/api.md".to_string(),
+            ],
+            &[
+                "docs/drafts/**".to_string(),
+                "!docs/drafts/keep.md".to_string(),
+            ],
+        )
+        .unwrap();
+        files.sort();
+        let unmatched = unmatched_patterns(
+            &root,
+            &["./docs/*.md".to_string(), "guide/*.md".to_string()],
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("docs/drafts/keep.md"),
+                PathBuf::from("docs/guide.md")
+            ]
+        );
+        assert_eq!(unmatched, vec!["guide/*.md".to_string()]);
+    }
+
+    #[test]
+    fn test_restrict_to_docstrings() {
+        let root = std::env::temp_dir().join(format!("driftcheck-docstr-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join("lib.rs"),
+            "/// Timeout in seconds.\nconst TIMEOUT: u64 = 30;\n\n\n\n\n\nfn timeout() {}\n",
+        )
+        .unwrap();
+
+        let chunk = |start, end| DocChunk {
+            file: "lib.rs".to_string(),
+            start_line: start,
+            end_line: end,
+            content: String::new(),
+            kind: DocKind::Docstring,
+        };
+        let sources = HashSet::from([PathBuf::from("lib.rs")]);
+        let chunks = restrict_to_docstrings(&root, vec![chunk(1, 4), chunk(5, 8)], &sources);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 1));
+        assert_eq!(chunks[0].content, "/// Timeout in seconds.");
+    }
+
+    #[test]
+    fn test_builtin_query_matches_ripgrep_chunks() {
+        let root = std::env::temp_dir().join(format!("driftcheck-builtin-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let doc: String = (1..=20)
+            .map(|n| match n {
+                2 | 5 | 16 => format!("run with --plain ({})\n", n),
+                _ => format!("line {}\n", n),
+            })
+            .collect();
+        std::fs::write(root.join("README.md"), doc).unwrap();
+        std::fs::write(root.join("logo.png"), b"--plain\0").unwrap();
+
+        let files = [PathBuf::from("README.md"), PathBuf::from("logo.png")];
+        let chunks = builtin_query(&root, "--pla[i]n", &files).unwrap();
+        let invalid = builtin_query(&root, "(", &files);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // Matches whose context overlaps form one chunk, as with `rg -C 3`
+        let spans: Vec<(&str, usize, usize)> = chunks
+            .iter()
+            .map(|c| (c.file.as_str(), c.start_line, c.end_line))
+            .collect();
+        assert_eq!(spans, vec![("README.md", 1, 8), ("README.md", 13, 19)]);
+        assert_eq!(chunks[0].content.lines().count(), 8);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_parse_ripgrep_json() {
+        // Paths with "-<digit>" and colons broke the old text parsing
+        let output = r#"{"type":"begin","data":{"path":{"text":"docs/v1-2:notes.md"}}}
+{"type":"context","data":{"path":{"text":"docs/v1-2:notes.md"},"lines":{"text":"time: 12:30:00\n"},"line_number":4,"absolute_offset":40,"submatches":[]}}
+{"type":"match","data":{"path":{"text":"docs/v1-2:notes.md"},"lines":{"text":"run --plain\r\n"},"line_number":5,"absolute_offset":55,"submatches":[{"match":{"text":"--plain"},"start":4,"end":11}]}}
+{"type":"context","data":{"path":{"text":"docs/v1-2:notes.md"},"lines":{"text":"far below\n"},"line_number":12,"absolute_offset":90,"submatches":[]}}
+{"type":"end","data":{"path":{"text":"docs/v1-2:notes.md"},"binary_offset":null,"stats":{}}}
+{"type":"match","data":{"path":{"bytes":"UkVBRE1FLm1k"},"lines":{"text":"--plain\n"},"line_number":1,"absolute_offset":0,"submatches":[]}}
+{"type":"summary","data":{"elapsed_total":{"secs":0,"nanos":1,"human":"0s"},"stats":{}}}
+"#;
+        let chunks = parse_ripgrep_json(output).unwrap();
+        let spans: Vec<(&str, usize, usize)> = chunks
+            .iter()
+            .map(|c| (c.file.as_str(), c.start_line, c.end_line))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("docs/v1-2:notes.md", 4, 5),
+                ("docs/v1-2:notes.md", 12, 12),
+                ("README.md", 1, 1),
+            ]
+        );
+        assert_eq!(chunks[0].content, "time: 12:30:00\nrun --plain");
+    }
+}
@@ -0,0 +1,484 @@
+use super::sources::{DocSource, SourceContext};
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use regex::Regex;
+use reqwest::RequestBuilder;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Remote pages are named `@<source>/<page>.md` in issues and reports
+pub const PREFIX: &str = "@";
+
+/// Fetched pages, one directory per source
+const CACHE_DIR: &str = ".git/driftcheck/sources";
+
+/// Written after a successful fetch; its age decides when to fetch again
+const FETCHED_MARKER: &str = ".fetched";
+
+/// Fixes to remote pages, saved as patches to apply by hand
+pub const PATCH_DIR: &str = ".git/driftcheck/remote-fixes";
+
+const DEFAULT_TTL: u64 = 3600;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const NOTION_API: &str = "https://api.notion.com/v1";
+
+const NOTION_VERSION: &str = "2022-06-28";
+
+/// Nested Notion blocks deeper than this are not fetched
+const MAX_BLOCK_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Service {
+    Confluence,
+    Notion,
+}
+
+impl Service {
+    fn default_token_env(self) -> &'static str {
+        match self {
+            Service::Confluence => "CONFLUENCE_API_TOKEN",
+            Service::Notion => "NOTION_TOKEN",
+        }
+    }
+}
+
+/// Pages of a Confluence space or Notion workspace, fetched through their API and cached
+/// as Markdown-like text under `.git/driftcheck/sources/<name>/` for `ttl` seconds.
+/// When a fetch fails, the previous copy is searched instead.
+pub struct Remote {
+    name: String,
+    dir: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl Remote {
+    /// A `type = "confluence"` source: `base_url` (e.g. `https://acme.atlassian.net/wiki`),
+    /// `space` key, and `user` (account email, for Atlassian Cloud) with the API token in
+    /// `token_env`; without `user` the token is sent as a bearer token (Data Center)
+    pub async fn confluence(context: &SourceContext<'_>) -> Result<Box<dyn DocSource>> {
+        Ok(Box::new(Self::open(context, Service::Confluence).await?))
+    }
+
+    /// A `type = "notion"` source: every page shared with the integration whose token is in
+    /// `token_env`, optionally only those matching `query`
+    pub async fn notion(context: &SourceContext<'_>) -> Result<Box<dyn DocSource>> {
+        Ok(Box::new(Self::open(context, Service::Notion).await?))
+    }
+
+    async fn open(context: &SourceContext<'_>, service: Service) -> Result<Self> {
+        let dir = Config::resolve_in(context.root, CACHE_DIR).join(context.name);
+        let ttl = context
+            .options
+            .get("ttl")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_TTL);
+
+        if is_stale(&dir, ttl) && !Config::is_read_only() {
+            match fetch(context, service).await {
+                Ok(pages) => store_pages(&dir, &pages)?,
+                Err(e) if dir.join(FETCHED_MARKER).exists() => {
+                    warn!("Using cached pages of {}: {}", context.name, e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .filter(|file| file.ends_with(".md"))
+                    .map(|file| PathBuf::from(format!("{}{}/{}", PREFIX, context.name, file)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        debug!("Doc source {}: {} pages", context.name, files.len());
+
+        Ok(Self {
+            name: context.name.to_string(),
+            dir,
+            files,
+        })
+    }
+}
+
+impl DocSource for Remote {
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.files.clone())
+    }
+
+    fn read(&self, file: &Path) -> Result<String> {
+        let page = file
+            .file_name()
+            .ok_or_else(|| DriftcheckError::SearchError(format!("{}: no page", self.name)))?;
+        fs::read_to_string(self.dir.join(page)).map_err(|e| {
+            DriftcheckError::SearchError(format!("Failed to read {}: {}", file.display(), e))
+        })
+    }
+}
+
+/// Whether `file` names a page of a remote source
+pub fn is_remote(file: &Path) -> bool {
+    file.to_string_lossy().starts_with(PREFIX)
+}
+
+/// Cached copy of a remote page, for reading it like a repository file
+pub fn cache_path(root: &Path, file: &Path) -> Option<PathBuf> {
+    let name = file.to_string_lossy();
+    let rest = name.strip_prefix(PREFIX)?;
+    Some(Config::resolve_in(root, CACHE_DIR).join(rest))
+}
+
+fn is_stale(dir: &Path, ttl: u64) -> bool {
+    fs::metadata(dir.join(FETCHED_MARKER))
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|fetched| SystemTime::now().duration_since(fetched).ok())
+        .is_none_or(|age| age.as_secs() >= ttl)
+}
+
+/// Replace the cached pages with `pages` (title and text)
+fn store_pages(dir: &Path, pages: &[(String, String)]) -> Result<()> {
+    let cache_error = |e: std::io::Error| DriftcheckError::CacheError(e.to_string());
+    if dir.exists() {
+        fs::remove_dir_all(dir).map_err(cache_error)?;
+    }
+    fs::create_dir_all(dir).map_err(cache_error)?;
+
+    let mut used = Vec::new();
+    for (title, text) in pages {
+        let mut file = format!("{}.md", slug(title));
+        let mut n = 2;
+        while used.contains(&file) {
+            file = format!("{}-{}.md", slug(title), n);
+            n += 1;
+        }
+        fs::write(dir.join(&file), format!("# {}\n\n{}", title, text)).map_err(cache_error)?;
+        used.push(file);
+    }
+    fs::write(dir.join(FETCHED_MARKER), "").map_err(cache_error)
+}
+
+/// File name for a page title: letters, digits, `-` and `_`
+fn slug(title: &str) -> String {
+    let slug: String = title
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug
+    }
+}
+
+async fn fetch(context: &SourceContext<'_>, service: Service) -> Result<Vec<(String, String)>> {
+    let option = |key: &str| context.options.get(key).and_then(Value::as_str);
+    let token_env = option("token_env").unwrap_or(service.default_token_env());
+    let token = env::var(token_env).map_err(|_| {
+        DriftcheckError::ConfigInvalid(format!(
+            "docs.sources.{}: set {} to the API token",
+            context.name, token_env
+        ))
+    })?;
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| DriftcheckError::SearchError(e.to_string()))?;
+
+    info!("Fetching pages of doc source {}", context.name);
+    match service {
+        Service::Confluence => {
+            let required = |key: &str| {
+                option(key).ok_or_else(|| {
+                    DriftcheckError::ConfigInvalid(format!(
+                        "docs.sources.{}.{} is required for Confluence",
+                        context.name, key
+                    ))
+                })
+            };
+            let auth = Auth {
+                user: option("user"),
+                token: &token,
+            };
+            fetch_confluence(&client, required("base_url")?, required("space")?, &auth).await
+        }
+        Service::Notion => fetch_notion(&client, &token, option("query")).await,
+    }
+}
+
+struct Auth<'a> {
+    user: Option<&'a str>,
+    token: &'a str,
+}
+
+impl Auth<'_> {
+    fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.user {
+            Some(user) => request.basic_auth(user, Some(self.token)),
+            None => request.bearer_auth(self.token),
+        }
+    }
+}
+
+async fn fetch_confluence(
+    client: &reqwest::Client,
+    base_url: &str,
+    space: &str,
+    auth: &Auth<'_>,
+) -> Result<Vec<(String, String)>> {
+    let url = format!("{}/rest/api/content", base_url.trim_end_matches('/'));
+    let limit = 50;
+    let mut pages = Vec::new();
+    let mut start = 0;
+    loop {
+        let request = client.get(&url).query(&[
+            ("spaceKey", space),
+            ("type", "page"),
+            ("status", "current"),
+            ("expand", "body.storage"),
+            ("limit", &limit.to_string()),
+            ("start", &start.to_string()),
+        ]);
+        let body = send(auth.apply(request)).await?;
+        let results = body["results"].as_array().cloned().unwrap_or_default();
+        for page in &results {
+            let title = page["title"].as_str().unwrap_or_default().to_string();
+            let storage = page["body"]["storage"]["value"]
+                .as_str()
+                .unwrap_or_default();
+            pages.push((title, html_to_text(storage)));
+        }
+        if results.len() < limit {
+            return Ok(pages);
+        }
+        start += results.len();
+    }
+}
+
+async fn fetch_notion(
+    client: &reqwest::Client,
+    token: &str,
+    query: Option<&str>,
+) -> Result<Vec<(String, String)>> {
+    let mut pages = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut body = json!({
+            "filter": {"property": "object", "value": "page"},
+            "page_size": 100,
+        });
+        if let Some(query) = query {
+            body["query"] = json!(query);
+        }
+        if let Some(cursor) = &cursor {
+            body["start_cursor"] = json!(cursor);
+        }
+        let request = notion(client.post(format!("{}/search", NOTION_API)), token).json(&body);
+        let response = send(request).await?;
+
+        for page in response["results"].as_array().into_iter().flatten() {
+            let Some(id) = page["id"].as_str() else {
+                continue;
+            };
+            let mut text = String::new();
+            notion_blocks(client, token, id, 0, &mut text).await?;
+            pages.push((notion_title(page), text));
+        }
+
+        cursor = response["next_cursor"].as_str().map(str::to_string);
+        if cursor.is_none() || response["has_more"] != json!(true) {
+            return Ok(pages);
+        }
+    }
+}
+
+/// Append the text of a Notion block's children to `out`, nested blocks indented
+async fn notion_blocks(
+    client: &reqwest::Client,
+    token: &str,
+    id: &str,
+    depth: usize,
+    out: &mut String,
+) -> Result<()> {
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut url = format!("{}/blocks/{}/children?page_size=100", NOTION_API, id);
+        if let Some(cursor) = &cursor {
+            url.push_str(&format!("&start_cursor={}", cursor));
+        }
+        let response = send(notion(client.get(&url), token)).await?;
+
+        for block in response["results"].as_array().into_iter().flatten() {
+            out.push_str(&"  ".repeat(depth));
+            out.push_str(&render_block(block));
+            out.push('\n');
+            if block["has_children"] == json!(true) && depth + 1 < MAX_BLOCK_DEPTH {
+                if let Some(child) = block["id"].as_str() {
+                    Box::pin(notion_blocks(client, token, child, depth + 1, out)).await?;
+                }
+            }
+        }
+
+        cursor = response["next_cursor"].as_str().map(str::to_string);
+        if cursor.is_none() || response["has_more"] != json!(true) {
+            return Ok(());
+        }
+    }
+}
+
+fn notion(request: RequestBuilder, token: &str) -> RequestBuilder {
+    request
+        .bearer_auth(token)
+        .header("Notion-Version", NOTION_VERSION)
+}
+
+async fn send(request: RequestBuilder) -> Result<Value> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| DriftcheckError::SearchError(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(DriftcheckError::SearchError(format!(
+            "HTTP {}: {}",
+            status,
+            text.chars().take(200).collect::<String>()
+        )));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| DriftcheckError::SearchError(e.to_string()))
+}
+
+/// The title property of a Notion page
+fn notion_title(page: &Value) -> String {
+    page["properties"]
+        .as_object()
+        .into_iter()
+        .flat_map(|properties| properties.values())
+        .find(|property| property["type"] == "title")
+        .map(|property| rich_text(&property["title"]))
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+/// One Notion block as a line of Markdown
+fn render_block(block: &Value) -> String {
+    let kind = block["type"].as_str().unwrap_or_default();
+    let text = rich_text(&block[kind]["rich_text"]);
+    match kind {
+        "heading_1" => format!("# {}", text),
+        "heading_2" => format!("## {}", text),
+        "heading_3" => format!("### {}", text),
+        "bulleted_list_item" => format!("- {}", text),
+        "numbered_list_item" => format!("1. {}", text),
+        "to_do" if block[kind]["checked"] == json!(true) => format!("- [x] {}", text),
+        "to_do" => format!("- [ ] {}", text),
+        "quote" => format!("> {}", text),
+        "code" => format!(
+            "```{}\n{}\n```",
+            block[kind]["language"].as_str().unwrap_or_default(),
+            text
+        ),
+        _ => text,
+    }
+}
+
+fn rich_text(value: &Value) -> String {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|span| span["plain_text"].as_str())
+        .collect()
+}
+
+/// Text of Confluence storage format (XHTML), with headings, list items and code blocks
+/// kept as Markdown
+fn html_to_text(html: &str) -> String {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    let rules = RULES.get_or_init(|| {
+        [
+            (r"(?i)<h([1-6])[^>]*>", "\n\n<h$1>"),
+            (r"(?i)<h1>", "# "),
+            (r"(?i)<h2>", "## "),
+            (r"(?i)<h3>", "### "),
+            (r"(?i)<h[4-6]>", "#### "),
+            (r"(?i)<li[^>]*>", "\n- "),
+            (r"(?i)<br\s*/?>", "\n"),
+            (r"(?i)</(p|h[1-6]|tr|pre|ul|ol)>", "\n"),
+            (r"(?i)<!\[CDATA\[", "\n```\n"),
+            (r"\]\]>", "\n```\n"),
+            (r"<[^>]+>", ""),
+            (r"\n{3,}", "\n\n"),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+        .collect()
+    });
+
+    let mut text = html.to_string();
+    for (pattern, replacement) in rules {
+        text = pattern.replace_all(&text, *replacement).into_owned();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_text() {
+        let storage = "<h2>Install</h2><p>Run <code>app --plain</code> &amp; wait.</p>\
+<ul><li>one</li><li>two</li></ul>\
+<ac:structured-macro ac:name=\"code\"><ac:plain-text-body><![CDATA[app deploy]]></ac:plain-text-body></ac:structured-macro>";
+        assert_eq!(
+            html_to_text(storage),
+            "## Install\nRun app --plain & wait.\n\n- one\n- two\n\n```\napp deploy\n```"
+        );
+
+        let block = json!({
+            "type": "heading_2",
+            "heading_2": {"rich_text": [{"plain_text": "Deploy "}, {"plain_text": "guide"}]}
+        });
+        assert_eq!(render_block(&block), "## Deploy guide");
+        assert_eq!(slug("Deploy: Guide / v2"), "Deploy-Guide-v2");
+        assert_eq!(
+            cache_path(Path::new("/repo"), Path::new("@wiki/Deploy.md")),
+            Some(PathBuf::from(
+                "/repo/.git/driftcheck/sources/wiki/Deploy.md"
+            ))
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use super::remote::Remote;
+use super::{
+    compile_query, expand_doc_paths, expand_docstring_paths, match_chunks, resolve_lfs_pointers,
+    restrict_to_docstrings, search_query,
+};
+use crate::config::{DocsConfig, SourceConfig};
+use crate::error::{DriftcheckError, Result};
+use crate::llm::DocChunk;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::debug;
+
+/// Type of the built-in source reading doc files from the repository
+pub const FILES: &str = "files";
+
+/// Type of the built-in source fetching the pages of a Confluence space
+pub const CONFLUENCE: &str = "confluence";
+
+/// Type of the built-in source fetching pages shared with a Notion integration
+pub const NOTION: &str = "notion";
+
+/// Where the docs of a corpus come from. The configured doc paths are one source; others
+/// (e.g. Confluence or Notion pages fetched through their API) are configured under
+/// `[docs.sources.<name>]` with a type registered through [`register`].
+pub trait DocSource: Send + Sync {
+    /// Doc files the source provides, as issues will name them
+    fn files(&self) -> Result<Vec<PathBuf>>;
+
+    /// Content of one of the source's files
+    fn read(&self, file: &Path) -> Result<String>;
+
+    /// Chunks matching `query`, a regex as for ripgrep, with a few lines of context around
+    /// each match. By default every file is read and searched in memory.
+    fn search(&self, query: &str) -> Result<Vec<DocChunk>> {
+        let pattern = compile_query(query)?;
+        let mut chunks = Vec::new();
+        for file in self.files()? {
+            let content = self.read(&file)?;
+            chunks.extend(match_chunks(&pattern, &file.to_string_lossy(), &content));
+        }
+        Ok(chunks)
+    }
+
+    /// Whether only the doc comments of `file` are documentation (`:docstrings` paths)
+    fn docstrings_only(&self, _file: &Path) -> bool {
+        false
+    }
+}
+
+/// What a source type is created from
+pub struct SourceContext<'a> {
+    /// Repository root
+    pub root: &'a Path,
+    /// Name of the `[docs.sources.<name>]` table
+    pub name: &'a str,
+    /// The table's other keys
+    pub options: &'a BTreeMap<String, Value>,
+}
+
+/// Creates a source of one type from its configuration
+pub type SourceFactory = fn(&SourceContext) -> Result<Box<dyn DocSource>>;
+
+static REGISTRY: OnceLock<Mutex<HashMap<String, SourceFactory>>> = OnceLock::new();
+
+/// Make a source type available to `[docs.sources.<name>]` tables with `type = "<kind>"`.
+/// Call before analyzing; registering a kind again replaces its factory.
+pub fn register(kind: &str, factory: SourceFactory) {
+    let registry = REGISTRY.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(mut registry) = registry.lock() {
+        registry.insert(kind.to_string(), factory);
+    }
+}
+
+/// The sources of a corpus: its doc paths, then its `[docs.sources]` entries
+pub async fn for_corpus(root: &Path, docs: &DocsConfig) -> Result<Vec<Arc<dyn DocSource>>> {
+    let mut sources: Vec<Arc<dyn DocSource>> = vec![Arc::new(Files::new(root, docs)?)];
+    for (name, config) in &docs.sources {
+        sources.push(Arc::from(create(root, name, config).await?));
+    }
+    Ok(sources)
+}
+
+/// A source from its `[docs.sources]` table; built-in remote sources fetch their pages here,
+/// before any search runs
+async fn create(root: &Path, name: &str, config: &SourceConfig) -> Result<Box<dyn DocSource>> {
+    let context = SourceContext {
+        root,
+        name,
+        options: &config.options,
+    };
+    match config.kind.as_str() {
+        FILES => return Ok(Box::new(Files::from_options(&context)?)),
+        CONFLUENCE => return Remote::confluence(&context).await,
+        NOTION => return Remote::notion(&context).await,
+        _ => {}
+    }
+
+    let factory = REGISTRY
+        .get()
+        .and_then(|registry| registry.lock().ok()?.get(&config.kind).copied());
+    match factory {
+        Some(factory) => factory(&context),
+        None => Err(DriftcheckError::ConfigInvalid(format!(
+            "docs.sources.{}: unknown source type {:?}",
+            name, config.kind
+        ))),
+    }
+}
+
+/// Doc files in the repository matched by doc path globs, searched with ripgrep (or the
+/// built-in search). Git LFS pointers are smudged or skipped, and in `:docstrings` source
+/// files only doc comments count.
+pub struct Files {
+    root: PathBuf,
+    files: Vec<PathBuf>,
+    docstring_sources: HashSet<PathBuf>,
+}
+
+impl Files {
+    pub fn new(root: &Path, docs: &DocsConfig) -> Result<Self> {
+        let files = expand_doc_paths(root, &docs.paths, &docs.ignore)?;
+        let files = resolve_lfs_pointers(root, files, docs.smudge_lfs);
+        let docstring_sources = expand_docstring_paths(root, &docs.paths, &docs.ignore)?
+            .into_iter()
+            .collect();
+        Ok(Self {
+            root: root.to_path_buf(),
+            files,
+            docstring_sources,
+        })
+    }
+
+    /// A "files" source: `paths` and `ignore` globs, as in `[docs]`
+    fn from_options(context: &SourceContext) -> Result<Self> {
+        let globs = |key: &str| -> Result<Vec<String>> {
+            match context.options.get(key) {
+                None => Ok(Vec::new()),
+                Some(value) => serde_json::from_value(value.clone()).map_err(|_| {
+                    DriftcheckError::ConfigInvalid(format!(
+                        "docs.sources.{}.{} must be a list of globs",
+                        context.name, key
+                    ))
+                }),
+            }
+        };
+        let docs = DocsConfig {
+            paths: globs("paths")?,
+            ignore: globs("ignore")?,
+            ..DocsConfig::default()
+        };
+        debug!("Doc source {}: {:?}", context.name, docs.paths);
+        Self::new(context.root, &docs)
+    }
+}
+
+impl DocSource for Files {
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.files.clone())
+    }
+
+    fn read(&self, file: &Path) -> Result<String> {
+        fs::read_to_string(self.root.join(file)).map_err(|e| {
+            DriftcheckError::SearchError(format!("Failed to read {}: {}", file.display(), e))
+        })
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<DocChunk>> {
+        if self.files.is_empty() {
+            return Ok(Vec::new());
+        }
+        let chunks = search_query(&self.root, query, &self.files)?;
+        Ok(restrict_to_docstrings(
+            &self.root,
+            chunks,
+            &self.docstring_sources,
+        ))
+    }
+
+    fn docstrings_only(&self, file: &Path) -> bool {
+        self.docstring_sources.contains(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pages held in memory, as a source fetching them from an API would
+    struct Pages(Vec<(PathBuf, String)>);
+
+    impl DocSource for Pages {
+        fn files(&self) -> Result<Vec<PathBuf>> {
+            Ok(self.0.iter().map(|(file, _)| file.clone()).collect())
+        }
+
+        fn read(&self, file: &Path) -> Result<String> {
+            Ok(self
+                .0
+                .iter()
+                .find(|(f, _)| f == file)
+                .map(|(_, content)| content.clone())
+                .unwrap_or_default())
+        }
+    }
+
+    fn wiki(context: &SourceContext) -> Result<Box<dyn DocSource>> {
+        let space = context.options["space"].as_str().unwrap_or_default();
+        Ok(Box::new(Pages(vec![(
+            PathBuf::from(format!("wiki/{}/Deploy.md", space)),
+            "# Deploy\n\nRun `app deploy --plain`.\n".to_string(),
+        )])))
+    }
+
+    #[tokio::test]
+    async fn test_registered_source() {
+        register("wiki", wiki);
+        let docs: DocsConfig = toml::from_str(
+            r#"
+            paths = []
+            [sources.eng]
+            type = "wiki"
+            space = "ENG"
+            "#,
+        )
+        .unwrap();
+
+        let root = std::env::temp_dir();
+        let sources = for_corpus(&root, &docs).await.unwrap();
+        assert_eq!(sources.len(), 2);
+        let chunks = sources[1].search("--plain").unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].file, "wiki/ENG/Deploy.md");
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 3));
+
+        let unknown: DocsConfig = toml::from_str("[sources.x]\ntype = \"jira\"\n").unwrap();
+        assert!(for_corpus(&root, &unknown).await.is_err());
+    }
+}
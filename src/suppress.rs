@@ -0,0 +1,93 @@
+use crate::analyzer::Issue;
+use crate::config::Config;
+use crate::output::markdown::locate_excerpt;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Suppresses everything up to `IGNORE_END` (or the end of the file)
+const IGNORE: &str = "driftcheck-ignore";
+const IGNORE_END: &str = "driftcheck-ignore-end";
+const IGNORE_NEXT_LINE: &str = "driftcheck-ignore-next-line";
+
+/// Mark which lines of a doc are suppressed by inline markers.
+/// Markers are matched anywhere in a line, so any comment syntax works
+/// (`<!-- driftcheck-ignore -->`, `.. driftcheck-ignore`, `# driftcheck-ignore`).
+/// Index 0 is line 1.
+pub fn suppressed_lines(content: &str) -> Vec<bool> {
+    let mut suppressed = Vec::new();
+    let mut in_region = false;
+    let mut next_line = false;
+
+    for line in content.lines() {
+        if line.contains(IGNORE_END) {
+            in_region = false;
+            suppressed.push(true);
+        } else if line.contains(IGNORE_NEXT_LINE) {
+            next_line = true;
+            suppressed.push(true);
+        } else if line.contains(IGNORE) {
+            in_region = true;
+            suppressed.push(true);
+        } else {
+            suppressed.push(in_region || next_line);
+            next_line = false;
+        }
+    }
+
+    suppressed
+}
+
+/// Drop issues whose excerpt (or line, when the excerpt can't be found) is suppressed
+pub fn filter_suppressed(root: &Path, issues: Vec<Issue>) -> Vec<Issue> {
+    let mut docs: HashMap<PathBuf, Option<(String, Vec<bool>)>> = HashMap::new();
+
+    issues
+        .into_iter()
+        .filter(|issue| {
+            let doc = docs.entry(issue.file.clone()).or_insert_with(|| {
+                let content = fs::read_to_string(Config::resolve_in(root, &issue.file)).ok()?;
+                let lines = suppressed_lines(&content);
+                Some((content, lines))
+            });
+            let Some((content, lines)) = doc else {
+                return true;
+            };
+
+            let (start, end) =
+                locate_excerpt(content, &issue.doc_excerpt).unwrap_or((issue.line, issue.line));
+            let suppressed = start > 0
+                && (start..=end).any(|line| lines.get(line - 1).copied().unwrap_or(false));
+            if suppressed {
+                debug!(
+                    "Suppressed issue in {}:{}",
+                    issue.file.display(),
+                    issue.line
+                );
+            }
+            !suppressed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suppressed_lines() {
+        let doc = "# Guide\n\
+                   <!-- driftcheck-ignore-next-line -->\n\
+                   Old flag: --legacy\n\
+                   Current flag: --plain\n\
+                   <!-- driftcheck-ignore -->\n\
+                   ## Migrating from 0.x\n\
+                   <!-- driftcheck-ignore-end -->\n\
+                   Done.\n";
+        assert_eq!(
+            suppressed_lines(doc),
+            vec![false, true, true, false, true, true, true, false]
+        );
+    }
+}
@@ -0,0 +1,421 @@
+use crate::analyzer::{self, Issue, SortBy};
+use crate::baseline;
+use crate::config::Config;
+use crate::error::{DriftcheckError, Result};
+use crate::excerpt;
+use crate::git;
+use crate::llm::{self, DocChunk, DocKind};
+use crate::paths;
+use crate::search::sources;
+use crate::suppress;
+use chrono::Utc;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Semaphore;
+use tracing::{debug, info, warn};
+
+/// Sections of files without headings, and the most lines one section may span
+const SECTION_LINES: usize = 80;
+
+/// Code files larger than this are not searched for references
+const MAX_CODE_FILE_BYTES: u64 = 512 * 1024;
+
+/// Budget for the code excerpts sent with one section (about 2000 tokens)
+const MAX_EXCERPT_CHARS: usize = 8_000;
+
+/// Matches shown per reference, and lines of context around each
+const HITS_PER_REFERENCE: usize = 2;
+const CONTEXT_LINES: usize = 3;
+
+/// Doc formats never searched as code, even when they are not in a corpus
+const DOC_EXTENSIONS: &[&str] = &["md", "mdx", "markdown", "rst", "txt", "adoc", "ipynb"];
+
+/// Result of verifying the docs against the current code (`driftcheck sweep`)
+#[derive(Debug)]
+pub struct SweepReport {
+    /// Doc files read
+    pub docs: usize,
+    /// Sections referring to code, the candidates for the sample
+    pub sections: usize,
+    /// Sections verified, most likely stale first
+    pub sampled: Vec<SectionRisk>,
+    pub issues: Vec<Issue>,
+}
+
+impl SweepReport {
+    pub fn is_clean(&self) -> bool {
+        !self.issues.iter().any(Issue::is_blocking)
+    }
+}
+
+/// How likely a doc section is to be stale
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionRisk {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: u32,
+    /// Code names, flags and keys the section refers to
+    pub references: usize,
+    /// References found nowhere in the code
+    pub missing: Vec<String>,
+    /// Days since the doc file last changed
+    pub age_days: Option<u64>,
+}
+
+/// A doc section with the code it refers to
+struct Candidate {
+    chunk: DocChunk,
+    corpus: Option<String>,
+    severity: analyzer::Severity,
+    excerpts: String,
+    risk: SectionRisk,
+}
+
+/// Verify up to `sample` doc sections of every corpus against the current code. Sections are
+/// ranked by references missing from the code, then by how many names they mention and how
+/// long the doc went unchanged; the top of the ranking is sent to the LLM, one section a call.
+pub async fn run(config: &Config, sample: usize) -> Result<SweepReport> {
+    let sort_by = SortBy::from_config(config)?;
+    let ages = git::last_changed(&config.root).unwrap_or_else(|e| {
+        debug!("No doc ages: {}", e);
+        HashMap::new()
+    });
+
+    let named = !config.docs.corpora.is_empty();
+    let mut docs = Vec::new();
+    for corpus in config.docs.corpora() {
+        let severity = analyzer::corpus_severity(&corpus)?;
+        for source in sources::for_corpus(&config.root, &corpus.docs).await? {
+            for file in source.files()? {
+                if source.docstrings_only(&file) {
+                    continue;
+                }
+                match source.read(&file) {
+                    Ok(content) => docs.push((
+                        paths::to_slash(&file),
+                        content,
+                        named.then(|| corpus.name.clone()),
+                        severity,
+                    )),
+                    Err(e) => debug!("Skipping {}: {}", file.display(), e),
+                }
+            }
+        }
+    }
+
+    let doc_files: HashSet<&str> = docs.iter().map(|(file, ..)| file.as_str()).collect();
+    let code = code_files(config, &doc_files)?;
+    info!(
+        "Sweeping {} doc files against {} code files",
+        docs.len(),
+        code.len()
+    );
+
+    let now = Utc::now().timestamp();
+    let mut candidates = Vec::new();
+    for (file, content, corpus, severity) in &docs {
+        let age_days = ages
+            .get(file)
+            .map(|changed| ((now - changed).max(0) / 86_400) as u64);
+        for chunk in sections(file, content) {
+            let names = references(&chunk.content);
+            if names.is_empty() {
+                continue;
+            }
+            let (excerpts, missing) = code_excerpts(&names, &code);
+            let score = 4 * missing.len() as u32
+                + names.len().min(5) as u32
+                + age_days.map_or(0, |days| (days / 90) as u32);
+            candidates.push(Candidate {
+                risk: SectionRisk {
+                    file: file.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    score,
+                    references: names.len(),
+                    missing,
+                    age_days,
+                },
+                chunk,
+                corpus: corpus.clone(),
+                severity: *severity,
+                excerpts,
+            });
+        }
+    }
+
+    let sections = candidates.len();
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.risk.score));
+    candidates.truncate(sample);
+    for c in &candidates {
+        debug!(
+            "Sweeping {}:{} (score {}, missing {:?})",
+            c.risk.file, c.risk.start_line, c.risk.score, c.risk.missing
+        );
+    }
+    let sampled: Vec<SectionRisk> = candidates.iter().map(|c| c.risk.clone()).collect();
+
+    let issues = verify(config, candidates).await?;
+    let issues = analyzer::filter_confidence(config.docs.min_confidence, issues);
+    let issues = analyzer::filter_categories(&config.general.disabled_categories, issues);
+    let issues = analyzer::apply_rules(&config.rules, issues);
+    let issues = suppress::filter_suppressed(&config.root, issues);
+    let mut issues = match baseline::load(config) {
+        Ok(baseline) => baseline.filter(issues),
+        Err(e) => {
+            warn!("Ignoring baseline: {}", e);
+            issues
+        }
+    };
+    excerpt::trim_all(&config.root, &mut issues);
+    analyzer::sort_issues(&mut issues, sort_by);
+
+    Ok(SweepReport {
+        docs: docs.len(),
+        sections,
+        sampled,
+        issues,
+    })
+}
+
+/// Send each sampled section to the LLM, at most `llm.max_concurrency` at a time. A failed
+/// section is skipped with a warning; the sweep fails only when every section failed.
+async fn verify(config: &Config, candidates: Vec<Candidate>) -> Result<Vec<Issue>> {
+    let config = Arc::new(config.clone());
+    let permits = Arc::new(Semaphore::new(config.llm.max_concurrency.max(1)));
+
+    let mut handles = Vec::new();
+    for candidate in candidates {
+        let config = Arc::clone(&config);
+        let permits = Arc::clone(&permits);
+        handles.push(tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await.ok();
+            let raw_issues = llm::analyze_sweep(
+                &config,
+                &candidate.excerpts,
+                &candidate.risk.missing,
+                std::slice::from_ref(&candidate.chunk),
+            )
+            .await;
+            (candidate, raw_issues)
+        }));
+    }
+
+    let total = handles.len();
+    let mut issues = Vec::new();
+    let mut failed = 0;
+    let mut last_error = None;
+    for handle in handles {
+        let (candidate, raw_issues) = handle
+            .await
+            .map_err(|e| DriftcheckError::LlmError(format!("sweep task failed: {}", e)))?;
+        match raw_issues {
+            Ok(raw_issues) => issues.extend(raw_issues.into_iter().map(|raw| Issue {
+                severity: candidate.severity,
+                corpus: candidate.corpus.clone(),
+                ..Issue::from(raw)
+            })),
+            Err(e) => {
+                warn!(
+                    "Could not verify {}:{}: {}",
+                    candidate.chunk.file, candidate.chunk.start_line, e
+                );
+                failed += 1;
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match last_error {
+        Some(e) if failed == total => Err(e),
+        _ => Ok(issues),
+    }
+}
+
+/// Split a doc into sections: at Markdown headings (outside code fences), otherwise every
+/// `SECTION_LINES` lines
+fn sections(file: &str, content: &str) -> Vec<DocChunk> {
+    let kind = DocKind::from_path(file);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let mut starts = vec![0];
+    let mut in_fence = false;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let last = *starts.last().unwrap_or(&0);
+        let heading = kind == DocKind::Markdown && !in_fence && trimmed.starts_with('#');
+        if i > last && (heading || i - last >= SECTION_LINES) {
+            starts.push(i);
+        }
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(lines.len());
+            DocChunk {
+                file: file.to_string(),
+                start_line: start + 1,
+                end_line: end.max(start + 1),
+                content: lines[start..end].join("\n"),
+                kind,
+            }
+        })
+        .filter(|chunk| !chunk.content.trim().is_empty())
+        .collect()
+}
+
+/// Code names a doc section refers to: flags, and identifiers with an underscore, a path
+/// separator or inner capitals, inside inline code spans
+fn references(text: &str) -> Vec<String> {
+    static SPAN: OnceLock<Regex> = OnceLock::new();
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    let span = SPAN.get_or_init(|| Regex::new(r"`([^`\n]+)`").unwrap());
+    let token = TOKEN.get_or_init(|| {
+        Regex::new(r"--[a-zA-Z][\w-]+|[A-Za-z_]\w*(?:(?:::|\.)[A-Za-z_]\w*)*").unwrap()
+    });
+
+    let mut names = Vec::new();
+    for code in span.captures_iter(text) {
+        for m in token.find_iter(&code[1]) {
+            let name = m.as_str();
+            let last = lookup_term(name);
+            let codelike = name.starts_with("--")
+                || name.contains('_')
+                || name.contains("::")
+                || name.contains('.')
+                || last.chars().skip(1).any(|c| c.is_ascii_uppercase());
+            if codelike && last.len() >= 4 && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// What to look for in the code: a flag without its dashes, a path's last segment
+fn lookup_term(name: &str) -> &str {
+    let name = name.trim_start_matches('-');
+    name.rsplit(['.', ':']).next().unwrap_or(name)
+}
+
+/// Tracked text files that are not docs
+fn code_files(config: &Config, doc_files: &HashSet<&str>) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for file in git::tracked_files(
+        &config.root,
+        &config.general.diff_include,
+        &config.general.diff_exclude,
+    )? {
+        let ext = file.rsplit_once('.').map(|(_, e)| e.to_lowercase());
+        if doc_files.contains(file.as_str())
+            || ext.is_some_and(|ext| DOC_EXTENSIONS.contains(&ext.as_str()))
+        {
+            continue;
+        }
+        let path = config.root.join(&file);
+        let small = fs::metadata(&path).is_ok_and(|m| m.len() <= MAX_CODE_FILE_BYTES);
+        if !small {
+            continue;
+        }
+        if let Ok(content) = fs::read_to_string(Path::new(&path)) {
+            files.push((file, content));
+        }
+    }
+    Ok(files)
+}
+
+/// Lines of code around the first matches of each name, and the names found nowhere
+fn code_excerpts(names: &[String], code: &[(String, String)]) -> (String, Vec<String>) {
+    let mut excerpts = String::new();
+    let mut missing = Vec::new();
+    for name in names {
+        let term = lookup_term(name);
+        // clap derives `--no-color` from a `no_color` field
+        let variants = [term.to_string(), term.replace('-', "_")];
+        let mut hits = 0;
+        'files: for (file, content) in code {
+            let lines: Vec<&str> = content.lines().collect();
+            for (i, line) in lines.iter().enumerate() {
+                if !variants.iter().any(|v| contains_word(line, v)) {
+                    continue;
+                }
+                hits += 1;
+                let start = i.saturating_sub(CONTEXT_LINES);
+                let end = (i + CONTEXT_LINES + 1).min(lines.len());
+                let excerpt = format!(
+                    "--- {} (lines {}-{}) ---\n{}\n\n",
+                    file,
+                    start + 1,
+                    end,
+                    lines[start..end].join("\n")
+                );
+                if excerpts.len() + excerpt.len() <= MAX_EXCERPT_CHARS {
+                    excerpts.push_str(&excerpt);
+                }
+                if hits >= HITS_PER_REFERENCE {
+                    break 'files;
+                }
+                break;
+            }
+        }
+        if hits == 0 {
+            missing.push(name.clone());
+        }
+    }
+    (excerpts, missing)
+}
+
+/// Whether `word` occurs in `line` with no identifier character on either side
+fn contains_word(line: &str, word: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(word).any(|(at, _)| {
+        let before = line[..at].chars().next_back();
+        let after = line[at + word.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_and_references() {
+        let doc = "# Usage\n\nRun `app --no-color` or set `output.color_mode`.\n\n```sh\n# not a heading\n```\n\n## API\n\nCall `Client::connect` and `fetchAll`, not `main`.\n";
+        let chunks = sections("README.md", doc);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 8));
+        assert_eq!(chunks[1].start_line, 9);
+
+        assert_eq!(
+            references(&chunks[0].content),
+            vec!["--no-color", "output.color_mode"]
+        );
+        assert_eq!(
+            references(&chunks[1].content),
+            vec!["Client::connect", "fetchAll"]
+        );
+
+        let code = vec![(
+            "src/cli.rs".to_string(),
+            "struct Args {\n    no_color: bool,\n}\nfn connect() {}\n".to_string(),
+        )];
+        let names: Vec<String> = ["--no-color", "Client::connect", "fetchAll"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (excerpts, missing) = code_excerpts(&names, &code);
+        assert!(excerpts.contains("no_color: bool"));
+        assert_eq!(missing, vec!["fetchAll"]);
+    }
+}
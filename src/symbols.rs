@@ -0,0 +1,215 @@
+use crate::analyzer::CodeLocation;
+use std::path::PathBuf;
+
+/// Keywords that introduce a definition of the name that follows them
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn",
+    "def",
+    "class",
+    "function",
+    "struct",
+    "enum",
+    "trait",
+    "interface",
+    "type",
+    "const",
+    "static",
+    "let",
+    "var",
+    "func",
+];
+
+/// Where a match sits in the diff, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Rank {
+    Definition,
+    Added,
+    Removed,
+    Context,
+}
+
+/// Where the code an issue is about changed: the symbol the model named, else the first
+/// backticked name in the description that appears in the diff. `files` are the diff's
+/// per-file sections (`git::file_diffs`).
+pub fn resolve(
+    files: &[(String, String)],
+    symbol: Option<&str>,
+    description: &str,
+) -> Option<CodeLocation> {
+    symbol
+        .map(str::to_string)
+        .into_iter()
+        .chain(backticked(description))
+        .find_map(|symbol| locate(files, &symbol))
+}
+
+/// The best location of `symbol` in the per-file diffs, preferring definitions, then added,
+/// removed and context lines. CLI flags fall back to their name without dashes and dotted
+/// config keys to their last segment.
+fn locate(files: &[(String, String)], symbol: &str) -> Option<CodeLocation> {
+    let symbol = clean(symbol);
+    if symbol.len() < 2 {
+        return None;
+    }
+
+    let best = |name: &str| {
+        files
+            .iter()
+            .filter_map(|(path, file_diff)| {
+                let (rank, line) = best_line(file_diff, name)?;
+                Some((rank, path, line))
+            })
+            .min_by_key(|(rank, _, _)| *rank)
+    };
+    let flag = symbol.trim_start_matches('-');
+    let last = symbol.rsplit(['.', ':']).next().unwrap_or(symbol);
+    let found = [symbol, flag, last]
+        .into_iter()
+        .filter(|name| name.len() >= 2)
+        .find_map(best)?;
+
+    let (_, path, line) = found;
+    Some(CodeLocation {
+        file: PathBuf::from(path),
+        line,
+        symbol: symbol.to_string(),
+    })
+}
+
+/// Best-ranked line mentioning `name` in one file's diff, with its line number in the new
+/// file. Removed lines are placed where they were removed.
+fn best_line(file_diff: &str, name: &str) -> Option<(Rank, usize)> {
+    let mut best: Option<(Rank, usize)> = None;
+    let mut new_line = 0;
+    let mut in_hunk = false;
+
+    for line in file_diff.lines() {
+        if let Some(header) = line.strip_prefix("@@") {
+            new_line = hunk_start(header).unwrap_or(1);
+            in_hunk = true;
+            continue;
+        }
+        if !in_hunk {
+            continue;
+        }
+        let (rank, text, line_no) = match line.chars().next() {
+            Some('+') => (Rank::Added, &line[1..], new_line),
+            Some('-') => (Rank::Removed, &line[1..], new_line.max(1)),
+            Some('\\') => continue,
+            _ => (Rank::Context, line.get(1..).unwrap_or_default(), new_line),
+        };
+        if rank != Rank::Removed {
+            new_line += 1;
+        }
+        if !mentions(text, name) {
+            continue;
+        }
+        let rank = if rank == Rank::Added && defines(text, name) {
+            Rank::Definition
+        } else {
+            rank
+        };
+        if best.is_none_or(|(r, _)| rank < r) {
+            best = Some((rank, line_no));
+        }
+    }
+    best
+}
+
+/// New-file start line from a hunk header's `+c,d` range
+fn hunk_start(header: &str) -> Option<usize> {
+    let range = header.split_whitespace().find(|p| p.starts_with('+'))?;
+    range[1..].split(',').next()?.parse().ok()
+}
+
+/// Whether `text` contains `name` as a whole word
+fn mentions(text: &str, name: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(name).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + name.len()..].chars().next();
+        !before.is_some_and(|c| is_word(c) || (c == '-' && name.starts_with('-')))
+            && !after.is_some_and(|c| is_word(c) || c == '-')
+    })
+}
+
+/// Whether `text` defines `name`: after a definition keyword, as an assignment or key,
+/// or as a quoted string (flag and key names in CLI and config definitions)
+fn defines(text: &str, name: &str) -> bool {
+    let words: Vec<&str> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words
+        .windows(2)
+        .any(|w| w[1] == name && DEFINITION_KEYWORDS.contains(&w[0]))
+    {
+        return true;
+    }
+    if text.contains(&format!("\"{}\"", name)) || text.contains(&format!("'{}'", name)) {
+        return true;
+    }
+    text.match_indices(name).any(|(at, _)| {
+        let rest = text[at + name.len()..].trim_start();
+        (rest.starts_with('=') && !rest.starts_with("==")) || rest.starts_with(':')
+    })
+}
+
+/// The symbol without backticks, call parentheses or a `=value` suffix
+fn clean(symbol: &str) -> &str {
+    let symbol = symbol.trim().trim_matches('`').trim();
+    let symbol = symbol.split('=').next().unwrap_or(symbol).trim();
+    let symbol = symbol.split('(').next().unwrap_or(symbol);
+    symbol.trim_end_matches("()").trim()
+}
+
+/// Names quoted in backticks, in order
+fn backticked(text: &str) -> Vec<String> {
+    text.split('`')
+        .skip(1)
+        .step_by(2)
+        .map(str::trim)
+        .filter(|s| !s.is_empty() && !s.contains(char::is_whitespace))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git;
+
+    const DIFF: &str = "diff --git a/src/main.rs b/src/main.rs
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -10,4 +10,5 @@ struct Cli {
+     /// Plain output
+-    #[arg(long = \"no-color\")]
++    #[arg(long = \"plain\")]
++    plain: bool,
+     verbose: bool,
+@@ -40,3 +41,3 @@ fn run() {
+-    let timeout = 30;
++    let timeout = 60;
+     connect(timeout);
+";
+
+    #[test]
+    fn test_locate_symbols_in_diff() {
+        let files = git::file_diffs(DIFF);
+        let at = |symbol: &str| locate(&files, symbol).map(|l| (l.file, l.line));
+        let main = PathBuf::from("src/main.rs");
+
+        assert_eq!(at("--plain"), Some((main.clone(), 11)));
+        assert_eq!(at("plain"), Some((main.clone(), 11)));
+        assert_eq!(at("`timeout`"), Some((main.clone(), 41)));
+        assert_eq!(at("llm.timeout"), Some((main.clone(), 41)));
+        assert_eq!(at("no-color"), Some((main.clone(), 11)));
+        assert_eq!(at("connect()"), Some((main, 42)));
+        assert_eq!(at("verbose_mode"), None);
+        assert_eq!(
+            resolve(&files, None, "The `--plain` flag replaced `--no-color`").map(|l| l.symbol),
+            Some("--plain".to_string())
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{warn, Level, Subscriber};
+use tracing_subscriber::filter::{Filtered, Targets};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// OTLP/HTTP collector base URL (e.g. `http://localhost:4318`); traces are exported when set
+pub const ENDPOINT_ENV: &str = "DRIFTCHECK_OTEL_ENDPOINT";
+
+/// Extra export headers as `key=value` pairs separated by commas (e.g. an API key)
+pub const HEADERS_ENV: &str = "DRIFTCHECK_OTEL_HEADERS";
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A span field holding an error message; a span that records it gets an error status
+pub const ERROR_FIELD: &str = "error";
+
+/// Spans from this crate, collected for one OTLP export when the process ends
+#[derive(Clone)]
+pub struct OtlpLayer {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    ids: RandomState,
+    counter: u64,
+    trace_id: String,
+    open: HashMap<u64, SpanData>,
+    finished: Vec<SpanData>,
+}
+
+struct SpanData {
+    span_id: String,
+    parent_id: Option<String>,
+    name: &'static str,
+    start: SystemTime,
+    end: Option<SystemTime>,
+    attributes: Vec<(String, Value)>,
+    error: Option<String>,
+}
+
+static EXPORT: OnceLock<(String, Arc<Mutex<State>>)> = OnceLock::new();
+
+/// The tracing layer exporting driftcheck's spans, or `None` when `DRIFTCHECK_OTEL_ENDPOINT`
+/// is not set. Only spans at info level or above from this crate are exported.
+pub fn layer<S>() -> Option<Filtered<OtlpLayer, Targets, S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let endpoint = env::var(ENDPOINT_ENV)
+        .ok()
+        .filter(|e| !e.trim().is_empty())?;
+
+    let ids = RandomState::new();
+    let trace_id = format!("{:016x}{:016x}", random(&ids, 0), random(&ids, 1));
+    let state = Arc::new(Mutex::new(State {
+        ids,
+        counter: 2,
+        trace_id,
+        open: HashMap::new(),
+        finished: Vec::new(),
+    }));
+    EXPORT.set((endpoint, Arc::clone(&state))).ok()?;
+
+    let targets = Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::INFO);
+    Some(OtlpLayer { state }.with_filter(targets))
+}
+
+/// Export the collected spans. Spans still open (e.g. when exiting early) end now.
+/// Runs the request to completion even when called from synchronous code.
+pub fn shutdown() {
+    let Some((endpoint, state)) = EXPORT.get() else {
+        return;
+    };
+    let body = {
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        let now = SystemTime::now();
+        let open: Vec<SpanData> = state.open.drain().map(|(_, span)| span).collect();
+        state.finished.extend(open.into_iter().map(|mut span| {
+            span.end.get_or_insert(now);
+            span
+        }));
+        if state.finished.is_empty() {
+            return;
+        }
+        let spans: Vec<SpanData> = state.finished.drain(..).collect();
+        export_body(&state.trace_id, &spans)
+    };
+
+    let url = traces_url(endpoint);
+    let send = async {
+        let client = reqwest::Client::builder()
+            .timeout(EXPORT_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut request = client.post(&url).json(&body);
+        for (key, value) in export_headers() {
+            request = request.header(key, value);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        Ok(())
+    };
+
+    let result = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => tokio::task::block_in_place(|| handle.block_on(send)),
+        Err(_) => Err("no async runtime".to_string()),
+    };
+    if let Err(e) = result {
+        warn!("Failed to export traces to {}: {}", url, e);
+    }
+}
+
+/// `/v1/traces` under the collector URL, unless the URL already names it
+fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim().trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+fn export_headers() -> Vec<(String, String)> {
+    env::var(HEADERS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .filter(|(k, _)| !k.is_empty())
+        .collect()
+}
+
+/// OTLP/JSON request body for one trace
+fn export_body(trace_id: &str, spans: &[SpanData]) -> Value {
+    let spans: Vec<Value> = spans
+        .iter()
+        .map(|span| {
+            let mut value = json!({
+                "traceId": trace_id,
+                "spanId": span.span_id,
+                "name": span.name,
+                "kind": 1,
+                "startTimeUnixNano": unix_nanos(span.start),
+                "endTimeUnixNano": unix_nanos(span.end.unwrap_or(span.start)),
+                "attributes": attributes(&span.attributes),
+                "status": match &span.error {
+                    Some(message) => json!({"code": 2, "message": message}),
+                    None => json!({"code": 0}),
+                },
+            });
+            if let Some(parent) = &span.parent_id {
+                value["parentSpanId"] = json!(parent);
+            }
+            value
+        })
+        .collect();
+
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": attributes(&[
+                    ("service.name".to_string(), json!("driftcheck")),
+                    ("service.version".to_string(), json!(env!("CARGO_PKG_VERSION"))),
+                ]),
+            },
+            "scopeSpans": [{
+                "scope": {"name": "driftcheck", "version": env!("CARGO_PKG_VERSION")},
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+/// OTLP key-value list; integers are strings in OTLP/JSON
+fn attributes(values: &[(String, Value)]) -> Vec<Value> {
+    values
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Bool(b) => json!({"boolValue": b}),
+                Value::Number(n) if n.is_f64() => json!({"doubleValue": n}),
+                Value::Number(n) => json!({"intValue": n.to_string()}),
+                Value::String(s) => json!({"stringValue": s}),
+                other => json!({"stringValue": other.to_string()}),
+            };
+            json!({"key": key, "value": value})
+        })
+        .collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// A random 64-bit value from the process's random hasher keys
+fn random(ids: &RandomState, counter: u64) -> u64 {
+    let mut hasher = ids.build_hasher();
+    hasher.write_u64(counter);
+    hasher.finish()
+}
+
+impl State {
+    fn next_span_id(&mut self) -> String {
+        self.counter += 1;
+        format!("{:016x}", random(&self.ids, self.counter))
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let parent = ctx
+            .span(id)
+            .and_then(|span| span.parent())
+            .map(|parent| parent.id().into_u64());
+        let mut fields = FieldVisitor::default();
+        attrs.record(&mut fields);
+
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let span_id = state.next_span_id();
+        let parent_id = parent.and_then(|p| state.open.get(&p).map(|s| s.span_id.clone()));
+        state.open.insert(
+            id.into_u64(),
+            SpanData {
+                span_id,
+                parent_id,
+                name: attrs.metadata().name(),
+                start: SystemTime::now(),
+                end: None,
+                attributes: fields.attributes,
+                error: fields.error,
+            },
+        );
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut fields = FieldVisitor::default();
+        values.record(&mut fields);
+
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(span) = state.open.get_mut(&id.into_u64()) {
+            for (key, value) in fields.attributes {
+                span.attributes.retain(|(k, _)| k != &key);
+                span.attributes.push((key, value));
+            }
+            if fields.error.is_some() {
+                span.error = fields.error;
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, _ctx: Context<'_, S>) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(mut span) = state.open.remove(&id.into_u64()) {
+            span.end = Some(SystemTime::now());
+            state.finished.push(span);
+        }
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    attributes: Vec<(String, Value)>,
+    error: Option<String>,
+}
+
+impl FieldVisitor {
+    fn push(&mut self, field: &Field, value: Value) {
+        if field.name() == ERROR_FIELD {
+            self.error = Some(match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            });
+        } else {
+            self.attributes.push((field.name().to_string(), value));
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, json!(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, json!(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, json!(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, json!(format!("{:?}", value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_spans_export_as_otlp() {
+        let layer = OtlpLayer {
+            state: Arc::new(Mutex::new(State {
+                ids: RandomState::new(),
+                counter: 0,
+                trace_id: "0af7651916cd43dd8448eb211c80319c".to_string(),
+                open: HashMap::new(),
+                finished: Vec::new(),
+            })),
+        };
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let root = info_span!("driftcheck", command = "check");
+            let _root = root.enter();
+            let chat = info_span!(
+                "llm.chat",
+                gen_ai.usage.input_tokens = tracing::field::Empty,
+                error = tracing::field::Empty
+            );
+            chat.record("gen_ai.usage.input_tokens", 1200u64);
+            chat.record("error", "HTTP 429");
+        });
+
+        let state = layer.state.lock().unwrap();
+        let body = export_body(&state.trace_id, &state.finished);
+        let spans = &body["resourceSpans"][0]["scopeSpans"][0]["spans"];
+
+        assert_eq!(spans[0]["name"], "llm.chat");
+        assert_eq!(spans[0]["parentSpanId"], spans[1]["spanId"]);
+        assert_eq!(
+            spans[0]["attributes"][0],
+            json!({"key": "gen_ai.usage.input_tokens", "value": {"intValue": "1200"}})
+        );
+        assert_eq!(
+            spans[0]["status"],
+            json!({"code": 2, "message": "HTTP 429"})
+        );
+        assert_eq!(spans[1]["status"], json!({"code": 0}));
+        assert!(spans[1].get("parentSpanId").is_none());
+        assert_eq!(
+            traces_url("http://otel:4318/"),
+            "http://otel:4318/v1/traces"
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::env;
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// NO_COLOR, CLICOLOR=0 or a dumb terminal
+    None,
+    /// The 8 basic ANSI colors (e.g. the Linux console)
+    Basic,
+    /// 16 colors or more
+    Full,
+}
+
+/// Symbols used in terminal output, with ASCII fallbacks
+#[derive(Debug)]
+pub struct Symbols {
+    pub pending: &'static str,
+    pub queued: &'static str,
+    pub skipped: &'static str,
+    pub applied: &'static str,
+    pub previewed: &'static str,
+    pub proposed: &'static str,
+    pub rejected: &'static str,
+    pub conflict: &'static str,
+    pub error: &'static str,
+    pub rule: &'static str,
+    pub dash: &'static str,
+    pub spinner: &'static [&'static str],
+}
+
+pub const UNICODE_SYMBOLS: Symbols = Symbols {
+    pending: "○",
+    queued: "…",
+    skipped: "⊘",
+    applied: "✓",
+    previewed: "◐",
+    proposed: "◇",
+    rejected: "!",
+    conflict: "≠",
+    error: "✗",
+    rule: "━",
+    dash: "—",
+    spinner: &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+};
+
+pub const ASCII_SYMBOLS: Symbols = Symbols {
+    pending: "o",
+    queued: ".",
+    skipped: "-",
+    applied: "+",
+    previewed: "~",
+    proposed: "?",
+    rejected: "!",
+    conflict: "#",
+    error: "x",
+    rule: "=",
+    dash: "-",
+    spinner: &["|", "/", "-", "\\"],
+};
+
+/// Detected terminal capabilities
+#[derive(Debug, Clone, Copy)]
+pub struct TermCaps {
+    pub color: ColorSupport,
+    pub unicode: bool,
+}
+
+impl TermCaps {
+    /// Detect capabilities from NO_COLOR, CLICOLOR, CLICOLOR_FORCE, TERM and the locale
+    pub fn detect() -> Self {
+        Self::from_env(|key| env::var(key).ok())
+    }
+
+    fn from_env(get: impl Fn(&str) -> Option<String>) -> Self {
+        let term = get("TERM").unwrap_or_default();
+        let set = |key: &str| get(key).is_some_and(|v| !v.is_empty());
+
+        let color = if set("NO_COLOR") {
+            ColorSupport::None
+        } else if get("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+            ColorSupport::Full
+        } else if get("CLICOLOR").as_deref() == Some("0") || term == "dumb" {
+            ColorSupport::None
+        } else if term == "linux" || term.ends_with("-8color") || term == "vt100" {
+            ColorSupport::Basic
+        } else {
+            ColorSupport::Full
+        };
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|key| get(key).filter(|v| !v.is_empty()))
+            .unwrap_or_default()
+            .to_uppercase();
+        let unicode = match term.as_str() {
+            "dumb" | "linux" | "vt100" => false,
+            // Windows terminals don't set a locale but render unicode fine
+            _ if cfg!(windows) => true,
+            _ => locale.contains("UTF-8") || locale.contains("UTF8"),
+        };
+
+        Self { color, unicode }
+    }
+
+    pub fn symbols(&self) -> &'static Symbols {
+        if self.unicode {
+            &UNICODE_SYMBOLS
+        } else {
+            &ASCII_SYMBOLS
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn caps(vars: &[(&str, &str)]) -> TermCaps {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        TermCaps::from_env(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_no_color_wins() {
+        let caps = caps(&[("NO_COLOR", "1"), ("TERM", "xterm-256color")]);
+        assert_eq!(caps.color, ColorSupport::None);
+    }
+
+    #[test]
+    fn test_linux_console_is_basic_and_ascii() {
+        let caps = caps(&[("TERM", "linux"), ("LANG", "en_US.UTF-8")]);
+        assert_eq!(caps.color, ColorSupport::Basic);
+        assert!(!caps.unicode);
+    }
+
+    #[test]
+    fn test_modern_terminal() {
+        let caps = caps(&[("TERM", "xterm-256color"), ("LANG", "de_DE.UTF-8")]);
+        assert_eq!(caps.color, ColorSupport::Full);
+        assert!(caps.unicode);
+    }
+}
@@ -1,6 +1,16 @@
-use crate::analyzer::Issue;
+use crate::analyzer::{self, Issue};
+use crate::baseline;
 use crate::config::Config;
 use crate::error::{DriftcheckError, Result};
+use crate::feedback;
+use crate::fix;
+use crate::history::{self, IssueState};
+use crate::lfs;
+use crate::llm::{stream, DocKind};
+use crate::output;
+use crate::patch::{self, Merge};
+use crate::preview::Preview;
+use crate::search::remote;
 use crate::tui::Theme;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
@@ -10,65 +20,280 @@ use crossterm::{
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
     text::{Line, Span},
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::io::{self, Stdout};
+use std::path::{Path, PathBuf};
 use tokio::task::JoinHandle;
 
 pub struct App {
     issues: Vec<Issue>,
+    /// Issues dropped by `general.max_issues`
+    hidden: usize,
     config: Config,
     theme: Theme,
     current_issue: usize,
     list_state: ListState,
     show_help: bool,
+    // Show the model's full excerpt instead of the trimmed one
+    show_full_excerpt: bool,
     actions: Vec<IssueAction>,
     should_quit: bool,
     should_abort: bool,
     status_message: Option<String>,
-    // Background task tracking
-    active_task: Option<ActiveTask>,
+    // Background task tracking: at most one task per file, files run in parallel
+    active_tasks: Vec<ActiveTask>,
+    // Issues waiting for another fix to the same file to finish
+    queued: VecDeque<usize>,
+    // Number of fixes applied per file during this session
+    applied_files: HashMap<PathBuf, usize>,
     spinner_frame: usize,
+    // Fixes rejected by the critic pass, kept for manual review
+    rejections: Vec<Option<Rejection>>,
+    // Generated fixes shown as a diff until the user accepts or rejects them (`tui.show_diff_preview`)
+    proposals: Vec<Option<Proposal>>,
+    // Preview directory receiving fixes when `tui.preview` is set, created on the first fix
+    preview: Option<Preview>,
+    // Whether the user was told that previewed fixes are not applied yet
+    preview_warned: bool,
+    // Fixes written to the working tree, most recent last, for undo
+    undo_stack: Vec<AppliedFix>,
+    // Set by 'o'; the run loop suspends the TUI and opens the current issue in $EDITOR
+    open_editor: bool,
+    // Set by 'O'; like `open_editor`, for the code the current issue is about
+    open_code: bool,
+    // Hash of each issue file as of session start, updated when driftcheck itself writes it,
+    // to catch changes made outside the session (editor autosave, branch checkout)
+    file_hashes: HashMap<PathBuf, Vec<u8>>,
+    // Issue whose file changed on disk, waiting for 'r' (rebase) or 'R' (re-analyze)
+    stale: Option<usize>,
+    // Fixes overlapping edits made since they were generated, being resolved in the merge view
+    merges: Vec<Option<Resolution>>,
+    // The analyzed diff, for verifying the applied fixes before exit (`tui.verify_fixes`)
+    diff: Option<String>,
+    // Final analysis of the fixed docs, running in the background
+    verification: Option<JoinHandle<Result<Vec<Issue>>>>,
+    // Fixed files and their hashes as of the last verification, so it runs again only after
+    // further fixes
+    verified: Vec<(PathBuf, Option<Vec<u8>>)>,
 }
 
 struct ActiveTask {
     issue_idx: usize,
-    handle: JoinHandle<Result<String>>,
+    file: PathBuf,
+    handle: JoinHandle<Result<FixOutcome>>,
+    // Text of the fix as it is generated, with `llm.stream`
+    live: Option<stream::Live>,
+}
+
+/// File contents around a fix written to the working tree
+struct Written {
+    file: PathBuf,
+    // Content before the fix
+    previous: String,
+    // Content the fix wrote, to detect later edits
+    content: String,
+}
+
+/// A fix written to the working tree that can be undone, possibly spanning several files
+struct AppliedFix {
+    issue_idx: usize,
+    written: Vec<Written>,
+    // Issue state before the fix, restored on undo
+    state: IssueState,
+}
+
+/// Result of a background fix task
+enum FixOutcome {
+    Applied(String, Vec<Written>),
+    /// Written to the preview directory instead of the working tree
+    Previewed(String, Vec<PathBuf>),
+    Rejected(Rejection),
+    /// Generated but not written, waiting for the user to accept the diff
+    Proposed(Proposal),
+    /// Written to the preview directory with conflict markers that the user has to resolve
+    Conflict(String),
+    /// Overlaps edits made since the fix was generated; resolved in the merge view
+    Overlaps(Resolution),
+}
+
+#[derive(Debug, Clone)]
+struct Rejection {
+    reason: String,
+    fixed_content: String,
+    // File content the fix was generated from, used as the merge base
+    original_content: String,
+    // Edits to other docs, written together with the fix
+    related: Vec<fix::RelatedFix>,
+}
+
+/// A fix overlapping edits made to its doc since it was generated, resolved conflict by
+/// conflict: keep your lines ('h'), take the fix's ('l'), both ('b'), or edit them ('o')
+#[derive(Debug, Clone)]
+struct Resolution {
+    // File content the fix was merged into; the result is only written over this content
+    current_content: String,
+    regions: Vec<patch::Region>,
+    // Chosen lines of each conflict, in order
+    picks: Vec<Option<String>>,
+    // Conflict shown in the merge view
+    selected: usize,
+}
+
+impl Resolution {
+    fn new(current_content: String, regions: Vec<patch::Region>) -> Self {
+        let count = regions
+            .iter()
+            .filter(|r| matches!(r, patch::Region::Conflict { .. }))
+            .count();
+        Self {
+            current_content,
+            regions,
+            picks: vec![None; count],
+            selected: 0,
+        }
+    }
+
+    /// Base, current and fix lines of each conflict
+    fn conflicts(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.regions.iter().filter_map(|region| match region {
+            patch::Region::Conflict { base, current, fix } => {
+                Some((base.as_str(), current.as_str(), fix.as_str()))
+            }
+            patch::Region::Clean(_) => None,
+        })
+    }
+
+    fn remaining(&self) -> usize {
+        self.picks.iter().filter(|p| p.is_none()).count()
+    }
+
+    /// Resolve the selected conflict with `text` and select the next unresolved one
+    fn pick(&mut self, text: String) {
+        self.picks[self.selected] = Some(text);
+        let count = self.picks.len();
+        if let Some(next) = (1..count)
+            .map(|i| (self.selected + i) % count)
+            .find(|&i| self.picks[i].is_none())
+        {
+            self.selected = next;
+        }
+    }
+
+    /// The merged file, once every conflict is resolved
+    fn merged(&self) -> Option<String> {
+        let mut picks = self.picks.iter();
+        let mut merged = String::new();
+        for region in &self.regions {
+            match region {
+                patch::Region::Clean(text) => merged.push_str(text),
+                patch::Region::Conflict { .. } => merged.push_str(picks.next()?.as_deref()?),
+            }
+        }
+        Some(merged)
+    }
+}
+
+/// A generated fix shown as a diff before it is written
+#[derive(Debug, Clone)]
+struct Proposal {
+    fixed_content: String,
+    // File content the fix was generated from, used as the merge base
+    original_content: String,
+    // Edits to other docs, written together with the fix
+    related: Vec<fix::RelatedFix>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum IssueAction {
     Pending,
+    Queued,
     Applying,
     Skip,
+    /// Hidden until the snooze runs out (`tui.snooze_days`)
+    Snoozed,
     Applied,
+    /// Written to the preview directory, waiting for "apply all previewed changes"
+    Previewed,
+    Rejected,
+    /// The generated fix is shown as a diff, waiting for accept ('y') or reject ('n')
+    Proposed,
+    /// The fix conflicted with changes made since it was generated
+    Conflict,
+    /// Fixed by hand in $EDITOR
+    Edited,
+    /// Fix to a remote page, saved as a patch to apply in its wiki
+    Exported,
     Error,
 }
 
+impl IssueAction {
+    /// Whether the user still has to decide on this issue
+    fn needs_review(&self) -> bool {
+        matches!(
+            self,
+            IssueAction::Pending | IssueAction::Rejected | IssueAction::Proposed
+        )
+    }
+}
+
 impl App {
-    pub fn new(issues: Vec<Issue>, config: Config, theme: Theme) -> Self {
+    pub fn new(
+        issues: Vec<Issue>,
+        hidden: usize,
+        diff: Option<String>,
+        config: Config,
+        theme: Theme,
+    ) -> Self {
         let count = issues.len();
         let mut list_state = ListState::default();
         if count > 0 {
             list_state.select(Some(0));
         }
 
+        let mut file_hashes = HashMap::new();
+        for issue in &issues {
+            if let Ok(content) = fs::read_to_string(config.resolve(&issue.file)) {
+                file_hashes.insert(issue.file.clone(), content_hash(&content));
+            }
+        }
+
         Self {
             issues,
+            hidden,
             config,
             theme,
             current_issue: 0,
             list_state,
             show_help: false,
+            show_full_excerpt: false,
             actions: vec![IssueAction::Pending; count],
             should_quit: false,
             should_abort: false,
             status_message: None,
-            active_task: None,
+            active_tasks: Vec::new(),
+            queued: VecDeque::new(),
+            applied_files: HashMap::new(),
             spinner_frame: 0,
+            rejections: vec![None; count],
+            proposals: vec![None; count],
+            preview: None,
+            preview_warned: false,
+            undo_stack: Vec::new(),
+            open_editor: false,
+            open_code: false,
+            file_hashes,
+            stale: None,
+            merges: vec![None; count],
+            diff,
+            verification: None,
+            verified: Vec::new(),
         }
     }
 
@@ -82,6 +307,10 @@ impl App {
         let mut terminal =
             Terminal::new(backend).map_err(|e| DriftcheckError::TuiError(e.to_string()))?;
 
+        if self.config.tui.auto_apply && !Config::is_read_only() {
+            self.apply_all();
+        }
+
         // Run the app
         let result = self.run_loop(&mut terminal).await;
 
@@ -104,16 +333,17 @@ impl App {
         loop {
             // Check if background task completed
             self.check_task_completion().await;
+            self.check_verification().await;
 
             // Update spinner
-            self.spinner_frame = (self.spinner_frame + 1) % 10;
+            self.spinner_frame = (self.spinner_frame + 1) % self.theme.symbols.spinner.len();
 
             terminal
                 .draw(|f| self.draw(f))
                 .map_err(|e| DriftcheckError::TuiError(e.to_string()))?;
 
             // Use shorter poll time when task is active (for spinner animation)
-            let poll_duration = if self.active_task.is_some() {
+            let poll_duration = if !self.active_tasks.is_empty() || self.verification.is_some() {
                 std::time::Duration::from_millis(80)
             } else {
                 std::time::Duration::from_millis(100)
@@ -127,6 +357,15 @@ impl App {
                 }
             }
 
+            if self.open_editor {
+                self.open_editor = false;
+                self.edit_current(terminal)?;
+            }
+            if self.open_code {
+                self.open_code = false;
+                self.view_code(terminal)?;
+            }
+
             if self.should_quit {
                 break;
             }
@@ -141,29 +380,395 @@ impl App {
         Ok(())
     }
 
+    /// Suspend the TUI, open the current issue's doc in $EDITOR at the issue line, and mark the
+    /// issue as fixed by hand if the file changed
+    fn edit_current(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let idx = self.current_issue;
+        let Some(issue) = self.issues.get(idx) else {
+            return Ok(());
+        };
+        if self.file_busy(&issue.file) {
+            self.status_message = Some("Waiting for fixes to finish...".to_string());
+            return Ok(());
+        }
+        if self.merges[idx].is_some() {
+            return self.edit_conflict(terminal);
+        }
+        if remote::is_remote(&issue.file) {
+            self.status_message = Some(format!(
+                "{} is a remote page; edit it in its wiki",
+                issue.file.display()
+            ));
+            return Ok(());
+        }
+        let file = issue.file.clone();
+        let path = self.config.resolve(&file);
+        let before = fs::read_to_string(&path).ok();
+
+        let line = issue.line;
+        if let Err(e) = suspended(terminal, || fix::open_in_editor(&path, line))? {
+            self.status_message = Some(format!("Error: {}", e));
+            return Ok(());
+        }
+        if fs::read_to_string(&path).ok() == before {
+            self.status_message = Some(format!("No changes made to {}", file.display()));
+            return Ok(());
+        }
+
+        self.record_written(&file);
+        self.actions[idx] = IssueAction::Edited;
+        self.rejections[idx] = None;
+        self.proposals[idx] = None;
+        self.persist_state(idx, IssueState::Fixed);
+        self.status_message = Some(format!("Marked as fixed by hand in {}", file.display()));
+        self.move_to_next_pending();
+        Ok(())
+    }
+
+    /// Suspend the TUI and open the selected conflict of the merge view in $EDITOR, with
+    /// conflict markers around your lines, the base and the fix; the saved lines resolve it
+    fn edit_conflict(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let idx = self.current_issue;
+        let Some(resolution) = &self.merges[idx] else {
+            return Ok(());
+        };
+        let Some((base, current, fix)) = resolution.conflicts().nth(resolution.selected) else {
+            return Ok(());
+        };
+        let name = self.issues[idx].file.file_name().unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "driftcheck-merge-{}-{}",
+            std::process::id(),
+            name.to_string_lossy()
+        ));
+        let text = format!(
+            "<<<<<<< yours\n{}||||||| base\n{}=======\n{}>>>>>>> fix\n",
+            current, base, fix
+        );
+        if let Err(e) = fs::write(&path, &text) {
+            self.status_message = Some(format!("Error: {}", e));
+            return Ok(());
+        }
+
+        let opened = suspended(terminal, || fix::open_in_editor(&path, 1))?;
+        let edited = fs::read_to_string(&path);
+        let _ = fs::remove_file(&path);
+        match (opened, edited) {
+            (Err(e), _) => self.status_message = Some(format!("Error: {}", e)),
+            (_, Err(e)) => self.status_message = Some(format!("Error: {}", e)),
+            (Ok(()), Ok(edited)) if edited == text => {
+                self.status_message = Some("Conflict left unresolved".to_string());
+            }
+            (Ok(()), Ok(edited))
+                if edited
+                    .lines()
+                    .any(|l| l.starts_with("<<<<<<<") || l.starts_with(">>>>>>>")) =>
+            {
+                self.status_message =
+                    Some("Conflict markers left in; the conflict is unresolved".to_string());
+            }
+            (Ok(()), Ok(edited)) => {
+                if let Some(resolution) = self.merges[idx].as_mut() {
+                    resolution.pick(edited);
+                }
+                self.merge_status();
+            }
+        }
+        Ok(())
+    }
+
+    /// Keys of the merge view: pick a side of the selected conflict, move between conflicts,
+    /// write the merged file or discard the fix. Returns whether the key was handled.
+    fn merge_key(&mut self, key: KeyCode) -> bool {
+        let idx = self.current_issue;
+        let Some(resolution) = self.merges[idx].as_mut() else {
+            return false;
+        };
+        let Some((_, current, fix)) = resolution.conflicts().nth(resolution.selected) else {
+            return false;
+        };
+        let (current, fix) = (current.to_string(), fix.to_string());
+        match key {
+            KeyCode::Char('h') | KeyCode::Left => resolution.pick(current),
+            KeyCode::Char('l') | KeyCode::Right => resolution.pick(fix),
+            KeyCode::Char('b') => resolution.pick(current + &fix),
+            KeyCode::Tab => {
+                resolution.selected = (resolution.selected + 1) % resolution.picks.len();
+                return true;
+            }
+            KeyCode::Char('y') => {
+                self.write_merge();
+                return true;
+            }
+            KeyCode::Char('n') => {
+                self.merges[idx] = None;
+                self.actions[idx] = IssueAction::Pending;
+                self.status_message = Some("Fix discarded".to_string());
+                return true;
+            }
+            _ => return false,
+        }
+        self.merge_status();
+        true
+    }
+
+    fn merge_status(&mut self) {
+        let Some(resolution) = &self.merges[self.current_issue] else {
+            return;
+        };
+        self.status_message = Some(match resolution.remaining() {
+            0 => "All conflicts resolved: 'y' writes the merged file".to_string(),
+            n => format!("{} conflict(s) left", n),
+        });
+    }
+
+    /// Write the merged file of the current issue's merge view, unless the file changed
+    /// since the merge started
+    fn write_merge(&mut self) {
+        let idx = self.current_issue;
+        let Some(resolution) = &self.merges[idx] else {
+            return;
+        };
+        let Some(content) = resolution.merged() else {
+            self.status_message = Some(format!(
+                "Resolve the {} remaining conflict(s) first",
+                resolution.remaining()
+            ));
+            return;
+        };
+        let written = vec![Written {
+            file: self.issues[idx].file.clone(),
+            previous: resolution.current_content.clone(),
+            content,
+        }];
+        if let Err(e) = write_all(&self.config, &written) {
+            self.status_message = Some(format!("Error: {}", e));
+            return;
+        }
+
+        self.merges[idx] = None;
+        for w in &written {
+            *self.applied_files.entry(w.file.clone()).or_default() += 1;
+            self.file_hashes
+                .insert(w.file.clone(), content_hash(&w.content));
+        }
+        self.status_message = Some(format!(
+            "Applied the merged fix to {}",
+            self.issues[idx].file.display()
+        ));
+        self.push_undo(idx, written);
+        self.actions[idx] = IssueAction::Applied;
+        self.persist_state(idx, IssueState::Fixed);
+        self.move_to_next_pending();
+    }
+
+    /// Suspend the TUI and open the code the current issue is about in $EDITOR, at the
+    /// changed line
+    fn view_code(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+        let Some(code) = self
+            .issues
+            .get(self.current_issue)
+            .and_then(|issue| issue.code_location.clone())
+        else {
+            self.status_message = Some("No code location for this issue".to_string());
+            return Ok(());
+        };
+        let path = self.config.resolve(&code.file);
+        if let Err(e) = suspended(terminal, || fix::open_in_editor(&path, code.line))? {
+            self.status_message = Some(format!("Error: {}", e));
+        }
+        Ok(())
+    }
+
     async fn check_task_completion(&mut self) {
-        if let Some(task) = &mut self.active_task {
-            // Check if task is finished (non-blocking)
-            if task.handle.is_finished() {
-                let task = self.active_task.take().unwrap();
-                match task.handle.await {
-                    Ok(Ok(msg)) => {
-                        self.actions[task.issue_idx] = IssueAction::Applied;
-                        self.status_message = Some(msg);
-                        // Move to next pending issue
+        // Collect finished tasks (non-blocking)
+        let (finished, running): (Vec<_>, Vec<_>) = self
+            .active_tasks
+            .drain(..)
+            .partition(|task| task.handle.is_finished());
+        self.active_tasks = running;
+
+        for task in finished {
+            let is_current = task.issue_idx == self.current_issue;
+            match task.handle.await {
+                Ok(Ok(FixOutcome::Applied(msg, written))) => {
+                    for w in &written {
+                        *self.applied_files.entry(w.file.clone()).or_default() += 1;
+                        self.file_hashes
+                            .insert(w.file.clone(), content_hash(&w.content));
+                    }
+                    self.push_undo(task.issue_idx, written);
+                    self.actions[task.issue_idx] = IssueAction::Applied;
+                    self.persist_state(task.issue_idx, IssueState::Fixed);
+                    self.rejections[task.issue_idx] = None;
+                    self.status_message = Some(msg);
+                    // Move to next pending issue
+                    if is_current {
+                        self.move_to_next_pending();
+                    }
+                }
+                Ok(Ok(FixOutcome::Previewed(msg, files))) => {
+                    self.actions[task.issue_idx] = IssueAction::Previewed;
+                    self.rejections[task.issue_idx] = None;
+                    for file in files {
+                        *self.applied_files.entry(file).or_default() += 1;
+                    }
+                    self.status_message = Some(msg);
+                    if is_current {
                         self.move_to_next_pending();
                     }
-                    Ok(Err(e)) => {
-                        self.actions[task.issue_idx] = IssueAction::Error;
-                        self.status_message = Some(format!("Error: {}", e));
+                }
+                Ok(Ok(FixOutcome::Overlaps(resolution))) => {
+                    self.actions[task.issue_idx] = IssueAction::Conflict;
+                    self.rejections[task.issue_idx] = None;
+                    self.status_message = Some(format!(
+                        "The fix overlaps your edits to {} in {} place(s): 'h' keeps yours, \
+                         'l' takes the fix, 'o' edits",
+                        task.file.display(),
+                        resolution.picks.len()
+                    ));
+                    self.merges[task.issue_idx] = Some(resolution);
+                }
+                Ok(Ok(FixOutcome::Conflict(msg))) => {
+                    self.actions[task.issue_idx] = IssueAction::Conflict;
+                    self.rejections[task.issue_idx] = None;
+                    *self.applied_files.entry(task.file).or_default() += 1;
+                    self.status_message = Some(msg);
+                    if is_current {
+                        self.move_to_next_pending();
                     }
-                    Err(e) => {
-                        self.actions[task.issue_idx] = IssueAction::Error;
-                        self.status_message = Some(format!("Task failed: {}", e));
+                }
+                Ok(Ok(FixOutcome::Proposed(proposal))) => {
+                    self.actions[task.issue_idx] = IssueAction::Proposed;
+                    self.status_message = Some(format!(
+                        "Review the fix for {}: 'y' to accept, 'n' to reject",
+                        task.file.display()
+                    ));
+                    self.proposals[task.issue_idx] = Some(proposal);
+                }
+                Ok(Ok(FixOutcome::Rejected(rejection))) => {
+                    self.actions[task.issue_idx] = IssueAction::Rejected;
+                    self.status_message =
+                        Some(format!("Fix rejected by critic: {}", rejection.reason));
+                    self.rejections[task.issue_idx] = Some(rejection);
+                    if is_current {
+                        self.move_to_next_pending();
                     }
                 }
+                Ok(Err(e)) => {
+                    self.actions[task.issue_idx] = IssueAction::Error;
+                    self.status_message = Some(format!("Error: {}", e));
+                }
+                Err(e) if e.is_cancelled() => {
+                    self.actions[task.issue_idx] = IssueAction::Pending;
+                }
+                Err(e) => {
+                    self.actions[task.issue_idx] = IssueAction::Error;
+                    self.status_message = Some(format!("Task failed: {}", e));
+                }
             }
         }
+
+        self.start_queued();
+    }
+
+    fn push_undo(&mut self, issue_idx: usize, written: Vec<Written>) {
+        self.undo_stack.push(AppliedFix {
+            issue_idx,
+            written,
+            state: self.issues[issue_idx].state,
+        });
+    }
+
+    /// Restore the files changed by the most recently applied fix and reopen its issue.
+    /// A multi-file fix is only undone when none of its files changed since.
+    fn undo_last(&mut self) {
+        let Some(last) = self.undo_stack.last() else {
+            self.status_message = Some("No applied fix to undo".to_string());
+            return;
+        };
+        if last.written.iter().any(|w| self.file_busy(&w.file)) {
+            self.status_message = Some("Waiting for fixes to finish...".to_string());
+            return;
+        }
+
+        for w in &last.written {
+            match fs::read_to_string(self.config.resolve(&w.file)) {
+                Ok(current) if current == w.content => {}
+                Ok(_) => {
+                    self.status_message = Some(format!(
+                        "{} changed since the fix was applied; revert it with git instead",
+                        w.file.display()
+                    ));
+                    return;
+                }
+                Err(e) => {
+                    self.status_message = Some(format!("Error: {}", e));
+                    return;
+                }
+            }
+        }
+        let restore: Vec<Written> = last
+            .written
+            .iter()
+            .map(|w| Written {
+                file: w.file.clone(),
+                previous: w.content.clone(),
+                content: w.previous.clone(),
+            })
+            .collect();
+        if let Err(e) = write_all(&self.config, &restore) {
+            self.status_message = Some(format!("Error: {}", e));
+            return;
+        }
+
+        for w in &restore {
+            self.file_hashes
+                .insert(w.file.clone(), content_hash(&w.content));
+        }
+
+        let Some(last) = self.undo_stack.pop() else {
+            return;
+        };
+        let idx = last.issue_idx;
+        self.actions[idx] = IssueAction::Pending;
+        self.persist_state(idx, last.state);
+        for w in &last.written {
+            if let Some(count) = self.applied_files.get_mut(&w.file) {
+                *count -= 1;
+                if *count == 0 {
+                    self.applied_files.remove(&w.file);
+                }
+            }
+        }
+        self.current_issue = idx;
+        self.list_state.select(Some(idx));
+        self.status_message = Some(format!(
+            "Reverted fix to {}",
+            file_list(last.written.iter().map(|w| w.file.as_path()))
+        ));
+    }
+
+    /// Start queued fixes whose file is no longer being edited
+    fn start_queued(&mut self) {
+        for _ in 0..self.queued.len() {
+            let Some(idx) = self.queued.pop_front() else {
+                break;
+            };
+            if self.actions[idx] != IssueAction::Queued {
+                // Skipped while waiting
+                continue;
+            }
+            if self.file_busy(&self.issues[idx].file) {
+                self.queued.push_back(idx);
+            } else {
+                self.spawn_fix(idx);
+            }
+        }
+    }
+
+    fn file_busy(&self, file: &Path) -> bool {
+        self.active_tasks.iter().any(|task| task.file == file)
     }
 
     fn move_to_next_pending(&mut self) {
@@ -179,8 +784,8 @@ impl App {
     }
 
     fn handle_key(&mut self, key: KeyCode, _modifiers: KeyModifiers) {
-        // Clear status message on any key (except when task is running)
-        if self.active_task.is_none() {
+        // Clear status message on any key (except when tasks are running)
+        if self.active_tasks.is_empty() {
             self.status_message = None;
         }
 
@@ -189,14 +794,17 @@ impl App {
             return;
         }
 
-        // Ignore most keys while task is running
-        if self.active_task.is_some() {
-            match key {
-                KeyCode::Char('q') | KeyCode::Esc => {
-                    self.should_abort = true;
-                }
-                _ => {}
-            }
+        if let Some(idx) = self.stale.take() {
+            self.resolve_stale(idx, key);
+            return;
+        }
+
+        if self
+            .merges
+            .get(self.current_issue)
+            .is_some_and(Option::is_some)
+            && self.merge_key(key)
+        {
             return;
         }
 
@@ -213,9 +821,39 @@ impl App {
             KeyCode::Char('a') => {
                 self.apply_current();
             }
+            KeyCode::Char('A') => {
+                self.apply_all();
+            }
+            KeyCode::Char('c') => {
+                self.cancel_fixes();
+            }
             KeyCode::Char('s') => {
                 self.skip_current();
             }
+            KeyCode::Char('z') => {
+                self.snooze_current();
+            }
+            KeyCode::Char('y') => {
+                self.accept_proposal();
+            }
+            KeyCode::Char('n') => {
+                self.reject_proposal();
+            }
+            KeyCode::Char('P') => {
+                self.apply_preview();
+            }
+            KeyCode::Char('u') => {
+                self.undo_last();
+            }
+            KeyCode::Char('e') => {
+                self.show_full_excerpt = !self.show_full_excerpt;
+            }
+            KeyCode::Char('o') => {
+                self.open_editor = true;
+            }
+            KeyCode::Char('O') => {
+                self.open_code = true;
+            }
             KeyCode::Enter => {
                 self.confirm_and_continue();
             }
@@ -247,62 +885,439 @@ impl App {
     }
 
     fn apply_current(&mut self) {
-        if self.current_issue >= self.issues.len() {
+        if Config::is_read_only() {
+            self.status_message = Some("Read-only mode: fixes cannot be applied".to_string());
             return;
         }
+        self.start_fix(self.current_issue);
+    }
 
-        // Don't start if already applying something
-        if self.active_task.is_some() {
+    /// Queue a fix for every pending issue; fixes to different files run in parallel
+    fn apply_all(&mut self) {
+        if Config::is_read_only() {
+            self.status_message = Some("Read-only mode: fixes cannot be applied".to_string());
             return;
         }
 
-        let issue = &self.issues[self.current_issue];
-        if !issue.file.exists() {
+        // Issues whose file changed on disk are left for the user to decide one by one
+        let (pending, stale): (Vec<usize>, Vec<usize>) = (0..self.issues.len())
+            .filter(|&idx| self.actions[idx] == IssueAction::Pending)
+            .partition(|&idx| !self.changed_on_disk(&self.issues[idx].file));
+        for &idx in &pending {
+            self.start_fix(idx);
+        }
+        let mut msg = format!("Applying {} fix(es), press 'c' to cancel", pending.len());
+        if !stale.is_empty() {
+            msg.push_str(&format!(
+                "; {} skipped because their file changed on disk, press 'a' on them to decide",
+                stale.len()
+            ));
+        }
+        self.status_message = Some(msg);
+    }
+
+    /// Stop fixes that are queued or still being generated; their issues become pending again
+    fn cancel_fixes(&mut self) {
+        let mut cancelled = 0;
+        for idx in self.queued.drain(..) {
+            if self.actions[idx] == IssueAction::Queued {
+                self.actions[idx] = IssueAction::Pending;
+                cancelled += 1;
+            }
+        }
+        // Aborted tasks stay in `active_tasks`: one that wrote its file before the abort took
+        // effect is still recorded by `check_task_completion`, for undo and change detection
+        for task in &self.active_tasks {
+            if task.handle.is_finished() {
+                continue;
+            }
+            // Fix tasks only write after their last await, so an aborted task never half-writes
+            task.handle.abort();
+            self.actions[task.issue_idx] = IssueAction::Pending;
+            cancelled += 1;
+        }
+
+        self.status_message = Some(if cancelled == 0 {
+            "No fixes to cancel".to_string()
+        } else {
+            format!("Cancelled {} fix(es)", cancelled)
+        });
+    }
+
+    /// Write the previewed fix of the current issue
+    fn accept_proposal(&mut self) {
+        if self.actions.get(self.current_issue) != Some(&IssueAction::Proposed) {
+            return;
+        }
+        self.start_fix(self.current_issue);
+    }
+
+    /// Discard the previewed fix of the current issue; 'a' generates a new one
+    fn reject_proposal(&mut self) {
+        if self.actions.get(self.current_issue) != Some(&IssueAction::Proposed) {
+            return;
+        }
+        self.proposals[self.current_issue] = None;
+        self.actions[self.current_issue] = IssueAction::Pending;
+        self.status_message = Some("Fix discarded".to_string());
+    }
+
+    /// Start fixing an issue, or queue it if its file already has a fix in flight
+    fn start_fix(&mut self, idx: usize) {
+        if idx >= self.issues.len() || !self.actions[idx].needs_review() {
+            return;
+        }
+
+        let issue = &self.issues[idx];
+        if !self.config.resolve(&issue.file).exists() {
             self.status_message = Some(format!("File not found: {}", issue.file.display()));
             return;
         }
+        if lfs::is_pointer_file(&self.config.resolve(&issue.file)) {
+            self.status_message = Some(format!(
+                "{} is a Git LFS pointer; run `git lfs pull` to fix it",
+                issue.file.display()
+            ));
+            return;
+        }
+
+        if self.config.tui.preview && self.preview.is_none() {
+            match Preview::create(&self.config) {
+                Ok(preview) => self.preview = Some(preview),
+                Err(e) => {
+                    self.status_message = Some(format!("Error: {}", e));
+                    return;
+                }
+            }
+        }
+
+        let issue = &self.issues[idx];
+        if !self.file_busy(&issue.file) && self.changed_on_disk(&issue.file) {
+            let choices = if self.proposals[idx].is_some() || self.rejections[idx].is_some() {
+                "'r' rebases the reviewed fix onto it, 'R' re-analyzes it"
+            } else {
+                "'R' re-analyzes it"
+            };
+            self.status_message = Some(format!(
+                "{} changed on disk since the review started: {}, any other key cancels",
+                issue.file.display(),
+                choices
+            ));
+            self.current_issue = idx;
+            self.list_state.select(Some(idx));
+            self.stale = Some(idx);
+            return;
+        }
+
+        if self.file_busy(&issue.file) {
+            self.actions[idx] = IssueAction::Queued;
+            self.queued.push_back(idx);
+            self.status_message = Some(format!(
+                "Queued behind another fix to {}",
+                issue.file.display()
+            ));
+            return;
+        }
 
+        self.spawn_fix(idx);
+    }
+
+    fn spawn_fix(&mut self, issue_idx: usize) {
         // Mark as applying
-        self.actions[self.current_issue] = IssueAction::Applying;
+        self.actions[issue_idx] = IssueAction::Applying;
 
         // Clone data needed for the async task
         let config = self.config.clone();
-        let issue = self.issues[self.current_issue].clone();
-        let issue_idx = self.current_issue;
-        let file_display = issue.file.display().to_string();
+        let preview = self.preview.clone();
+        let issue = self.issues[issue_idx].clone();
+        let file = issue.file.clone();
+        // Auto-applied fixes are written without a review; remote pages are never written
+        let remote = remote::is_remote(&file);
+        let propose = (config.tui.show_diff_preview && !config.tui.auto_apply) || remote;
+        let file_display = file.display().to_string();
+
+        // A rejected or previewed fix was reviewed by the user, so write it as-is
+        // (merged with any changes made to the file since it was generated)
+        let reviewed = match self.rejections[issue_idx].take() {
+            Some(rejection) => Some((
+                rejection.original_content,
+                rejection.fixed_content,
+                rejection.related,
+            )),
+            None => self.proposals[issue_idx].take().map(|proposal| {
+                (
+                    proposal.original_content,
+                    proposal.fixed_content,
+                    proposal.related,
+                )
+            }),
+        };
+        let mut live = None;
+        let handle = match reviewed {
+            Some((original_content, fixed_content, related)) if remote => {
+                self.export_fix(issue_idx, &original_content, &fixed_content, &related);
+                return;
+            }
+            Some((original_content, fixed_content, related)) => {
+                self.status_message = Some(format!("Writing reviewed fix to {}...", file_display));
+                tokio::spawn(async move {
+                    write_fix(
+                        &config,
+                        preview.as_ref(),
+                        &issue,
+                        &original_content,
+                        &fixed_content,
+                        &related,
+                    )
+                })
+            }
+            None => {
+                self.status_message = Some(format!("Generating fix for {}...", file_display));
+                let generated = stream::Live::new();
+                live = config.llm.stream.then(|| generated.clone());
+                tokio::spawn(stream::scope(generated, async move {
+                    apply_fix_task(config, preview, issue, propose).await
+                }))
+            }
+        };
+
+        self.active_tasks.push(ActiveTask {
+            issue_idx,
+            file,
+            handle,
+            live,
+        });
+    }
 
-        // Spawn background task
-        let handle = tokio::spawn(async move { apply_fix_task(config, issue).await });
+    /// Save the reviewed fix of a remote page as a patch under `.git/driftcheck/remote-fixes`,
+    /// together with its edits to other docs, for applying by hand in the page's wiki
+    fn export_fix(
+        &mut self,
+        idx: usize,
+        original_content: &str,
+        fixed_content: &str,
+        related: &[fix::RelatedFix],
+    ) {
+        if let Err(e) = Config::ensure_writable("exporting fixes of remote pages") {
+            self.actions[idx] = IssueAction::Error;
+            self.status_message = Some(e.to_string());
+            return;
+        }
 
-        self.active_task = Some(ActiveTask { issue_idx, handle });
+        let file = self.issues[idx].file.clone();
+        let mut patch = fix::unified_patch(&file, original_content, fixed_content);
+        for r in related {
+            patch.push_str(&fix::unified_patch(
+                &r.file,
+                &r.original_content,
+                &r.fixed_content,
+            ));
+        }
+
+        let page = file.to_string_lossy();
+        let page = page
+            .trim_start_matches(remote::PREFIX)
+            .trim_end_matches(".md");
+        let path = self
+            .config
+            .resolve(remote::PATCH_DIR)
+            .join(format!("{}.patch", page));
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, patch));
+        match written {
+            Ok(()) => {
+                self.actions[idx] = IssueAction::Exported;
+                self.status_message = Some(format!(
+                    "{} is a remote page; saved the fix to {}",
+                    file.display(),
+                    path.display()
+                ));
+                self.move_to_next_pending();
+            }
+            Err(e) => {
+                self.actions[idx] = IssueAction::Error;
+                self.status_message = Some(format!("Error: {}", e));
+            }
+        }
+    }
 
-        self.status_message = Some(format!("Generating fix for {}...", file_display));
+    /// Whether an issue file differs from its content at session start or after
+    /// driftcheck's last write to it
+    fn changed_on_disk(&self, file: &Path) -> bool {
+        let Some(expected) = self.file_hashes.get(file) else {
+            return false;
+        };
+        match fs::read_to_string(self.config.resolve(file)) {
+            Ok(content) => &content_hash(&content) != expected,
+            Err(_) => false,
+        }
+    }
+
+    /// Accept a file's current content as the new baseline
+    fn record_written(&mut self, file: &Path) {
+        if let Ok(content) = fs::read_to_string(self.config.resolve(file)) {
+            self.file_hashes
+                .insert(file.to_path_buf(), content_hash(&content));
+        }
+    }
+
+    /// Act on the answer to a changed-on-disk prompt: 'r' three-way merges the reviewed fix
+    /// into the new content, 'R' drops it and generates a fix from the new content
+    fn resolve_stale(&mut self, idx: usize, key: KeyCode) {
+        let reviewed = self.proposals[idx].is_some() || self.rejections[idx].is_some();
+        match key {
+            KeyCode::Char('r') if reviewed => {}
+            KeyCode::Char('R') => {
+                self.proposals[idx] = None;
+                self.rejections[idx] = None;
+                self.actions[idx] = IssueAction::Pending;
+            }
+            _ => {
+                self.status_message = Some("Fix cancelled".to_string());
+                return;
+            }
+        }
+        let file = self.issues[idx].file.clone();
+        self.record_written(&file);
+        self.start_fix(idx);
     }
 
     fn skip_current(&mut self) {
         if self.current_issue < self.actions.len() {
             self.actions[self.current_issue] = IssueAction::Skip;
+            self.merges[self.current_issue] = None;
+            self.persist_state(self.current_issue, IssueState::Dismissed);
+            feedback::record_dismissal(&self.config, &self.issues[self.current_issue]);
             self.next_issue();
         }
     }
 
+    /// Hide the current issue for `tui.snooze_days` by adding it to the baseline's snoozes
+    fn snooze_current(&mut self) {
+        let idx = self.current_issue;
+        if idx >= self.actions.len() || !self.actions[idx].needs_review() {
+            return;
+        }
+        let issue = &self.issues[idx];
+        let until = chrono::Utc::now() + chrono::Duration::days(self.config.tui.snooze_days.into());
+        let snoozed = baseline::load(&self.config).and_then(|mut snoozes| {
+            snoozes.snooze(baseline::Snooze {
+                fingerprint: issue.fingerprint.clone(),
+                file: issue.file.display().to_string(),
+                description: issue.description.clone(),
+                until,
+            });
+            baseline::save(&self.config, &snoozes)
+        });
+        match snoozed {
+            Ok(()) => {
+                self.status_message = Some(format!(
+                    "Snoozed until {} (in {})",
+                    until.format("%Y-%m-%d"),
+                    baseline::BASELINE_FILE
+                ));
+                self.actions[idx] = IssueAction::Snoozed;
+                self.merges[idx] = None;
+                self.next_issue();
+            }
+            Err(e) => self.status_message = Some(format!("Error: {}", e)),
+        }
+    }
+
+    /// Merge every previewed fix into the working tree as one explicit step
+    fn apply_preview(&mut self) {
+        let Some(preview) = &self.preview else {
+            self.status_message = Some("No previewed fixes to apply".to_string());
+            return;
+        };
+        if !self.active_tasks.is_empty() || !self.queued.is_empty() {
+            self.status_message = Some("Waiting for fixes to finish...".to_string());
+            return;
+        }
+
+        let applied = match preview.apply() {
+            Ok(applied) => applied,
+            Err(e) => {
+                self.status_message = Some(format!("Error: {}", e));
+                return;
+            }
+        };
+
+        for idx in 0..self.issues.len() {
+            if self.actions[idx] != IssueAction::Previewed {
+                continue;
+            }
+            let file = &self.issues[idx].file;
+            if applied.skipped.contains(file) {
+                self.actions[idx] = IssueAction::Error;
+            } else if applied.conflicts.contains(file) {
+                self.actions[idx] = IssueAction::Conflict;
+            } else {
+                self.actions[idx] = IssueAction::Applied;
+                self.persist_state(idx, IssueState::Fixed);
+            }
+            let file = self.issues[idx].file.clone();
+            self.record_written(&file);
+        }
+
+        let mut msg = format!("Applied previewed fixes to {} file(s)", applied.files);
+        if !applied.conflicts.is_empty() {
+            msg.push_str(&format!(
+                "; {} need a manual merge",
+                applied.conflicts.len()
+            ));
+        }
+        if !applied.skipped.is_empty() {
+            msg.push_str(&format!(
+                "; {} skipped (conflicts would break the JSON)",
+                applied.skipped.len()
+            ));
+        }
+        self.status_message = Some(msg);
+        // Later fixes start a new preview run
+        self.preview = None;
+    }
+
+    fn persist_state(&mut self, idx: usize, state: IssueState) {
+        self.issues[idx].state = state;
+        if Config::is_read_only() {
+            return;
+        }
+        if let Err(e) = history::set_state(&self.issues[idx].fingerprint, state) {
+            tracing::debug!("Failed to update issue history: {}", e);
+        }
+    }
+
     fn confirm_and_continue(&mut self) {
-        // Don't allow confirm while task is running
-        if self.active_task.is_some() {
+        // Don't allow confirm while tasks are running
+        if !self.active_tasks.is_empty() || !self.queued.is_empty() {
+            self.status_message = Some("Waiting for fixes to finish...".to_string());
             return;
         }
 
-        let pending = self
-            .actions
-            .iter()
-            .filter(|a| **a == IssueAction::Pending)
-            .count();
+        let previewed = self.previewed_count();
+        if previewed > 0 && !self.preview_warned {
+            self.preview_warned = true;
+            self.status_message = Some(format!(
+                "{} previewed fix(es) not applied: press 'P' to apply them, or Enter again to continue without them",
+                previewed
+            ));
+            return;
+        }
+
+        let pending = self.actions.iter().filter(|a| a.needs_review()).count();
         if pending == 0 {
-            self.should_quit = true;
+            if self.verification.is_some() {
+                self.status_message = Some("Verifying the applied fixes...".to_string());
+            } else if !self.start_verification() {
+                self.should_quit = true;
+            }
         } else {
             // Jump to next pending issue
             for (i, action) in self.actions.iter().enumerate() {
-                if *action == IssueAction::Pending {
+                if action.needs_review() {
                     self.current_issue = i;
                     self.list_state.select(Some(i));
                     break;
@@ -311,9 +1326,136 @@ impl App {
         }
     }
 
+    /// Doc files changed in the working tree this session, by fixes or by hand
+    fn fixed_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self
+            .undo_stack
+            .iter()
+            .flat_map(|fix| fix.written.iter().map(|w| w.file.clone()))
+            .chain(
+                self.issues
+                    .iter()
+                    .zip(&self.actions)
+                    .filter(|(_, action)| **action == IssueAction::Edited)
+                    .map(|(issue, _)| issue.file.clone()),
+            )
+            .filter(|file| !remote::is_remote(file))
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Start the final analysis of the fixed docs, unless it is disabled or nothing changed
+    /// since the last one. Its LLM calls share the configured rate limits.
+    fn start_verification(&mut self) -> bool {
+        let Some(diff) = self.diff.clone().filter(|_| self.config.tui.verify_fixes) else {
+            return false;
+        };
+        let files = self.fixed_files();
+        let snapshot: Vec<_> = files
+            .iter()
+            .map(|file| (file.clone(), self.file_hashes.get(file).cloned()))
+            .collect();
+        if files.is_empty() || snapshot == self.verified {
+            return false;
+        }
+        self.verified = snapshot;
+
+        self.status_message = Some(format!(
+            "Verifying the fixes in {} file(s) against the diff...",
+            files.len()
+        ));
+        let config = self.config.clone();
+        self.verification = Some(tokio::spawn(async move {
+            analyzer::verify_fixes(&config, &diff, &files).await
+        }));
+        true
+    }
+
+    /// Report the result of a finished verification: exit if the drift is resolved, or add
+    /// the remaining issues to the list for review
+    async fn check_verification(&mut self) {
+        if !self
+            .verification
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            return;
+        }
+        let Some(handle) = self.verification.take() else {
+            return;
+        };
+        match handle.await {
+            Ok(Ok(remaining)) => {
+                let added = self.add_remaining(remaining);
+                if added == 0 {
+                    self.should_quit = true;
+                } else {
+                    self.status_message = Some(format!(
+                        "{} issue(s) remain after the fixes; review them, then press Enter again",
+                        added
+                    ));
+                    self.move_to_next_pending();
+                }
+            }
+            Ok(Err(e)) => {
+                self.status_message = Some(format!(
+                    "Could not verify the fixes: {}; press Enter to exit",
+                    e
+                ));
+            }
+            Err(e) => {
+                self.status_message =
+                    Some(format!("Verification failed: {}; press Enter to exit", e));
+            }
+        }
+    }
+
+    /// Add issues found by the verification as pending, and reopen fixed issues found again;
+    /// issues the user decided to leave stay as they are. New issues get the severity of the
+    /// session's issues in the same file.
+    fn add_remaining(&mut self, remaining: Vec<Issue>) -> usize {
+        let mut added = 0;
+        for mut issue in remaining {
+            if let Some(idx) = self
+                .issues
+                .iter()
+                .position(|known| known.fingerprint == issue.fingerprint)
+            {
+                if matches!(
+                    self.actions[idx],
+                    IssueAction::Applied | IssueAction::Edited
+                ) {
+                    self.actions[idx] = IssueAction::Pending;
+                    self.persist_state(idx, IssueState::Recurring);
+                    added += 1;
+                }
+                continue;
+            }
+            if let Some(known) = self.issues.iter().find(|known| known.file == issue.file) {
+                issue.severity = known.severity;
+                issue.corpus = known.corpus.clone();
+            }
+            self.issues.push(issue);
+            self.actions.push(IssueAction::Pending);
+            self.rejections.push(None);
+            self.proposals.push(None);
+            self.merges.push(None);
+            added += 1;
+        }
+        added
+    }
+
+    fn previewed_count(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|a| **a == IssueAction::Previewed)
+            .count()
+    }
+
     fn get_spinner_char(&self) -> &'static str {
-        const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-        SPINNER[self.spinner_frame]
+        self.theme.symbols.spinner[self.spinner_frame]
     }
 
     fn draw(&mut self, f: &mut Frame) {
@@ -346,12 +1488,12 @@ impl App {
         let applied = self
             .actions
             .iter()
-            .filter(|a| **a == IssueAction::Applied)
+            .filter(|a| matches!(a, IssueAction::Applied | IssueAction::Edited))
             .count();
         let skipped = self
             .actions
             .iter()
-            .filter(|a| **a == IssueAction::Skip)
+            .filter(|a| matches!(a, IssueAction::Skip | IssueAction::Snoozed))
             .count();
         let applying = self
             .actions
@@ -360,11 +1502,16 @@ impl App {
             .count();
 
         let title = format!(
-            " driftcheck - {} issues ({} pending, {} applied, {} skipped) ",
+            " driftcheck - {} issues ({} pending, {} applied, {} skipped){} ",
             self.issues.len(),
             pending,
             applied,
-            skipped
+            skipped,
+            if Config::is_read_only() {
+                " [read-only]"
+            } else {
+                ""
+            }
         );
 
         let block = Block::default()
@@ -372,7 +1519,7 @@ impl App {
             .border_style(self.theme.border_style())
             .title(Span::styled(title, self.theme.title_style()));
 
-        let status_text = if applying > 0 {
+        let status_text = if applying > 0 || self.verification.is_some() {
             Span::styled(
                 format!(
                     "{} {}",
@@ -389,7 +1536,15 @@ impl App {
             Span::styled("All issues addressed", self.theme.success_style())
         };
 
-        let paragraph = Paragraph::new(Line::from(status_text)).block(block);
+        let mut spans = vec![status_text];
+        if self.hidden > 0 {
+            spans.push(Span::styled(
+                format!("  {}", output::overflow_summary(self.hidden)),
+                self.theme.muted_style(),
+            ));
+        }
+
+        let paragraph = Paragraph::new(Line::from(spans)).block(block);
 
         f.render_widget(paragraph, area);
     }
@@ -406,6 +1561,7 @@ impl App {
 
     fn draw_issue_list(&mut self, f: &mut Frame, area: Rect) {
         let spinner = self.get_spinner_char();
+        let symbols = self.theme.symbols;
 
         let items: Vec<ListItem> = self
             .issues
@@ -414,18 +1570,32 @@ impl App {
             .map(|(i, issue)| {
                 let action = &self.actions[i];
                 let prefix = match action {
-                    IssueAction::Pending => "○",
+                    IssueAction::Pending => symbols.pending,
+                    IssueAction::Queued => symbols.queued,
                     IssueAction::Applying => spinner,
-                    IssueAction::Skip => "⊘",
-                    IssueAction::Applied => "✓",
-                    IssueAction::Error => "✗",
+                    IssueAction::Skip | IssueAction::Snoozed => symbols.skipped,
+                    IssueAction::Applied => symbols.applied,
+                    IssueAction::Previewed => symbols.previewed,
+                    IssueAction::Rejected => symbols.rejected,
+                    IssueAction::Proposed => symbols.proposed,
+                    IssueAction::Conflict => symbols.conflict,
+                    IssueAction::Edited => symbols.applied,
+                    IssueAction::Exported => symbols.previewed,
+                    IssueAction::Error => symbols.error,
                 };
 
                 let style = match action {
                     IssueAction::Pending => self.theme.normal_style(),
+                    IssueAction::Queued => self.theme.muted_style(),
                     IssueAction::Applying => self.theme.highlight_style(),
-                    IssueAction::Skip => self.theme.muted_style(),
+                    IssueAction::Skip | IssueAction::Snoozed => self.theme.muted_style(),
                     IssueAction::Applied => self.theme.success_style(),
+                    IssueAction::Previewed => self.theme.highlight_style(),
+                    IssueAction::Rejected => self.theme.warning_style(),
+                    IssueAction::Proposed => self.theme.highlight_style(),
+                    IssueAction::Conflict => self.theme.warning_style(),
+                    IssueAction::Edited => self.theme.success_style(),
+                    IssueAction::Exported => self.theme.highlight_style(),
                     IssueAction::Error => self.theme.warning_style(),
                 };
 
@@ -436,7 +1606,15 @@ impl App {
                     issue.line
                 );
 
-                ListItem::new(text).style(style)
+                let mut spans = vec![Span::styled(text, style)];
+                if issue.state == IssueState::New {
+                    spans.push(Span::styled(" NEW", self.theme.warning_style()));
+                }
+                if issue.snooze_expired {
+                    spans.push(Span::styled(" EXPIRED", self.theme.warning_style()));
+                }
+
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
@@ -475,21 +1653,98 @@ impl App {
 
         // Issue description
         let mut lines = vec![
-            Line::from(Span::styled(
-                format!("{}", issue.file.display()),
-                self.theme.highlight_style(),
-            )),
+            Line::from(vec![
+                Span::styled(
+                    format!("{}", issue.file.display()),
+                    self.theme.highlight_style(),
+                ),
+                Span::styled(
+                    format!("  [{}]", issue.status_label()),
+                    self.theme.muted_style(),
+                ),
+            ]),
             Line::from(""),
             Line::from(issue.description.as_str()),
         ];
 
-        if !issue.doc_excerpt.is_empty() {
+        if let Some(code) = &issue.code_location {
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::styled("Code: ", self.theme.muted_style()),
+                Span::styled(code.to_string(), self.theme.highlight_style()),
+                Span::styled(
+                    format!(" ({})  press 'O' to open", code.symbol),
+                    self.theme.muted_style(),
+                ),
+            ]));
+        }
+
+        if let Some(count) = self.applied_files.get(&issue.file) {
             lines.push(Line::from(""));
             lines.push(Line::from(Span::styled(
-                "Documentation excerpt:",
+                format!(
+                    "{} fix(es) already applied to this file; the next fix starts from the updated file",
+                    count
+                ),
                 self.theme.muted_style(),
             )));
-            for line in issue.doc_excerpt.lines().take(5) {
+        }
+
+        if self.actions[self.current_issue] == IssueAction::Previewed {
+            if let Some(preview) = &self.preview {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    format!(
+                        "Fix written to the preview in {}. Press 'P' to apply all previewed changes",
+                        preview.dir().display()
+                    ),
+                    self.theme.highlight_style(),
+                )));
+            }
+        }
+
+        if let Some(resolution) = &self.merges[self.current_issue] {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "The fix overlaps edits made to {} since it was generated: {} of {} conflict(s) resolved",
+                    issue.file.display(),
+                    resolution.picks.len() - resolution.remaining(),
+                    resolution.picks.len()
+                ),
+                self.theme.warning_style(),
+            )));
+        } else if self.actions[self.current_issue] == IssueAction::Conflict {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "Needs manual merge: the fix conflicts with other changes. Resolve the conflict markers in {}",
+                    issue.file.display()
+                ),
+                self.theme.warning_style(),
+            )));
+        }
+
+        if !issue.doc_excerpt.is_empty() {
+            let (excerpt, label, limit) = match &issue.full_excerpt {
+                Some(full) if self.show_full_excerpt => {
+                    (full.as_str(), "Full excerpt from the model:", usize::MAX)
+                }
+                Some(_) => (
+                    issue.doc_excerpt.as_str(),
+                    "Documentation excerpt (trimmed, 'e' for full):",
+                    5,
+                ),
+                None if self.show_full_excerpt => (
+                    issue.doc_excerpt.as_str(),
+                    "Documentation excerpt:",
+                    usize::MAX,
+                ),
+                None => (issue.doc_excerpt.as_str(), "Documentation excerpt:", 5),
+            };
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(label, self.theme.muted_style())));
+            for line in excerpt.lines().take(limit) {
                 lines.push(Line::from(format!("  {}", line)));
             }
         }
@@ -520,38 +1775,246 @@ impl App {
 
         f.render_widget(desc_para, chunks[0]);
 
-        // Suggested fix
-        let fix_text = issue
-            .suggested_fix
-            .as_deref()
-            .unwrap_or("No fix suggestion available");
+        // Suggested fix, the generated fix's diff, the critic's verdict if it was rejected, or
+        // the conflicts of a fix overlapping local edits
+        let (fix_lines, fix_title) = match (
+            &self.rejections[self.current_issue],
+            &self.proposals[self.current_issue],
+        ) {
+            _ if self.merges[self.current_issue].is_some() => {
+                let resolution = self.merges[self.current_issue].as_ref();
+                let lines = resolution.map(|r| self.merge_lines(r)).unwrap_or_default();
+                let title = resolution.map_or_else(String::new, |r| {
+                    format!(" Merge Fix: Conflict {}/{} ", r.selected + 1, r.picks.len())
+                });
+                (lines, title)
+            }
+            (Some(rejection), _) => {
+                let mut lines = vec![
+                    Line::from(rejection.reason.as_str()),
+                    Line::from(""),
+                    Line::from("Press 'a' to apply the generated fix anyway, or 's' to skip."),
+                ];
+                if self.config.tui.show_diff_preview {
+                    lines.push(Line::from(""));
+                    lines.extend(self.diff_lines(
+                        &issue.file,
+                        &rejection.original_content,
+                        &rejection.fixed_content,
+                    ));
+                    lines.extend(self.related_diff_lines(&rejection.related));
+                }
+                (lines, " Fix Rejected by Critic ".to_string())
+            }
+            (None, Some(proposal)) => {
+                let mut lines = vec![
+                    Line::from(Span::styled(
+                        "Press 'y' to write this change, or 'n' to discard it.",
+                        self.theme.highlight_style(),
+                    )),
+                    Line::from(""),
+                ];
+                lines.extend(self.diff_lines(
+                    &issue.file,
+                    &proposal.original_content,
+                    &proposal.fixed_content,
+                ));
+                lines.extend(self.related_diff_lines(&proposal.related));
+                let title = if proposal.related.is_empty() {
+                    " Proposed Change ".to_string()
+                } else {
+                    format!(" Proposed Change ({} files) ", proposal.related.len() + 1)
+                };
+                (lines, title)
+            }
+            (None, None) if is_applying && self.live_fix().is_some() => {
+                let text = self.live_fix().unwrap_or_default();
+                // Follow the end of the text as it grows
+                let height = chunks[1].height.saturating_sub(2) as usize;
+                let lines: Vec<&str> = text.lines().collect();
+                let tail = &lines[lines.len().saturating_sub(height)..];
+                (
+                    tail.iter()
+                        .map(|line| Line::from(line.to_string()))
+                        .collect(),
+                    format!(" Generating Fix ({} chars) ", text.chars().count()),
+                )
+            }
+            (None, None) => (
+                issue
+                    .suggested_fix
+                    .as_deref()
+                    .unwrap_or("No fix suggestion available")
+                    .lines()
+                    .map(Line::from)
+                    .collect(),
+                " Suggested Fix ".to_string(),
+            ),
+        };
 
-        let fix_para = Paragraph::new(fix_text)
+        let fix_para = Paragraph::new(fix_lines)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
                     .border_style(self.theme.border_style())
-                    .title(" Suggested Fix "),
+                    .title(fix_title),
             )
             .wrap(Wrap { trim: false });
 
         f.render_widget(fix_para, chunks[1]);
     }
 
-    fn draw_footer(&self, f: &mut Frame, area: Rect) {
-        let keybindings = if self.active_task.is_some() {
-            vec![("q", "Abort")]
-        } else {
-            vec![
-                ("a", "Apply"),
-                ("s", "Skip"),
-                ("j/k", "Nav"),
-                ("Enter", "Done"),
-                ("q", "Abort"),
-                ("?", "Help"),
-            ]
+    /// Text streamed so far of the fix being generated for the current issue
+    fn live_fix(&self) -> Option<String> {
+        self.active_tasks
+            .iter()
+            .find(|task| task.issue_idx == self.current_issue)
+            .and_then(|task| task.live.as_ref())
+            .map(stream::Live::text)
+            .filter(|text| !text.is_empty())
+    }
+
+    /// The selected conflict of a merge view: the base lines, yours and the fix's, marked like
+    /// the lines of a diff
+    fn merge_lines(&self, resolution: &Resolution) -> Vec<Line<'static>> {
+        let Some((base, current, fix)) = resolution.conflicts().nth(resolution.selected) else {
+            return Vec::new();
+        };
+        let picked = resolution.picks[resolution.selected].as_deref();
+        let label = |text: &str, side: &str| match picked {
+            Some(p) if p == side => format!("{} (picked)", text),
+            _ => text.to_string(),
         };
 
+        let mut lines = vec![
+            Line::from(Span::styled(
+                "'h' keeps yours, 'l' takes the fix, 'b' keeps both, 'o' edits, Tab for the next conflict",
+                self.theme.highlight_style(),
+            )),
+            Line::from(""),
+        ];
+        let mut section = |title: String, text: &str, prefix: &str, style: Style| {
+            lines.push(Line::from(Span::styled(title, self.theme.muted_style())));
+            for line in text.lines() {
+                lines.push(Line::from(Span::styled(
+                    format!("{}{}", prefix, line),
+                    style,
+                )));
+            }
+            if text.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "  (no lines)",
+                    self.theme.muted_style(),
+                )));
+            }
+            lines.push(Line::from(""));
+        };
+        section(
+            "Base (when the fix was generated):".to_string(),
+            base,
+            "  ",
+            self.theme.muted_style(),
+        );
+        section(
+            label("Yours ('h'):", current),
+            current,
+            "- ",
+            self.theme.diff_remove_style(),
+        );
+        section(
+            label("Fix ('l'):", fix),
+            fix,
+            "+ ",
+            self.theme.diff_add_style(),
+        );
+        if let Some(p) = picked.filter(|p| *p != current && *p != fix) {
+            section(
+                "Resolution:".to_string(),
+                p,
+                "  ",
+                self.theme.normal_style(),
+            );
+        }
+        lines
+    }
+
+    /// Diffs of the other files a multi-file fix changes, each after a blank line
+    fn related_diff_lines(&self, related: &[fix::RelatedFix]) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        for r in related {
+            lines.push(Line::from(""));
+            lines.extend(self.diff_lines(&r.file, &r.original_content, &r.fixed_content));
+        }
+        lines
+    }
+
+    /// Colored unified diff of a generated fix
+    fn diff_lines(&self, file: &Path, original: &str, fixed: &str) -> Vec<Line<'static>> {
+        let diff = fix::unified_patch(file, original, fixed);
+        if diff.is_empty() {
+            return vec![Line::from(Span::styled(
+                "The generated fix makes no changes",
+                self.theme.muted_style(),
+            ))];
+        }
+
+        diff.lines()
+            .map(|line| {
+                let style = if line.starts_with("+++") || line.starts_with("---") {
+                    self.theme.muted_style()
+                } else if line.starts_with('+') {
+                    self.theme.diff_add_style()
+                } else if line.starts_with('-') {
+                    self.theme.diff_remove_style()
+                } else if line.starts_with("@@") {
+                    self.theme.highlight_style()
+                } else {
+                    self.theme.normal_style()
+                };
+                Line::from(Span::styled(line.to_string(), style))
+            })
+            .collect()
+    }
+
+    fn draw_footer(&self, f: &mut Frame, area: Rect) {
+        let mut keybindings = vec![
+            ("a", "Apply"),
+            ("A", "Apply all"),
+            ("s", "Skip"),
+            ("z", "Snooze"),
+            ("j/k", "Nav"),
+            ("Enter", "Done"),
+            ("q", "Abort"),
+            ("?", "Help"),
+        ];
+        if self.previewed_count() > 0 {
+            keybindings.insert(2, ("P", "Apply previews"));
+        }
+        if !self.undo_stack.is_empty() {
+            keybindings.insert(2, ("u", "Undo"));
+        }
+        if !self.active_tasks.is_empty() {
+            keybindings.retain(|(key, _)| *key != "Enter");
+            keybindings.insert(2, ("c", "Cancel fixes"));
+        }
+        if Config::is_read_only() {
+            keybindings.retain(|(key, _)| *key != "A");
+            keybindings[0] = ("a", "Apply (disabled: read-only)");
+        }
+        if self.actions.get(self.current_issue) == Some(&IssueAction::Proposed) {
+            keybindings.insert(0, ("y/n", "Accept/Reject change"));
+        }
+        if self
+            .merges
+            .get(self.current_issue)
+            .is_some_and(Option::is_some)
+        {
+            keybindings.insert(0, ("y/n", "Write/Discard merge"));
+            keybindings.insert(0, ("o", "Edit"));
+            keybindings.insert(0, ("Tab", "Next conflict"));
+            keybindings.insert(0, ("h/l/b", "Yours/Fix/Both"));
+        }
+
         let spans: Vec<Span> = keybindings
             .into_iter()
             .flat_map(|(key, action)| {
@@ -578,7 +2041,24 @@ impl App {
             Line::from(Span::styled("Keybindings", self.theme.title_style())),
             Line::from(""),
             Line::from("  a        Apply fix (uses LLM to generate fix)"),
+            Line::from("           On a rejected fix, writes it anyway"),
+            Line::from("           Disabled in read-only mode"),
+            Line::from("  y / n    Accept or reject the proposed change"),
+            Line::from("           (tui.show_diff_preview)"),
+            Line::from("  A        Apply fixes for all pending issues"),
+            Line::from("  c        Cancel queued and running fixes"),
             Line::from("  s        Skip this issue"),
+            Line::from("  z        Snooze this issue (tui.snooze_days)"),
+            Line::from("  u        Undo the most recently applied fix"),
+            Line::from("  r / R    Rebase the fix or re-analyze when the doc changed"),
+            Line::from("           on disk during the session"),
+            Line::from("  h / l    When a fix overlaps your edits: keep your lines or take"),
+            Line::from("           the fix's for the selected conflict ('b' keeps both,"),
+            Line::from("           'o' edits it, Tab selects the next, 'y' writes)"),
+            Line::from("  P        Apply all previewed changes (tui.preview)"),
+            Line::from("  e        Toggle the full documentation excerpt"),
+            Line::from("  o        Fix by hand: open the doc in $EDITOR at the issue"),
+            Line::from("  O        Open the changed code the issue is about in $EDITOR"),
             Line::from("  j / Down Next issue"),
             Line::from("  k / Up   Previous issue"),
             Line::from("  Enter    Confirm all and continue push"),
@@ -609,6 +2089,34 @@ impl App {
     }
 }
 
+/// Run `f` with the terminal restored to normal mode, e.g. while an editor is open
+fn suspended<T>(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    f: impl FnOnce() -> T,
+) -> Result<T> {
+    disable_raw_mode().map_err(|e| DriftcheckError::TuiError(e.to_string()))?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )
+    .map_err(|e| DriftcheckError::TuiError(e.to_string()))?;
+
+    let result = f();
+
+    enable_raw_mode().map_err(|e| DriftcheckError::TuiError(e.to_string()))?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture
+    )
+    .map_err(|e| DriftcheckError::TuiError(e.to_string()))?;
+    terminal
+        .clear()
+        .map_err(|e| DriftcheckError::TuiError(e.to_string()))?;
+    Ok(result)
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -630,64 +2138,247 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 }
 
 /// Background task to apply a fix
-async fn apply_fix_task(config: Config, issue: Issue) -> Result<String> {
+/// With `propose`, the fix is returned for review instead of being written
+async fn apply_fix_task(
+    config: Config,
+    preview: Option<Preview>,
+    issue: Issue,
+    propose: bool,
+) -> Result<FixOutcome> {
     let file_path = &issue.file;
 
-    // Read the current file content
-    let original_content = fs::read_to_string(file_path).map_err(|e| {
-        DriftcheckError::TuiError(format!("Failed to read {}: {}", file_path.display(), e))
-    })?;
+    // Read the current file content (the previewed version in preview mode)
+    let original_content = read_target(&config, preview.as_ref(), file_path)?;
+
+    // Other docs repeating the drifted text may be fixed in the same transaction
+    let related: Vec<(PathBuf, String)> = if config.tui.multi_file_fixes {
+        fix::related_docs(&config, &issue)
+            .into_iter()
+            .filter_map(|file| {
+                let content = read_target(&config, preview.as_ref(), &file).ok()?;
+                Some((file, content))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
 
     // Generate the fix using LLM
-    let fixed_content = generate_doc_fix(&config, &issue, &original_content).await?;
+    let (fixed_content, related) =
+        fix::generate_fix_set(&config, &issue, &original_content, &related).await?;
+
+    // Let the critic veto fixes that stray from the issue, in any of the files
+    if config.tui.critic_pass {
+        let mut verdicts = vec![(
+            file_path.clone(),
+            fix::review_doc_fix(&config, &issue, &original_content, &fixed_content).await?,
+        )];
+        for r in &related {
+            verdicts.push((
+                r.file.clone(),
+                fix::review_doc_fix(&config, &issue, &r.original_content, &r.fixed_content).await?,
+            ));
+        }
+        if let Some((file, review)) = verdicts.into_iter().find(|(_, r)| !r.approved) {
+            let reason = if related.is_empty() {
+                review.reason
+            } else {
+                format!("{}: {}", file.display(), review.reason)
+            };
+            return Ok(FixOutcome::Rejected(Rejection {
+                reason,
+                fixed_content,
+                original_content,
+                related,
+            }));
+        }
+    }
 
-    // Write the fixed content
-    fs::write(file_path, &fixed_content).map_err(|e| {
-        DriftcheckError::TuiError(format!("Failed to write {}: {}", file_path.display(), e))
-    })?;
+    if propose {
+        return Ok(FixOutcome::Proposed(Proposal {
+            fixed_content,
+            original_content,
+            related,
+        }));
+    }
 
-    Ok(format!("Applied fix to {}", file_path.display()))
+    write_fix(
+        &config,
+        preview.as_ref(),
+        &issue,
+        &original_content,
+        &fixed_content,
+        &related,
+    )
 }
 
-/// Generate a fixed version of the documentation using LLM
-async fn generate_doc_fix(
+/// Write fixed content for an issue to disk, using the writer for its doc kind.
+/// `base_content` is the file content the fix was generated from; edits made
+/// since then are kept by a three-way merge. `related` edits to other docs are
+/// written as one transaction with the fix: all files are written, or none.
+fn write_fix(
     config: &Config,
+    preview: Option<&Preview>,
     issue: &Issue,
-    original_content: &str,
-) -> Result<String> {
-    use crate::llm::LlmClient;
-
-    let client = LlmClient::new(&config.llm)?;
-
-    let system_prompt = r#"You are a documentation editor. Given an issue description and the current documentation content, output the COMPLETE fixed documentation file.
-
-Rules:
-1. Output ONLY the fixed file content, no explanations
-2. Make minimal changes - only fix what's necessary
-3. Preserve all formatting, whitespace, and structure
-4. If the issue mentions missing documentation, add it in the appropriate place"#;
-
-    let user_prompt = format!(
-        r#"## Issue
-File: {}
-Line: {}
-Problem: {}
-
-## Suggested Fix
-{}
-
-## Current File Content
-```
-{}
-```
-
-Output the complete fixed file content:"#,
-        issue.file.display(),
-        issue.line,
-        issue.description,
-        issue.suggested_fix.as_deref().unwrap_or("(none)"),
-        original_content
-    );
-
-    client.chat(system_prompt, &user_prompt).await
+    base_content: &str,
+    fixed_content: &str,
+    related: &[fix::RelatedFix],
+) -> Result<FixOutcome> {
+    let file_path = &issue.file;
+
+    let current_content = read_target(config, preview, file_path)?;
+    let fix_content = fixed_content;
+    let (fixed_content, conflicts) =
+        match patch::merge3(base_content, &current_content, fixed_content)? {
+            Merge::Clean(merged) => (merged, 0),
+            Merge::Conflicts(merged, count) => (merged, count),
+        };
+    let fixed_content = fixed_content.as_str();
+
+    // Conflict markers in one file of a transaction would leave it half applied
+    if conflicts > 0 && !related.is_empty() {
+        return Err(DriftcheckError::PatchError(format!(
+            "Fix conflicts with changes to {}; none of its {} files were written",
+            file_path.display(),
+            related.len() + 1
+        )));
+    }
+    let mut others = Vec::new();
+    for r in related {
+        let current = read_target(config, preview, &r.file)?;
+        let Merge::Clean(merged) = patch::merge3(&r.original_content, &current, &r.fixed_content)?
+        else {
+            return Err(DriftcheckError::PatchError(format!(
+                "Fix conflicts with changes to {}; none of its {} files were written",
+                r.file.display(),
+                related.len() + 1
+            )));
+        };
+        if is_json(&r.file)
+            && serde_json::from_str::<serde_json::Value>(&r.original_content).is_ok()
+        {
+            serde_json::from_str::<serde_json::Value>(&merged).map_err(|e| {
+                DriftcheckError::TuiError(format!(
+                    "Generated fix for {} is not valid JSON: {}",
+                    r.file.display(),
+                    e
+                ))
+            })?;
+        }
+        others.push(Written {
+            file: r.file.clone(),
+            previous: current,
+            content: merged,
+        });
+    }
+
+    // Structured formats must stay parseable, or the fix would break the file
+    if matches!(issue.kind, DocKind::Notebook | DocKind::OpenApi) && is_json(file_path) {
+        if conflicts > 0 {
+            return Err(DriftcheckError::PatchError(format!(
+                "Fix conflicts with changes to {}; conflict markers would break the JSON",
+                file_path.display()
+            )));
+        }
+        serde_json::from_str::<serde_json::Value>(fixed_content).map_err(|e| {
+            DriftcheckError::TuiError(format!(
+                "Generated {} fix for {} is not valid JSON: {}",
+                issue.kind.as_str(),
+                file_path.display(),
+                e
+            ))
+        })?;
+    }
+
+    if conflicts > 0 && preview.is_none() {
+        let regions = patch::merge3_regions(base_content, &current_content, fix_content)?;
+        return Ok(FixOutcome::Overlaps(Resolution::new(
+            current_content,
+            regions,
+        )));
+    }
+
+    let mut written = vec![Written {
+        file: file_path.clone(),
+        previous: current_content,
+        content: fixed_content.to_string(),
+    }];
+    written.extend(others);
+    let files = file_list(written.iter().map(|w| w.file.as_path()));
+
+    if let Some(preview) = preview {
+        for w in &written {
+            preview.write(&w.file, &w.content)?;
+        }
+        if conflicts > 0 {
+            return Ok(FixOutcome::Conflict(format!(
+                "Needs manual merge: {} conflict(s) in the preview of {}",
+                conflicts,
+                file_path.display()
+            )));
+        }
+        return Ok(FixOutcome::Previewed(
+            format!("Previewed fix for {}", files),
+            written.into_iter().map(|w| w.file).collect(),
+        ));
+    }
+
+    write_all(config, &written)?;
+    Ok(FixOutcome::Applied(
+        format!("Applied fix to {}", files),
+        written,
+    ))
+}
+
+/// Write every file of a fix, restoring the files already written if one write fails.
+/// Each file is re-read first, so content changed since the fix was merged is never clobbered.
+fn write_all(config: &Config, written: &[Written]) -> Result<()> {
+    for (i, w) in written.iter().enumerate() {
+        let path = config.resolve(&w.file);
+        let result = match fs::read_to_string(&path) {
+            Ok(current) if current != w.previous => {
+                Err("it changed on disk while the fix was being written".to_string())
+            }
+            _ => fs::write(&path, &w.content).map_err(|e| e.to_string()),
+        };
+        if let Err(e) = result {
+            for done in &written[..i] {
+                let _ = fs::write(config.resolve(&done.file), &done.previous);
+            }
+            return Err(DriftcheckError::TuiError(format!(
+                "Failed to write {}: {}",
+                w.file.display(),
+                e
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn content_hash(content: &str) -> Vec<u8> {
+    Sha256::digest(content.as_bytes()).to_vec()
+}
+
+fn is_json(file_path: &Path) -> bool {
+    file_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("ipynb"))
+}
+
+/// Comma-separated file names for status messages
+fn file_list<'a>(files: impl Iterator<Item = &'a Path>) -> String {
+    files
+        .map(|f| f.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Content a fix should be generated from and merged into: the preview, or the working tree
+fn read_target(config: &Config, preview: Option<&Preview>, file_path: &Path) -> Result<String> {
+    match preview {
+        Some(preview) => preview.current(file_path),
+        None => fs::read_to_string(config.resolve(file_path)).map_err(|e| {
+            DriftcheckError::TuiError(format!("Failed to read {}: {}", file_path.display(), e))
+        }),
+    }
 }
@@ -4,13 +4,27 @@ mod theme;
 use crate::analyzer::Issue;
 use crate::config::Config;
 use crate::error::Result;
+use crate::term::TermCaps;
 
 pub use app::App;
 pub use theme::Theme;
 
 /// Run the TUI application
-pub async fn run(config: &Config, issues: Vec<Issue>) -> Result<()> {
-    let theme = Theme::from_name(&config.tui.theme);
-    let mut app = App::new(issues, config.clone(), theme);
+/// `hidden` is the number of issues dropped by `general.max_issues`; `diff` is the analyzed
+/// diff, if known, for verifying the applied fixes before exit
+pub async fn run(
+    config: &Config,
+    issues: Vec<Issue>,
+    hidden: usize,
+    diff: Option<&str>,
+) -> Result<()> {
+    let theme = Theme::from_name(&config.tui.theme).for_terminal(&TermCaps::detect());
+    let mut app = App::new(
+        issues,
+        hidden,
+        diff.map(str::to_string),
+        config.clone(),
+        theme,
+    );
     app.run().await
 }
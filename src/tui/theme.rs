@@ -1,3 +1,4 @@
+use crate::term::{ColorSupport, Symbols, TermCaps, UNICODE_SYMBOLS};
 use ratatui::style::{Color, Modifier, Style};
 
 #[derive(Debug, Clone)]
@@ -6,29 +7,36 @@ pub struct Theme {
     pub highlight: Color,
     pub warning: Color,
     pub success: Color,
+    /// Removed lines in diffs
+    pub error: Color,
     pub muted: Color,
     pub border: Color,
     pub selection: Color,
+    pub symbols: &'static Symbols,
 }
 
-impl Theme {
-    pub fn from_name(name: &str) -> Self {
-        match name {
-            "minimal" => Self::minimal(),
-            "colorful" => Self::colorful(),
-            _ => Self::default(),
-        }
-    }
-
-    pub fn default() -> Self {
+impl Default for Theme {
+    fn default() -> Self {
         Self {
             foreground: Color::Reset,
             highlight: Color::Cyan,
             warning: Color::Yellow,
             success: Color::Green,
+            error: Color::Red,
             muted: Color::DarkGray,
             border: Color::Gray,
             selection: Color::Blue,
+            symbols: &UNICODE_SYMBOLS,
+        }
+    }
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "minimal" => Self::minimal(),
+            "colorful" => Self::colorful(),
+            _ => Self::default(),
         }
     }
 
@@ -38,9 +46,11 @@ impl Theme {
             highlight: Color::White,
             warning: Color::Yellow,
             success: Color::Green,
+            error: Color::Red,
             muted: Color::DarkGray,
             border: Color::DarkGray,
             selection: Color::White,
+            symbols: &UNICODE_SYMBOLS,
         }
     }
 
@@ -50,10 +60,47 @@ impl Theme {
             highlight: Color::Magenta,
             warning: Color::LightYellow,
             success: Color::LightGreen,
+            error: Color::LightRed,
             muted: Color::Gray,
             border: Color::Cyan,
             selection: Color::LightBlue,
+            symbols: &UNICODE_SYMBOLS,
+        }
+    }
+
+    /// Degrade the palette and symbols to what the terminal supports
+    pub fn for_terminal(mut self, caps: &TermCaps) -> Self {
+        self.symbols = caps.symbols();
+
+        match caps.color {
+            ColorSupport::Full => {}
+            ColorSupport::Basic => {
+                for color in [
+                    &mut self.foreground,
+                    &mut self.highlight,
+                    &mut self.warning,
+                    &mut self.success,
+                    &mut self.error,
+                    &mut self.muted,
+                    &mut self.border,
+                    &mut self.selection,
+                ] {
+                    *color = to_basic(*color);
+                }
+            }
+            ColorSupport::None => {
+                self.foreground = Color::Reset;
+                self.highlight = Color::Reset;
+                self.warning = Color::Reset;
+                self.success = Color::Reset;
+                self.error = Color::Reset;
+                self.muted = Color::Reset;
+                self.border = Color::Reset;
+                self.selection = Color::Reset;
+            }
         }
+
+        self
     }
 
     pub fn title_style(&self) -> Style {
@@ -78,7 +125,19 @@ impl Theme {
         Style::default().fg(self.success)
     }
 
+    pub fn diff_add_style(&self) -> Style {
+        Style::default().fg(self.success)
+    }
+
+    pub fn diff_remove_style(&self) -> Style {
+        Style::default().fg(self.error)
+    }
+
     pub fn selected_style(&self) -> Style {
+        if self.selection == Color::Reset {
+            // Without colors, reverse video is the only way to show the selection
+            return Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD);
+        }
         Style::default()
             .bg(self.selection)
             .fg(Color::White)
@@ -95,3 +154,17 @@ impl Theme {
             .add_modifier(Modifier::BOLD)
     }
 }
+
+/// Map bright and gray colors onto the 8 basic ANSI colors
+fn to_basic(color: Color) -> Color {
+    match color {
+        Color::LightRed => Color::Red,
+        Color::LightGreen => Color::Green,
+        Color::LightYellow => Color::Yellow,
+        Color::LightBlue => Color::Blue,
+        Color::LightMagenta => Color::Magenta,
+        Color::LightCyan => Color::Cyan,
+        Color::Gray | Color::DarkGray => Color::White,
+        other => other,
+    }
+}